@@ -0,0 +1,191 @@
+use regex::Regex;
+use serde_json::Value;
+
+use crate::{
+    environments::{self, Variables},
+    models::{query_json, KeyValueRow, Response},
+};
+
+/// What an assertion row checks, the left column
+enum Target<'t> {
+    Status,
+    /// Total time in milliseconds
+    Time,
+    Header(&'t str),
+    Body,
+    /// JSONPath, or a jq style path, into a JSON body
+    Json(&'t str),
+}
+
+impl<'t> Target<'t> {
+    fn parse(text: &'t str) -> Result<Self, String> {
+        let text = text.trim();
+        let target = match text.split_once(char::is_whitespace) {
+            Some(("header", name)) => Self::Header(name.trim()),
+            _ => match text {
+                "status" => Self::Status,
+                "time" => Self::Time,
+                "body" => Self::Body,
+                path if path.starts_with('$') || path.starts_with('.') => Self::Json(path),
+                _ => {
+                    return Err(format!(
+                        "can't check `{text}`, use status, time, header <name>, body or a JSONPath"
+                    ))
+                }
+            },
+        };
+        Ok(target)
+    }
+
+    /// `None` when there's nothing there
+    fn actual(&self, response: &Response) -> Result<Option<Value>, String> {
+        let actual = match self {
+            Self::Status => Some(Value::from(response.status)),
+            Self::Time => Some(Value::from(response.timing.total.as_millis() as u64)),
+            Self::Header(name) => response
+                .headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| Value::from(value.as_str())),
+            Self::Body => Some(Value::from(response.body.as_str())),
+            Self::Json(path) => {
+                let body = serde_json::from_str::<Value>(&response.body)
+                    .map_err(|_| "response is not JSON".to_string())?;
+                query_json(path, &body)?.into_iter().next()
+            }
+        };
+        Ok(actual)
+    }
+}
+
+/// The right column, `== ` when no operator is given
+enum Check {
+    Exists,
+    Missing,
+    Equal(Value),
+    NotEqual(Value),
+    Less(f64),
+    LessOrEqual(f64),
+    Greater(f64),
+    GreaterOrEqual(f64),
+    Contains(Value),
+    Matches(Regex),
+    /// `2xx` and the like
+    Class(char),
+}
+
+/// Numbers, booleans and quoted strings as JSON, anything else as a string
+fn literal(text: &str) -> Value {
+    serde_json::from_str(text).unwrap_or_else(|_| Value::from(text))
+}
+
+fn number(text: &str) -> Result<f64, String> {
+    text.parse()
+        .map_err(|_| format!("`{text}` is not a number"))
+}
+
+impl Check {
+    fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let (operator, operand) = text
+            .split_once(char::is_whitespace)
+            .map(|(operator, operand)| (operator, operand.trim()))
+            .unwrap_or((text, ""));
+        let check = match operator {
+            "exists" if operand.is_empty() => Self::Exists,
+            "!exists" if operand.is_empty() => Self::Missing,
+            "==" => Self::Equal(literal(operand)),
+            "!=" => Self::NotEqual(literal(operand)),
+            "<" => Self::Less(number(operand)?),
+            "<=" => Self::LessOrEqual(number(operand)?),
+            ">" => Self::Greater(number(operand)?),
+            ">=" => Self::GreaterOrEqual(number(operand)?),
+            "contains" => Self::Contains(literal(operand)),
+            "matches" => Self::Matches(Regex::new(operand).map_err(|err| err.to_string())?),
+            _ => match text.as_bytes() {
+                [class @ b'1'..=b'5', b'x', b'x'] => Self::Class(*class as char),
+                _ => Self::Equal(literal(text)),
+            },
+        };
+        Ok(check)
+    }
+
+    fn passes(&self, actual: Option<&Value>) -> bool {
+        let Some(actual) = actual else {
+            return matches!(self, Self::Missing);
+        };
+        let as_number = || match actual {
+            Value::Number(number) => number.as_f64(),
+            Value::String(text) => text.trim().parse().ok(),
+            _ => None,
+        };
+        match self {
+            Self::Exists => true,
+            Self::Missing => false,
+            Self::Equal(expected) => equal(actual, expected),
+            Self::NotEqual(expected) => !equal(actual, expected),
+            Self::Less(n) => as_number().is_some_and(|actual| actual < *n),
+            Self::LessOrEqual(n) => as_number().is_some_and(|actual| actual <= *n),
+            Self::Greater(n) => as_number().is_some_and(|actual| actual > *n),
+            Self::GreaterOrEqual(n) => as_number().is_some_and(|actual| actual >= *n),
+            Self::Contains(expected) => match actual {
+                Value::Array(items) => items.iter().any(|item| equal(item, expected)),
+                Value::Object(map) => expected.as_str().is_some_and(|key| map.contains_key(key)),
+                actual => text(actual).contains(&text(expected)),
+            },
+            Self::Matches(regex) => regex.is_match(&text(actual)),
+            Self::Class(class) => {
+                let actual = text(actual);
+                actual.len() == 3 && actual.starts_with(*class)
+            }
+        }
+    }
+}
+
+/// Strings unquoted, so `"200"` in a header equals `200`
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn equal(actual: &Value, expected: &Value) -> bool {
+    actual == expected || text(actual) == text(expected)
+}
+
+fn shown(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) if text.chars().count() > 60 => {
+            format!("{:?}...", text.chars().take(60).collect::<String>())
+        }
+        Some(value) => value.to_string(),
+        None => "missing".to_string(),
+    }
+}
+
+/// Checks the enabled rows against a response, returning why each failing one
+/// failed. Variables are filled in on both sides first
+pub fn check(rows: &[KeyValueRow], response: &Response, variables: &Variables) -> Vec<String> {
+    rows.iter()
+        .filter(|row| row.enabled && !row.key.is_empty())
+        .filter_map(|row| check_row(row, response, variables).err())
+        .collect()
+}
+
+fn check_row(row: &KeyValueRow, response: &Response, variables: &Variables) -> Result<(), String> {
+    let resolve = |text: &str| environments::resolve(text, variables).map_err(|e| e.to_string());
+    let (target, expected) = (resolve(row.key.trim())?, resolve(&row.value)?);
+    let invalid = |err: String| format!("{target}: {err}");
+    let actual = Target::parse(&target)
+        .and_then(|parsed| parsed.actual(response))
+        .map_err(invalid)?;
+    let check = Check::parse(&expected).map_err(invalid)?;
+    match check.passes(actual.as_ref()) {
+        true => Ok(()),
+        false => Err(format!(
+            "{target} is {}, expected {expected}",
+            shown(actual.as_ref())
+        )),
+    }
+}
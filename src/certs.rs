@@ -0,0 +1,110 @@
+use std::net::IpAddr;
+
+use openssl::{
+    asn1::Asn1Time,
+    x509::{X509NameRef, X509Ref},
+};
+
+/// Certificates expiring sooner than this are highlighted
+pub const EXPIRY_WARNING_DAYS: i32 = 30;
+
+pub struct Certificate {
+    pub subject: String,
+    pub issuer: String,
+    pub sans: Vec<String>,
+    pub not_before: String,
+    pub not_after: String,
+    /// Negative once expired
+    pub days_left: Option<i32>,
+}
+
+impl Certificate {
+    pub fn new(cert: &X509Ref) -> Self {
+        let sans = cert
+            .subject_alt_names()
+            .map(|names| {
+                names
+                    .iter()
+                    .filter_map(|name| match (name.dnsname(), name.ipaddress()) {
+                        (Some(dns), _) => Some(dns.to_string()),
+                        (None, Some(&[a, b, c, d])) => Some(IpAddr::from([a, b, c, d]).to_string()),
+                        (None, Some(ip)) => <[u8; 16]>::try_from(ip)
+                            .ok()
+                            .map(|ip| IpAddr::from(ip).to_string()),
+                        (None, None) => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let days_left = Asn1Time::days_from_now(0)
+            .and_then(|now| now.diff(cert.not_after()))
+            .ok()
+            .map(|diff| diff.days);
+
+        Self {
+            subject: name(cert.subject_name()),
+            issuer: name(cert.issuer_name()),
+            sans,
+            not_before: cert.not_before().to_string(),
+            not_after: cert.not_after().to_string(),
+            days_left,
+        }
+    }
+}
+
+fn name(name: &X509NameRef) -> String {
+    name.entries()
+        .map(|entry| {
+            let key = entry.object().nid().short_name().unwrap_or("?");
+            let value = entry
+                .data()
+                .as_utf8()
+                .map(|value| value.to_string())
+                .unwrap_or_default();
+            format!("{key}={value}")
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::{
+        hash::MessageDigest,
+        pkey::PKey,
+        rsa::Rsa,
+        x509::{extension::SubjectAlternativeName, X509NameBuilder, X509},
+    };
+
+    use super::*;
+
+    #[test]
+    fn reads_certificate() {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "example.com").unwrap();
+        let name = name.build();
+        let mut builder = X509::builder().unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(10).unwrap())
+            .unwrap();
+        let sans = SubjectAlternativeName::new()
+            .dns("example.com")
+            .ip("127.0.0.1")
+            .build(&builder.x509v3_context(None, None))
+            .unwrap();
+        builder.append_extension(sans).unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+        let cert = Certificate::new(&builder.build());
+        assert_eq!(cert.subject, "CN=example.com");
+        assert_eq!(cert.issuer, "CN=example.com");
+        assert_eq!(cert.sans, ["example.com", "127.0.0.1"]);
+        assert!(matches!(cert.days_left, Some(9..=10)));
+    }
+}
@@ -0,0 +1,700 @@
+use std::{
+    fs,
+    io::{self, Read, Write},
+    time::{Duration, Instant},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{json, Map, Value};
+use tokio::runtime::Runtime;
+
+use crate::{
+    assertions,
+    collections::{Collections, SavedItem},
+    config::Config,
+    environments::{Environments, Variables},
+    models::{
+        self, is_valid_uri, BodyMode, Editors, Request, RequestError, RequestOptions, Response,
+    },
+    workspaces,
+};
+
+pub const USAGE: &str = "\
+usage: curl-rs                 start the ui
+       curl-rs <url> [curl options]
+                               start the ui with the request filled in, e.g.
+                               `curl-rs example.com/users -X POST -H 'X: y' -d '{}'`
+       curl-rs run <collection/request> [options]
+       curl-rs test <collection[/folder]> [options]
+
+run sends a saved request and prints the response body, the status goes to
+stderr. It exits with 0 for a status below 400, 1 for other statuses and 2
+when no response was received.
+
+Settings like the timeout and default headers are read from
+$XDG_CONFIG_HOME/curl-rs/config.toml, or ~/.config/curl-rs/config.toml.
+
+test sends every request under a collection or folder in order, checks them
+against their assertions and prints which passed. Captured values are passed
+on to the requests after, without being saved. Requests without assertions
+pass with a status below 400. It exits with 0 when all passed, 1 when any
+failed and 2 when nothing could be run.
+
+options:
+  -e, --env <name>          fill in variables from this environment instead of the active one
+  -w, --workspace <name>    look the request up in this workspace instead of the current one
+  -d, --data <body>         run: send this body instead of the saved one, @file reads it from
+                            a file and @- from stdin, e.g. `jq .user x.json | curl-rs run
+                            users/create -d @-`
+  -o, --output <body|json>  run: print the body, the default, or one JSON document with the
+                            status, headers, body and timing. JSON bodies are included as
+                            JSON, binary ones in base64 with \"body_encoding\": \"base64\"
+  -t, --tag <tag>           test: only requests tagged with it in their options, e.g.
+                            `\"tags\": [\"smoke\"]`, can be given more than once
+      --junit <file>        test: also write the results as JUnit XML";
+
+const HTTP_ERROR: i32 = 1;
+const TESTS_FAILED: i32 = 1;
+const FAILED: i32 = 2;
+
+#[derive(Default)]
+struct Args {
+    /// `collection/folder/.../name`
+    path: String,
+    env: Option<String>,
+    workspace: Option<String>,
+    data: Option<Data>,
+    json: bool,
+    tags: Vec<String>,
+    junit: Option<String>,
+}
+
+/// A body given on the command line
+enum Data {
+    Text(String),
+    File(String),
+    Stdin,
+}
+
+impl Data {
+    fn parse(value: String) -> Self {
+        match value.strip_prefix('@') {
+            Some("-") => Self::Stdin,
+            Some(path) => Self::File(path.to_string()),
+            None => Self::Text(value),
+        }
+    }
+
+    /// Files are sent as they are, stdin has to be text since it's put in
+    /// the body editor
+    fn load(&self, editors: &mut Editors) -> Result<(), String> {
+        match self {
+            Self::Text(text) => {
+                editors.body_mode = BodyMode::Raw;
+                editors.body.set_text(text);
+            }
+            Self::File(path) => {
+                editors.body_mode = BodyMode::Binary;
+                editors.file.set_text(path);
+            }
+            Self::Stdin => {
+                let mut bytes = Vec::new();
+                io::stdin()
+                    .read_to_end(&mut bytes)
+                    .map_err(|err| format!("can't read stdin: {err}"))?;
+                let text = String::from_utf8(bytes)
+                    .map_err(|_| "stdin isn't UTF-8 text, pass a file with -d @path".to_string())?;
+                editors.body_mode = BodyMode::Raw;
+                editors.body.set_text(&text);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Args {
+    /// The options of `command`, others are refused
+    fn parse(command: &str, args: &[String]) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            // `--env=prod` as well as `--env prod`
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next().cloned())
+                    .ok_or_else(|| format!("{flag} needs a value"))
+            };
+            match (command, flag) {
+                (_, "-e" | "--env") => parsed.env = Some(value()?),
+                (_, "-w" | "--workspace") => parsed.workspace = Some(value()?),
+                ("run", "-d" | "--data") => parsed.data = Some(Data::parse(value()?)),
+                ("run", "-o" | "--output") => {
+                    parsed.json = match value()?.as_str() {
+                        "json" => true,
+                        "body" => false,
+                        output => return Err(format!("unknown output {output}, use body or json")),
+                    }
+                }
+                ("test", "-t" | "--tag") => parsed.tags.push(value()?),
+                ("test", "--junit") => parsed.junit = Some(value()?),
+                (_, flag) if flag.starts_with('-') && flag.len() > 1 => {
+                    return Err(format!("unknown option {flag} for {command}"))
+                }
+                _ if !parsed.path.is_empty() => return Err(format!("unexpected {arg}")),
+                (_, path) => parsed.path = path.trim_matches('/').to_string(),
+            }
+        }
+        if parsed.path.is_empty() {
+            return Err(format!(
+                "which requests to {command}? e.g. `curl-rs {command} default/users`"
+            ));
+        }
+        Ok(parsed)
+    }
+
+    fn workspace(&self) -> String {
+        self.workspace.clone().unwrap_or_else(workspaces::current)
+    }
+}
+
+fn help(args: &[String]) -> bool {
+    let help = args.iter().any(|arg| arg == "-h" || arg == "--help");
+    if help {
+        println!("{USAGE}");
+    }
+    help
+}
+
+/// `curl-rs run ...`, returns the exit code
+pub fn run(args: &[String]) -> i32 {
+    if help(args) {
+        return 0;
+    }
+    let args = Args::parse("run", args);
+    let json = args.as_ref().is_ok_and(|args| args.json);
+    let result = args.and_then(|args| {
+        let response = send(&args)?;
+        let mut stdout = io::stdout().lock();
+        let written = match json {
+            true => writeln!(stdout, "{}", output(&response)),
+            false => {
+                eprintln!("{} {}", response.status, response.reason());
+                stdout.write_all(&response.bytes)
+            }
+        };
+        written
+            .and_then(|()| stdout.flush())
+            .map_err(|err| format!("can't write the response: {err}"))?;
+        Ok(response.status)
+    });
+    match result {
+        Ok(status) if status < 400 => 0,
+        Ok(_) => HTTP_ERROR,
+        // Still one document on stdout for whatever reads it
+        Err(err) if json => {
+            println!("{}", json!({ "error": err }));
+            FAILED
+        }
+        Err(err) => {
+            eprintln!("curl-rs: {err}");
+            FAILED
+        }
+    }
+}
+
+/// `--output json`, header names lowercased with repeated ones as arrays
+fn output(response: &Response) -> Value {
+    let mut headers = Map::new();
+    for (name, value) in &response.headers {
+        let value = Value::from(value.as_str());
+        match headers.get_mut(&name.to_ascii_lowercase()) {
+            Some(Value::Array(values)) => values.push(value),
+            Some(first) => *first = Value::Array(vec![first.take(), value]),
+            None => {
+                headers.insert(name.to_ascii_lowercase(), value);
+            }
+        }
+    }
+    let millis = |duration: Duration| (duration.as_secs_f64() * 1e6).round() / 1e3;
+    let timing = &response.timing;
+    let mut output = json!({
+        "status": response.status,
+        "headers": headers,
+        "body": Value::Null,
+        "timing": {
+            "dns_ms": timing.dns.map(millis),
+            "connect_ms": timing.connect.map(millis),
+            "tls_ms": timing.tls.map(millis),
+            "first_byte_ms": millis(timing.first_byte),
+            "total_ms": millis(timing.total),
+        },
+    });
+    // JSON bodies inline, anything else as text, e.g. event streams which
+    // are pretty printed too
+    output["body"] = match &response.hex {
+        Some(_) => {
+            output["body_encoding"] = Value::from("base64");
+            Value::from(STANDARD.encode(&response.bytes))
+        }
+        None if response.is_json() => serde_json::from_str(&response.body)
+            .unwrap_or_else(|_| Value::from(response.body.as_str())),
+        None => Value::from(response.body.as_str()),
+    };
+    output
+}
+
+fn variables(workspace: &str, env: Option<&str>) -> Result<Variables, String> {
+    let environments = Environments::load(workspace);
+    match env {
+        Some(name) => environments
+            .list
+            .iter()
+            .find(|environment| environment.name == name)
+            .map(|environment| environment.all_variables())
+            .ok_or_else(|| format!("no environment `{name}` in workspace {workspace}")),
+        None => Ok(environments.variables()),
+    }
+}
+
+/// Every command loads it before anything else, see `Config::load`
+fn config() -> Config {
+    let (config, errors) = Config::load();
+    errors.iter().for_each(|err| eprintln!("curl-rs: {err}"));
+    config
+}
+
+fn prepare(
+    item: &SavedItem,
+    data: Option<&Data>,
+    variables: &Variables,
+    config: &Config,
+) -> Result<Request, String> {
+    let mut editors = Editors::new();
+    editors.load(&item.request);
+    if let Some(data) = data {
+        data.load(&mut editors)?;
+    }
+    let request = Request::new(&editors, config).and_then(|mut request| {
+        request.resolve(variables)?;
+        match is_valid_uri(&request.uri) {
+            true => Ok(request),
+            false => Err(RequestError::InvalidUri),
+        }
+    });
+    request.map_err(|err| err.to_string())
+}
+
+fn send(args: &Args) -> Result<Response, String> {
+    let config = config();
+    let workspace = args.workspace();
+    let collections = Collections::load(&workspace);
+    let item = collections
+        .find(&args.path)
+        .ok_or_else(|| format!("no request `{}` in workspace {workspace}", args.path))?;
+    let variables = variables(&workspace, args.env.as_deref())?;
+
+    let request = prepare(item, args.data.as_ref(), &variables, &config)?;
+    let runtime = Runtime::new().map_err(|err| err.to_string())?;
+    runtime
+        .block_on(request.fetch())
+        .map_err(|err| err.to_string())
+}
+
+enum Outcome {
+    Passed,
+    Failed(Vec<String>),
+    /// No response to check
+    Error(String),
+}
+
+struct TestCase {
+    path: String,
+    /// `status reason`, empty without a response
+    status: String,
+    seconds: f64,
+    outcome: Outcome,
+}
+
+/// `curl-rs test ...`, returns the exit code
+pub fn test(args: &[String]) -> i32 {
+    if help(args) {
+        return 0;
+    }
+    let result = Args::parse("test", args).and_then(|args| {
+        let started = Instant::now();
+        let cases = run_tests(&args)?;
+        let failed = cases
+            .iter()
+            .filter(|case| !matches!(case.outcome, Outcome::Passed))
+            .count();
+        println!(
+            "{} passed, {failed} failed of {} in {:.2} s",
+            cases.len() - failed,
+            cases.len(),
+            started.elapsed().as_secs_f64()
+        );
+        if let Some(path) = &args.junit {
+            fs::write(path, junit(&args.path, &cases))
+                .map_err(|err| format!("can't write {path}: {err}"))?;
+        }
+        Ok(failed)
+    });
+    match result {
+        Ok(0) => 0,
+        Ok(_) => TESTS_FAILED,
+        Err(err) => {
+            eprintln!("curl-rs: {err}");
+            FAILED
+        }
+    }
+}
+
+fn tagged(item: &SavedItem, tags: &[String]) -> bool {
+    if tags.is_empty() {
+        return true;
+    }
+    let options: RequestOptions = match item.request.options.trim() {
+        "" => RequestOptions::default(),
+        options => serde_json::from_str(options).unwrap_or_default(),
+    };
+    options
+        .tags
+        .unwrap_or_default()
+        .iter()
+        .any(|tag| tags.contains(tag))
+}
+
+/// Prints every case as it's done
+fn run_tests(args: &Args) -> Result<Vec<TestCase>, String> {
+    let config = config();
+    let workspace = args.workspace();
+    let collections = Collections::load(&workspace);
+    let items = collections
+        .requests(&args.path)
+        .ok_or_else(|| format!("nothing at `{}` in workspace {workspace}", args.path))?;
+    let items: Vec<_> = items
+        .into_iter()
+        .filter(|(_, item)| tagged(item, &args.tags))
+        .collect();
+    if items.is_empty() {
+        return Err(match args.tags.is_empty() {
+            true => format!("no requests to test under `{}`", args.path),
+            false => format!(
+                "no requests tagged {} under `{}`",
+                args.tags.join(", "),
+                args.path
+            ),
+        });
+    }
+    let mut variables = variables(&workspace, args.env.as_deref())?;
+    let runtime = Runtime::new().map_err(|err| err.to_string())?;
+
+    let mut cases = Vec::new();
+    for (path, item) in items {
+        let started = Instant::now();
+        let response = prepare(item, None, &variables, &config)
+            .and_then(|request| runtime.block_on(request.fetch()).map_err(|e| e.to_string()));
+        let seconds = started.elapsed().as_secs_f64();
+        let case = match response {
+            Ok(response) => {
+                let mut failures = Vec::new();
+                match models::captured(&item.request.captures, &response) {
+                    Some(Ok(values)) => variables.extend(values),
+                    Some(Err(err)) => failures.push(err),
+                    None => {}
+                }
+                let assertions = &item.request.assertions;
+                match assertions
+                    .iter()
+                    .any(|row| row.enabled && !row.key.is_empty())
+                {
+                    true => failures.extend(assertions::check(assertions, &response, &variables)),
+                    false if response.status >= 400 => {
+                        failures.push(format!("status is {}, expected < 400", response.status))
+                    }
+                    false => {}
+                }
+                TestCase {
+                    path,
+                    status: format!("{} {}", response.status, response.reason()),
+                    seconds,
+                    outcome: match failures.is_empty() {
+                        true => Outcome::Passed,
+                        false => Outcome::Failed(failures),
+                    },
+                }
+            }
+            Err(err) => TestCase {
+                path,
+                status: String::new(),
+                seconds,
+                outcome: Outcome::Error(err),
+            },
+        };
+        print(&case);
+        cases.push(case);
+    }
+    Ok(cases)
+}
+
+fn print(case: &TestCase) {
+    let millis = (case.seconds * 1000.0).round();
+    let (path, status) = (&case.path, &case.status);
+    match &case.outcome {
+        Outcome::Passed => println!("PASS  {path}  {status}  {millis} ms"),
+        Outcome::Failed(failures) => {
+            println!("FAIL  {path}  {status}  {millis} ms");
+            failures
+                .iter()
+                .for_each(|failure| println!("      {failure}"));
+        }
+        Outcome::Error(err) => println!("ERROR {path}  {err}"),
+    }
+}
+
+fn xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// One suite for the path tested, with a case per request named after its
+/// folder the way JUnit names classes
+fn junit(suite: &str, cases: &[TestCase]) -> String {
+    let count = |error: bool| {
+        cases
+            .iter()
+            .filter(|case| match case.outcome {
+                Outcome::Passed => false,
+                Outcome::Failed(_) => !error,
+                Outcome::Error(_) => error,
+            })
+            .count()
+    };
+    let seconds: f64 = cases.iter().map(|case| case.seconds).sum();
+    let mut out = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n  \
+         <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{seconds:.3}\">\n",
+        xml(suite),
+        cases.len(),
+        count(false),
+        count(true),
+    );
+    for case in cases {
+        let (class, name) = case.path.rsplit_once('/').unwrap_or(("", &case.path));
+        out += &format!(
+            "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+            xml(&class.replace('/', ".")),
+            xml(name),
+            case.seconds,
+        );
+        match &case.outcome {
+            Outcome::Passed => out += "/>\n",
+            Outcome::Failed(failures) => {
+                out += &format!(
+                    ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+                    xml(&failures[0]),
+                    xml(&failures.join("\n")),
+                );
+            }
+            Outcome::Error(err) => {
+                out += &format!(
+                    ">\n      <error message=\"{}\"/>\n    </testcase>\n",
+                    xml(err)
+                );
+            }
+        }
+    }
+    out + "  </testsuite>\n</testsuites>\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::{
+        models::{Body, SavedRequest},
+        timing::Timing,
+    };
+
+    fn response(content_type: &str, body: &str) -> Response {
+        Response {
+            status: 200,
+            headers: vec![("Content-Type".to_string(), content_type.to_string())],
+            body: body.to_string(),
+            pretty: Some(body.to_string()),
+            ..Response::default()
+        }
+    }
+
+    #[test]
+    fn json_output_body() {
+        let json = output(&response("application/json", r#"{"a":1}"#));
+        assert_eq!(json["body"], json!({ "a": 1 }));
+        assert_eq!(json["headers"]["content-type"], "application/json");
+
+        let events = "data: {\"a\":1}\n\n";
+        let json = output(&response("text/event-stream", events));
+        assert_eq!(json["body"], events);
+
+        // Not what the header says
+        let json = output(&response("application/json", "oops"));
+        assert_eq!(json["body"], "oops");
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn item(uri: &str) -> SavedItem {
+        SavedItem {
+            name: String::new(),
+            request: SavedRequest {
+                uri: uri.to_string(),
+                ..SavedRequest::default()
+            },
+        }
+    }
+
+    #[test]
+    fn parses_run_args() {
+        let parsed =
+            Args::parse("run", &args(&["/api/users/", "--env=prod", "-w", "team"])).unwrap();
+        assert_eq!(parsed.path, "api/users");
+        assert_eq!(parsed.env.as_deref(), Some("prod"));
+        assert_eq!(parsed.workspace(), "team");
+
+        let err = |list: &[&str]| Args::parse("run", &args(list)).err().unwrap();
+        assert_eq!(err(&["a", "b"]), "unexpected b");
+        assert_eq!(err(&["a", "--env"]), "--env needs a value");
+        assert_eq!(
+            err(&["a", "--tag", "smoke"]),
+            "unknown option --tag for run"
+        );
+        assert!(err(&[]).starts_with("which requests to run?"));
+    }
+
+    #[test]
+    fn fills_in_saved_requests() {
+        let variables = Variables::from([("host".to_string(), "example.com".to_string())]);
+        let config = Config::default();
+        let request = prepare(&item("http://{{host}}/a"), None, &variables, &config).unwrap();
+        assert_eq!(request.uri, "http://example.com/a");
+
+        let err = prepare(&item("http://{{missing}}/"), None, &variables, &config);
+        assert_eq!(
+            err.err().unwrap(),
+            RequestError::UnknownVariable("missing".to_string()).to_string()
+        );
+        assert!(prepare(&item("not a url"), None, &variables, &config).is_err());
+    }
+
+    #[test]
+    fn replaces_the_body() {
+        let data = |list: &[&str]| Args::parse("run", &args(list)).unwrap().data;
+        assert!(matches!(data(&["a", "-d", "@-"]), Some(Data::Stdin)));
+        assert!(
+            matches!(data(&["a", "--data=@x.json"]), Some(Data::File(path)) if path == "x.json")
+        );
+        assert!(data(&["a"]).is_none());
+
+        let variables = Variables::new();
+        let config = Config::default();
+        let text = Data::parse("{\"a\": 1}".to_string());
+        let request =
+            prepare(&item("http://localhost/"), Some(&text), &variables, &config).unwrap();
+        assert!(matches!(request.body, Some(Body::Raw(body)) if body == "{\"a\": 1}"));
+        let file = Data::parse("@body.bin".to_string());
+        let request =
+            prepare(&item("http://localhost/"), Some(&file), &variables, &config).unwrap();
+        assert!(matches!(request.body, Some(Body::File(path)) if path.ends_with("body.bin")));
+    }
+
+    #[test]
+    fn filters_by_tag() {
+        let mut smoke = item("http://localhost/");
+        smoke.request.options = r#"{"tags": ["smoke", "users"]}"#.to_string();
+        let untagged = item("http://localhost/");
+        let tags = args(&["smoke"]);
+        assert!(tagged(&smoke, &tags));
+        assert!(!tagged(&untagged, &tags));
+        assert!(tagged(&untagged, &[]));
+        assert!(!tagged(&smoke, &args(&["slow"])));
+    }
+
+    #[test]
+    fn writes_junit() {
+        let case = |path: &str, outcome| TestCase {
+            path: path.to_string(),
+            status: "200 OK".to_string(),
+            seconds: 0.25,
+            outcome,
+        };
+        let cases = [
+            case("api/users/list", Outcome::Passed),
+            case(
+                "api/users/create",
+                Outcome::Failed(vec!["status is 500, expected < 400".to_string()]),
+            ),
+            case("api/<ping>", Outcome::Error("timed out".to_string())),
+        ];
+        assert_eq!(
+            junit("api", &cases),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="api" tests="3" failures="1" errors="1" time="0.750">
+    <testcase classname="api.users" name="list" time="0.250"/>
+    <testcase classname="api.users" name="create" time="0.250">
+      <failure message="status is 500, expected &lt; 400">status is 500, expected &lt; 400</failure>
+    </testcase>
+    <testcase classname="api" name="&lt;ping&gt;" time="0.250">
+      <error message="timed out"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+
+    #[test]
+    fn json_output() {
+        let mut response = response("image/png", "");
+        response
+            .headers
+            .push(("set-cookie".to_string(), "a=1".to_string()));
+        response
+            .headers
+            .push(("Set-Cookie".to_string(), "b=2".to_string()));
+        response.bytes = Bytes::from_static(b"\x89PNG");
+        response.hex = Some(String::new());
+        response.timing = Timing {
+            connect: Some(Duration::from_micros(1500)),
+            first_byte: Duration::from_millis(20),
+            total: Duration::from_millis(25),
+            ..Timing::default()
+        };
+        assert_eq!(
+            output(&response),
+            json!({
+                "status": 200,
+                "headers": {"content-type": "image/png", "set-cookie": ["a=1", "b=2"]},
+                "body": "iVBORw==",
+                "body_encoding": "base64",
+                "timing": {
+                    "dns_ms": null,
+                    "connect_ms": 1.5,
+                    "tls_ms": null,
+                    "first_byte_ms": 20.0,
+                    "total_ms": 25.0,
+                },
+            })
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use std::io::{self, Write};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+pub struct Clipboard {
+    /// Kept alive since X11 clipboards are served by the owning process
+    system: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        Self {
+            system: arboard::Clipboard::new().ok(),
+        }
+    }
+
+    /// Falls back to OSC 52 when there's no system clipboard, e.g. over ssh
+    pub fn copy(&mut self, text: &str) -> io::Result<()> {
+        if let Some(system) = &mut self.system {
+            if system.set_text(text).is_ok() {
+                return Ok(());
+            }
+        }
+
+        let mut stdout = io::stdout();
+        write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+        stdout.flush()
+    }
+
+    /// Reading over OSC 52 is mostly refused by terminals, so it's only the
+    /// system clipboard here
+    pub fn paste(&mut self) -> Result<String, String> {
+        match &mut self.system {
+            Some(system) => system
+                .get_text()
+                .map_err(|err| format!("can't paste: {err}")),
+            None => Err("no system clipboard to paste from".to_string()),
+        }
+    }
+}
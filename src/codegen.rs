@@ -0,0 +1,598 @@
+use std::{collections::BTreeSet, path::Path};
+
+use base64::Engine;
+use url::Url;
+
+use crate::{
+    auth::Auth,
+    config::Config,
+    models::{Body, Request},
+    traits::Tab,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    Python = 0,
+    JavaScript = 1,
+    Go = 2,
+    Rust = 3,
+}
+
+impl Language {
+    pub const TITLES: [&'static str; 4] = ["Python", "JavaScript", "Go", "Rust"];
+}
+
+impl Tab for Language {
+    fn as_int(&self) -> u8 {
+        *self as u8
+    }
+
+    fn to_enum(&self, num: u8) -> Self {
+        match num {
+            0 => Self::Python,
+            1 => Self::JavaScript,
+            2 => Self::Go,
+            3 => Self::Rust,
+            _ => Self::Python,
+        }
+    }
+
+    fn next(&self) -> Self {
+        self.to_enum((self.as_int() + 1) % 4)
+    }
+
+    fn previous(&self) -> Self {
+        self.to_enum((self.as_int() + 3) % 4)
+    }
+}
+
+/// The request as code in every language, generated when the overlay opens
+pub struct Snippets {
+    pub language: Language,
+    /// By language
+    pub code: Vec<String>,
+    /// What the snippets leave out
+    pub note: Option<&'static str>,
+    pub scroll: u16,
+}
+
+impl Snippets {
+    pub fn new(request: &Request) -> Self {
+        let parts = Parts::new(request);
+        Self {
+            language: Language::Python,
+            code: vec![
+                python(request, &parts),
+                javascript(request, &parts),
+                go(request, &parts),
+                rust(request, &parts),
+            ],
+            note: parts.note,
+            scroll: 0,
+        }
+    }
+
+    pub fn current(&self) -> &str {
+        &self.code[self.language.as_int() as usize]
+    }
+}
+
+/// What every language sends the same way
+struct Parts<'r> {
+    url: String,
+    /// Including those auth adds, basic auth aside
+    headers: Vec<(String, String)>,
+    basic: Option<(&'r str, &'r str)>,
+    /// Timeouts are only set when they differ from the default
+    timeout: Option<f64>,
+    note: Option<&'static str>,
+}
+
+impl<'r> Parts<'r> {
+    fn new(request: &'r Request) -> Self {
+        let mut params = request.params.clone();
+        let mut headers = request.headers.clone();
+        let mut basic = None;
+        let mut note = None;
+        let bearer = |token: &str| ("Authorization".to_string(), format!("Bearer {token}"));
+        match &request.auth {
+            Auth::None => {}
+            Auth::Basic { username, password } => {
+                basic = Some((username.as_str(), password.as_str()))
+            }
+            Auth::Bearer { token } => headers.push(bearer(token)),
+            Auth::ApiKey {
+                key,
+                value,
+                in_query: true,
+            } => params.push((key.clone(), value.clone())),
+            Auth::ApiKey { key, value, .. } => headers.push((key.clone(), value.clone())),
+            Auth::OAuth2 { config, tokens } => match config.cached_token(tokens) {
+                Some(token) => headers.push(bearer(&token)),
+                None => note = Some("without oauth2, send once to get a token"),
+            },
+            Auth::Digest { .. } => note = Some("without digest auth"),
+            Auth::AwsSigV4(_) => note = Some("without aws sigv4 signing"),
+        }
+
+        let has_content_type = headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("content-type"));
+        if let (Some(content_type), false) = (request.content_type, has_content_type) {
+            headers.push(("Content-Type".to_string(), content_type.to_string()));
+        }
+
+        let url = match Url::parse(&request.uri) {
+            Ok(mut url) if !params.is_empty() => {
+                url.query_pairs_mut().extend_pairs(&params);
+                url.to_string()
+            }
+            _ => request.uri.clone(),
+        };
+        let timeout =
+            (request.timeout != Config::default().timeout).then_some(request.timeout.as_secs_f64());
+        Self {
+            url,
+            headers,
+            basic,
+            timeout,
+            note,
+        }
+    }
+}
+
+/// A double quoted literal, the escapes of JSON work in Python, JavaScript and
+/// Go alike
+fn quote(text: &str) -> String {
+    serde_json::to_string(text).unwrap_or_default()
+}
+
+fn file_name(path: &str) -> &str {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path)
+}
+
+fn python(request: &Request, parts: &Parts) -> String {
+    let mut code = String::from("import requests\n\n");
+    code.push_str(&format!("url = {}\n", quote(&parts.url)));
+    let mut args = vec![quote(&request.method), "url".to_string()];
+
+    if !parts.headers.is_empty() {
+        code.push_str("headers = {\n");
+        for (name, value) in &parts.headers {
+            code.push_str(&format!("    {}: {},\n", quote(name), quote(value)));
+        }
+        code.push_str("}\n");
+        args.push("headers=headers".to_string());
+    }
+    match &request.body {
+        Some(Body::Raw(text)) => {
+            code.push_str(&format!("data = {}\n", quote(text)));
+            args.push("data=data".to_string());
+        }
+        Some(Body::Form(pairs)) => {
+            code.push_str("data = [\n");
+            for (key, value) in pairs {
+                code.push_str(&format!("    ({}, {}),\n", quote(key), quote(value)));
+            }
+            code.push_str("]\n");
+            args.push("data=data".to_string());
+        }
+        Some(Body::Multipart(pairs)) => {
+            code.push_str("files = [\n");
+            for (key, value) in pairs {
+                let value = match value.strip_prefix('@') {
+                    Some(path) => format!("open({}, \"rb\")", quote(path)),
+                    None => format!("(None, {})", quote(value)),
+                };
+                code.push_str(&format!("    ({}, {value}),\n", quote(key)));
+            }
+            code.push_str("]\n");
+            args.push("files=files".to_string());
+        }
+        Some(Body::File(path)) => {
+            code.push_str(&format!(
+                "data = open({}, \"rb\")\n",
+                quote(&path.to_string_lossy())
+            ));
+            args.push("data=data".to_string());
+        }
+        None => {}
+    }
+    if let Some((username, password)) = parts.basic {
+        args.push(format!("auth=({}, {})", quote(username), quote(password)));
+    }
+    if let Some(timeout) = parts.timeout {
+        args.push(format!("timeout={timeout}"));
+    }
+    if !request.follow_redirects {
+        args.push("allow_redirects=False".to_string());
+    }
+
+    code.push_str(&format!(
+        "\nresponse = requests.request({})\n",
+        args.join(", ")
+    ));
+    code.push_str("print(response.status_code)\nprint(response.text)\n");
+    code
+}
+
+fn javascript(request: &Request, parts: &Parts) -> String {
+    let mut code = String::new();
+    let mut options = vec![format!("  method: {},", quote(&request.method))];
+
+    let mut headers = parts.headers.clone();
+    if let Some((username, password)) = parts.basic {
+        let credentials =
+            base64::engine::general_purpose::STANDARD.encode(format!("{username}:{password}"));
+        headers.push(("Authorization".to_string(), format!("Basic {credentials}")));
+    }
+    if !headers.is_empty() {
+        options.push("  headers: {".to_string());
+        for (name, value) in &headers {
+            options.push(format!("    {}: {},", quote(name), quote(value)));
+        }
+        options.push("  },".to_string());
+    }
+    match &request.body {
+        Some(Body::Raw(text)) => options.push(format!("  body: {},", quote(text))),
+        Some(Body::Form(pairs)) => {
+            code.push_str("const body = new URLSearchParams([\n");
+            for (key, value) in pairs {
+                code.push_str(&format!("  [{}, {}],\n", quote(key), quote(value)));
+            }
+            code.push_str("]);\n\n");
+            options.push("  body,".to_string());
+        }
+        Some(Body::Multipart(pairs)) => {
+            if pairs.iter().any(|(_, value)| value.starts_with('@')) {
+                code.push_str("import { openAsBlob } from \"node:fs\";\n\n");
+            }
+            code.push_str("const body = new FormData();\n");
+            for (key, value) in pairs {
+                match value.strip_prefix('@') {
+                    Some(path) => code.push_str(&format!(
+                        "body.append({}, await openAsBlob({}), {});\n",
+                        quote(key),
+                        quote(path),
+                        quote(file_name(path))
+                    )),
+                    None => {
+                        code.push_str(&format!("body.append({}, {});\n", quote(key), quote(value)))
+                    }
+                }
+            }
+            code.push('\n');
+            options.push("  body,".to_string());
+        }
+        Some(Body::File(path)) => {
+            code.push_str("import { openAsBlob } from \"node:fs\";\n\n");
+            let path = path.to_string_lossy();
+            options.push(format!("  body: await openAsBlob({}),", quote(&path)));
+        }
+        None => {}
+    }
+    if !request.follow_redirects {
+        options.push("  redirect: \"manual\",".to_string());
+    }
+    if let Some(timeout) = parts.timeout {
+        let millis = (timeout * 1000.0).round();
+        options.push(format!("  signal: AbortSignal.timeout({millis}),"));
+    }
+
+    code.push_str(&format!(
+        "const response = await fetch({}, {{\n{}\n}});\n",
+        quote(&parts.url),
+        options.join("\n")
+    ));
+    code.push_str("console.log(response.status);\nconsole.log(await response.text());\n");
+    code
+}
+
+fn go(request: &Request, parts: &Parts) -> String {
+    let mut imports = BTreeSet::from(["fmt", "io", "net/http"]);
+    let mut body = String::new();
+    let mut content_type = None;
+    let check = "\tif err != nil {\n\t\tpanic(err)\n\t}\n";
+    let reader = match &request.body {
+        Some(Body::Raw(text)) => {
+            imports.insert("strings");
+            format!("strings.NewReader({})", quote(text))
+        }
+        Some(Body::Form(pairs)) => {
+            imports.extend(["net/url", "strings"]);
+            body.push_str("\tform := url.Values{}\n");
+            for (key, value) in pairs {
+                body.push_str(&format!("\tform.Add({}, {})\n", quote(key), quote(value)));
+            }
+            body.push('\n');
+            "strings.NewReader(form.Encode())".to_string()
+        }
+        Some(Body::Multipart(pairs)) => {
+            imports.extend(["bytes", "mime/multipart"]);
+            body.push_str("\tbody := &bytes.Buffer{}\n\twriter := multipart.NewWriter(body)\n");
+            for (key, value) in pairs {
+                match value.strip_prefix('@') {
+                    Some(path) => {
+                        imports.insert("os");
+                        body.push_str(&format!(
+                            "\t{{\n\t\tfile, err := os.Open({})\n",
+                            quote(path)
+                        ));
+                        body.push_str("\t\tif err != nil {\n\t\t\tpanic(err)\n\t\t}\n");
+                        body.push_str(&format!(
+                            "\t\tpart, err := writer.CreateFormFile({}, {})\n",
+                            quote(key),
+                            quote(file_name(path))
+                        ));
+                        body.push_str("\t\tif err != nil {\n\t\t\tpanic(err)\n\t\t}\n");
+                        body.push_str("\t\tio.Copy(part, file)\n\t\tfile.Close()\n\t}\n");
+                    }
+                    None => body.push_str(&format!(
+                        "\twriter.WriteField({}, {})\n",
+                        quote(key),
+                        quote(value)
+                    )),
+                }
+            }
+            body.push_str("\twriter.Close()\n\n");
+            content_type = Some("writer.FormDataContentType()");
+            "body".to_string()
+        }
+        Some(Body::File(path)) => {
+            imports.insert("os");
+            let path = path.to_string_lossy();
+            body.push_str(&format!(
+                "\tbody, err := os.Open({})\n{check}",
+                quote(&path)
+            ));
+            body.push_str("\tdefer body.Close()\n\n");
+            "body".to_string()
+        }
+        None => "nil".to_string(),
+    };
+
+    let mut code = body;
+    code.push_str(&format!(
+        "\treq, err := http.NewRequest({}, {}, {reader})\n{check}",
+        quote(&request.method),
+        quote(&parts.url)
+    ));
+    for (name, value) in &parts.headers {
+        code.push_str(&format!(
+            "\treq.Header.Add({}, {})\n",
+            quote(name),
+            quote(value)
+        ));
+    }
+    if let Some(content_type) = content_type {
+        code.push_str(&format!(
+            "\treq.Header.Set(\"Content-Type\", {content_type})\n"
+        ));
+    }
+    if let Some((username, password)) = parts.basic {
+        code.push_str(&format!(
+            "\treq.SetBasicAuth({}, {})\n",
+            quote(username),
+            quote(password)
+        ));
+    }
+
+    let mut client = Vec::new();
+    if let Some(timeout) = parts.timeout {
+        imports.insert("time");
+        let millis = (timeout * 1000.0).round();
+        client.push(format!("\t\tTimeout: {millis} * time.Millisecond,\n"));
+    }
+    if !request.follow_redirects {
+        client.push(
+            "\t\tCheckRedirect: func(*http.Request, []*http.Request) error {\n\t\t\treturn http.ErrUseLastResponse\n\t\t},\n"
+                .to_string(),
+        );
+    }
+    match client.is_empty() {
+        true => code.push_str("\n\tresp, err := http.DefaultClient.Do(req)\n"),
+        false => code.push_str(&format!(
+            "\n\tclient := &http.Client{{\n{}\t}}\n\tresp, err := client.Do(req)\n",
+            client.concat()
+        )),
+    }
+    code.push_str(check);
+    code.push_str("\tdefer resp.Body.Close()\n\ttext, err := io.ReadAll(resp.Body)\n");
+    code.push_str(check);
+    code.push_str("\tfmt.Println(resp.Status)\n\tfmt.Println(string(text))\n");
+
+    let imports: Vec<_> = imports
+        .iter()
+        .map(|import| format!("\t{}\n", quote(import)))
+        .collect();
+    format!(
+        "package main\n\nimport (\n{})\n\nfunc main() {{\n{code}}}\n",
+        imports.concat()
+    )
+}
+
+fn rust(request: &Request, parts: &Parts) -> String {
+    // Debug formatting gives a Rust literal
+    let quote = |text: &str| format!("{text:?}");
+    let mut code = String::from("// reqwest with the \"blocking\" feature");
+    if matches!(request.body, Some(Body::Multipart(_))) {
+        code.push_str(" and \"multipart\"");
+    }
+    code.push_str("\nfn main() -> Result<(), Box<dyn std::error::Error>> {\n");
+
+    let client = match (request.follow_redirects, request.max_redirects) {
+        (false, _) => Some("reqwest::redirect::Policy::none()".to_string()),
+        (true, 10) => None,
+        (true, max) => Some(format!("reqwest::redirect::Policy::limited({max})")),
+    };
+    match client {
+        Some(policy) => code.push_str(&format!(
+            "    let client = reqwest::blocking::Client::builder()\n        .redirect({policy})\n        .build()?;\n"
+        )),
+        None => code.push_str("    let client = reqwest::blocking::Client::new();\n"),
+    }
+
+    if let Some(Body::Multipart(pairs)) = &request.body {
+        code.push_str("    let form = reqwest::blocking::multipart::Form::new()");
+        for (key, value) in pairs {
+            match value.strip_prefix('@') {
+                Some(path) => code.push_str(&format!(
+                    "\n        .file({}, {})?",
+                    quote(key),
+                    quote(path)
+                )),
+                None => code.push_str(&format!(
+                    "\n        .text({}, {})",
+                    quote(key),
+                    quote(value)
+                )),
+            }
+        }
+        code.push_str(";\n");
+    }
+
+    let method = match request.method.as_str() {
+        method @ ("GET" | "POST" | "PUT" | "DELETE" | "HEAD" | "OPTIONS" | "CONNECT" | "PATCH"
+        | "TRACE") => format!("reqwest::Method::{method}"),
+        method => format!("reqwest::Method::from_bytes({}.as_bytes())?", quote(method)),
+    };
+    let mut calls = vec![format!(".request({method}, {})", quote(&parts.url))];
+    for (name, value) in &parts.headers {
+        calls.push(format!(".header({}, {})", quote(name), quote(value)));
+    }
+    if let Some((username, password)) = parts.basic {
+        calls.push(format!(
+            ".basic_auth({}, Some({}))",
+            quote(username),
+            quote(password)
+        ));
+    }
+    if let Some(timeout) = parts.timeout {
+        calls.push(format!(
+            ".timeout(std::time::Duration::from_secs_f64({timeout:?}))"
+        ));
+    }
+    match &request.body {
+        Some(Body::Raw(text)) => calls.push(format!(".body({})", quote(text))),
+        Some(Body::Form(pairs)) => {
+            let pairs: Vec<_> = pairs
+                .iter()
+                .map(|(key, value)| format!("({}, {})", quote(key), quote(value)))
+                .collect();
+            calls.push(format!(".form(&[{}])", pairs.join(", ")));
+        }
+        Some(Body::Multipart(_)) => calls.push(".multipart(form)".to_string()),
+        Some(Body::File(path)) => calls.push(format!(
+            ".body(std::fs::File::open({})?)",
+            quote(&path.to_string_lossy())
+        )),
+        None => {}
+    }
+    calls.push(".send()?;".to_string());
+
+    code.push_str("    let response = client\n");
+    for call in calls {
+        code.push_str(&format!("        {call}\n"));
+    }
+    code.push_str("    println!(\"{}\", response.status());\n");
+    code.push_str("    println!(\"{}\", response.text()?);\n    Ok(())\n}\n");
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{BodyMode, Editors, KeyValueRow, SavedRequest};
+
+    fn snippets(saved: SavedRequest) -> Snippets {
+        let mut editors = Editors::new();
+        editors.load(&saved);
+        Snippets::new(&Request::new(&editors, &Config::default()).unwrap())
+    }
+
+    fn row(key: &str, value: &str) -> KeyValueRow {
+        KeyValueRow {
+            key: key.to_string(),
+            value: value.to_string(),
+            ..KeyValueRow::new()
+        }
+    }
+
+    #[test]
+    fn generates_every_language() {
+        let mut snippets = snippets(SavedRequest {
+            method: "POST".to_string(),
+            uri: "http://example.com/login".to_string(),
+            params: vec![row("next", "/home")],
+            headers: vec![row("X-Note", "say \"hi\"")],
+            body_mode: BodyMode::Form,
+            form: vec![row("user", "a b")],
+            options: r#"{"timeout": 2.5, "follow_redirects": false}"#.to_string(),
+            ..SavedRequest::default()
+        });
+        assert!(snippets.note.is_none());
+        assert_eq!(
+            snippets.current(),
+            r#"import requests
+
+url = "http://example.com/login?next=%2Fhome"
+headers = {
+    "X-Note": "say \"hi\"",
+}
+data = [
+    ("user", "a b"),
+]
+
+response = requests.request("POST", url, headers=headers, data=data, timeout=2.5, allow_redirects=False)
+print(response.status_code)
+print(response.text)
+"#
+        );
+
+        snippets.language = Language::JavaScript;
+        assert!(snippets.current().contains("  redirect: \"manual\",\n"));
+        assert!(snippets.current().contains("AbortSignal.timeout(2500)"));
+        snippets.language = Language::Go;
+        assert!(snippets
+            .current()
+            .contains("\tform.Add(\"user\", \"a b\")\n"));
+        assert!(snippets
+            .current()
+            .contains("Timeout: 2500 * time.Millisecond"));
+        snippets.language = Language::Rust;
+        assert!(snippets.current().contains(".form(&[(\"user\", \"a b\")])"));
+        assert!(snippets
+            .current()
+            .contains(".redirect(reqwest::redirect::Policy::none())"));
+    }
+
+    #[test]
+    fn multipart_files() {
+        let mut snippets = snippets(SavedRequest {
+            method: "PUT".to_string(),
+            uri: "http://example.com/upload".to_string(),
+            body_mode: BodyMode::Multipart,
+            multipart: vec![row("name", "photo"), row("file", "@dir/photo.png")],
+            ..SavedRequest::default()
+        });
+        assert!(snippets
+            .current()
+            .contains("    (\"file\", open(\"dir/photo.png\", \"rb\")),\n"));
+        snippets.language = Language::JavaScript;
+        assert!(snippets.current().starts_with("import { openAsBlob }"));
+        assert!(snippets.current().contains(
+            "body.append(\"file\", await openAsBlob(\"dir/photo.png\"), \"photo.png\");"
+        ));
+        snippets.language = Language::Go;
+        assert!(snippets
+            .current()
+            .contains("writer.CreateFormFile(\"file\", \"photo.png\")"));
+        snippets.language = Language::Rust;
+        assert!(snippets
+            .current()
+            .starts_with("// reqwest with the \"blocking\" feature and \"multipart\""));
+    }
+}
@@ -0,0 +1,602 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{models::SavedRequest, workspaces};
+
+pub const DEFAULT_COLLECTION: &str = "default";
+
+const EXTENSION: &str = "toml";
+
+#[derive(Clone, Deserialize)]
+pub struct SavedItem {
+    pub name: String,
+    pub request: SavedRequest,
+}
+
+/// Folders and requests are kept sorted by name, the order they're read back
+/// from disk in
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Folder {
+    pub name: String,
+    pub folders: Vec<Folder>,
+    pub requests: Vec<SavedItem>,
+}
+
+impl Folder {
+    /// Creates the missing folders on the way
+    fn folder_mut(&mut self, path: &[&str]) -> &mut Folder {
+        let Some((name, rest)) = path.split_first() else {
+            return self;
+        };
+        let index = match self.folders.binary_search_by(|f| f.name.as_str().cmp(name)) {
+            Ok(index) => index,
+            Err(index) => {
+                let folder = Folder {
+                    name: name.to_string(),
+                    ..Folder::default()
+                };
+                self.folders.insert(index, folder);
+                index
+            }
+        };
+        self.folders[index].folder_mut(rest)
+    }
+
+    /// Replacing any request of the same name
+    fn insert(&mut self, item: SavedItem) {
+        match self
+            .requests
+            .binary_search_by(|saved| saved.name.cmp(&item.name))
+        {
+            Ok(index) => self.requests[index] = item,
+            Err(index) => self.requests.insert(index, item),
+        }
+    }
+
+    fn sort(&mut self) {
+        self.folders.sort_by(|a, b| a.name.cmp(&b.name));
+        self.requests.sort_by(|a, b| a.name.cmp(&b.name));
+        self.folders.iter_mut().for_each(Folder::sort);
+    }
+
+    /// A directory with a pretty printed TOML file per request
+    fn read(dir: &Path, name: String) -> Self {
+        let mut folder = Folder {
+            name,
+            ..Folder::default()
+        };
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if path.is_dir() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                folder.folders.push(Folder::read(&path, name));
+            } else if path.extension().is_some_and(|ext| ext == EXTENSION) {
+                let Some(name) = path.file_stem().map(|n| n.to_string_lossy().into_owned()) else {
+                    continue;
+                };
+                let Some(request) = fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|text| toml::from_str(&text).ok())
+                else {
+                    continue;
+                };
+                folder.requests.push(SavedItem { name, request });
+            }
+        }
+        folder.sort();
+        folder
+    }
+
+    /// Notes every file written in `written`
+    fn write(&self, dir: &Path, written: &mut HashSet<PathBuf>) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        for item in &self.requests {
+            let path = dir.join(format!("{}.{EXTENSION}", item.name));
+            let toml = toml::to_string_pretty(&item.request).map_err(io::Error::other)?;
+            // Left alone when unchanged, so only edited requests show up as changed
+            if fs::read_to_string(&path).ok().as_deref() != Some(toml.as_str()) {
+                fs::write(&path, toml)?;
+            }
+            written.insert(path);
+        }
+        for folder in &self.folders {
+            folder.write(&dir.join(&folder.name), written)?;
+        }
+        Ok(())
+    }
+}
+
+/// Removes the request files that weren't just written and the directories
+/// left empty by that, anything else in there is kept
+fn prune(dir: &Path, written: &HashSet<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            prune(&path, written)?;
+            // Fails when something is left inside, which is fine
+            let _ = fs::remove_dir(&path);
+        } else if path.extension().is_some_and(|ext| ext == EXTENSION) && !written.contains(&path) {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// A tree of folders and named requests, stored as a directory named after the
+/// collection. Collections used to be single JSON files, those are still read
+/// and replaced by a directory when next written
+#[derive(Default, Deserialize)]
+pub struct Collection {
+    #[serde(skip)]
+    pub name: String,
+    #[serde(flatten)]
+    pub root: Folder,
+}
+
+pub enum NodeKind<'c> {
+    Folder { expanded: bool },
+    Request(&'c SavedItem),
+}
+
+/// A visible row of the collections tree
+pub struct Node<'c> {
+    /// `collection/folder/.../name`
+    pub path: String,
+    pub name: &'c str,
+    pub depth: usize,
+    pub kind: NodeKind<'c>,
+}
+
+#[derive(Default)]
+pub struct Collections {
+    dir: Option<PathBuf>,
+    /// Sorted by name
+    pub list: Vec<Collection>,
+    /// Paths of the folders and collections shown closed
+    pub collapsed: HashSet<String>,
+}
+
+impl Collections {
+    pub fn load(workspace: &str) -> Self {
+        let dir = workspaces::dir(workspace).map(|dir| dir.join("collections"));
+        let mut list: Vec<Collection> = dir
+            .as_ref()
+            .and_then(|dir| fs::read_dir(dir).ok())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.file_name()?.to_string_lossy().starts_with('.') {
+                    return None;
+                }
+                if path.is_dir() {
+                    return Some(Collection {
+                        name: path.file_name()?.to_string_lossy().into_owned(),
+                        root: Folder::read(&path, String::new()),
+                    });
+                }
+                if path.extension()? != "json" || path.with_extension("").is_dir() {
+                    return None;
+                }
+                let name = path.file_stem()?.to_string_lossy().into_owned();
+                let mut collection: Collection =
+                    serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+                collection.name = name;
+                collection.root.sort();
+                Some(collection)
+            })
+            .collect();
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        Self {
+            dir,
+            list,
+            collapsed: HashSet::new(),
+        }
+    }
+
+    /// Saves under `collection/folder/.../name`, or the default collection
+    /// without a slash, replacing any request of the same name
+    pub fn save(&mut self, path: &str, request: SavedRequest) -> Result<String, String> {
+        let (collection, folders, name) = split(path)?;
+        let path = [collection]
+            .into_iter()
+            .chain(folders.iter().copied())
+            .chain([name])
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let index = self.collection_mut(collection);
+        self.list[index]
+            .root
+            .folder_mut(&folders)
+            .insert(SavedItem {
+                name: name.to_string(),
+                request,
+            });
+
+        self.write(index)
+            .map(|()| format!("saved {path}"))
+            .map_err(|err| format!("can't save {path}: {err}"))
+    }
+
+    /// Moves a saved request into `collection/folder/...`, keeping its name
+    pub fn move_request(&mut self, from: &str, to: &str) -> Result<String, String> {
+        let (collection, folders, name) = split(from)?;
+        let target: Vec<_> = to
+            .split('/')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .collect();
+        let Some((&to_collection, to_folders)) = target.split_first() else {
+            return Err("give a collection to move to".to_string());
+        };
+        target.iter().try_for_each(|part| check_name(part))?;
+
+        let requests = &mut self
+            .list
+            .iter_mut()
+            .find(|c| c.name == collection)
+            .ok_or_else(|| format!("no collection `{collection}`"))?
+            .root
+            .folder_mut(&folders)
+            .requests;
+        let position = requests
+            .iter()
+            .position(|item| item.name == name)
+            .ok_or_else(|| format!("no request `{from}`"))?;
+        let item = requests.remove(position);
+
+        let to_index = self.collection_mut(to_collection);
+        self.list[to_index].root.folder_mut(to_folders).insert(item);
+
+        // Looked up again, creating the target may have shifted it
+        let from_index = self
+            .list
+            .iter()
+            .position(|c| c.name == collection)
+            .unwrap_or(to_index);
+        self.write(from_index)
+            .and_then(|()| self.write(to_index))
+            .map(|()| format!("moved {name} to {}", target.join("/")))
+            .map_err(|err| format!("can't move {from}: {err}"))
+    }
+
+    pub fn find(&self, path: &str) -> Option<&SavedItem> {
+        let (collection, folders, name) = split(path).ok()?;
+        let mut folder = &self.list.iter().find(|c| c.name == collection)?.root;
+        for name in folders {
+            folder = folder.folders.iter().find(|f| f.name == name)?;
+        }
+        folder.requests.iter().find(|item| item.name == name)
+    }
+
+    /// Every request under a collection or folder, or the request itself, with
+    /// its path. Folders come first, the order they're shown in
+    pub fn requests(&self, path: &str) -> Option<Vec<(String, &SavedItem)>> {
+        fn walk<'c>(folder: &'c Folder, path: &str, found: &mut Vec<(String, &'c SavedItem)>) {
+            for child in &folder.folders {
+                walk(child, &format!("{path}/{}", child.name), found);
+            }
+            found.extend(
+                folder
+                    .requests
+                    .iter()
+                    .map(|item| (format!("{path}/{}", item.name), item)),
+            );
+        }
+
+        let path = path.trim_matches('/');
+        if let Some(item) = self.find(path) {
+            return Some(vec![(path.to_string(), item)]);
+        }
+        let mut parts = path.split('/');
+        let mut folder = &self
+            .list
+            .iter()
+            .find(|c| Some(c.name.as_str()) == parts.next())?
+            .root;
+        for name in parts {
+            folder = folder.folders.iter().find(|f| f.name == name)?;
+        }
+        let mut found = Vec::new();
+        walk(folder, path, &mut found);
+        Some(found)
+    }
+
+    /// `path copy`, or `path copy 2` and up when that's taken too. Copies of
+    /// copies count up instead of growing another suffix
+    pub fn copy_path(&self, path: &str) -> String {
+        let path = path.trim_end_matches('/');
+        let path = path
+            .trim_end_matches(|c: char| c.is_ascii_digit())
+            .strip_suffix(" copy ")
+            .or_else(|| path.strip_suffix(" copy"))
+            .unwrap_or(path);
+        (1..)
+            .map(|n| match n {
+                1 => format!("{path} copy"),
+                n => format!("{path} copy {n}"),
+            })
+            .find(|copy| self.find(copy).is_none())
+            .unwrap_or_default()
+    }
+
+    pub fn toggle(&mut self, path: &str) {
+        if !self.collapsed.remove(path) {
+            self.collapsed.insert(path.to_string());
+        }
+    }
+
+    /// Folders first, then requests, skipping the insides of collapsed ones
+    pub fn nodes(&self) -> Vec<Node<'_>> {
+        let mut nodes = Vec::new();
+        for collection in &self.list {
+            self.push_folder(
+                &collection.root,
+                &collection.name,
+                collection.name.clone(),
+                0,
+                &mut nodes,
+            );
+        }
+        nodes
+    }
+
+    fn push_folder<'c>(
+        &'c self,
+        folder: &'c Folder,
+        name: &'c str,
+        path: String,
+        depth: usize,
+        nodes: &mut Vec<Node<'c>>,
+    ) {
+        let expanded = !self.collapsed.contains(&path);
+        nodes.push(Node {
+            path: path.clone(),
+            name,
+            depth,
+            kind: NodeKind::Folder { expanded },
+        });
+        if !expanded {
+            return;
+        }
+
+        for child in &folder.folders {
+            let child_path = format!("{path}/{}", child.name);
+            self.push_folder(child, &child.name, child_path, depth + 1, nodes);
+        }
+        nodes.extend(folder.requests.iter().map(|item| Node {
+            path: format!("{path}/{}", item.name),
+            name: &item.name,
+            depth: depth + 1,
+            kind: NodeKind::Request(item),
+        }));
+    }
+
+    /// Index of the collection, created if it doesn't exist yet
+    fn collection_mut(&mut self, name: &str) -> usize {
+        match self.list.binary_search_by(|c| c.name.as_str().cmp(name)) {
+            Ok(index) => index,
+            Err(index) => {
+                let collection = Collection {
+                    name: name.to_string(),
+                    ..Collection::default()
+                };
+                self.list.insert(index, collection);
+                index
+            }
+        }
+    }
+
+    fn write(&self, index: usize) -> io::Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let collection = &self.list[index];
+        let root = dir.join(&collection.name);
+        let mut written = HashSet::new();
+        collection.root.write(&root, &mut written)?;
+        prune(&root, &written)?;
+
+        let legacy = dir.join(format!("{}.json", collection.name));
+        match fs::remove_file(legacy) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// `(collection, folders, name)` of a request path
+fn split(path: &str) -> Result<(&str, Vec<&str>, &str), String> {
+    let mut parts: Vec<_> = path.split('/').map(str::trim).collect();
+    let name = parts.pop().unwrap_or_default();
+    if name.is_empty() || parts.iter().any(|part| part.is_empty()) {
+        return Err("give the request a name".to_string());
+    }
+    let collection = match parts.is_empty() {
+        true => DEFAULT_COLLECTION,
+        false => parts.remove(0),
+    };
+    [collection, name]
+        .iter()
+        .chain(&parts)
+        .try_for_each(|part| check_name(part))?;
+    Ok((collection, parts, name))
+}
+
+/// Every part of a path names a file or directory, so no path tricks or
+/// hidden files
+fn check_name(name: &str) -> Result<(), String> {
+    match name.starts_with('.') || name.contains('\\') {
+        true => Err(format!("bad name `{name}`")),
+        false => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(uri: &str) -> SavedRequest {
+        SavedRequest {
+            uri: uri.to_string(),
+            ..SavedRequest::default()
+        }
+    }
+
+    #[test]
+    fn saves_into_collections() {
+        let mut collections = Collections::default();
+        assert_eq!(
+            collections.save("api/login", request("http://a")),
+            Ok("saved api/login".to_string())
+        );
+        assert_eq!(
+            collections.save("ping", request("http://b")),
+            Ok("saved default/ping".to_string())
+        );
+        // Same name replaces the request
+        collections.save("api/login", request("http://c")).unwrap();
+
+        let names: Vec<_> = collections.list.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["api", "default"]);
+        assert_eq!(collections.list[0].root.requests.len(), 1);
+        assert_eq!(
+            collections.find("api/login").unwrap().request.uri,
+            "http://c"
+        );
+        assert_eq!(
+            collections.find("default/ping").unwrap().request.uri,
+            "http://b"
+        );
+        assert!(collections.find("api/missing").is_none());
+
+        assert!(collections.save("api/", request("")).is_err());
+        assert!(collections.save("api/.hidden", request("")).is_err());
+        assert!(collections.save("..\\up/name", request("")).is_err());
+    }
+
+    #[test]
+    fn nests_folders() {
+        let mut collections = Collections::default();
+        collections.save("api/users/list", request("")).unwrap();
+        collections.save("api/auth/login", request("")).unwrap();
+        collections.save("api/ping", request("")).unwrap();
+
+        let tree = |collections: &Collections| {
+            collections
+                .nodes()
+                .iter()
+                .map(|node| format!("{}{}", "  ".repeat(node.depth), node.name))
+                .collect::<Vec<_>>()
+        };
+        // Folders first, sorted by name
+        assert_eq!(
+            tree(&collections),
+            [
+                "api",
+                "  auth",
+                "    login",
+                "  users",
+                "    list",
+                "  ping"
+            ]
+        );
+        collections.toggle("api/auth");
+        assert_eq!(
+            tree(&collections),
+            ["api", "  auth", "  users", "    list", "  ping"]
+        );
+        collections.toggle("api/auth");
+
+        let paths: Vec<_> = collections
+            .requests("api/")
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(paths, ["api/auth/login", "api/users/list", "api/ping"]);
+
+        assert_eq!(
+            collections.move_request("api/ping", "other/health"),
+            Ok("moved ping to other/health".to_string())
+        );
+        assert!(collections.find("api/ping").is_none());
+        assert!(collections.find("other/health/ping").is_some());
+        assert!(collections.move_request("api/missing", "other").is_err());
+        assert!(collections.move_request("api/auth/login", "").is_err());
+    }
+
+    #[test]
+    fn numbers_copies() {
+        let mut collections = Collections::default();
+        collections.save("api/login", request("")).unwrap();
+        assert_eq!(collections.copy_path("api/login"), "api/login copy");
+
+        collections.save("api/login copy", request("")).unwrap();
+        assert_eq!(collections.copy_path("api/login"), "api/login copy 2");
+        // Not `login copy copy`
+        assert_eq!(collections.copy_path("api/login copy"), "api/login copy 2");
+
+        collections.save("api/login copy 2", request("")).unwrap();
+        assert_eq!(
+            collections.copy_path("api/login copy 2"),
+            "api/login copy 3"
+        );
+        assert_eq!(collections.copy_path("api/login 2"), "api/login 2 copy");
+    }
+
+    #[test]
+    fn stores_a_file_per_request() {
+        let data = std::env::temp_dir().join(format!("curl-rs-tests-{}", std::process::id()));
+        crate::history::set_data_dir(data);
+        let workspace = "collections";
+        let dir = workspaces::dir(workspace).unwrap().join("collections");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        // Collections used to be single JSON files
+        let legacy = r#"{"requests": [{"name": "ping", "request": {"uri": "http://a"}}]}"#;
+        fs::write(dir.join("api.json"), legacy).unwrap();
+        fs::write(dir.join("notes.txt"), "kept").unwrap();
+
+        let mut collections = Collections::load(workspace);
+        assert_eq!(
+            collections.find("api/ping").unwrap().request.uri,
+            "http://a"
+        );
+        collections
+            .save("api/users/list", request("http://b"))
+            .unwrap();
+        assert!(!dir.join("api.json").exists());
+        let ping = dir.join("api/ping.toml");
+        let text = fs::read_to_string(&ping).unwrap();
+        assert!(text.contains("uri = \"http://a\""));
+
+        // Unchanged files aren't written again
+        let modified = fs::metadata(&ping).unwrap().modified().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        collections.move_request("api/users/list", "other").unwrap();
+        assert_eq!(fs::metadata(&ping).unwrap().modified().unwrap(), modified);
+        assert!(!dir.join("api/users").exists());
+        assert!(dir.join("other/list.toml").exists());
+        assert!(dir.join("notes.txt").exists());
+
+        let collections = Collections::load(workspace);
+        fs::remove_dir_all(&dir).unwrap();
+        let names: Vec<_> = collections.list.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["api", "other"]);
+        assert_eq!(
+            collections.find("other/list").unwrap().request.uri,
+            "http://b"
+        );
+    }
+}
@@ -0,0 +1,201 @@
+/// Most suggestions shown at once
+const MAX_ITEMS: usize = 8;
+
+const HEADER_NAMES: [&str; 24] = [
+    "Accept",
+    "Accept-Encoding",
+    "Accept-Language",
+    "Authorization",
+    "Cache-Control",
+    "Connection",
+    "Content-Disposition",
+    "Content-Encoding",
+    "Content-Length",
+    "Content-Type",
+    "Cookie",
+    "Host",
+    "If-Match",
+    "If-Modified-Since",
+    "If-None-Match",
+    "Origin",
+    "Pragma",
+    "Range",
+    "Referer",
+    "User-Agent",
+    "X-Api-Key",
+    "X-Forwarded-For",
+    "X-Requested-With",
+    "X-Request-Id",
+];
+
+const MEDIA_TYPES: [&str; 9] = [
+    "application/json",
+    "application/xml",
+    "application/x-www-form-urlencoded",
+    "application/octet-stream",
+    "multipart/form-data",
+    "text/plain",
+    "text/html",
+    "text/csv",
+    "*/*",
+];
+
+/// Where the suggestions go
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Uri,
+    /// A cell of the headers table, column 0 for the name
+    Header {
+        row: usize,
+        column: usize,
+    },
+    /// The name before the cursor in the GraphQL query
+    Query,
+}
+
+/// Suggestions for what's being typed, shown under the editor
+pub struct Completion {
+    pub items: Vec<String>,
+    pub selected: usize,
+    /// What they were worked out for, they're stale once it's changed
+    pub typed: String,
+    pub target: Target,
+}
+
+impl Completion {
+    /// The `candidates` containing `typed`, the ones starting with it first
+    /// and otherwise in the order given. `None` when nothing's typed or
+    /// nothing else matches
+    pub fn new(
+        target: Target,
+        typed: &str,
+        candidates: impl IntoIterator<Item = String>,
+    ) -> Option<Self> {
+        let needle = typed.trim().to_lowercase();
+        if needle.is_empty() {
+            return None;
+        }
+        let (mut starting, mut containing) = (Vec::new(), Vec::new());
+        for candidate in candidates {
+            let lower = candidate.to_lowercase();
+            if lower == needle || starting.contains(&candidate) || containing.contains(&candidate) {
+                continue;
+            }
+            if lower.starts_with(&needle) {
+                starting.push(candidate);
+                if starting.len() == MAX_ITEMS {
+                    break;
+                }
+            } else if lower.contains(&needle) {
+                containing.push(candidate);
+            }
+        }
+        starting.append(&mut containing);
+        starting.truncate(MAX_ITEMS);
+        (!starting.is_empty()).then(|| Self {
+            items: starting,
+            selected: 0,
+            typed: typed.to_string(),
+            target,
+        })
+    }
+
+    pub fn move_selection(&mut self, delta: i32) {
+        let last = self.items.len().saturating_sub(1) as i32;
+        self.selected = (self.selected as i32 + delta).clamp(0, last) as usize;
+    }
+
+    pub fn choice(&self) -> &str {
+        &self.items[self.selected]
+    }
+}
+
+/// Standard header names for column 0, otherwise the usual values of the
+/// header called `name`
+pub fn header_candidates(column: usize, name: &str) -> Vec<String> {
+    let values: &[&str] = match (column, name.to_lowercase().as_str()) {
+        (0, _) => &HEADER_NAMES,
+        (_, "accept" | "content-type") => &MEDIA_TYPES,
+        (_, "accept-encoding" | "content-encoding") => &["gzip", "deflate", "br", "identity"],
+        (_, "accept-language") => &["en-US", "en", "*"],
+        (_, "authorization") => &["Bearer ", "Basic "],
+        (_, "cache-control" | "pragma") => &["no-cache", "no-store", "max-age=0"],
+        (_, "connection") => &["keep-alive", "close"],
+        (_, "content-disposition") => &["inline", "attachment; filename="],
+        (_, "x-requested-with") => &["XMLHttpRequest"],
+        _ => &[],
+    };
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uris(uris: &[&str]) -> Vec<String> {
+        uris.iter().map(|uri| uri.to_string()).collect()
+    }
+
+    #[test]
+    fn suggests_matches() {
+        let candidates = uris(&[
+            "http://localhost/users",
+            "https://api.example.com/users",
+            "http://localhost/users",
+            "http://localhost/",
+            "https://localhost.test/",
+        ]);
+        // Those starting with what's typed first, repeats once
+        let completion = Completion::new(Target::Uri, "HTTP://LOCAL", candidates.clone()).unwrap();
+        assert_eq!(
+            completion.items,
+            ["http://localhost/users", "http://localhost/"]
+        );
+        let completion = Completion::new(Target::Uri, "users", candidates.clone()).unwrap();
+        assert_eq!(
+            completion.items,
+            ["http://localhost/users", "https://api.example.com/users"]
+        );
+        // Nothing to add to what's typed
+        assert!(Completion::new(
+            Target::Uri,
+            "http://localhost/",
+            uris(&["http://localhost/"])
+        )
+        .is_none());
+        assert!(Completion::new(Target::Uri, " ", candidates).is_none());
+    }
+
+    #[test]
+    fn shows_a_few() {
+        let candidates = (0..20).map(|n| format!("http://localhost/{n}"));
+        let mut completion = Completion::new(Target::Uri, "local", candidates).unwrap();
+        assert_eq!(completion.items.len(), MAX_ITEMS);
+        completion.move_selection(-1);
+        assert_eq!(completion.choice(), "http://localhost/0");
+        completion.move_selection(3);
+        assert_eq!(completion.choice(), "http://localhost/3");
+        completion.move_selection(100);
+        assert_eq!(completion.selected, MAX_ITEMS - 1);
+    }
+
+    #[test]
+    fn suggests_headers() {
+        let names = header_candidates(0, "");
+        let completion = Completion::new(Target::Header { row: 0, column: 0 }, "content-", names);
+        assert_eq!(
+            completion.unwrap().items,
+            [
+                "Content-Disposition",
+                "Content-Encoding",
+                "Content-Length",
+                "Content-Type"
+            ]
+        );
+        // Values go by the header's name, whatever its case
+        let values = header_candidates(1, "content-TYPE");
+        let completion = Completion::new(Target::Header { row: 0, column: 1 }, "json", values);
+        assert_eq!(completion.unwrap().items, ["application/json"]);
+        assert!(header_candidates(1, "X-Custom").is_empty());
+    }
+}
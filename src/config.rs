@@ -0,0 +1,380 @@
+use std::{collections::BTreeMap, fs, io, path::PathBuf, time::Duration};
+
+use reqwest::header::{HeaderName, HeaderValue};
+use serde::Deserialize;
+
+use crate::{
+    history,
+    keys::{KeyMap, Keys},
+    models::ClientCert,
+    theme::{self, THEMES},
+};
+
+pub struct Config {
+    pub timeout: Duration,
+    pub follow_redirects: bool,
+    pub max_redirects: usize,
+    pub retry: RetryPolicy,
+    pub keys: KeyMap,
+    /// Used unless the request options set their own
+    pub client_cert: Option<ClientCert>,
+    /// Index into `theme::THEMES`
+    pub theme: usize,
+    /// Sent with every request that doesn't set the same header
+    pub default_headers: Vec<(String, String)>,
+    /// Older history entries are dropped at startup, all are kept without it
+    pub history_size: Option<usize>,
+    /// Vim style modal editing in the text editors
+    pub vim: bool,
+    /// Percent of the width the request pane gets, the response pane has the
+    /// rest
+    pub split: u16,
+}
+
+pub const SPLIT_RANGE: std::ops::RangeInclusive<u16> = 20..=80;
+
+/// Most retries a request can make
+pub const MAX_RETRIES: u32 = 10;
+/// Longest wait between two attempts, the backoff stops doubling there
+pub const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// `config.toml`, every setting is optional
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    /// Seconds
+    timeout: Option<f64>,
+    follow_redirects: Option<bool>,
+    max_redirects: Option<usize>,
+    retries: Option<u32>,
+    /// Milliseconds
+    retry_backoff: Option<u64>,
+    retry_on: Option<Vec<u16>>,
+    theme: Option<String>,
+    default_headers: BTreeMap<String, String>,
+    history_size: Option<usize>,
+    /// Where history, collections and workspaces are kept, `~/` is expanded
+    data_dir: Option<PathBuf>,
+    /// Action names and their keys, e.g. `quit = "ctrl+q"`
+    keys: BTreeMap<String, Keys>,
+    vim: bool,
+    split: Option<u16>,
+}
+
+/// `$XDG_CONFIG_HOME/curl-rs`, falling back to `~/.config/curl-rs`
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("curl-rs"))
+}
+
+impl Config {
+    /// The config file over the defaults, with what was wrong in it. Settings
+    /// that don't validate are left at their default, a file that doesn't
+    /// parse is ignored altogether. Also points `history::data_dir` at the
+    /// configured directory, so it has to run before anything is loaded
+    pub fn load() -> (Self, Vec<String>) {
+        let config = Self::default();
+        let Some(path) = config_dir().map(|dir| dir.join("config.toml")) else {
+            return (config, Vec::new());
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (config, Vec::new()),
+            Err(err) => (
+                config,
+                vec![format!("can't read {}: {err}", path.display())],
+            ),
+        }
+    }
+
+    /// The settings of a config file, see `Config::load`
+    fn parse(text: &str) -> (Self, Vec<String>) {
+        let mut config = Self::default();
+        let file: ConfigFile = match toml::from_str(text) {
+            Ok(file) => file,
+            Err(err) => {
+                let line = err
+                    .span()
+                    .map(|span| text[..span.start].matches('\n').count() + 1);
+                let at = line.map(|line| format!(" line {line}")).unwrap_or_default();
+                let message = err.message().trim().replace('\n', ", ");
+                return (config, vec![format!("config.toml{at}: {message}")]);
+            }
+        };
+
+        let mut errors = Vec::new();
+        if let Some(secs) = file.timeout {
+            match Duration::try_from_secs_f64(secs) {
+                Ok(timeout) if !timeout.is_zero() => config.timeout = timeout,
+                _ => errors.push(format!("bad timeout `{secs}`, give a number of seconds")),
+            }
+        }
+        config.follow_redirects = file.follow_redirects.unwrap_or(config.follow_redirects);
+        config.max_redirects = file.max_redirects.unwrap_or(config.max_redirects);
+        match file.retries {
+            Some(retries) if retries > MAX_RETRIES => {
+                errors.push(format!("retries has to be at most {MAX_RETRIES}"))
+            }
+            retries => config.retry.retries = retries.unwrap_or(config.retry.retries),
+        }
+        match file.retry_backoff.map(Duration::from_millis) {
+            Some(backoff) if backoff > MAX_DELAY => errors.push(format!(
+                "retry_backoff has to be at most {} milliseconds",
+                MAX_DELAY.as_millis()
+            )),
+            Some(backoff) => config.retry.backoff = backoff,
+            None => {}
+        }
+        config.retry.retry_on = file.retry_on.unwrap_or(config.retry.retry_on);
+        match file.theme.as_deref().map(|name| (name, theme::find(name))) {
+            Some((_, Some(index))) => config.theme = index,
+            Some((name, None)) => {
+                let names: Vec<_> = THEMES.iter().map(|theme| theme.name).collect();
+                errors.push(format!(
+                    "unknown theme `{name}`, there's {}",
+                    names.join(", ")
+                ))
+            }
+            None => {}
+        }
+        for (name, value) in file.default_headers {
+            match (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(&value),
+            ) {
+                (Ok(_), Ok(_)) => config.default_headers.push((name, value)),
+                _ => errors.push(format!("bad default header `{name}`")),
+            }
+        }
+        match file.history_size {
+            Some(0) => errors.push("history_size has to be at least 1".to_string()),
+            size => config.history_size = size,
+        }
+        if let Some(dir) = file.data_dir {
+            let home = std::env::var_os("HOME").map(PathBuf::from);
+            let dir = match (dir.strip_prefix("~"), home) {
+                (Ok(rest), Some(home)) => home.join(rest),
+                _ => dir,
+            };
+            match dir.is_absolute() {
+                true => history::set_data_dir(dir),
+                false => errors.push(format!("data_dir `{}` has to be absolute", dir.display())),
+            }
+        }
+
+        config.vim = file.vim;
+        match file.split {
+            Some(split) if SPLIT_RANGE.contains(&split) => config.split = split,
+            Some(_) => errors.push(format!(
+                "split has to be from {} to {}",
+                SPLIT_RANGE.start(),
+                SPLIT_RANGE.end()
+            )),
+            None => {}
+        }
+
+        let (keys, key_errors) = KeyMap::new(file.keys);
+        config.keys = keys;
+        errors.extend(key_errors);
+
+        let errors = errors
+            .into_iter()
+            .map(|err| format!("config.toml: {err}"))
+            .collect();
+        (config, errors)
+    }
+}
+
+/// Writes `split` into config.toml, leaving the rest of it as it was
+pub fn save_split(split: u16) -> Result<(), String> {
+    let Some(dir) = config_dir() else {
+        return Ok(());
+    };
+    let path = dir.join("config.toml");
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(format!("can't read {}: {err}", path.display())),
+    };
+    fs::create_dir_all(&dir)
+        .and_then(|()| fs::write(&path, with_split(&text, split)))
+        .map_err(|err| format!("can't save the split to {}: {err}", path.display()))
+}
+
+/// The config text with its `split` setting replaced or added
+fn with_split(text: &str, split: u16) -> String {
+    let setting = format!("split = {split}");
+    let mut lines: Vec<&str> = text.lines().collect();
+    // Top level settings come before the first table
+    let tables = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with('['))
+        .unwrap_or(lines.len());
+    let existing = lines[..tables].iter().position(|line| {
+        line.split_once('=')
+            .is_some_and(|(key, _)| key.trim() == "split")
+    });
+    match existing {
+        Some(index) => lines[index] = &setting,
+        None => {
+            let end = lines[..tables]
+                .iter()
+                .rposition(|line| !line.trim().is_empty())
+                .map_or(0, |index| index + 1);
+            lines.insert(end, &setting)
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+#[derive(Clone)]
+pub struct RetryPolicy {
+    pub retries: u32,
+    pub backoff: Duration,
+    pub retry_on: Vec<u16>,
+}
+
+impl RetryPolicy {
+    /// How long to wait before `attempt`, the backoff doubling from the
+    /// second one on, up to `MAX_DELAY`
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.backoff
+            .checked_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .map_or(MAX_DELAY, |delay| delay.min(MAX_DELAY))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 0,
+            backoff: Duration::from_millis(500),
+            retry_on: vec![429, 502, 503, 504],
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            follow_redirects: true,
+            max_redirects: 10,
+            retry: RetryPolicy::default(),
+            keys: KeyMap::default(),
+            client_cert: None,
+            theme: 0,
+            default_headers: Vec::new(),
+            history_size: None,
+            vim: false,
+            split: 50,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_doubles() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(1), Duration::from_millis(500));
+        assert_eq!(policy.delay(2), Duration::from_secs(1));
+        assert_eq!(policy.delay(4), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn delay_is_capped() {
+        let policy = RetryPolicy {
+            backoff: MAX_DELAY,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.delay(8), MAX_DELAY);
+        assert_eq!(policy.delay(u32::MAX), MAX_DELAY);
+    }
+
+    #[test]
+    fn reads_settings() {
+        let (config, errors) = Config::parse(
+            r#"
+timeout = 2.5
+follow_redirects = false
+retries = 3
+theme = "light"
+history_size = 100
+split = 60
+
+[default_headers]
+User-Agent = "curl-rs"
+"#,
+        );
+        assert!(errors.is_empty());
+        assert_eq!(config.timeout, Duration::from_millis(2500));
+        assert!(!config.follow_redirects);
+        assert_eq!(config.retry.retries, 3);
+        assert_eq!(THEMES[config.theme].name, "light");
+        assert_eq!(config.history_size, Some(100));
+        assert_eq!(config.split, 60);
+        assert_eq!(
+            config.default_headers,
+            [("User-Agent".to_string(), "curl-rs".to_string())]
+        );
+    }
+
+    #[test]
+    fn reports_bad_settings() {
+        // Each is left at its default
+        let (config, errors) = Config::parse(
+            r#"
+timeout = 0
+retries = 11
+theme = "neon"
+history_size = 0
+data_dir = "relative"
+split = 90
+
+[default_headers]
+"bad header" = "x"
+"#,
+        );
+        assert_eq!(
+            errors,
+            [
+                "config.toml: bad timeout `0`, give a number of seconds",
+                "config.toml: retries has to be at most 10",
+                "config.toml: unknown theme `neon`, there's dark, light, solarized",
+                "config.toml: bad default header `bad header`",
+                "config.toml: history_size has to be at least 1",
+                "config.toml: data_dir `relative` has to be absolute",
+                "config.toml: split has to be from 20 to 80",
+            ]
+        );
+        assert_eq!(config.timeout, Config::default().timeout);
+        assert_eq!(config.split, 50);
+
+        let (_, errors) = Config::parse("timeout = 1\nunknown = true\n");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("config.toml line 2: unknown field `unknown`"));
+    }
+
+    #[test]
+    fn saves_split() {
+        assert_eq!(with_split("", 40), "split = 40\n");
+        assert_eq!(
+            with_split("timeout = 5\nsplit = 50 # wide\n", 65),
+            "timeout = 5\nsplit = 65\n"
+        );
+        // Before the first table, where top level settings go
+        assert_eq!(
+            with_split("vim = true\n\n[keys]\nsplit = \"s\"\n", 30),
+            "vim = true\nsplit = 30\n\n[keys]\nsplit = \"s\"\n"
+        );
+        let (config, errors) = Config::parse(&with_split("[keys]\n", 70));
+        assert!(errors.is_empty());
+        assert_eq!(config.split, 70);
+    }
+}
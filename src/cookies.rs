@@ -0,0 +1,102 @@
+/// A parsed `Set-Cookie` header
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    pub domain: Option<String>,
+    pub path: Option<String>,
+    /// `Expires` as sent, or `Max-Age` in seconds
+    pub expires: Option<String>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<String>,
+}
+
+impl Cookie {
+    pub fn parse(header: &str) -> Option<Self> {
+        let mut parts = header.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let mut cookie = Self {
+            name: name.trim().to_string(),
+            value: value.trim().trim_matches('"').to_string(),
+            domain: None,
+            path: None,
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        };
+        if cookie.name.is_empty() {
+            return None;
+        }
+
+        for attribute in parts {
+            let (key, value) = match attribute.split_once('=') {
+                Some((key, value)) => (key.trim(), Some(value.trim().to_string())),
+                None => (attribute.trim(), None),
+            };
+            match key.to_ascii_lowercase().as_str() {
+                "domain" => cookie.domain = value,
+                "path" => cookie.path = value,
+                // Max-Age wins over Expires
+                "max-age" => cookie.expires = value.map(|secs| format!("in {secs}s")),
+                "expires" if cookie.expires.is_none() => cookie.expires = value,
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "samesite" => cookie.same_site = value,
+                _ => {}
+            }
+        }
+        Some(cookie)
+    }
+
+    pub fn flags(&self) -> String {
+        let mut flags = Vec::new();
+        if self.secure {
+            flags.push("Secure".to_string());
+        }
+        if self.http_only {
+            flags.push("HttpOnly".to_string());
+        }
+        if let Some(same_site) = &self.same_site {
+            flags.push(format!("SameSite={same_site}"));
+        }
+        flags.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_attributes() {
+        let cookie = Cookie::parse(
+            "session=\"abc=123\"; Domain=example.com; Path=/; Expires=Wed, 21 Oct 2026 07:28:00 GMT; Secure; HttpOnly; SameSite=Lax",
+        )
+        .unwrap();
+        assert_eq!(cookie.name, "session");
+        assert_eq!(cookie.value, "abc=123");
+        assert_eq!(cookie.domain.as_deref(), Some("example.com"));
+        assert_eq!(cookie.path.as_deref(), Some("/"));
+        assert_eq!(
+            cookie.expires.as_deref(),
+            Some("Wed, 21 Oct 2026 07:28:00 GMT")
+        );
+        assert_eq!(cookie.flags(), "Secure HttpOnly SameSite=Lax");
+    }
+
+    #[test]
+    fn max_age_wins() {
+        let cookie =
+            Cookie::parse("id=1; max-age=60; expires=Thu, 01 Jan 1970 00:00:00 GMT").unwrap();
+        assert_eq!(cookie.expires.as_deref(), Some("in 60s"));
+        assert_eq!(cookie.flags(), "");
+    }
+
+    #[test]
+    fn needs_a_name() {
+        assert!(Cookie::parse("=value").is_none());
+        assert!(Cookie::parse("no value").is_none());
+        assert_eq!(Cookie::parse("empty=").unwrap().value, "");
+    }
+}
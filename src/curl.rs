@@ -0,0 +1,804 @@
+use reqwest::Url;
+use serde_json::{Map, Value};
+
+use crate::{
+    auth::{Auth, AuthMode},
+    config::Config,
+    models::{Body, BodyMode, KeyValueRow, Request, SavedRequest, Syntax},
+};
+
+/// Short options that take a value, the rest are flags that can be combined
+/// like `-sSL`
+const SHORT_VALUES: &str = "XHdFuAebmoxEwTrcDzyYCKPtUQ";
+
+/// Long options that take a value but have no counterpart in the editors
+const IGNORED_VALUES: &[&str] = &[
+    "output",
+    "proxy",
+    "proxy-user",
+    "cacert",
+    "capath",
+    "write-out",
+    "upload-file",
+    "range",
+    "cookie-jar",
+    "dump-header",
+    "resolve",
+    "connect-to",
+    "connect-timeout",
+    "interface",
+    "limit-rate",
+    "retry-delay",
+    "retry-max-time",
+    "config",
+];
+
+fn row(key: &str, value: &str) -> KeyValueRow {
+    KeyValueRow {
+        key: key.to_string(),
+        value: value.to_string(),
+        ..KeyValueRow::new()
+    }
+}
+
+/// Splits a command line into words the way a POSIX shell would, including
+/// `$'...'` strings and backslash line continuations
+fn words(command: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\\' => match chars.next() {
+                Some('\n') => {}
+                Some('\r') if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                }
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => {}
+            },
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated ' quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('\n') => {}
+                            Some(c @ ('$' | '`' | '"' | '\\')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated \" quote".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated \" quote".to_string()),
+                    }
+                }
+            }
+            '$' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some('\\') => word.push(ansi_escape(&mut chars)?),
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated $' quote".to_string()),
+                    }
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// The character after a backslash in a `$'...'` string, which browsers use
+/// to quote bodies
+fn ansi_escape(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<char, String> {
+    fn hex(chars: &mut std::iter::Peekable<std::str::Chars>, len: usize) -> Result<char, String> {
+        let digits: String = (0..len)
+            .map_while(|_| chars.next_if(char::is_ascii_hexdigit))
+            .collect();
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("bad escape `{digits}`"))
+    }
+    Ok(match chars.next() {
+        Some('n') => '\n',
+        Some('t') => '\t',
+        Some('r') => '\r',
+        Some('0') => '\0',
+        Some('e') | Some('E') => '\x1b',
+        Some('x') => hex(chars, 2)?,
+        Some('u') => hex(chars, 4)?,
+        Some('U') => hex(chars, 8)?,
+        Some(c) => c,
+        None => return Err("unterminated $' quote".to_string()),
+    })
+}
+
+/// `a=1&b=2` as decoded pairs
+fn pairs(query: &str) -> Vec<KeyValueRow> {
+    let Ok(url) = Url::parse(&format!("http://localhost/?{query}")) else {
+        return Vec::new();
+    };
+    url.query_pairs()
+        .map(|(key, value)| row(&key, &value))
+        .collect()
+}
+
+enum Data {
+    /// As typed, `@file` reads the file
+    Text(String),
+    Raw(String),
+    UrlEncode(String),
+}
+
+/// Fills a request from a `curl ...` command line, as copied from browser
+/// devtools. Also returns the options that change the request but were left
+/// out
+pub fn parse(command: &str) -> Result<(SavedRequest, Vec<String>), String> {
+    let mut words = words(command)?.into_iter();
+    if !words
+        .next()
+        .is_some_and(|curl| curl == "curl" || curl.ends_with("/curl"))
+    {
+        return Err("not a curl command".to_string());
+    }
+    from_args(words)
+}
+
+/// What follows `curl` in a command, e.g. the arguments curl-rs was started with
+pub fn from_args(
+    mut words: impl Iterator<Item = String>,
+) -> Result<(SavedRequest, Vec<String>), String> {
+    // Normalised to long names, with their values
+    let mut args: Vec<(String, Option<String>)> = Vec::new();
+    let mut ignored = Vec::new();
+    let mut uri = None;
+    while let Some(word) = words.next() {
+        if let Some(long) = word.strip_prefix("--") {
+            let value = match long {
+                "request" | "header" | "data" | "data-raw" | "data-binary" | "data-ascii"
+                | "data-urlencode" | "json" | "form" | "form-string" | "user" | "user-agent"
+                | "referer" | "cookie" | "url" | "max-time" | "max-redirs" | "retry" | "cert"
+                | "key" | "pass" | "oauth2-bearer" | "aws-sigv4" | "unix-socket" => words.next(),
+                long if IGNORED_VALUES.contains(&long) => {
+                    words.next();
+                    ignored.push(word.clone());
+                    continue;
+                }
+                _ => None,
+            };
+            args.push((long.to_string(), value));
+        } else if let Some(short) = word.strip_prefix('-').filter(|short| !short.is_empty()) {
+            for (index, flag) in short.char_indices() {
+                let long = match flag {
+                    'X' => "request",
+                    'H' => "header",
+                    'd' => "data",
+                    'F' => "form",
+                    'u' => "user",
+                    'A' => "user-agent",
+                    'e' => "referer",
+                    'b' => "cookie",
+                    'm' => "max-time",
+                    'E' => "cert",
+                    'G' => "get",
+                    'I' => "head",
+                    'L' => "location",
+                    'k' => "insecure",
+                    _ => "",
+                };
+                if !SHORT_VALUES.contains(flag) {
+                    if !long.is_empty() {
+                        args.push((long.to_string(), None));
+                    }
+                    continue;
+                }
+                // The rest of the word, or the next one
+                let rest = &short[index + flag.len_utf8()..];
+                let value = match rest.is_empty() {
+                    true => words.next(),
+                    false => Some(rest.to_string()),
+                };
+                match long {
+                    "" => ignored.push(format!("-{flag}")),
+                    long => args.push((long.to_string(), value)),
+                }
+                break;
+            }
+        } else {
+            uri = Some(word);
+        }
+    }
+
+    let mut request = SavedRequest::default();
+    let mut method = None;
+    let mut data = Vec::new();
+    let mut get = false;
+    let mut head = false;
+    let mut json = false;
+    let mut digest = false;
+    let mut aws = None;
+    let mut user = None;
+    let mut options = Map::new();
+    for (name, value) in args {
+        let value = value.unwrap_or_default();
+        match name.as_str() {
+            "request" => method = Some(value),
+            "header" => match value.split_once(':') {
+                Some((key, value)) => request.headers.push(row(key.trim(), value.trim())),
+                // `Name;` sends the header empty
+                None => request
+                    .headers
+                    .push(row(value.trim().trim_end_matches(';'), "")),
+            },
+            "data" | "data-ascii" | "data-binary" => data.push(Data::Text(value)),
+            "data-raw" => data.push(Data::Raw(value)),
+            "data-urlencode" => data.push(Data::UrlEncode(value)),
+            "json" => {
+                json = true;
+                data.push(Data::Text(value));
+            }
+            "form" | "form-string" => match value.split_once('=') {
+                Some((key, value)) => request.multipart.push(row(key, value)),
+                None => request.multipart.push(row(&value, "")),
+            },
+            "user" => user = Some(value),
+            "user-agent" => request.headers.push(row("User-Agent", &value)),
+            "referer" => request.headers.push(row("Referer", &value)),
+            // Without a `=` it names a cookie file
+            "cookie" if value.contains('=') => request.headers.push(row("Cookie", &value)),
+            "url" => uri = Some(value),
+            "get" => get = true,
+            "head" => head = true,
+            "digest" => digest = true,
+            "location" => {
+                options.insert("follow_redirects".to_string(), Value::Bool(true));
+            }
+            "compressed" => {
+                options.insert("compressed".to_string(), Value::Bool(true));
+            }
+            "max-time" | "max-redirs" | "retry" => {
+                let key = match name.as_str() {
+                    "max-time" => "timeout",
+                    "max-redirs" => "max_redirects",
+                    _ => "retries",
+                };
+                let number = value
+                    .parse::<f64>()
+                    .map_err(|_| format!("bad --{name} `{value}`"))?;
+                let number = match key {
+                    "timeout" => Value::from(number),
+                    _ => Value::from(number as u64),
+                };
+                options.insert(key.to_string(), number);
+            }
+            "cert" => {
+                // `file:password`, leaving alone the colon of Windows drive letters
+                let (cert, password) = match value.rsplit_once(':') {
+                    Some((cert, password)) if cert.len() > 1 => (cert, Some(password)),
+                    _ => (value.as_str(), None),
+                };
+                options.insert("client_cert".to_string(), Value::from(cert));
+                if let Some(password) = password {
+                    options.insert("client_cert_password".to_string(), Value::from(password));
+                }
+            }
+            "key" => {
+                options.insert("client_key".to_string(), Value::from(value));
+            }
+            "unix-socket" => {
+                options.insert("unix_socket".to_string(), Value::from(value));
+            }
+            "pass" => {
+                options.insert("client_cert_password".to_string(), Value::from(value));
+            }
+            "oauth2-bearer" => {
+                request.auth_mode = AuthMode::Bearer;
+                request.auth = vec![row("token", &value)];
+            }
+            "aws-sigv4" => aws = Some(value),
+            "http1.1" | "http2-prior-knowledge" | "http3" | "http3-only" => {
+                let version = match name.as_str() {
+                    "http1.1" => "1.1",
+                    "http2-prior-knowledge" => "2",
+                    _ => "3",
+                };
+                options.insert("http_version".to_string(), Value::from(version));
+            }
+            "insecure" => ignored.push(format!("--{name}")),
+            // Output and progress flags among others, which don't change what's sent
+            _ => {}
+        }
+    }
+
+    if let Some(user) = user {
+        let (username, password) = user.split_once(':').unwrap_or((&user, ""));
+        match aws {
+            // `aws:amz:region:service`, with the keys as the user
+            Some(provider) => {
+                let mut scope = provider.split(':').skip(2);
+                request.auth_mode = AuthMode::AwsSigV4;
+                request.auth = vec![
+                    row("access key", username),
+                    row("secret key", password),
+                    row("region", scope.next().unwrap_or_default()),
+                    row("service", scope.next().unwrap_or_default()),
+                ];
+            }
+            None => {
+                request.auth_mode = match digest {
+                    true => AuthMode::Digest,
+                    false => AuthMode::Basic,
+                };
+                request.auth = vec![row("username", username), row("password", password)];
+            }
+        }
+    }
+
+    let has_header = |request: &SavedRequest, name: &str| {
+        request
+            .headers
+            .iter()
+            .any(|header| header.key.eq_ignore_ascii_case(name))
+    };
+    if json {
+        for name in ["Content-Type", "Accept"] {
+            if !has_header(&request, name) {
+                request.headers.push(row(name, "application/json"));
+            }
+        }
+    }
+
+    let has_data = !data.is_empty();
+    // Decoded, the params and form editors encode them again
+    let rows = |data: Vec<Data>| -> Vec<KeyValueRow> {
+        data.into_iter()
+            .flat_map(|data| match data {
+                Data::Text(text) | Data::Raw(text) => pairs(&text),
+                Data::UrlEncode(text) => match text.split_once('=') {
+                    Some((key, value)) => vec![row(key, value)],
+                    None => vec![row(&text, "")],
+                },
+            })
+            .collect()
+    };
+    if get {
+        request.params = rows(data);
+    } else if data.iter().any(|data| matches!(data, Data::UrlEncode(_))) {
+        request.body_mode = BodyMode::Form;
+        request.form = rows(data);
+    } else if let [Data::Text(text)] = data.as_slice() {
+        match text.strip_prefix('@') {
+            Some(file) => {
+                request.body_mode = BodyMode::Binary;
+                request.file = file.to_string();
+            }
+            None => request.body = text.clone(),
+        }
+    } else {
+        let bodies: Vec<_> = data
+            .into_iter()
+            .map(|data| match data {
+                Data::Text(text) | Data::Raw(text) | Data::UrlEncode(text) => text,
+            })
+            .collect();
+        request.body = bodies.join("&");
+    }
+    if request.body_mode == BodyMode::Raw && !request.body.is_empty() {
+        request.syntax = match serde_json::from_str::<Value>(&request.body) {
+            Ok(_) => Syntax::Json,
+            Err(_) => Syntax::Text,
+        };
+        // What curl sends `-d` as, the editors would go by the syntax
+        if !json && !has_header(&request, "Content-Type") {
+            request
+                .headers
+                .push(row("Content-Type", "application/x-www-form-urlencoded"));
+        }
+    }
+    if !request.multipart.is_empty() {
+        request.body_mode = BodyMode::Multipart;
+    }
+
+    request.method = match (method, head, get) {
+        (Some(method), _, _) => method,
+        (None, true, _) => "HEAD".to_string(),
+        (None, false, true) => "GET".to_string(),
+        (None, false, false) if has_data || !request.multipart.is_empty() => "POST".to_string(),
+        (None, false, false) => "GET".to_string(),
+    };
+    let uri = uri.ok_or_else(|| "the curl command has no url".to_string())?;
+    request.uri = match uri.contains("://") || uri.starts_with("{{") {
+        true => uri,
+        // curl's default scheme
+        false => format!("http://{uri}"),
+    };
+    if !options.is_empty() {
+        request.options =
+            serde_json::to_string_pretty(&Value::Object(options)).map_err(|err| err.to_string())?;
+    }
+    Ok((request, ignored))
+}
+
+/// Single quoted for POSIX shells, unless it's plain enough not to need it
+fn quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c));
+    match plain {
+        true => word.to_string(),
+        false => format!("'{}'", word.replace('\'', r"'\''")),
+    }
+}
+
+/// A curl command sending the same request, one option per line. Also says
+/// what couldn't be expressed
+pub fn command(request: &Request) -> (String, Option<&'static str>) {
+    let mut args: Vec<Vec<String>> = Vec::new();
+    let mut arg = |parts: &[&str]| args.push(parts.iter().map(|part| quote(part)).collect());
+    let header = |name: &str, value: &str| format!("{name}: {value}");
+    let mut note = None;
+
+    let mut params = request.params.clone();
+    match &request.auth {
+        Auth::None => {}
+        Auth::Basic { username, password } => arg(&["-u", &format!("{username}:{password}")]),
+        Auth::Digest { username, password } => {
+            arg(&["--digest", "-u", &format!("{username}:{password}")])
+        }
+        Auth::Bearer { token } => {
+            arg(&["-H", &header("Authorization", &format!("Bearer {token}"))])
+        }
+        Auth::ApiKey {
+            key,
+            value,
+            in_query: true,
+        } => params.push((key.clone(), value.clone())),
+        Auth::ApiKey { key, value, .. } => arg(&["-H", &header(key, value)]),
+        Auth::OAuth2 { config, tokens } => match config.cached_token(tokens) {
+            Some(token) => arg(&["-H", &header("Authorization", &format!("Bearer {token}"))]),
+            None => note = Some("without oauth2, send once to get a token"),
+        },
+        Auth::AwsSigV4(credentials) => {
+            let provider = format!("aws:amz:{}:{}", credentials.region, credentials.service);
+            let user = format!("{}:{}", credentials.access_key, credentials.secret_key);
+            arg(&["--aws-sigv4", &provider, "-u", &user]);
+            if !credentials.session_token.is_empty() {
+                arg(&[
+                    "-H",
+                    &header("x-amz-security-token", &credentials.session_token),
+                ]);
+            }
+        }
+    }
+
+    for (name, value) in &request.headers {
+        arg(&["-H", &header(name, value)]);
+    }
+    let has_content_type = request
+        .headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("content-type"));
+    if let (Some(content_type), false) = (request.content_type, has_content_type) {
+        arg(&["-H", &header("Content-Type", content_type)]);
+    }
+    match &request.body {
+        Some(Body::Raw(text)) => arg(&["--data-raw", text]),
+        Some(Body::Form(pairs)) => {
+            for (key, value) in pairs {
+                arg(&["--data-urlencode", &format!("{key}={value}")]);
+            }
+        }
+        Some(Body::Multipart(pairs)) => {
+            for (key, value) in pairs {
+                match value.starts_with('@') {
+                    true => arg(&["-F", &format!("{key}={value}")]),
+                    false => arg(&["--form-string", &format!("{key}={value}")]),
+                }
+            }
+        }
+        Some(Body::File(path)) => {
+            // curl would otherwise label it as a form
+            if !has_content_type {
+                arg(&["-H", "Content-Type:"]);
+            }
+            arg(&["--data-binary", &format!("@{}", path.display())]);
+        }
+        None => {}
+    }
+
+    if request.follow_redirects {
+        arg(&["-L", "--max-redirs", &request.max_redirects.to_string()]);
+    }
+    if request.timeout != Config::default().timeout {
+        arg(&["-m", &request.timeout.as_secs_f64().to_string()]);
+    }
+    if request.retry.retries > 0 {
+        arg(&["--retry", &request.retry.retries.to_string()]);
+    }
+    if request.compressed {
+        arg(&["--compressed"]);
+    }
+    if let Some(version) = request.http_version {
+        arg(&[version.flag()]);
+    }
+    if let Some(path) = &request.unix_socket {
+        arg(&["--unix-socket", &path.display().to_string()]);
+    }
+
+    let uri = match Url::parse(&request.uri) {
+        Ok(mut url) if !params.is_empty() => {
+            url.query_pairs_mut().extend_pairs(&params);
+            url.to_string()
+        }
+        _ => request.uri.clone(),
+    };
+    let has_body = request.body.is_some();
+    let mut first = vec!["curl".to_string()];
+    match request.method.as_str() {
+        "GET" if !has_body => {}
+        "POST" if has_body => {}
+        "HEAD" if !has_body => first.push("--head".to_string()),
+        method => first.extend(["-X".to_string(), quote(method)]),
+    }
+    first.push(quote(&uri));
+
+    let lines: Vec<String> = [first]
+        .into_iter()
+        .chain(args)
+        .map(|parts| parts.join(" "))
+        .collect();
+    (lines.join(" \\\n  "), note)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::pairs;
+
+    #[test]
+    fn devtools_command() {
+        let command = r#"curl 'https://api.example.com/users?page=2' \
+  -H 'accept: application/json' \
+  -H $'x-note: it\'s\tfine' \
+  -H 'Empty;' \
+  --data-raw '{"name":"a b"}' \
+  --compressed"#;
+        let (request, ignored) = parse(command).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.uri, "https://api.example.com/users?page=2");
+        assert_eq!(
+            pairs(&request.headers),
+            [
+                ("accept", "application/json"),
+                ("x-note", "it's\tfine"),
+                ("Empty", ""),
+                ("Content-Type", "application/x-www-form-urlencoded"),
+            ]
+        );
+        assert_eq!(request.body, r#"{"name":"a b"}"#);
+        assert_eq!(request.syntax, Syntax::Json);
+        assert!(request.options.contains(r#""compressed": true"#));
+        assert!(ignored.is_empty());
+    }
+
+    #[test]
+    fn combined_short_flags() {
+        let args = ["-sSLXPUT", "-uuser:pa:ss", "example.com", "-ocut.txt", "-k"];
+        let (request, ignored) = from_args(args.into_iter().map(String::from)).unwrap();
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.uri, "http://example.com");
+        assert_eq!(request.auth_mode, AuthMode::Basic);
+        assert_eq!(
+            pairs(&request.auth),
+            [("username", "user"), ("password", "pa:ss")]
+        );
+        assert!(request.options.contains(r#""follow_redirects": true"#));
+        assert_eq!(ignored, ["-o", "--insecure"]);
+    }
+
+    #[test]
+    fn bodies() {
+        let from = |command: &str| parse(command).unwrap().0;
+
+        let request = from("curl -G example.com -d a=1 -d 'b=x%20y'");
+        assert_eq!(request.method, "GET");
+        assert_eq!(pairs(&request.params), [("a", "1"), ("b", "x y")]);
+
+        let request = from("curl example.com --data-urlencode 'q=a&b' -d c=1");
+        assert_eq!(request.body_mode, BodyMode::Form);
+        assert_eq!(pairs(&request.form), [("q", "a&b"), ("c", "1")]);
+
+        let request = from("curl example.com -F name=value -F file=@photo.png");
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.body_mode, BodyMode::Multipart);
+        assert_eq!(
+            pairs(&request.multipart),
+            [("name", "value"), ("file", "@photo.png")]
+        );
+
+        let request = from("curl example.com --data-binary @body.bin");
+        assert_eq!(request.body_mode, BodyMode::Binary);
+        assert_eq!(request.file, "body.bin");
+
+        let request = from(r#"curl example.com --json '{"a":1}' -H 'Accept: */*'"#);
+        assert_eq!(
+            pairs(&request.headers),
+            [("Accept", "*/*"), ("Content-Type", "application/json")]
+        );
+    }
+
+    #[test]
+    fn auth_and_options() {
+        let (request, _) = parse(
+            "curl --aws-sigv4 aws:amz:eu-west-1:s3 --user AKID:secret https://s3.amazonaws.com",
+        )
+        .unwrap();
+        assert_eq!(request.auth_mode, AuthMode::AwsSigV4);
+        assert_eq!(
+            pairs(&request.auth),
+            [
+                ("access key", "AKID"),
+                ("secret key", "secret"),
+                ("region", "eu-west-1"),
+                ("service", "s3"),
+            ]
+        );
+
+        let (request, _) = parse(
+            "curl --digest -u u:p -m 2.5 --max-redirs 3 --retry 2 -E 'C:\\cert.pem' --http1.1 --unix-socket /tmp/s http://localhost/",
+        )
+        .unwrap();
+        assert_eq!(request.auth_mode, AuthMode::Digest);
+        let options: Value = serde_json::from_str(&request.options).unwrap();
+        assert_eq!(
+            options,
+            serde_json::json!({
+                "timeout": 2.5,
+                "max_redirects": 3,
+                "retries": 2,
+                "client_cert": "C:\\cert.pem",
+                "http_version": "1.1",
+                "unix_socket": "/tmp/s",
+            })
+        );
+    }
+
+    #[test]
+    fn http_versions() {
+        for (flag, version) in [
+            ("--http1.1", "1.1"),
+            ("--http2-prior-knowledge", "2"),
+            ("--http3", "3"),
+            ("--http3-only", "3"),
+        ] {
+            let (request, _) = parse(&format!("curl {flag} http://localhost/")).unwrap();
+            let options: Value = serde_json::from_str(&request.options).unwrap();
+            assert_eq!(options, serde_json::json!({ "http_version": version }));
+        }
+    }
+
+    #[test]
+    fn unix_sockets() {
+        let (saved, _) =
+            parse("curl --unix-socket /run/docker.sock http://localhost/info").unwrap();
+        let options: Value = serde_json::from_str(&saved.options).unwrap();
+        assert_eq!(
+            options,
+            serde_json::json!({ "unix_socket": "/run/docker.sock" })
+        );
+        let mut editors = crate::models::Editors::new();
+        editors.load(&saved);
+        let request = Request::new(&editors, &Config::default()).unwrap();
+        assert!(super::command(&request)
+            .0
+            .contains(" \\\n  --unix-socket /run/docker.sock"));
+    }
+
+    #[test]
+    fn rejects() {
+        assert!(parse("wget example.com").is_err());
+        assert!(parse("curl -H 'unterminated").is_err());
+        assert!(parse("curl -X POST").is_err());
+        assert!(parse("curl -m soon example.com").is_err());
+    }
+
+    #[test]
+    fn exports_commands() {
+        let export = |command: &str| {
+            let (saved, _) = parse(command).unwrap();
+            let mut editors = crate::models::Editors::new();
+            editors.load(&saved);
+            if let Some(fields) = editors.auth.fields_mut() {
+                // Secret fields aren't loaded, as when importing
+                saved
+                    .auth
+                    .iter()
+                    .for_each(|row| fields.set_value(&row.key, &row.value));
+            }
+            let request = Request::new(&editors, &Config::default()).unwrap();
+            super::command(&request)
+        };
+
+        let (command, note) = export("curl example.com/a?b=1 -H 'X-Note: it'\\''s'");
+        // Following redirects and decompressing are on by default here
+        assert_eq!(
+            command,
+            [
+                "curl 'http://example.com/a?b=1'",
+                "-H 'X-Note: it'\\''s'",
+                "-L --max-redirs 10",
+                "--compressed",
+            ]
+            .join(" \\\n  ")
+        );
+        assert!(note.is_none());
+
+        let (command, _) = export("curl -X PUT example.com -d a=1 -u user:pass -L -m 2");
+        assert_eq!(
+            command,
+            [
+                "curl -X PUT http://example.com",
+                "-u user:pass",
+                "-H 'Content-Type: application/x-www-form-urlencoded'",
+                "--data-raw a=1",
+                "-L --max-redirs 10",
+                "-m 2",
+                "--compressed",
+            ]
+            .join(" \\\n  ")
+        );
+
+        // What goes out comes back in
+        let (command, _) = export("curl --head localhost --http2-prior-knowledge -k");
+        assert_eq!(export(&command).0, command);
+        assert!(command.starts_with("curl --head http://localhost"));
+    }
+
+    #[test]
+    fn launch_args() {
+        // Already split by the shell, so nothing is unquoted again
+        let args = [
+            "example.com/users",
+            "-X",
+            "POST",
+            "-H",
+            "X: 'y'",
+            "-d",
+            "{}",
+        ];
+        let (request, ignored) = from_args(args.into_iter().map(String::from)).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.uri, "http://example.com/users");
+        assert_eq!(
+            pairs(&request.headers),
+            [
+                ("X", "'y'"),
+                ("Content-Type", "application/x-www-form-urlencoded")
+            ]
+        );
+        assert_eq!(request.body, "{}");
+        assert!(ignored.is_empty());
+
+        assert!(from_args(["-X".to_string()].into_iter()).is_err());
+    }
+}
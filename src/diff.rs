@@ -0,0 +1,161 @@
+use serde_json::Value;
+
+/// Larger bodies would need too much memory for the line table
+const MAX_CELLS: usize = 4_000_000;
+
+/// Diffs JSON bodies by structure and anything else line by line, lines are
+/// prefixed with `+ `, `- ` or two spaces
+pub fn diff(old: &str, new: &str) -> String {
+    let lines = match (
+        serde_json::from_str::<Value>(old),
+        serde_json::from_str::<Value>(new),
+    ) {
+        (Ok(old), Ok(new)) => {
+            let mut lines = Vec::new();
+            json("$", &old, &new, &mut lines);
+            lines
+        }
+        _ => text(old, new),
+    };
+
+    match lines.iter().all(|line| line.starts_with("  ")) {
+        true => "  no differences".to_string(),
+        false => lines.join("\n"),
+    }
+}
+
+/// Longest common subsequence of the lines between a shared prefix and suffix
+fn text(old: &str, new: &str) -> Vec<String> {
+    let old: Vec<_> = old.lines().collect();
+    let new: Vec<_> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut lines: Vec<_> = old[..prefix]
+        .iter()
+        .map(|line| format!("  {line}"))
+        .collect();
+    if (a.len() + 1) * (b.len() + 1) > MAX_CELLS {
+        lines.extend(a.iter().map(|line| format!("- {line}")));
+        lines.extend(b.iter().map(|line| format!("+ {line}")));
+    } else {
+        // table[i][j] is the LCS length of a[i..] and b[j..]
+        let width = b.len() + 1;
+        let mut table = vec![0u32; (a.len() + 1) * width];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                table[i * width + j] = if a[i] == b[j] {
+                    table[(i + 1) * width + j + 1] + 1
+                } else {
+                    table[(i + 1) * width + j].max(table[i * width + j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                lines.push(format!("  {}", a[i]));
+                i += 1;
+                j += 1;
+            } else if j < b.len()
+                && (i == a.len() || table[i * width + j + 1] >= table[(i + 1) * width + j])
+            {
+                lines.push(format!("+ {}", b[j]));
+                j += 1;
+            } else {
+                lines.push(format!("- {}", a[i]));
+                i += 1;
+            }
+        }
+    }
+    lines.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| format!("  {line}")),
+    );
+    lines
+}
+
+/// Only the paths that changed, with their old and new values
+fn json(path: &str, old: &Value, new: &Value, lines: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, value) in old {
+                let path = key_path(path, key);
+                match new.get(key) {
+                    Some(other) => json(&path, value, other, lines),
+                    None => lines.push(format!("- {path}: {value}")),
+                }
+            }
+            for (key, value) in new.iter().filter(|(key, _)| !old.contains_key(*key)) {
+                lines.push(format!("+ {}: {value}", key_path(path, key)));
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for index in 0..old.len().max(new.len()) {
+                let path = format!("{path}[{index}]");
+                match (old.get(index), new.get(index)) {
+                    (Some(value), Some(other)) => json(&path, value, other, lines),
+                    (Some(value), None) => lines.push(format!("- {path}: {value}")),
+                    (None, Some(other)) => lines.push(format!("+ {path}: {other}")),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ if old == new => {}
+        _ => {
+            lines.push(format!("- {path}: {old}"));
+            lines.push(format!("+ {path}: {new}"));
+        }
+    }
+}
+
+fn key_path(path: &str, key: &str) -> String {
+    let plain = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    match plain {
+        true => format!("{path}.{key}"),
+        false => format!("{path}[{}]", Value::from(key)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_json_by_path() {
+        let old = r#"{"id": 1, "tags": ["a", "b"], "user name": "x", "gone": true}"#;
+        let new = r#"{"id": 2, "tags": ["a"], "user name": "y", "added": null}"#;
+        assert_eq!(
+            diff(old, new),
+            "- $.id: 1\n\
+             + $.id: 2\n\
+             - $.tags[1]: \"b\"\n\
+             - $[\"user name\"]: \"x\"\n\
+             + $[\"user name\"]: \"y\"\n\
+             - $.gone: true\n\
+             + $.added: null"
+        );
+        assert_eq!(diff(old, old), "  no differences");
+    }
+
+    #[test]
+    fn diffs_text_by_line() {
+        assert_eq!(diff("a\nb\nc\nd", "a\nc\nx\nd"), "  a\n- b\n  c\n+ x\n  d");
+        assert_eq!(diff("same", "same"), "  no differences");
+    }
+}
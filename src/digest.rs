@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use md5::Md5;
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::{header::HeaderValue, Method, Url};
+use sha2::{Digest as _, Sha256, Sha512_256};
+
+use crate::{models::RequestError, utils::hex};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Algorithm {
+    Md5,
+    Sha256,
+    Sha512_256,
+}
+
+impl Algorithm {
+    fn parse(name: &str) -> Option<(Self, bool)> {
+        let upper = name.to_ascii_uppercase();
+        let (base, session) = match upper.strip_suffix("-SESS") {
+            Some(base) => (base, true),
+            None => (upper.as_str(), false),
+        };
+
+        let algorithm = match base {
+            "MD5" => Self::Md5,
+            "SHA-256" => Self::Sha256,
+            "SHA-512-256" => Self::Sha512_256,
+            _ => return None,
+        };
+        Some((algorithm, session))
+    }
+
+    fn hash(&self, data: &str) -> String {
+        match self {
+            Self::Md5 => hex(&Md5::digest(data)),
+            Self::Sha256 => hex(&Sha256::digest(data)),
+            Self::Sha512_256 => hex(&Sha512_256::digest(data)),
+        }
+    }
+}
+
+/// A parsed `WWW-Authenticate: Digest ...` challenge
+pub struct Challenge {
+    params: HashMap<String, String>,
+}
+
+impl Challenge {
+    pub fn parse(header: &str) -> Option<Self> {
+        let (scheme, rest) = header.trim().split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("digest") {
+            return None;
+        }
+
+        let mut params = HashMap::new();
+        let mut chars = rest.chars().peekable();
+        loop {
+            while chars.peek().is_some_and(|c| *c == ',' || c.is_whitespace()) {
+                chars.next();
+            }
+
+            let key: String = chars.by_ref().take_while(|c| *c != '=').collect();
+            if key.is_empty() {
+                break;
+            }
+
+            let value = if chars.peek() == Some(&'"') {
+                chars.next();
+                let mut value = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => value.extend(chars.next()),
+                        '"' => break,
+                        c => value.push(c),
+                    }
+                }
+                value
+            } else {
+                chars.by_ref().take_while(|c| *c != ',').collect()
+            };
+            params.insert(key.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+
+        params.contains_key("nonce").then_some(Self { params })
+    }
+
+    fn param(&self, key: &str) -> &str {
+        self.params.get(key).map_or("", String::as_str)
+    }
+
+    /// Builds the Authorization header answering this challenge, only the
+    /// `auth` quality of protection is supported
+    pub fn respond(
+        &self,
+        username: &str,
+        password: &str,
+        method: &Method,
+        url: &Url,
+    ) -> Result<HeaderValue, RequestError> {
+        let cnonce: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(16)
+            .map(char::from)
+            .collect();
+        let header = self.answer(username, password, method, url, &cnonce)?;
+        let mut value = HeaderValue::from_str(&header)
+            .map_err(|_| RequestError::InvalidAuth("bad digest credentials".to_string()))?;
+        value.set_sensitive(true);
+        Ok(value)
+    }
+
+    /// `respond` with the client nonce given
+    fn answer(
+        &self,
+        username: &str,
+        password: &str,
+        method: &Method,
+        url: &Url,
+        cnonce: &str,
+    ) -> Result<String, RequestError> {
+        let algorithm_name = match self.param("algorithm") {
+            "" => "MD5",
+            name => name,
+        };
+        let (algorithm, session) = Algorithm::parse(algorithm_name).ok_or_else(|| {
+            RequestError::InvalidAuth(format!("unsupported digest algorithm `{algorithm_name}`"))
+        })?;
+
+        let qop = self.param("qop");
+        let qop_auth = qop.split(',').any(|qop| qop.trim() == "auth");
+        if !qop.is_empty() && !qop_auth {
+            return Err(RequestError::InvalidAuth(format!(
+                "unsupported digest qop `{qop}`"
+            )));
+        }
+
+        let realm = self.param("realm");
+        let nonce = self.param("nonce");
+        let uri = match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_string(),
+        };
+        let nc = "00000001";
+
+        let mut ha1 = algorithm.hash(&format!("{username}:{realm}:{password}"));
+        if session {
+            ha1 = algorithm.hash(&format!("{ha1}:{nonce}:{cnonce}"));
+        }
+        let ha2 = algorithm.hash(&format!("{method}:{uri}"));
+        let response = if qop_auth {
+            algorithm.hash(&format!("{ha1}:{nonce}:{nc}:{cnonce}:auth:{ha2}"))
+        } else {
+            algorithm.hash(&format!("{ha1}:{nonce}:{ha2}"))
+        };
+
+        let quote = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut header = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={algorithm_name}, response=\"{response}\"",
+            quote(username),
+            quote(realm),
+            quote(nonce),
+            quote(&uri),
+        );
+        if qop_auth {
+            header.push_str(&format!(", qop=auth, nc={nc}, cnonce=\"{cnonce}\""));
+        }
+        if !self.param("opaque").is_empty() {
+            header.push_str(&format!(", opaque=\"{}\"", quote(self.param("opaque"))));
+        }
+        Ok(header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_challenge() {
+        let challenge = Challenge::parse(
+            r#"Digest realm="a \"quoted\" realm", qop="auth,auth-int" , nonce=abc,Opaque="x""#,
+        )
+        .unwrap();
+        assert_eq!(challenge.param("realm"), r#"a "quoted" realm"#);
+        assert_eq!(challenge.param("qop"), "auth,auth-int");
+        assert_eq!(challenge.param("nonce"), "abc");
+        assert_eq!(challenge.param("opaque"), "x");
+        assert!(Challenge::parse(r#"Basic realm="x""#).is_none());
+        assert!(Challenge::parse(r#"Digest realm="x""#).is_none());
+    }
+
+    #[test]
+    fn rfc_2617_example() {
+        let challenge = Challenge::parse(
+            r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#,
+        )
+        .unwrap();
+        let url = Url::parse("http://www.nowhere.org/dir/index.html").unwrap();
+        let header = challenge
+            .answer("Mufasa", "Circle Of Life", &Method::GET, &url, "0a4f113b")
+            .unwrap();
+        assert!(header.contains(r#"response="6629fae49393a05397450978507c4ef1""#));
+        assert!(header.contains(r#", qop=auth, nc=00000001, cnonce="0a4f113b""#));
+        assert!(header.ends_with(r#"opaque="5ccc069c403ebaf9f0171e9517f40e41""#));
+    }
+
+    #[test]
+    fn rfc_7616_example() {
+        let url = Url::parse("http://www.example.org/dir/index.html").unwrap();
+        let cnonce = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+        for (algorithm, response) in [
+            ("MD5", "8ca523f5e9506fed4657c9700eebdbec"),
+            (
+                "SHA-256",
+                "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1",
+            ),
+        ] {
+            let challenge = Challenge::parse(&format!(
+                r#"Digest realm="http-auth@example.org", qop="auth, auth-int", algorithm={algorithm}, nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#
+            ))
+            .unwrap();
+            let header = challenge
+                .answer("Mufasa", "Circle of Life", &Method::GET, &url, cnonce)
+                .unwrap();
+            assert!(
+                header.contains(&format!(r#"response="{response}""#)),
+                "{header}"
+            );
+        }
+    }
+
+    #[test]
+    fn unsupported_challenges() {
+        let url = Url::parse("http://example.com/").unwrap();
+        for header in [
+            r#"Digest nonce="n", algorithm=SHA-1"#,
+            r#"Digest nonce="n", qop="auth-int""#,
+        ] {
+            let err = Challenge::parse(header)
+                .unwrap()
+                .respond("user", "pass", &Method::GET, &url)
+                .unwrap_err();
+            assert!(matches!(err, RequestError::InvalidAuth(_)));
+        }
+    }
+}
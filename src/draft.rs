@@ -0,0 +1,90 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::{history::data_dir, models::SavedRequest};
+
+/// How often the editors are checked for changes worth writing out
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The editors as they were last autosaved, kept across quitting so an
+/// accidental Alt+q or a crash can be recovered from
+#[derive(Serialize, Deserialize)]
+pub struct Draft {
+    /// RFC 3339, local time
+    pub time: String,
+    pub workspace: String,
+    pub saved_path: Option<String>,
+    pub request: SavedRequest,
+}
+
+impl Draft {
+    pub fn new(workspace: &str, saved_path: Option<&str>, request: SavedRequest) -> Self {
+        Self {
+            time: Local::now().to_rfc3339(),
+            workspace: workspace.to_string(),
+            saved_path: saved_path.map(str::to_string),
+            request,
+        }
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("draft.json"))
+}
+
+pub fn load() -> Option<Draft> {
+    serde_json::from_str(&fs::read_to_string(path()?).ok()?).ok()
+}
+
+pub fn discard() {
+    if let Some(path) = path() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Written next to the draft and renamed over it, so a crash halfway through
+/// leaves the previous draft intact
+pub fn save(draft: &Draft) -> Result<(), String> {
+    let Some(path) = path() else {
+        return Ok(());
+    };
+    let temp = path.with_extension("json.tmp");
+    let json = serde_json::to_string_pretty(draft).map_err(|err| err.to_string())?;
+    path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| fs::write(&temp, json))
+        .and_then(|()| fs::rename(&temp, &path))
+        .map_err(|err| format!("can't autosave: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_and_restores() {
+        let dir = std::env::temp_dir().join(format!("curl-rs-tests-{}", std::process::id()));
+        crate::history::set_data_dir(dir);
+        let request = SavedRequest {
+            method: "PUT".to_string(),
+            uri: "http://api/items/1".to_string(),
+            ..SavedRequest::default()
+        };
+        save(&Draft::new("team", Some("api/update"), request)).unwrap();
+        let draft = load().unwrap();
+        assert_eq!(draft.workspace, "team");
+        assert_eq!(draft.saved_path.as_deref(), Some("api/update"));
+        assert_eq!(draft.request.method, "PUT");
+        assert_eq!(draft.request.uri, "http://api/items/1");
+
+        // Replaced, with nothing left over from writing it
+        save(&Draft::new("team", None, SavedRequest::default())).unwrap();
+        assert!(load().unwrap().request.uri.is_empty());
+        assert!(!path().unwrap().with_extension("json.tmp").exists());
+
+        discard();
+        assert!(load().is_none());
+    }
+}
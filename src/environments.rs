@@ -0,0 +1,538 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::PathBuf,
+};
+
+use chrono::{SecondsFormat, Utc};
+use keyring::Entry;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{models::RequestError, utils::hex, workspaces};
+
+pub type Variables = BTreeMap<String, String>;
+
+/// Shown instead of secret values
+pub const MASK: &str = "••••••";
+
+const KEYRING_SERVICE: &str = "curl-rs";
+
+/// A named set of variables, kept as a TOML file in the workspace's
+/// environments directory. Secret ones are only named in the file, their
+/// values live in the OS keyring
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Environment {
+    #[serde(skip)]
+    pub name: String,
+    pub variables: Variables,
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub secrets: BTreeSet<String>,
+    /// `.env` file whose variables are used unless defined here, relative to
+    /// the directory curl-rs was started in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env_file: Option<String>,
+    /// Falls back on the variables curl-rs was started with
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub process_env: bool,
+    /// What `env_file` and `process_env` held when last read
+    #[serde(skip)]
+    pub sourced: Variables,
+    #[serde(skip)]
+    pub source_error: Option<String>,
+}
+
+impl Environment {
+    /// Later sources are overridden by earlier ones: own variables, then the
+    /// `.env` file, then the process environment
+    fn read_sources(&mut self) {
+        self.sourced = match self.process_env {
+            true => std::env::vars().collect(),
+            false => Variables::new(),
+        };
+        self.source_error = None;
+        let Some(path) = &self.env_file else {
+            return;
+        };
+        let read = dotenvy::from_path_iter(path)
+            .and_then(|iter| iter.collect::<Result<Vec<_>, _>>())
+            .map_err(|err| format!("can't read {path}: {err}"));
+        match read {
+            Ok(pairs) => self.sourced.extend(pairs),
+            Err(err) => self.source_error = Some(err),
+        }
+    }
+
+    pub fn all_variables(&self) -> Variables {
+        let mut variables = self.sourced.clone();
+        variables.extend(self.variables.clone());
+        variables
+    }
+}
+
+#[derive(Default)]
+pub struct Environments {
+    workspace: String,
+    dir: Option<PathBuf>,
+    /// Sorted by name
+    pub list: Vec<Environment>,
+    /// Whose variables fill in the requests, remembered per workspace
+    pub active: Option<String>,
+}
+
+impl Environments {
+    pub fn load(workspace: &str) -> Self {
+        let workspace_dir = workspaces::dir(workspace);
+        let dir = workspace_dir.as_ref().map(|dir| dir.join("environments"));
+        let mut list: Vec<Environment> = dir
+            .as_ref()
+            .and_then(|dir| fs::read_dir(dir).ok())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "toml" {
+                    return None;
+                }
+                let mut environment: Environment =
+                    toml::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+                environment.name = path.file_stem()?.to_string_lossy().into_owned();
+                Some(environment)
+            })
+            .collect();
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let active = workspace_dir
+            .and_then(|dir| fs::read_to_string(dir.join("environment")).ok())
+            .map(|name| name.trim().to_string())
+            .filter(|name| list.iter().any(|env| env.name == *name));
+        let mut environments = Self {
+            workspace: workspace.to_string(),
+            dir,
+            list,
+            active,
+        };
+        environments.load_secrets();
+        environments
+            .list
+            .iter_mut()
+            .for_each(Environment::read_sources);
+        environments
+    }
+
+    /// Missing keyring entries are left out, so they show up as unknown
+    /// variables when used
+    fn load_secrets(&mut self) {
+        for index in 0..self.list.len() {
+            let environment = &self.list[index];
+            let values: Vec<_> = environment
+                .secrets
+                .iter()
+                .filter_map(|key| {
+                    let value = self.keyring_entry(&environment.name, key).ok()?;
+                    Some((key.clone(), value.get_password().ok()?))
+                })
+                .collect();
+            self.list[index].variables.extend(values);
+        }
+    }
+
+    fn keyring_entry(&self, environment: &str, key: &str) -> keyring::Result<Entry> {
+        Entry::new(
+            KEYRING_SERVICE,
+            &format!("{}/{environment}/{key}", self.workspace),
+        )
+    }
+
+    pub fn active(&self) -> Option<&Environment> {
+        let active = self.active.as_ref()?;
+        self.list.iter().find(|env| env.name == *active)
+    }
+
+    /// Empty without an active environment
+    pub fn variables(&self) -> Variables {
+        self.active()
+            .map(Environment::all_variables)
+            .unwrap_or_default()
+    }
+
+    /// The variables with the secret values masked, for showing
+    pub fn masked_variables(&self) -> Variables {
+        let Some(environment) = self.active() else {
+            return Variables::new();
+        };
+        let mut variables = environment.all_variables();
+        for key in &environment.secrets {
+            if let Some(value) = variables.get_mut(key) {
+                *value = MASK.to_string();
+            }
+        }
+        variables
+    }
+
+    /// `None` stops filling in variables, the sources are read again so edits
+    /// to the `.env` file are picked up
+    pub fn activate(&mut self, name: Option<String>) -> Result<String, String> {
+        self.active = name;
+        if let Some(environment) = self
+            .list
+            .iter_mut()
+            .find(|env| Some(&env.name) == self.active.as_ref())
+        {
+            environment.read_sources();
+            if let Some(err) = &environment.source_error {
+                return Err(err.clone());
+            }
+        }
+        let notice = match &self.active {
+            Some(name) => format!("using environment {name}"),
+            None => "not using an environment".to_string(),
+        };
+        let Some(file) = self.dir.as_ref().and_then(|dir| dir.parent()) else {
+            return Ok(notice);
+        };
+        let file = file.join("environment");
+        let result = match &self.active {
+            Some(name) => fs::write(file, format!("{name}\n")),
+            None => fs::remove_file(file).or_else(|err| match err.kind() {
+                std::io::ErrorKind::NotFound => Ok(()),
+                _ => Err(err),
+            }),
+        };
+        result
+            .map(|()| notice)
+            .map_err(|err| format!("can't switch environment: {err}"))
+    }
+
+    /// Creates the environment or replaces its variables, the secret ones go to
+    /// the keyring and are kept in memory only if that fails
+    pub fn save(
+        &mut self,
+        name: &str,
+        variables: Variables,
+        secrets: BTreeSet<String>,
+    ) -> Result<String, String> {
+        let name = name.trim();
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return Err(format!("bad environment name `{name}`"));
+        }
+
+        let index = match self
+            .list
+            .binary_search_by(|env| env.name.as_str().cmp(name))
+        {
+            Ok(index) => index,
+            Err(index) => {
+                let environment = Environment {
+                    name: name.to_string(),
+                    ..Environment::default()
+                };
+                self.list.insert(index, environment);
+                index
+            }
+        };
+        let previous = std::mem::take(&mut self.list[index].secrets);
+        let mut failed = None;
+        for key in previous.difference(&secrets) {
+            if let Ok(entry) = self.keyring_entry(name, key) {
+                // No entry is fine, it may never have been stored
+                let _ = entry.delete_password();
+            }
+        }
+        for key in &secrets {
+            let value = variables.get(key).map(String::as_str).unwrap_or_default();
+            let stored = self
+                .keyring_entry(name, key)
+                .and_then(|entry| entry.set_password(value));
+            if let Err(err) = stored {
+                failed.get_or_insert(format!("can't keep `{key}` in the keyring: {err}"));
+            }
+        }
+        self.list[index].variables = variables;
+        self.list[index].secrets = secrets;
+
+        self.write(index)
+            .map_err(|err| format!("can't save environment {name}: {err}"))?;
+        match failed {
+            Some(err) => Err(err),
+            None => Ok(format!("saved environment {name}")),
+        }
+    }
+
+    /// Sets variables of the active environment, for chaining requests
+    pub fn capture(&mut self, values: Variables) -> Result<String, String> {
+        let Some(environment) = self.active() else {
+            return Err("activate an environment to capture into".to_string());
+        };
+        let name = environment.name.clone();
+        let secrets = environment.secrets.clone();
+        let captured = values.keys().cloned().collect::<Vec<_>>().join(", ");
+        let mut variables = environment.variables.clone();
+        variables.extend(values);
+        self.save(&name, variables, secrets)
+            .map(|_| format!("captured {captured} into {name}"))
+    }
+
+    /// An empty path stops reading a `.env` file
+    pub fn set_env_file(&mut self, name: &str, path: &str) -> Result<String, String> {
+        let index = self.index(name)?;
+        let path = path.trim();
+        let environment = &mut self.list[index];
+        environment.env_file = (!path.is_empty()).then(|| path.to_string());
+        environment.read_sources();
+        let notice = match &environment.env_file {
+            Some(path) => format!("{name} reads {path}"),
+            None => format!("{name} reads no .env file"),
+        };
+        self.finish_sources(index, notice)
+    }
+
+    pub fn toggle_process_env(&mut self, name: &str) -> Result<String, String> {
+        let index = self.index(name)?;
+        let environment = &mut self.list[index];
+        environment.process_env = !environment.process_env;
+        environment.read_sources();
+        let notice = match environment.process_env {
+            true => format!("{name} falls back on the process environment"),
+            false => format!("{name} ignores the process environment"),
+        };
+        self.finish_sources(index, notice)
+    }
+
+    fn finish_sources(&self, index: usize, notice: String) -> Result<String, String> {
+        let environment = &self.list[index];
+        self.write(index)
+            .map_err(|err| format!("can't save environment {}: {err}", environment.name))?;
+        match &environment.source_error {
+            Some(err) => Err(err.clone()),
+            None => Ok(notice),
+        }
+    }
+
+    fn index(&self, name: &str) -> Result<usize, String> {
+        self.list
+            .iter()
+            .position(|env| env.name == name)
+            .ok_or_else(|| format!("no environment `{name}`"))
+    }
+
+    fn write(&self, index: usize) -> std::io::Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let environment = &self.list[index];
+        let file = Environment {
+            name: String::new(),
+            variables: environment
+                .variables
+                .iter()
+                .filter(|(key, _)| !environment.secrets.contains(*key))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            secrets: environment.secrets.clone(),
+            env_file: environment.env_file.clone(),
+            process_env: environment.process_env,
+            ..Environment::default()
+        };
+        let toml = toml::to_string_pretty(&file).map_err(std::io::Error::other)?;
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(format!("{}.toml", environment.name)), toml)
+    }
+}
+
+pub enum Part<'t> {
+    Text(&'t str),
+    /// Name inside the braces, spaces around it are ignored
+    Variable(&'t str),
+}
+
+/// Splits text into plain parts and `{{name}}` placeholders
+pub fn parts(text: &str) -> Vec<Part<'_>> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        if start > 0 {
+            parts.push(Part::Text(&rest[..start]));
+        }
+        parts.push(Part::Variable(rest[start + 2..start + 2 + end].trim()));
+        rest = &rest[start + 4 + end..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest));
+    }
+    parts
+}
+
+/// Generated anew for every placeholder, unless a variable of the same name
+/// is defined
+pub fn dynamic(name: &str) -> Option<String> {
+    let mut rng = rand::thread_rng();
+    let value = match name {
+        "uuid" => {
+            let mut bytes: [u8; 16] = rng.gen();
+            // Version 4, RFC 4122 variant
+            bytes[6] = (bytes[6] & 0x0f) | 0x40;
+            bytes[8] = (bytes[8] & 0x3f) | 0x80;
+            let hex = hex(&bytes);
+            format!(
+                "{}-{}-{}-{}-{}",
+                &hex[..8],
+                &hex[8..12],
+                &hex[12..16],
+                &hex[16..20],
+                &hex[20..]
+            )
+        }
+        "timestamp" => Utc::now().timestamp().to_string(),
+        "isoTimestamp" => Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        "randomInt" => rng.gen_range(0..1000).to_string(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Placeholders that are neither variables nor dynamic, in order of use
+pub fn unknown<'t>(text: &'t str, variables: &Variables) -> Vec<&'t str> {
+    parts(text)
+        .into_iter()
+        .filter_map(|part| match part {
+            Part::Variable(name) if !variables.contains_key(name) && dynamic(name).is_none() => {
+                Some(name)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Replaces every `{{name}}` with its variable
+pub fn resolve(text: &str, variables: &Variables) -> Result<String, RequestError> {
+    parts(text)
+        .into_iter()
+        .map(|part| match part {
+            Part::Text(text) => Ok(text.to_string()),
+            Part::Variable(name) => variables
+                .get(name)
+                .cloned()
+                .or_else(|| dynamic(name))
+                .ok_or_else(|| RequestError::UnknownVariable(name.to_string())),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> Variables {
+        Variables::from([
+            ("host".to_string(), "example.com".to_string()),
+            ("id".to_string(), "7".to_string()),
+        ])
+    }
+
+    #[test]
+    fn resolves_placeholders() {
+        let resolved = resolve("https://{{host}}/{{ host }}?id={{id}}", &variables()).unwrap();
+        assert_eq!(resolved, "https://example.com/example.com?id=7");
+        // Unclosed braces are left as they are
+        assert_eq!(resolve("{{host", &variables()).unwrap(), "{{host");
+        assert_eq!(resolve("{{host}}}", &variables()).unwrap(), "example.com}");
+    }
+
+    #[test]
+    fn unknown_variables() {
+        let err = resolve("{{host}}/{{missing}}", &variables()).unwrap_err();
+        assert!(matches!(err, RequestError::UnknownVariable(name) if name == "missing"));
+        assert_eq!(unknown("{{a}} {{host}} {{b}}", &variables()), ["a", "b"]);
+    }
+
+    #[test]
+    fn splits_parts() {
+        let parts = parts("a{{ b }}c{{d}}");
+        let parts: Vec<_> = parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => format!("text {text}"),
+                Part::Variable(name) => format!("variable {name}"),
+            })
+            .collect();
+        assert_eq!(parts, ["text a", "variable b", "text c", "variable d"]);
+    }
+
+    #[test]
+    fn dynamic_variables() {
+        let uuid = resolve("{{uuid}}", &Variables::new()).unwrap();
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+        let numbers = resolve("{{randomInt}} {{timestamp}}", &Variables::new()).unwrap();
+        assert!(numbers.split(' ').all(|part| part.parse::<i64>().is_ok()));
+        assert!(dynamic("unknown").is_none());
+        // Defined variables win, dynamic ones aren't unknown
+        let defined = Variables::from([("uuid".to_string(), "fixed".to_string())]);
+        assert_eq!(resolve("{{uuid}}", &defined).unwrap(), "fixed");
+        assert!(unknown("{{timestamp}} {{isoTimestamp}}", &defined).is_empty());
+    }
+
+    #[test]
+    fn keeps_secrets_out_of_files() {
+        let data = std::env::temp_dir().join(format!("curl-rs-tests-{}", std::process::id()));
+        crate::history::set_data_dir(data);
+        let workspace = "secrets";
+        let dir = workspaces::dir(workspace).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        let mut environments = Environments::load(workspace);
+        let variables = Variables::from([
+            ("host".to_string(), "example.com".to_string()),
+            ("token".to_string(), "hunter2".to_string()),
+        ]);
+        // The keyring may well be missing here, the value is kept in memory then
+        let saved = environments.save("dev", variables, BTreeSet::from(["token".to_string()]));
+        if let Err(err) = &saved {
+            assert!(err.starts_with("can't keep `token` in the keyring"));
+        }
+        environments.activate(Some("dev".to_string())).unwrap();
+
+        let text = fs::read_to_string(dir.join("environments/dev.toml")).unwrap();
+        let masked = environments.masked_variables();
+        let token = environments.variables()["token"].clone();
+        // Takes the secret out of the keyring again
+        let _ = environments.save("dev", Variables::new(), BTreeSet::new());
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(text.contains("host = \"example.com\""));
+        assert!(text.contains("secrets = [\"token\"]"));
+        assert!(!text.contains("hunter2"));
+        assert_eq!(token, "hunter2");
+        assert_eq!(masked["token"], MASK);
+        assert_eq!(masked["host"], "example.com");
+    }
+
+    #[test]
+    fn reads_env_files() {
+        let path = std::env::temp_dir().join(format!("curl-rs-{}.env", std::process::id()));
+        fs::write(&path, "host=from-file\nTOKEN=\"quoted\"\nPATH=file-path\n").unwrap();
+        let mut environments = Environments::default();
+        let own = Variables::from([("host".to_string(), "own".to_string())]);
+        environments.save("dev", own, BTreeSet::new()).unwrap();
+        environments.activate(Some("dev".to_string())).unwrap();
+
+        let file = path.to_string_lossy().into_owned();
+        environments.set_env_file("dev", &file).unwrap();
+        environments.toggle_process_env("dev").unwrap();
+        let variables = environments.variables();
+        // Own variables, then the file, then the process environment
+        assert_eq!(variables["host"], "own");
+        assert_eq!(variables["TOKEN"], "quoted");
+        assert_eq!(variables["PATH"], "file-path");
+        assert!(variables.contains_key("HOME"));
+
+        fs::remove_file(&path).unwrap();
+        assert!(environments.set_env_file("dev", &file).is_err());
+        environments.set_env_file("dev", "").unwrap();
+        environments.toggle_process_env("dev").unwrap();
+        assert_eq!(environments.variables().len(), 1);
+    }
+}
@@ -0,0 +1,65 @@
+use std::{env, fs, process::Command};
+
+/// `$VISUAL`, then `$EDITOR`, then vi, which may have arguments, e.g.
+/// `code --wait`
+fn editor() -> Vec<String> {
+    let command = ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(env::var_os)
+        .map(|command| command.to_string_lossy().into_owned())
+        .find(|command| !command.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    command.split_whitespace().map(String::from).collect()
+}
+
+/// Opens `text` in the user's editor and waits for it to exit, the
+/// extension is only there so the editor picks the right highlighting.
+/// Expects the terminal to be handed over already
+pub fn edit(text: &str, extension: &str) -> Result<String, String> {
+    let path = env::temp_dir().join(format!("curl-rs-{}.{extension}", std::process::id()));
+    fs::write(&path, text).map_err(|err| format!("can't write {}: {err}", path.display()))?;
+
+    let command = editor();
+    let status = Command::new(&command[0])
+        .args(&command[1..])
+        .arg(&path)
+        .status();
+    let edited = match status {
+        Ok(status) if status.success() => {
+            fs::read_to_string(&path).map_err(|err| format!("can't read {}: {err}", path.display()))
+        }
+        Ok(status) => Err(format!(
+            "{} exited with {status}, kept the old text",
+            command[0]
+        )),
+        Err(err) => Err(format!("can't run {}: {err}", command[0])),
+    };
+    let _ = fs::remove_file(&path);
+    // Editors add a final newline the editors here don't have
+    edited.map(|text| text.strip_suffix('\n').unwrap_or(&text).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_in_editor() {
+        // The only test setting these
+        env::remove_var("EDITOR");
+        env::set_var("VISUAL", "sed -i s/old/new/");
+        assert_eq!(editor(), ["sed", "-i", "s/old/new/"]);
+        assert_eq!(
+            edit("{\"a\": \"old\"}", "json"),
+            Ok("{\"a\": \"new\"}".to_string())
+        );
+
+        // What was there is kept when the editor fails
+        env::set_var("VISUAL", "false");
+        let err = edit("old", "txt").unwrap_err();
+        assert!(err.starts_with("false exited with"), "{err}");
+        env::set_var("VISUAL", " ");
+        assert_eq!(editor(), ["vi"]);
+        env::remove_var("VISUAL");
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    env,
+    fmt::Write as _,
+    io::{self, Cursor, Write},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crossterm::{
+    cursor::{MoveTo, RestorePosition, SavePosition},
+    queue,
+    style::Print,
+    terminal,
+};
+use image::{
+    imageops::{self, FilterType},
+    ImageOutputFormat, RgbaImage,
+};
+use ratatui::layout::Rect;
+
+/// How images are put on screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    Kitty,
+    Sixel,
+    /// Drawn with text, see `widgets::ImagePreview`
+    HalfBlocks,
+}
+
+impl Protocol {
+    /// Guessed from the environment, terminals don't all answer queries and
+    /// tmux would need the escapes wrapped
+    pub fn detect() -> Self {
+        let var = |name| env::var(name).unwrap_or_default().to_lowercase();
+        let (term, program) = (var("TERM"), var("TERM_PROGRAM"));
+        if env::var_os("TMUX").is_some() || term.starts_with("screen") {
+            Self::HalfBlocks
+        } else if env::var_os("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || ["wezterm", "ghostty"].contains(&program.as_str())
+        {
+            Self::Kitty
+        } else if ["foot", "mlterm", "contour", "sixel"]
+            .iter()
+            .any(|name| term.contains(name))
+            || program == "iterm.app"
+        {
+            Self::Sixel
+        } else {
+            Self::HalfBlocks
+        }
+    }
+}
+
+/// Puts the image preview on screen after each draw, for the protocols that
+/// don't go through the buffer
+pub struct Graphics {
+    pub protocol: Protocol,
+    /// Where the last image went and which image it was
+    shown: Option<(Rect, u64)>,
+}
+
+impl Graphics {
+    /// Id of the one kitty image there is
+    const KITTY_ID: u32 = 1;
+
+    pub fn new(protocol: Protocol) -> Self {
+        Self {
+            protocol,
+            shown: None,
+        }
+    }
+
+    /// Draws `image` into `area`, or takes the last one down with `None`.
+    /// Nothing is written while the area and id stay the same. True when the
+    /// screen has to be drawn again from scratch, sixels only go away by
+    /// drawing over them
+    pub fn show(
+        &mut self,
+        out: &mut impl Write,
+        image: Option<(Rect, (u64, &RgbaImage))>,
+    ) -> io::Result<bool> {
+        let next = image.map(|(area, (id, _))| (area, id));
+        if self.protocol == Protocol::HalfBlocks || next == self.shown {
+            return Ok(false);
+        }
+        if self.shown.take().is_some() {
+            match self.protocol {
+                Protocol::Kitty => {
+                    write!(out, "\x1b_Ga=d,d=I,i={},q=2\x1b\\", Self::KITTY_ID)?;
+                }
+                _ => return Ok(true),
+            }
+        }
+        if let Some((area, (_, image))) = image {
+            let (cell_width, cell_height) = cell_size();
+            let (place, width, height) = fit(image, area, cell_width, cell_height);
+            let escape = match self.protocol {
+                Protocol::Kitty => kitty(image, place, Self::KITTY_ID),
+                _ => sixel(&imageops::resize(
+                    image,
+                    width,
+                    height,
+                    FilterType::Triangle,
+                )),
+            };
+            queue!(
+                out,
+                SavePosition,
+                MoveTo(place.x, place.y),
+                Print(escape),
+                RestorePosition
+            )?;
+            self.shown = next;
+        }
+        out.flush()?;
+        Ok(false)
+    }
+}
+
+/// Pixels per cell, from the terminal or a common guess when it doesn't say
+fn cell_size() -> (u32, u32) {
+    match terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => (
+            ((size.width / size.columns) as u32).max(1),
+            ((size.height / size.rows) as u32).max(1),
+        ),
+        _ => (8, 16),
+    }
+}
+
+/// The cells `image` takes up when scaled to fit `area`, centered, and its
+/// size in pixels then
+fn fit(image: &RgbaImage, area: Rect, cell_width: u32, cell_height: u32) -> (Rect, u32, u32) {
+    let (cell_width, cell_height) = (cell_width.max(1), cell_height.max(1));
+    let (width, height) = image.dimensions();
+    let scale = f64::min(
+        (area.width as u32 * cell_width) as f64 / width.max(1) as f64,
+        (area.height as u32 * cell_height) as f64 / height.max(1) as f64,
+    );
+    let width = ((width as f64 * scale) as u32).max(1);
+    let height = ((height as f64 * scale) as u32).max(1);
+    let columns = (width.div_ceil(cell_width) as u16).min(area.width);
+    let rows = (height.div_ceil(cell_height) as u16).min(area.height);
+    let place = Rect {
+        x: area.x + (area.width - columns) / 2,
+        y: area.y + (area.height - rows) / 2,
+        width: columns,
+        height: rows,
+    };
+    (place, width, height)
+}
+
+/// Kitty graphics escapes sending `image` as PNG and placing it over `place`,
+/// in chunks as the protocol wants them
+fn kitty(image: &RgbaImage, place: Rect, id: u32) -> String {
+    let mut png = Vec::new();
+    if image
+        .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+        .is_err()
+    {
+        return String::new();
+    }
+    let data = STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+    let mut escape = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = (index + 1 < chunks.len()) as u8;
+        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+        match index {
+            // The cursor stays put so nothing scrolls
+            0 => write!(
+                escape,
+                "\x1b_Ga=T,f=100,i={id},c={},r={},C=1,q=2,m={more};{chunk}\x1b\\",
+                place.width, place.height
+            ),
+            _ => write!(escape, "\x1b_Gm={more};{chunk}\x1b\\"),
+        }
+        .unwrap_or_default();
+    }
+    escape
+}
+
+/// Sixel escape drawing `image` as is, its colors cut down to a 6×6×6 cube.
+/// Transparent pixels are blended onto black like with half blocks
+fn sixel(image: &RgbaImage) -> String {
+    let (width, height) = image.dimensions();
+    let level = |channel: u8, alpha: u8| (channel as u32 * alpha as u32 / 255 * 5 + 127) / 255;
+    let colors: Vec<u8> = image
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b, a] = pixel.0;
+            (level(r, a) * 36 + level(g, a) * 6 + level(b, a)) as u8
+        })
+        .collect();
+
+    let mut escape = format!("\x1bPq\"1;1;{width};{height}");
+    let mut used = [false; 216];
+    colors.iter().for_each(|&color| used[color as usize] = true);
+    for color in (0..216).filter(|&color| used[color]) {
+        let percent = |level: usize| level * 100 / 5;
+        let (r, g, b) = (color / 36, color / 6 % 6, color % 6);
+        let _ = write!(
+            escape,
+            "#{color};2;{};{};{}",
+            percent(r),
+            percent(g),
+            percent(b)
+        );
+    }
+
+    // Six rows at a time, each color in turn over the same band
+    for top in (0..height).step_by(6) {
+        let rows = top..(top + 6).min(height);
+        let mut band = [false; 216];
+        for y in rows.clone() {
+            let row = &colors[(y * width) as usize..((y + 1) * width) as usize];
+            row.iter().for_each(|&color| band[color as usize] = true);
+        }
+        for color in (0..216u8).filter(|&color| band[color as usize]) {
+            let _ = write!(escape, "#{color}");
+            let bits = (0..width).map(|x| {
+                rows.clone()
+                    .filter(|&y| colors[(y * width + x) as usize] == color)
+                    .fold(0u8, |bits, y| bits | 1 << (y - top))
+            });
+            run_length(&mut escape, bits);
+            escape.push('$');
+        }
+        escape.push('-');
+    }
+    escape.push_str("\x1b\\");
+    escape
+}
+
+/// Sixel characters for `bits`, runs of more than three as `!count`
+fn run_length(escape: &mut String, bits: impl Iterator<Item = u8>) {
+    let push = |escape: &mut String, bits: u8, count: usize| {
+        let sixel = (63 + bits) as char;
+        match count {
+            0 => {}
+            1..=3 => escape.extend(std::iter::repeat_n(sixel, count)),
+            _ => {
+                let _ = write!(escape, "!{count}{sixel}");
+            }
+        }
+    };
+    let mut run = (0, 0);
+    for bits in bits {
+        if bits == run.0 {
+            run.1 += 1;
+        } else {
+            push(escape, run.0, run.1);
+            run = (bits, 1);
+        }
+    }
+    push(escape, run.0, run.1);
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn fits_into_area() {
+        let image = RgbaImage::new(200, 100);
+        let area = Rect::new(10, 5, 40, 20);
+        let (place, width, height) = fit(&image, area, 10, 20);
+        assert_eq!((width, height), (400, 200));
+        assert_eq!(place, Rect::new(10, 10, 40, 10));
+    }
+
+    #[test]
+    fn fits_with_empty_cells() {
+        // Terminals reporting fewer pixels than cells
+        let image = RgbaImage::new(200, 100);
+        let area = Rect::new(0, 0, 40, 20);
+        let (place, width, height) = fit(&image, area, 0, 0);
+        assert_eq!((width, height), (40, 20));
+        assert_eq!(place, area);
+    }
+
+    #[test]
+    fn redraws_new_images() {
+        let image = RgbaImage::new(2, 2);
+        let area = Rect::new(0, 0, 4, 2);
+        let mut graphics = Graphics::new(Protocol::Kitty);
+        let mut out = Vec::new();
+        graphics.show(&mut out, Some((area, (1, &image)))).unwrap();
+        assert!(!out.is_empty());
+
+        // Same image in the same place
+        out.clear();
+        graphics.show(&mut out, Some((area, (1, &image)))).unwrap();
+        assert!(out.is_empty());
+
+        // A response decoded to an equal image still replaces it
+        graphics.show(&mut out, Some((area, (2, &image)))).unwrap();
+        assert!(String::from_utf8_lossy(&out).starts_with("\x1b_Ga=d,d=I,i=1,q=2\x1b\\"));
+    }
+
+    #[test]
+    fn sixel_bands() {
+        // Red on top, a row of blue at the bottom of a second band
+        let mut image = RgbaImage::from_pixel(4, 7, Rgba([255, 0, 0, 255]));
+        (0..4).for_each(|x| image.put_pixel(x, 6, Rgba([0, 0, 255, 255])));
+        let escape = sixel(&image);
+        assert!(escape.starts_with("\x1bPq\"1;1;4;7"));
+        assert!(escape.contains("#5;2;0;0;100#180;2;100;0;0"));
+        // All six rows red, then the last row blue
+        assert!(escape.contains("#180!4~$-#5!4@$-\x1b\\"));
+    }
+
+    #[test]
+    fn kitty_chunks() {
+        // Noise, so the PNG doesn't compress into a single chunk
+        let image = RgbaImage::from_fn(64, 64, |x, y| {
+            let noise = ((x * 7919) ^ (y * 104729)).wrapping_mul(2654435761);
+            Rgba(noise.to_le_bytes())
+        });
+        let escape = kitty(&image, Rect::new(0, 0, 8, 4), 1);
+        let chunks: Vec<&str> = escape.split_terminator("\x1b\\").collect();
+        assert!(chunks.len() > 1);
+        assert!(chunks[0].starts_with("\x1b_Ga=T,f=100,i=1,c=8,r=4,C=1,q=2,m=1;"));
+        assert!(chunks[1..chunks.len() - 1]
+            .iter()
+            .all(|chunk| chunk.starts_with("\x1b_Gm=1;")));
+        assert!(chunks[chunks.len() - 1].starts_with("\x1b_Gm=0;"));
+    }
+}
@@ -0,0 +1,604 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{history::data_dir, models::Request, utils::hex};
+
+/// Just what completion and validation need, with type references unwrapped
+/// through seven levels of lists and non-nulls
+pub const INTROSPECTION: &str = "query IntrospectionQuery {
+  __schema {
+    queryType { name }
+    mutationType { name }
+    subscriptionType { name }
+    types {
+      kind
+      name
+      fields(includeDeprecated: true) {
+        name
+        args { name type { ...TypeRef } }
+        type { ...TypeRef }
+      }
+      enumValues(includeDeprecated: true) { name }
+    }
+  }
+}
+
+fragment TypeRef on __Type {
+  name
+  ofType { name ofType { name ofType { name ofType { name ofType { name ofType { name ofType { name } } } } } } }
+}";
+
+const KEYWORDS: [&str; 4] = ["query", "mutation", "subscription", "fragment"];
+
+const DIRECTIVES: [&str; 2] = ["include", "skip"];
+
+pub struct Argument {
+    pub name: String,
+    /// The named type, without list or non-null wrappers
+    pub kind: String,
+}
+
+pub struct Field {
+    pub name: String,
+    pub kind: String,
+    pub args: Vec<Argument>,
+}
+
+pub struct Type {
+    /// OBJECT, INTERFACE, UNION, ENUM, INPUT_OBJECT or SCALAR
+    pub kind: String,
+    pub fields: Vec<Field>,
+    /// Of an enum
+    pub values: Vec<String>,
+}
+
+/// What introspection told about an endpoint
+pub struct Schema {
+    pub query: Option<String>,
+    pub mutation: Option<String>,
+    pub subscription: Option<String>,
+    pub types: BTreeMap<String, Type>,
+}
+
+impl Schema {
+    /// From the response to `INTROSPECTION`
+    pub fn parse(response: &Value) -> Result<Self, String> {
+        let schema = response.pointer("/data/__schema").filter(|s| s.is_object());
+        let Some(schema) = schema else {
+            let error = response
+                .pointer("/errors/0/message")
+                .and_then(Value::as_str);
+            return Err(error
+                .unwrap_or("no schema in the response, is introspection turned off?")
+                .to_string());
+        };
+        let root = |key: &str| Some(schema.get(key)?.get("name")?.as_str()?.to_string());
+        let list = |value: &Value, key: &str| value[key].as_array().cloned().unwrap_or_default();
+        let mut types = BTreeMap::new();
+        for kind in list(schema, "types") {
+            let Some(name) = kind["name"].as_str() else {
+                continue;
+            };
+            let fields = list(&kind, "fields")
+                .iter()
+                .filter_map(|field| {
+                    let args = list(field, "args")
+                        .iter()
+                        .filter_map(|arg| {
+                            Some(Argument {
+                                name: arg["name"].as_str()?.to_string(),
+                                kind: named_type(&arg["type"])?,
+                            })
+                        })
+                        .collect();
+                    Some(Field {
+                        name: field["name"].as_str()?.to_string(),
+                        kind: named_type(&field["type"])?,
+                        args,
+                    })
+                })
+                .collect();
+            let values = list(&kind, "enumValues")
+                .iter()
+                .filter_map(|value| Some(value["name"].as_str()?.to_string()))
+                .collect();
+            let kind = Type {
+                kind: kind["kind"].as_str().unwrap_or_default().to_string(),
+                fields,
+                values,
+            };
+            types.insert(name.to_string(), kind);
+        }
+        Ok(Self {
+            query: root("queryType"),
+            mutation: root("mutationType"),
+            subscription: root("subscriptionType"),
+            types,
+        })
+    }
+
+    fn field(&self, kind: &str, name: &str) -> Option<&Field> {
+        self.types
+            .get(kind)?
+            .fields
+            .iter()
+            .find(|field| field.name == name)
+    }
+
+    /// Names that fit where the cursor is in `query`, the one being typed
+    /// there included
+    pub fn candidates(&self, query: &str, cursor: (usize, usize)) -> Vec<String> {
+        let Some(offset) = offset(query, cursor) else {
+            return Vec::new();
+        };
+        let before = &query[..offset];
+        let start = before.trim_end_matches(is_name_char).len();
+        let (tokens, open) = tokenize(&before[..start]);
+        if open || before[..start].ends_with('$') {
+            return Vec::new();
+        }
+        let walk = Walk::new(self, &tokens);
+        if let Some((field, 0)) = walk.args {
+            let arg = match (walk.previous, tokens.len().checked_sub(2)) {
+                (Some(Token::Punct(':')), Some(index)) => match tokens[index].0 {
+                    Token::Name(name) => field.and_then(|f| f.args.iter().find(|a| a.name == name)),
+                    _ => None,
+                },
+                (Some(Token::Punct(':')), None) => None,
+                // Another argument's name
+                _ => {
+                    let args = field.map(|field| field.args.as_slice()).unwrap_or_default();
+                    return args.iter().map(|arg| arg.name.clone()).collect();
+                }
+            };
+            return match arg.map(|arg| arg.kind.as_str()) {
+                Some("Boolean") => vec!["true".to_string(), "false".to_string()],
+                Some(kind) => self
+                    .types
+                    .get(kind)
+                    .map(|kind| kind.values.clone())
+                    .unwrap_or_default(),
+                None => Vec::new(),
+            };
+        }
+        if walk.args.is_some() {
+            return Vec::new();
+        }
+        match walk.previous {
+            Some(Token::Name("on")) => {
+                return self
+                    .types
+                    .iter()
+                    .filter(|(name, kind)| {
+                        !name.starts_with("__")
+                            && matches!(kind.kind.as_str(), "OBJECT" | "INTERFACE" | "UNION")
+                    })
+                    .map(|(name, _)| name.clone())
+                    .collect();
+            }
+            Some(Token::Punct('@')) => return DIRECTIVES.map(str::to_string).to_vec(),
+            Some(Token::Spread) => return vec!["on".to_string()],
+            _ => {}
+        }
+        match walk.scopes.last() {
+            None if matches!(walk.previous, None | Some(Token::Punct('}'))) => {
+                KEYWORDS.map(str::to_string).to_vec()
+            }
+            Some(Some(kind)) => {
+                let fields = self.types.get(*kind).map(|kind| kind.fields.as_slice());
+                let mut fields: Vec<String> = fields
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|field| field.name.clone())
+                    .collect();
+                fields.push("__typename".to_string());
+                fields
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Fields, arguments and types the schema doesn't have, by line counted
+    /// from 1
+    pub fn problems(&self, query: &str) -> Vec<(usize, String)> {
+        Walk::new(self, &tokenize(query).0).problems
+    }
+}
+
+/// The innermost name of a type reference
+fn named_type(mut reference: &Value) -> Option<String> {
+    while !reference.is_null() {
+        if let Some(name) = reference["name"].as_str() {
+            return Some(name.to_string());
+        }
+        reference = &reference["ofType"];
+    }
+    None
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Byte offset of a row and a column in chars
+fn offset(text: &str, (row, column): (usize, usize)) -> Option<usize> {
+    let mut start = 0;
+    for (index, line) in text.split('\n').enumerate() {
+        if index == row {
+            let column = line
+                .char_indices()
+                .nth(column)
+                .map_or(line.len(), |(i, _)| i);
+            return Some(start + column);
+        }
+        start += line.len() + 1;
+    }
+    None
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    Name(&'a str),
+    /// `...`
+    Spread,
+    Punct(char),
+    /// Strings, numbers and `$variables`
+    Value,
+}
+
+/// Tokens with their lines counted from 1. Commas are left out, GraphQL
+/// ignores them. True when it ends inside a string or comment
+fn tokenize(text: &str) -> (Vec<(Token<'_>, usize)>, bool) {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let at = line;
+        let length = match c {
+            '\n' => {
+                line += 1;
+                1
+            }
+            '#' => match rest.find('\n') {
+                Some(end) => end,
+                None => return (tokens, true),
+            },
+            '"' => {
+                let quote = if rest.starts_with("\"\"\"") {
+                    "\"\"\""
+                } else {
+                    "\""
+                };
+                let mut end = None;
+                let mut escaped = false;
+                for (i, c) in rest.char_indices().skip(quote.len()) {
+                    if !escaped && rest[i..].starts_with(quote) {
+                        end = Some(i + quote.len());
+                        break;
+                    }
+                    escaped = c == '\\' && !escaped;
+                }
+                let Some(end) = end else {
+                    return (tokens, true);
+                };
+                line += rest[..end].matches('\n').count();
+                tokens.push((Token::Value, at));
+                end
+            }
+            _ if rest.starts_with("...") => {
+                tokens.push((Token::Spread, at));
+                3
+            }
+            '$' | '-' | '0'..='9' => {
+                let end = rest[1..].find(|c| !is_name_char(c) && c != '.');
+                tokens.push((Token::Value, at));
+                end.map_or(rest.len(), |end| end + 1)
+            }
+            _ if is_name_char(c) => {
+                let end = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+                tokens.push((Token::Name(&rest[..end]), at));
+                end
+            }
+            _ if c.is_whitespace() || c == ',' => c.len_utf8(),
+            _ => {
+                tokens.push((Token::Punct(c), at));
+                c.len_utf8()
+            }
+        };
+        rest = &rest[length..];
+    }
+    (tokens, false)
+}
+
+/// Where a walk through the tokens of a document got to
+struct Walk<'s, 'a> {
+    /// Types of the selection sets it's inside, `None` where it isn't known
+    scopes: Vec<Option<&'s str>>,
+    /// What the next `{` selects from
+    next: Option<Option<&'s str>>,
+    /// The field a `(` would open the arguments of
+    field: Option<&'s Field>,
+    /// Inside arguments, of the field if it's known, and how deep in
+    /// brackets of their values
+    args: Option<(Option<&'s Field>, usize)>,
+    previous: Option<Token<'a>>,
+    problems: Vec<(usize, String)>,
+}
+
+impl<'s, 'a> Walk<'s, 'a> {
+    fn new(schema: &'s Schema, tokens: &[(Token<'a>, usize)]) -> Self {
+        let mut walk = Self {
+            scopes: Vec::new(),
+            next: None,
+            field: None,
+            args: None,
+            previous: None,
+            problems: Vec::new(),
+        };
+        for (index, &(token, line)) in tokens.iter().enumerate() {
+            let following = tokens.get(index + 1).map(|(token, _)| *token);
+            walk.step(schema, token, following, line);
+            walk.previous = Some(token);
+        }
+        walk
+    }
+
+    fn step(
+        &mut self,
+        schema: &'s Schema,
+        token: Token<'a>,
+        following: Option<Token>,
+        line: usize,
+    ) {
+        if let Some((field, depth)) = &mut self.args {
+            match token {
+                Token::Punct('(' | '[' | '{') => *depth += 1,
+                Token::Punct(')' | ']' | '}') if *depth > 0 => *depth -= 1,
+                Token::Punct(')') => self.args = None,
+                Token::Name(name)
+                    if *depth == 0
+                        && following == Some(Token::Punct(':'))
+                        && self.previous != Some(Token::Punct(':')) =>
+                {
+                    if let Some(field) = field.filter(|f| !f.args.iter().any(|a| a.name == name)) {
+                        let problem = format!("no argument `{name}` on `{}`", field.name);
+                        self.problems.push((line, problem));
+                    }
+                }
+                _ => {}
+            }
+            return;
+        }
+        match token {
+            // Variable definitions and directive arguments have no field
+            Token::Punct('(') => self.args = Some((self.field.take(), 0)),
+            Token::Punct('{') => {
+                let scope = match self.next.take() {
+                    Some(scope) => scope,
+                    // The query shorthand, or an inline fragment without a
+                    // type condition
+                    None => match self.scopes.last() {
+                        Some(scope) => *scope,
+                        None => schema.query.as_deref(),
+                    },
+                };
+                self.scopes.push(scope);
+                self.field = None;
+            }
+            Token::Punct('}') => {
+                self.scopes.pop();
+                self.next = None;
+                self.field = None;
+            }
+            // The directive's arguments aren't the field's
+            Token::Punct('@') => self.field = None,
+            Token::Spread => {
+                self.next = None;
+                self.field = None;
+            }
+            Token::Name(name) => self.name(schema, name, following, line),
+            _ => {}
+        }
+    }
+
+    fn name(&mut self, schema: &'s Schema, name: &str, following: Option<Token>, line: usize) {
+        match self.previous {
+            // A directive
+            Some(Token::Punct('@')) => return,
+            Some(Token::Name("on")) => {
+                self.next = match schema.types.get_key_value(name) {
+                    Some((name, _)) => Some(Some(name.as_str())),
+                    None => {
+                        self.problems.push((line, format!("no type `{name}`")));
+                        Some(None)
+                    }
+                };
+                return;
+            }
+            // A fragment spread, or the `on` of an inline fragment
+            Some(Token::Spread) => return,
+            _ => {}
+        }
+        let Some(scope) = self.scopes.last() else {
+            let root = match name {
+                "query" => &schema.query,
+                "mutation" => &schema.mutation,
+                "subscription" => &schema.subscription,
+                // Operation and fragment names, and the `on` of fragments
+                _ => return,
+            };
+            if root.is_none() {
+                self.problems
+                    .push((line, format!("the schema has no {name} type")));
+            }
+            self.next = Some(root.as_deref());
+            return;
+        };
+        // An alias, the field's name comes after the colon
+        if following == Some(Token::Punct(':')) {
+            return;
+        }
+        self.next = Some(None);
+        self.field = None;
+        let Some(kind) = scope else {
+            return;
+        };
+        if name.starts_with("__") {
+            return;
+        }
+        match schema.field(kind, name) {
+            Some(field) => {
+                self.next = Some(Some(&field.kind));
+                self.field = Some(field);
+            }
+            None => self
+                .problems
+                .push((line, format!("no field `{name}` on `{kind}`"))),
+        }
+    }
+}
+
+/// The identifier of what's before the cursor, which completion replaces
+pub fn typed(query: &str, cursor: (usize, usize)) -> String {
+    let before = offset(query, cursor).map_or("", |offset| &query[..offset]);
+    let start = before.trim_end_matches(is_name_char).len();
+    before[start..].to_string()
+}
+
+/// Introspected schemas are kept under the data directory by a hash of the
+/// endpoint
+fn path(endpoint: &str) -> Option<PathBuf> {
+    let name = hex(&Sha256::digest(endpoint.as_bytes()));
+    data_dir().map(|dir| dir.join("graphql").join(format!("{}.json", &name[..16])))
+}
+
+/// The schema introspected from `endpoint` before
+pub fn cached(endpoint: &str) -> Option<Schema> {
+    let text = fs::read_to_string(path(endpoint)?).ok()?;
+    Schema::parse(&serde_json::from_str(&text).ok()?).ok()
+}
+
+/// Sends the introspection query with `request`'s headers and auth, and
+/// caches the schema for `endpoint`
+pub async fn introspect(request: &Request, endpoint: &str) -> Result<Schema, String> {
+    let response = request.fetch().await.map_err(|err| err.to_string())?;
+    let value: Value = serde_json::from_str(&response.body)
+        .map_err(|_| format!("got a {} without JSON", response.status))?;
+    let schema = Schema::parse(&value)?;
+    if let Some(path) = path(endpoint) {
+        let _ = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&path, value.to_string()));
+    }
+    Ok(schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema() -> Schema {
+        let scalar = |name| json!({ "kind": "SCALAR", "name": name });
+        let non_null = |name| json!({ "name": null, "ofType": { "name": name } });
+        let response = json!({ "data": { "__schema": {
+            "queryType": { "name": "Query" },
+            "mutationType": null,
+            "subscriptionType": null,
+            "types": [
+                { "kind": "OBJECT", "name": "Query", "fields": [{
+                    "name": "user",
+                    "args": [
+                        { "name": "id", "type": non_null("ID") },
+                        { "name": "active", "type": { "name": "Boolean" } },
+                        { "name": "role", "type": { "name": "Role" } },
+                    ],
+                    "type": { "name": "User" },
+                }] },
+                { "kind": "OBJECT", "name": "User", "fields": [
+                    { "name": "id", "args": [], "type": non_null("ID") },
+                    { "name": "name", "args": [], "type": { "name": "String" } },
+                    { "name": "friends", "args": [], "type": { "name": null, "ofType": non_null("User") } },
+                ] },
+                { "kind": "ENUM", "name": "Role", "enumValues": [{ "name": "ADMIN" }, { "name": "GUEST" }] },
+                { "kind": "OBJECT", "name": "__Type", "fields": [] },
+                scalar("ID"),
+                scalar("String"),
+                scalar("Boolean"),
+            ],
+        } } });
+        Schema::parse(&response).unwrap()
+    }
+
+    /// Candidates with the cursor at the end of `query`
+    fn candidates(query: &str) -> Vec<String> {
+        let lines: Vec<_> = query.split('\n').collect();
+        let cursor = (lines.len() - 1, lines[lines.len() - 1].chars().count());
+        schema().candidates(query, cursor)
+    }
+
+    #[test]
+    fn parses_schema() {
+        let schema = schema();
+        assert_eq!(schema.query.as_deref(), Some("Query"));
+        assert!(schema.mutation.is_none());
+        // Through lists and non-nulls
+        let friends = schema.field("User", "friends").unwrap();
+        assert_eq!(friends.kind, "User");
+        assert_eq!(schema.field("Query", "user").unwrap().args[0].kind, "ID");
+        assert_eq!(schema.types["Role"].values, ["ADMIN", "GUEST"]);
+
+        let refused = json!({ "errors": [{ "message": "introspection is disabled" }] });
+        assert_eq!(
+            Schema::parse(&refused).err().as_deref(),
+            Some("introspection is disabled")
+        );
+    }
+
+    #[test]
+    fn completes_by_position() {
+        assert_eq!(candidates(""), KEYWORDS);
+        assert_eq!(
+            candidates("{\n  user {\n    na"),
+            ["id", "name", "friends", "__typename"]
+        );
+        assert_eq!(candidates("query { user(id: 1, "), ["id", "active", "role"]);
+        assert_eq!(candidates("{ user(role: "), ["ADMIN", "GUEST"]);
+        assert_eq!(candidates("{ user(active: t"), ["true", "false"]);
+        assert_eq!(candidates("{ user { ... on "), ["Query", "User"]);
+        assert_eq!(candidates("{ user { name @"), DIRECTIVES);
+        // Nothing inside strings, comments or variables
+        assert!(candidates("{ user(id: \"na").is_empty());
+        assert!(candidates("{ user # na").is_empty());
+        assert!(candidates("query ($na").is_empty());
+        assert_eq!(typed("{ user {\n  na", (1, 4)), "na");
+    }
+
+    #[test]
+    fn finds_problems() {
+        let schema = schema();
+        let query = "query {\n  user(nope: 1) {\n    me: name\n    age\n  }\n}";
+        assert_eq!(
+            schema.problems(query),
+            [
+                (2, "no argument `nope` on `user`".to_string()),
+                (4, "no field `age` on `User`".to_string()),
+            ]
+        );
+        assert_eq!(
+            schema.problems("mutation { user { id } }"),
+            [(1, "the schema has no mutation type".to_string())]
+        );
+        assert_eq!(
+            schema.problems("{ user { ... on Ghost { id } __typename } }"),
+            [(1, "no type `Ghost`".to_string())]
+        );
+        assert!(schema
+            .problems("{ user(id: \"}\") @skip(if: false) { friends { id } } }")
+            .is_empty());
+    }
+}
@@ -0,0 +1,469 @@
+use std::{io::Read, path::Path, time::Instant};
+
+use bytes::Bytes;
+use flate2::read::GzDecoder;
+use hyper::{
+    body::HttpBody,
+    client::conn::{self, SendRequest},
+    header::{HeaderMap, HeaderValue, CONTENT_TYPE, TE},
+    Body as HyperBody,
+};
+use reqwest::Url;
+use serde_json::Value;
+
+use crate::{
+    models::{self, Body, Message, Request, RequestError, Response},
+    net,
+    protobuf::{self, Schema, Wire},
+    timing::Timing,
+};
+
+/// Tried in turn, servers often only have the older one
+const REFLECTION: [&str; 2] = [
+    "grpc.reflection.v1alpha.ServerReflection",
+    "grpc.reflection.v1.ServerReflection",
+];
+/// Rounds of asking for missing imports before giving up on reflection
+const REFLECTION_ROUNDS: usize = 10;
+const UNIMPLEMENTED: u32 = 12;
+
+pub fn is_grpc(uri: &str) -> bool {
+    let scheme = uri.split_once("://").map(|(scheme, _)| scheme);
+    matches!(scheme, Some("grpc" | "grpcs"))
+}
+
+/// Name of a gRPC status code
+pub fn status_name(code: u32) -> &'static str {
+    match code {
+        0 => "OK",
+        1 => "CANCELLED",
+        2 => "UNKNOWN",
+        3 => "INVALID_ARGUMENT",
+        4 => "DEADLINE_EXCEEDED",
+        5 => "NOT_FOUND",
+        6 => "ALREADY_EXISTS",
+        7 => "PERMISSION_DENIED",
+        8 => "RESOURCE_EXHAUSTED",
+        9 => "FAILED_PRECONDITION",
+        10 => "ABORTED",
+        11 => "OUT_OF_RANGE",
+        12 => "UNIMPLEMENTED",
+        13 => "INTERNAL",
+        14 => "UNAVAILABLE",
+        15 => "DATA_LOSS",
+        16 => "UNAUTHENTICATED",
+        _ => "",
+    }
+}
+
+fn grpc_error(err: impl ToString) -> RequestError {
+    RequestError::Grpc(err.to_string())
+}
+
+/// A message with its length prefix, uncompressed
+fn frame(message: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0];
+    frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    frame.extend_from_slice(message);
+    frame
+}
+
+/// Takes the complete messages off the front of `buffer`
+fn unframe(buffer: &mut Vec<u8>) -> Result<Vec<Vec<u8>>, RequestError> {
+    let mut messages = Vec::new();
+    while buffer.len() >= 5 {
+        let length = u32::from_be_bytes([buffer[1], buffer[2], buffer[3], buffer[4]]) as usize;
+        if buffer.len() < 5 + length {
+            break;
+        }
+        let compressed = buffer[0] == 1;
+        let message: Vec<u8> = buffer.drain(..5 + length).skip(5).collect();
+        messages.push(match compressed {
+            true => {
+                let mut decoded = Vec::new();
+                GzDecoder::new(message.as_slice())
+                    .read_to_end(&mut decoded)
+                    .map_err(|err| grpc_error(format!("compressed message: {err}")))?;
+                decoded
+            }
+            false => message,
+        });
+    }
+    Ok(messages)
+}
+
+/// Status code and message from the trailers, or the headers of a
+/// trailers-only response
+fn status(headers: &[(String, String)]) -> Option<(u32, String)> {
+    let find = |name: &str| {
+        headers
+            .iter()
+            .rev()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+    };
+    let code = find("grpc-status")?.parse().ok()?;
+    Some((code, find("grpc-message").unwrap_or_default()))
+}
+
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).to_string(),
+            )
+        })
+        .collect()
+}
+
+/// A connection and what each call on it sends along
+struct Channel {
+    sender: SendRequest<HyperBody>,
+    /// `http://host:port`, what the calls' paths are appended to
+    origin: String,
+    metadata: HeaderMap,
+}
+
+impl Channel {
+    async fn open(
+        url: &Url,
+        metadata: HeaderMap,
+        unix_socket: Option<&Path>,
+    ) -> Result<Self, RequestError> {
+        let tls = url.scheme() == "grpcs";
+        let stream = net::connect(url, tls, &["h2"], unix_socket)
+            .await
+            .map_err(grpc_error)?;
+        let (sender, connection) = conn::Builder::new()
+            .http2_only(true)
+            .handshake(stream)
+            .await
+            .map_err(grpc_error)?;
+        tokio::spawn(connection);
+        let host = url.host_str().unwrap_or_default();
+        let origin = match (tls, url.port()) {
+            (true, Some(port)) => format!("https://{host}:{port}"),
+            (true, None) => format!("https://{host}"),
+            (false, Some(port)) => format!("http://{host}:{port}"),
+            (false, None) => format!("http://{host}"),
+        };
+        Ok(Self {
+            sender,
+            origin,
+            metadata,
+        })
+    }
+
+    /// Starts a call of `path`, `Service/Method`, sending `messages`
+    async fn start(
+        &mut self,
+        path: &str,
+        messages: &[Vec<u8>],
+    ) -> Result<hyper::Response<HyperBody>, RequestError> {
+        let body: Vec<u8> = messages.iter().flat_map(|message| frame(message)).collect();
+        let mut request = hyper::Request::post(format!("{}/{path}", self.origin))
+            .body(HyperBody::from(body))
+            .map_err(grpc_error)?;
+        let headers = request.headers_mut();
+        headers.extend(self.metadata.clone());
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+        headers.insert(TE, HeaderValue::from_static("trailers"));
+        std::future::poll_fn(|cx| self.sender.poll_ready(cx))
+            .await
+            .map_err(grpc_error)?;
+        self.sender.send_request(request).await.map_err(grpc_error)
+    }
+
+    /// A call whose response is read whole, for reflection
+    async fn unary(
+        &mut self,
+        path: &str,
+        messages: &[Vec<u8>],
+    ) -> Result<(Vec<Vec<u8>>, Option<(u32, String)>), RequestError> {
+        let response = self.start(path, messages).await?;
+        let mut headers = header_pairs(response.headers());
+        let mut body = response.into_body();
+        let mut buffer = Vec::new();
+        let mut messages = Vec::new();
+        while let Some(chunk) = body.data().await {
+            buffer.extend_from_slice(&chunk.map_err(grpc_error)?);
+            messages.extend(unframe(&mut buffer)?);
+        }
+        if let Some(trailers) = body.trailers().await.map_err(grpc_error)? {
+            headers.extend(header_pairs(&trailers));
+        }
+        Ok((messages, status(&headers)))
+    }
+}
+
+/// A `ServerReflectionRequest` with one of its string fields set
+fn reflection_request(field: u32, value: &str) -> Vec<u8> {
+    let mut request = Vec::new();
+    protobuf::write_len(&mut request, field, value.as_bytes());
+    request
+}
+
+/// Asks the reflection service, returning the `ServerReflectionResponse`s
+async fn ask(channel: &mut Channel, requests: &[Vec<u8>]) -> Result<Vec<Vec<u8>>, RequestError> {
+    for service in REFLECTION {
+        let (responses, status) = channel
+            .unary(&format!("{service}/ServerReflectionInfo"), requests)
+            .await?;
+        match status {
+            Some((UNIMPLEMENTED, _)) => continue,
+            Some((0, _)) | None => return Ok(responses),
+            Some((code, message)) => {
+                return Err(grpc_error(format!(
+                    "reflection failed with {code} {}: {message}",
+                    status_name(code)
+                )))
+            }
+        }
+    }
+    Err(grpc_error(
+        "the server has no reflection service, set `proto` in the options",
+    ))
+}
+
+/// The schema of `services` as the server describes it, all of its own
+/// services when not given
+async fn reflect(channel: &mut Channel, services: Vec<String>) -> Result<Schema, RequestError> {
+    let services = match services.is_empty() {
+        false => services,
+        true => {
+            let mut services = Vec::new();
+            // list_services
+            for response in ask(channel, &[reflection_request(7, "")]).await? {
+                for (number, value) in protobuf::wire_fields(&response).map_err(grpc_error)? {
+                    // list_services_response.service.name
+                    let (6, Wire::Bytes(list)) = (number, value) else {
+                        continue;
+                    };
+                    for (_, service) in protobuf::wire_fields(list).map_err(grpc_error)? {
+                        let Wire::Bytes(service) = service else {
+                            continue;
+                        };
+                        for (number, name) in protobuf::wire_fields(service).map_err(grpc_error)? {
+                            if let (1, Wire::Bytes(name)) = (number, name) {
+                                services.push(String::from_utf8_lossy(name).into_owned());
+                            }
+                        }
+                    }
+                }
+            }
+            services.retain(|service| !service.starts_with("grpc.reflection."));
+            services
+        }
+    };
+
+    let mut schema = Schema::default();
+    // file_containing_symbol
+    let mut requests: Vec<Vec<u8>> = services
+        .iter()
+        .map(|service| reflection_request(4, service))
+        .collect();
+    for _ in 0..REFLECTION_ROUNDS {
+        if requests.is_empty() {
+            break;
+        }
+        let mut dependencies = Vec::new();
+        for response in ask(channel, &requests).await? {
+            for (number, value) in protobuf::wire_fields(&response).map_err(grpc_error)? {
+                match (number, value) {
+                    // file_descriptor_response.file_descriptor_proto
+                    (4, Wire::Bytes(files)) => {
+                        for (_, file) in protobuf::wire_fields(files).map_err(grpc_error)? {
+                            if let Wire::Bytes(file) = file {
+                                dependencies
+                                    .extend(schema.add_descriptor(file).map_err(grpc_error)?);
+                            }
+                        }
+                    }
+                    // error_response
+                    (7, Wire::Bytes(error)) => {
+                        let message = protobuf::wire_fields(error)
+                            .map_err(grpc_error)?
+                            .into_iter()
+                            .find_map(|field| match field {
+                                (2, Wire::Bytes(message)) => {
+                                    Some(String::from_utf8_lossy(message).into_owned())
+                                }
+                                _ => None,
+                            })
+                            .unwrap_or_default();
+                        return Err(grpc_error(format!("reflection: {message}")));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        dependencies.retain(|file| !schema.has_file(file));
+        dependencies.sort();
+        dependencies.dedup();
+        // file_by_filename
+        requests = dependencies
+            .iter()
+            .map(|file| reflection_request(3, file))
+            .collect();
+    }
+    schema.resolve().map_err(grpc_error)?;
+    Ok(schema)
+}
+
+/// The messages as pretty JSON, a blank line apart
+fn render(schema: &Schema, output: &str, message: &[u8]) -> String {
+    match schema.decode(output, message) {
+        Ok(value) => serde_json::to_string_pretty(&value).unwrap_or_default(),
+        Err(err) => format!("undecodable message: {err}"),
+    }
+}
+
+/// Calls the method in the uri, `grpc://host:port/package.Service/Method`,
+/// with the raw body as the JSON request message. Without a method, the
+/// response lists the server's services instead
+pub async fn call(request: &Request) -> Result<Response, RequestError> {
+    let mut url = Url::parse(&request.uri).map_err(|_| RequestError::InvalidUri)?;
+    let path = url.path().trim_matches('/').to_string();
+    let mut metadata = request.header_map()?;
+    request
+        .auth
+        .apply(&mut url, &mut metadata, request.timeout)
+        .await?;
+
+    let start = Instant::now();
+    let open = Channel::open(&url, metadata, request.unix_socket.as_deref());
+    let mut channel = tokio::time::timeout(request.timeout, open)
+        .await
+        .map_err(|_| RequestError::Timeout(request.timeout))??;
+    let schema = match &request.proto {
+        Some(proto) => Schema::load(proto, &request.proto_paths).map_err(grpc_error)?,
+        None => {
+            let service = path
+                .rsplit_once('/')
+                .map(|(service, _)| service.to_string());
+            let reflect = reflect(&mut channel, service.into_iter().collect());
+            tokio::time::timeout(request.timeout, reflect)
+                .await
+                .map_err(|_| RequestError::Timeout(request.timeout))??
+        }
+    };
+
+    if path.is_empty() {
+        let description = schema.describe();
+        return Ok(Response {
+            status: 200,
+            version: "HTTP/2.0".to_string(),
+            body_size: description.len(),
+            bytes: Bytes::from(description.clone()),
+            body: description,
+            attempts: 1,
+            timing: Timing {
+                first_byte: start.elapsed(),
+                total: start.elapsed(),
+                ..Timing::default()
+            },
+            ..Response::default()
+        });
+    }
+
+    let method = schema
+        .method(&path)
+        .ok_or_else(|| grpc_error(format!("no method `{path}`, send without one to list them")))?;
+    if method.client_streaming {
+        return Err(grpc_error("client streaming methods aren't supported"));
+    }
+    let message = match &request.body {
+        Some(Body::Raw(text)) => {
+            serde_json::from_str(text).map_err(|err| RequestError::InvalidBody(err.to_string()))?
+        }
+        None => Value::Object(Default::default()),
+        Some(_) => {
+            return Err(RequestError::InvalidBody(
+                "gRPC messages are written as JSON in the raw body".to_string(),
+            ))
+        }
+    };
+    let message = schema
+        .encode(&method.input, &message)
+        .map_err(RequestError::InvalidBody)?;
+
+    let response = tokio::time::timeout(request.timeout, channel.start(&path, &[message]))
+        .await
+        .map_err(|_| RequestError::Timeout(request.timeout))??;
+    let first_byte = start.elapsed();
+    let status = response.status().as_u16();
+    let mut headers = header_pairs(response.headers());
+    if let Some(progress) = &request.progress {
+        let _ = progress.send(Message::Started(Box::new(Response {
+            status,
+            version: "HTTP/2.0".to_string(),
+            headers: headers.clone(),
+            ..Response::default()
+        })));
+    }
+
+    let mut body = response.into_body();
+    let mut buffer = Vec::new();
+    let mut text = String::new();
+    let mut wire_size = 0;
+    let mut stopped = false;
+    loop {
+        let chunk = tokio::select! {
+            chunk = body.data() => chunk,
+            () = models::notified(request.stop.as_deref()) => {
+                stopped = true;
+                break;
+            }
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+        let chunk = chunk.map_err(grpc_error)?;
+        wire_size += chunk.len();
+        buffer.extend_from_slice(&chunk);
+        for message in unframe(&mut buffer)? {
+            let mut rendered = render(&schema, &method.output, &message);
+            if !text.is_empty() {
+                rendered.insert_str(0, "\n\n");
+            }
+            if let Some(progress) = &request.progress {
+                let _ = progress.send(Message::Chunk(Bytes::from(rendered.clone())));
+            }
+            text.push_str(&rendered);
+        }
+    }
+    if !stopped {
+        if let Some(trailers) = body.trailers().await.map_err(grpc_error)? {
+            headers.extend(header_pairs(&trailers));
+        }
+    }
+
+    Ok(Response {
+        status,
+        version: "HTTP/2.0".to_string(),
+        headers,
+        body_size: wire_size,
+        bytes: Bytes::from(text.clone()),
+        body: text,
+        attempts: 1,
+        stopped,
+        timing: Timing {
+            first_byte,
+            total: start.elapsed(),
+            ..Timing::default()
+        },
+        ..Response::default()
+    })
+}
+
+/// `grpc-status` and `grpc-message` of a finished call, `None` for `OK`
+pub fn failure(response: &Response) -> Option<String> {
+    let (code, message) = status(&response.headers)?;
+    (code != 0).then(|| {
+        format!("{code} {} {message}", status_name(code))
+            .trim_end()
+            .to_string()
+    })
+}
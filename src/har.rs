@@ -0,0 +1,441 @@
+use std::path::Path;
+
+use base64::Engine;
+use serde_json::{json, Value};
+use url::{form_urlencoded, Url};
+
+use crate::{
+    auth::AuthMode,
+    environments::{self, Part, Variables},
+    history::Entry,
+    import::{self, row, Imported},
+    models::{BodyMode, KeyValueRow, SavedRequest, Syntax},
+};
+
+/// Set by the client for every request, or by the browser and meaningless
+/// on their own
+const SKIPPED_HEADERS: [&str; 5] = [
+    "content-length",
+    "host",
+    "connection",
+    "cookie",
+    "accept-encoding",
+];
+
+pub fn is_har(json: &Value) -> bool {
+    json["log"]["entries"].is_array()
+}
+
+/// Every recorded request goes into a collection named after the file, in a
+/// folder by host. Cookies are left to the cookie jar and credentials out,
+/// like everywhere else requests are saved
+pub fn import(json: &Value, source: &str) -> Imported {
+    let mut imported = Imported::default();
+    let collection = Path::new(source)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(|stem| stem.trim_end_matches(".har"))
+        .unwrap_or("har");
+    let collection = import::name(collection);
+
+    for entry in json["log"]["entries"].as_array().into_iter().flatten() {
+        let request = &entry["request"];
+        let Some(url) = request["url"].as_str().and_then(|url| Url::parse(url).ok()) else {
+            continue;
+        };
+        let host = url.host_str().unwrap_or("unknown").to_string();
+        let folders = [collection.clone(), import::name(&host)];
+        let method = request["method"].as_str().unwrap_or("GET");
+        let last = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|segment| !segment.is_empty())
+            .unwrap_or(&host);
+        imported.push(
+            &folders,
+            &format!("{method} {last}"),
+            self::request(request, url),
+        );
+    }
+    imported
+}
+
+fn rows(list: &Value) -> Vec<KeyValueRow> {
+    list.as_array()
+        .into_iter()
+        .flatten()
+        .map(|entry| {
+            let value = match entry["fileName"].as_str() {
+                Some(file) => format!("@{file}"),
+                None => entry["value"].as_str().unwrap_or_default().to_string(),
+            };
+            row(entry["name"].as_str().unwrap_or_default(), value)
+        })
+        .collect()
+}
+
+fn request(json: &Value, mut url: Url) -> SavedRequest {
+    let params = url
+        .query_pairs()
+        .map(|(key, value)| row(&key, value.into_owned()))
+        .collect();
+    url.set_query(None);
+    let mut request = SavedRequest {
+        method: json["method"].as_str().unwrap_or("GET").to_string(),
+        uri: url.to_string(),
+        params,
+        ..SavedRequest::default()
+    };
+
+    for header in rows(&json["headers"]) {
+        let name = header.key.to_ascii_lowercase();
+        if name.starts_with(':') || SKIPPED_HEADERS.contains(&name.as_str()) {
+            continue;
+        }
+        if name == "authorization" {
+            (request.auth_mode, request.auth) = auth(&header.value);
+            continue;
+        }
+        request.headers.push(header);
+    }
+
+    let body = &json["postData"];
+    let mime = body["mimeType"].as_str().unwrap_or_default();
+    let text = body["text"].as_str().unwrap_or_default().to_string();
+    match mime.split(';').next().unwrap_or_default().trim() {
+        "application/x-www-form-urlencoded" => {
+            request.body_mode = BodyMode::Form;
+            request.form = match body["params"].as_array().is_some_and(|p| !p.is_empty()) {
+                true => rows(&body["params"]),
+                false => form_urlencoded::parse(text.as_bytes())
+                    .map(|(key, value)| row(&key, value.into_owned()))
+                    .collect(),
+            };
+        }
+        // The boundary in the recorded header wouldn't match a new body
+        "multipart/form-data" => {
+            request.body_mode = BodyMode::Multipart;
+            request.multipart = rows(&body["params"]);
+            request
+                .headers
+                .retain(|h| !h.key.eq_ignore_ascii_case("content-type"));
+        }
+        mime => {
+            request.syntax = match mime {
+                mime if mime.contains("json") => Syntax::Json,
+                mime if mime.contains("xml") => Syntax::Xml,
+                _ => Syntax::Text,
+            };
+            request.body = text;
+        }
+    }
+    request
+}
+
+/// The scheme is kept, the credentials aren't
+fn auth(value: &str) -> (AuthMode, Vec<KeyValueRow>) {
+    let (scheme, credentials) = value.split_once(' ').unwrap_or((value, ""));
+    match scheme.to_ascii_lowercase().as_str() {
+        "basic" => {
+            let decoded = base64::engine::general_purpose::STANDARD
+                .decode(credentials.trim())
+                .ok()
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .unwrap_or_default();
+            let username = decoded.split(':').next().unwrap_or_default();
+            (AuthMode::Basic, vec![row("username", username.to_string())])
+        }
+        "bearer" => (AuthMode::Bearer, Vec::new()),
+        _ => (AuthMode::None, Vec::new()),
+    }
+}
+
+/// HAR 1.2 of the sent requests with their status and time. Variables are
+/// filled in where known, responses are only as complete as history keeps
+/// them. Responses a request was sent again after, like 401s, are entries
+/// of their own
+pub fn export(entries: &[Entry], variables: &Variables) -> Value {
+    let entries: Vec<_> = entries
+        .iter()
+        .flat_map(|entry| {
+            let exported = export_entry(entry, variables);
+            let attempts = entry.attempts.iter().map(|attempt| {
+                let mut exported = exported.clone();
+                exported["response"]["status"] = Value::from(attempt.status);
+                if let Some(challenge) = &attempt.challenge {
+                    exported["response"]["headers"] =
+                        json!([{ "name": "WWW-Authenticate", "value": challenge }]);
+                }
+                exported["time"] = Value::from(attempt.duration_ms);
+                exported["timings"]["wait"] = Value::from(attempt.duration_ms);
+                exported
+            });
+            attempts.collect::<Vec<_>>().into_iter().chain([exported])
+        })
+        .collect();
+    json!({
+        "log": {
+            "version": "1.2",
+            "creator": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+            "entries": entries,
+        }
+    })
+}
+
+/// Unknown and dynamic placeholders are left as written, what they were
+/// sent as isn't kept
+fn fill(text: &str, variables: &Variables) -> String {
+    environments::parts(text)
+        .into_iter()
+        .map(|part| match part {
+            Part::Text(text) => text.to_string(),
+            Part::Variable(name) => variables
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| format!("{{{{{name}}}}}")),
+        })
+        .collect()
+}
+
+fn export_rows(rows: &[KeyValueRow], variables: &Variables) -> Vec<Value> {
+    rows.iter()
+        .filter(|row| row.enabled && !row.key.is_empty())
+        .map(|row| json!({ "name": fill(&row.key, variables), "value": fill(&row.value, variables) }))
+        .collect()
+}
+
+fn export_entry(entry: &Entry, variables: &Variables) -> Value {
+    let request = &entry.request;
+    let method = match request.method.trim() {
+        "" => "GET",
+        method => method,
+    };
+    let query = export_rows(&request.params, variables);
+    let uri = fill(&request.uri, variables);
+    let url = match Url::parse(&uri) {
+        Ok(mut url) => {
+            for param in &query {
+                let (key, value) = (param["name"].as_str(), param["value"].as_str());
+                url.query_pairs_mut()
+                    .append_pair(key.unwrap_or_default(), value.unwrap_or_default());
+            }
+            url.to_string()
+        }
+        Err(_) => uri,
+    };
+
+    let mut headers = export_rows(&request.headers, variables);
+    let content_type = headers
+        .iter()
+        .find(|h| {
+            h["name"]
+                .as_str()
+                .is_some_and(|n| n.eq_ignore_ascii_case("content-type"))
+        })
+        .and_then(|h| h["value"].as_str())
+        .map(str::to_string);
+    let post_data = match request.body_mode {
+        BodyMode::Raw if request.body.is_empty() => Value::Null,
+        BodyMode::Raw => {
+            let mime = content_type.clone().unwrap_or_else(|| {
+                match request.syntax {
+                    Syntax::Json => "application/json",
+                    Syntax::Xml => "application/xml",
+                    Syntax::Text | Syntax::GraphQl => "text/plain",
+                }
+                .to_string()
+            });
+            json!({ "mimeType": mime, "text": fill(&request.body, variables) })
+        }
+        BodyMode::Form => {
+            let params = export_rows(&request.form, variables);
+            let text = form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(params.iter().map(|p| {
+                    (
+                        p["name"].as_str().unwrap_or_default(),
+                        p["value"].as_str().unwrap_or_default(),
+                    )
+                }))
+                .finish();
+            json!({ "mimeType": "application/x-www-form-urlencoded", "params": params, "text": text })
+        }
+        BodyMode::Multipart => {
+            let mut params = export_rows(&request.multipart, variables);
+            for param in &mut params {
+                if let Some(file) = param["value"].as_str().and_then(|v| v.strip_prefix('@')) {
+                    param["fileName"] = Value::from(file);
+                    param["value"] = Value::from("");
+                }
+            }
+            json!({ "mimeType": "multipart/form-data", "params": params, "text": "" })
+        }
+        BodyMode::Binary => json!({
+            "mimeType": content_type.clone().unwrap_or_else(|| "application/octet-stream".to_string()),
+            "text": "",
+            "comment": fill(&request.file, variables),
+        }),
+        BodyMode::GraphQl => {
+            let variables_json: Value =
+                serde_json::from_str(&fill(&request.variables, variables)).unwrap_or(Value::Null);
+            let text =
+                json!({ "query": fill(&request.query, variables), "variables": variables_json });
+            json!({ "mimeType": "application/json", "text": text.to_string() })
+        }
+    };
+    if let (None, Some(mime)) = (&content_type, post_data["mimeType"].as_str()) {
+        headers.push(json!({ "name": "Content-Type", "value": mime }));
+    }
+
+    let mut har_request = json!({
+        "method": method,
+        "url": url,
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": headers,
+        "queryString": query,
+        "headersSize": -1,
+        "bodySize": -1,
+    });
+    if !post_data.is_null() {
+        har_request["postData"] = post_data;
+    }
+
+    let mut response = json!({
+        "status": entry.status.unwrap_or(0),
+        "statusText": "",
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": [],
+        "content": { "size": -1, "mimeType": "" },
+        "redirectURL": "",
+        "headersSize": -1,
+        "bodySize": -1,
+    });
+    // Where browsers put requests that never got a response
+    if let Some(error) = &entry.error {
+        response["_error"] = Value::from(error.as_str());
+    }
+
+    json!({
+        "startedDateTime": entry.time,
+        "time": entry.duration_ms,
+        "request": har_request,
+        "response": response,
+        "cache": {},
+        "timings": { "send": 0, "wait": entry.duration_ms, "receive": 0 },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{import::pairs, models::Response};
+
+    #[test]
+    fn imports_entries() {
+        let har = json!({ "log": { "entries": [
+            { "request": {
+                "method": "POST",
+                "url": "https://api.example.com/v1/users?page=2&q=a%20b",
+                "headers": [
+                    { "name": ":authority", "value": "api.example.com" },
+                    { "name": "Host", "value": "api.example.com" },
+                    { "name": "Authorization", "value": "Basic dXNlcjpzZWNyZXQ=" },
+                    { "name": "Content-Type", "value": "application/json; charset=utf-8" },
+                ],
+                "postData": { "mimeType": "application/json; charset=utf-8", "text": "{\"a\":1}" },
+            } },
+            { "request": {
+                "method": "POST",
+                "url": "https://api.example.com/v1/users",
+                "headers": [{ "name": "Content-Type", "value": "multipart/form-data; boundary=x" }],
+                "postData": { "mimeType": "multipart/form-data; boundary=x", "params": [
+                    { "name": "name", "value": "a" },
+                    { "name": "photo", "fileName": "photo.png" },
+                ] },
+            } },
+            { "request": {
+                "method": "PUT",
+                "url": "http://localhost:8080/",
+                "postData": { "mimeType": "application/x-www-form-urlencoded", "text": "a=1&b=x+y" },
+            } },
+            { "request": { "method": "GET", "url": "not a url" } },
+        ] } });
+        assert!(is_har(&har));
+        let imported = import(&har, "/tmp/session.har");
+        let paths: Vec<&str> = imported
+            .requests
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "session/api.example.com/POST users",
+                "session/api.example.com/POST users 2",
+                "session/localhost/PUT localhost",
+            ]
+        );
+
+        let json = &imported.requests[0].1;
+        assert_eq!(json.uri, "https://api.example.com/v1/users");
+        assert_eq!(pairs(&json.params), [("page", "2"), ("q", "a b")]);
+        assert_eq!(
+            pairs(&json.headers),
+            [("Content-Type", "application/json; charset=utf-8")]
+        );
+        // The password stays out
+        assert_eq!(json.auth_mode, AuthMode::Basic);
+        assert_eq!(pairs(&json.auth), [("username", "user")]);
+        assert_eq!(json.syntax, Syntax::Json);
+        assert_eq!(json.body, "{\"a\":1}");
+
+        let multipart = &imported.requests[1].1;
+        assert_eq!(multipart.body_mode, BodyMode::Multipart);
+        assert_eq!(
+            pairs(&multipart.multipart),
+            [("name", "a"), ("photo", "@photo.png")]
+        );
+        assert!(multipart.headers.is_empty());
+
+        let form = &imported.requests[2].1;
+        assert_eq!(form.body_mode, BodyMode::Form);
+        assert_eq!(pairs(&form.form), [("a", "1"), ("b", "x y")]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let request = SavedRequest {
+            method: "POST".to_string(),
+            uri: "{{host}}/login".to_string(),
+            params: vec![row("next", "{{path}}".to_string())],
+            headers: vec![row("X-Trace", "{{unknown}}".to_string())],
+            body_mode: BodyMode::Form,
+            form: vec![row("user", "a b".to_string())],
+            ..SavedRequest::default()
+        };
+        let entry = Entry::new(request, &Ok(Response::default()), 5);
+        let variables = Variables::from([
+            ("host".to_string(), "https://example.com".to_string()),
+            ("path".to_string(), "/home".to_string()),
+        ]);
+        let exported = export(&[entry], &variables);
+        let har_request = &exported["log"]["entries"][0]["request"];
+        assert_eq!(har_request["url"], "https://example.com/login?next=%2Fhome");
+        assert_eq!(har_request["postData"]["text"], "user=a+b");
+
+        let imported = import(&exported, "export.har");
+        let (path, request) = &imported.requests[0];
+        assert_eq!(path, "export/example.com/POST login");
+        assert_eq!(request.uri, "https://example.com/login");
+        assert_eq!(pairs(&request.params), [("next", "/home")]);
+        assert_eq!(
+            pairs(&request.headers),
+            [
+                ("X-Trace", "{{unknown}}"),
+                ("Content-Type", "application/x-www-form-urlencoded"),
+            ]
+        );
+        assert_eq!(pairs(&request.form), [("user", "a b")]);
+    }
+}
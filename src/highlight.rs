@@ -0,0 +1,167 @@
+use ratatui::{
+    style::Style,
+    text::{Line, Span},
+};
+
+use crate::theme;
+
+/// Colors one line of pretty printed JSON, strings never span lines there
+pub fn json_line(line: &str) -> Line<'_> {
+    let theme = theme::current();
+    let mut spans = Vec::new();
+    let mut rest = line;
+
+    while let Some(c) = rest.chars().next() {
+        let (len, color) = match c {
+            '"' => {
+                let mut escaped = false;
+                let end = rest[1..]
+                    .char_indices()
+                    .find(|&(_, c)| {
+                        let close = c == '"' && !escaped;
+                        escaped = c == '\\' && !escaped;
+                        close
+                    })
+                    .map_or(rest.len(), |(index, _)| index + 2);
+                let is_key = rest[end..].trim_start().starts_with(':');
+                (end, Some(if is_key { theme.focus } else { theme.success }))
+            }
+            '-' | '0'..='9' => {
+                let end = rest
+                    .find(|c: char| !matches!(c, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'))
+                    .unwrap_or(rest.len());
+                (end, Some(theme.accent))
+            }
+            't' | 'f' | 'n' => {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphabetic())
+                    .unwrap_or(rest.len());
+                (end, Some(theme.literal))
+            }
+            _ => (c.len_utf8(), None),
+        };
+
+        let (token, tail) = rest.split_at(len);
+        spans.push(match color {
+            Some(color) => Span::styled(token, Style::default().fg(color)),
+            None => Span::raw(token),
+        });
+        rest = tail;
+    }
+
+    Line::from(spans)
+}
+
+/// Dims the offset and colors the ASCII column of a hex dump line
+pub fn hex_line(line: &str) -> Line<'_> {
+    let theme = theme::current();
+    let (offset, rest) = line.split_at(line.find(' ').unwrap_or(line.len()));
+    let (bytes, ascii) = rest.split_at(rest.find('|').unwrap_or(rest.len()));
+    Line::from(vec![
+        Span::styled(offset, Style::default().fg(theme.muted)),
+        Span::raw(bytes),
+        Span::styled(ascii, Style::default().fg(theme.focus)),
+    ])
+}
+
+/// Colors added and removed lines of a diff by their prefix
+pub fn diff_line(line: &str) -> Line<'_> {
+    let theme = theme::current();
+    let color = match line.as_bytes().first() {
+        Some(b'+') => theme.success,
+        Some(b'-') => theme.error,
+        _ => return Line::from(line),
+    };
+    Line::from(Span::styled(line, Style::default().fg(color)))
+}
+
+/// Byte ranges of every case insensitive occurrence of `query`
+pub fn find(line: &str, query: &str) -> Vec<(usize, usize)> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let line = line.to_ascii_lowercase();
+    let query = query.to_ascii_lowercase();
+    line.match_indices(&query)
+        .map(|(start, found)| (start, start + found.len()))
+        .collect()
+}
+
+/// Restyles the byte ranges of a line, keeping the colors of the rest
+pub fn mark<'a>(line: Line<'a>, ranges: &[(usize, usize, Style)]) -> Line<'a> {
+    if ranges.is_empty() {
+        return line;
+    }
+
+    let mut spans = Vec::new();
+    let mut offset = 0;
+    for span in line.spans {
+        let content = span.content.as_ref();
+        let end = offset + content.len();
+        let mut cursor = offset;
+
+        for &(start, stop, style) in ranges {
+            let (start, stop) = (start.max(cursor), stop.min(end));
+            if start >= stop {
+                continue;
+            }
+            if start > cursor {
+                spans.push(Span::styled(
+                    content[cursor - offset..start - offset].to_string(),
+                    span.style,
+                ));
+            }
+            spans.push(Span::styled(
+                content[start - offset..stop - offset].to_string(),
+                span.style.patch(style),
+            ));
+            cursor = stop;
+        }
+        if cursor < end {
+            spans.push(Span::styled(
+                content[cursor - offset..].to_string(),
+                span.style,
+            ));
+        }
+        offset = end;
+    }
+
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::Color;
+
+    use super::*;
+
+    /// Each span's text with its color
+    fn colors(line: Line) -> Vec<(String, Option<Color>)> {
+        line.spans
+            .into_iter()
+            .filter(|span| !span.content.trim().is_empty())
+            .map(|span| (span.content.to_string(), span.style.fg))
+            .collect()
+    }
+
+    #[test]
+    fn colors_json() {
+        let theme = theme::current();
+        assert_eq!(
+            colors(json_line(r#"  "k\"ey": ["v", -1.5e3, null],"#)),
+            [
+                (r#""k\"ey""#.to_string(), Some(theme.focus)),
+                (":".to_string(), None),
+                ("[".to_string(), None),
+                (r#""v""#.to_string(), Some(theme.success)),
+                (",".to_string(), None),
+                ("-1.5e3".to_string(), Some(theme.accent)),
+                (",".to_string(), None),
+                ("null".to_string(), Some(theme.literal)),
+                ("]".to_string(), None),
+                (",".to_string(), None),
+            ]
+        );
+    }
+}
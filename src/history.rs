@@ -0,0 +1,330 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::OnceLock,
+};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{RequestError, Response, SavedRequest};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// RFC 3339, local time
+    pub time: String,
+    pub request: SavedRequest,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    /// Responses before the final one that were answered by sending again,
+    /// oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attempts: Vec<Attempt>,
+}
+
+/// A response the request was sent again after, like a 401 answered with
+/// new credentials
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Attempt {
+    pub status: u16,
+    /// The `WWW-Authenticate` header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<String>,
+    /// Since the request was sent
+    pub duration_ms: u64,
+}
+
+impl Entry {
+    pub fn new(
+        request: SavedRequest,
+        response: &Result<Response, RequestError>,
+        duration_ms: u64,
+    ) -> Self {
+        let (status, error, attempts) = match response {
+            Ok(resp) => (
+                Some(resp.status),
+                None,
+                resp.challenged.iter().cloned().collect(),
+            ),
+            Err(err) => (None, Some(err.to_string()), Vec::new()),
+        };
+        Self {
+            time: Local::now().to_rfc3339(),
+            request,
+            status,
+            error,
+            duration_ms,
+            attempts,
+        }
+    }
+}
+
+/// Every sent request, appended as JSON lines to the history file
+#[derive(Default)]
+pub struct History {
+    path: Option<PathBuf>,
+    /// Oldest first
+    pub entries: Vec<Entry>,
+    /// How many of the entries were sent before the app started
+    pub earlier: usize,
+}
+
+impl History {
+    /// Keeping the newest `size` entries, the file is rewritten when there
+    /// were more
+    pub fn load(size: Option<usize>) -> Self {
+        let path = data_dir().map(|dir| dir.join("history.jsonl"));
+        let mut entries: Vec<Entry> = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| {
+                text.lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if let (Some(size), Some(path)) = (size, &path) {
+            if entries.len() > size {
+                entries.drain(..entries.len() - size);
+                // Still kept in memory when it can't be rewritten
+                let _ = rewrite(path, &entries);
+            }
+        }
+        Self {
+            path,
+            earlier: entries.len(),
+            entries,
+        }
+    }
+
+    pub fn record(&mut self, entry: Entry) -> Result<(), String> {
+        let result = match &self.path {
+            Some(path) => append(path, &entry).map_err(|err| format!("can't save history: {err}")),
+            None => Ok(()),
+        };
+        self.entries.push(entry);
+        result
+    }
+
+    /// Sent since the app started
+    pub fn session(&self) -> &[Entry] {
+        &self.entries[self.earlier.min(self.entries.len())..]
+    }
+}
+
+/// Search typed into the history pane, the entry must match every word:
+/// `2xx` or `err` for a status class, an uppercase `POST` for a method,
+/// `2026-10-14`, `>2026-10` or `<2026-10-01` for dates and anything else as a
+/// URL substring
+#[derive(Default)]
+pub struct HistoryView {
+    pub query: String,
+    /// Counted from the newest match
+    pub selected: usize,
+}
+
+impl HistoryView {
+    pub fn entries<'h>(&self, history: &'h History) -> Vec<&'h Entry> {
+        let words: Vec<_> = self.query.split_whitespace().collect();
+        history
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| words.iter().all(|word| Self::matches(entry, word)))
+            .collect()
+    }
+
+    fn matches(entry: &Entry, word: &str) -> bool {
+        let is_date =
+            |date: &str| date.contains('-') && date.chars().all(|c| c.is_ascii_digit() || c == '-');
+        let status_class = match word.as_bytes() {
+            [class @ b'1'..=b'5', b'x', b'x'] => Some((class - b'0') as u16),
+            _ => None,
+        };
+        // RFC 3339 times sort like the date prefixes they're compared with
+        let time = entry.time.as_str();
+
+        match word {
+            "err" => entry.status.is_none(),
+            _ if status_class.is_some() => entry.status.map(|status| status / 100) == status_class,
+            _ if word.chars().all(|c| c.is_ascii_uppercase()) => {
+                match entry.request.method.trim() {
+                    "" => word == "GET",
+                    method => method.eq_ignore_ascii_case(word),
+                }
+            }
+            _ if word.starts_with('>') && is_date(&word[1..]) => time >= &word[1..],
+            _ if word.starts_with('<') && is_date(&word[1..]) => time < &word[1..],
+            _ if is_date(word) => time.starts_with(word),
+            _ => entry
+                .request
+                .uri
+                .to_ascii_lowercase()
+                .contains(&word.to_ascii_lowercase()),
+        }
+    }
+}
+
+fn append(path: &PathBuf, entry: &Entry) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(line.as_bytes())
+}
+
+fn rewrite(path: &PathBuf, entries: &[Entry]) -> std::io::Result<()> {
+    let mut text = String::new();
+    for entry in entries {
+        text += &serde_json::to_string(entry)?;
+        text.push('\n');
+    }
+    fs::write(path, text)
+}
+
+/// Set from the config before anything is read
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn set_data_dir(dir: PathBuf) {
+    let _ = DATA_DIR.set(dir);
+}
+
+/// The configured directory, or `$XDG_DATA_HOME/curl-rs`, falling back to
+/// `~/.local/share/curl-rs`
+pub fn data_dir() -> Option<PathBuf> {
+    if let Some(dir) = DATA_DIR.get() {
+        return Some(dir.clone());
+    }
+    let base = match std::env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(base.join("curl-rs"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_challenged_attempt() {
+        let response = Ok(Response {
+            status: 200,
+            challenged: Some(Attempt {
+                status: 401,
+                challenge: Some("Digest realm=\"api\"".to_string()),
+                duration_ms: 12,
+            }),
+            ..Response::default()
+        });
+        let entry = Entry::new(SavedRequest::default(), &response, 30);
+        assert_eq!(entry.status, Some(200));
+        assert_eq!(entry.attempts.len(), 1);
+
+        let line = serde_json::to_string(&entry).unwrap();
+        let entry: Entry = serde_json::from_str(&line).unwrap();
+        let attempt = &entry.attempts[0];
+        assert_eq!(attempt.status, 401);
+        assert_eq!(attempt.challenge.as_deref(), Some("Digest realm=\"api\""));
+        assert_eq!(attempt.duration_ms, 12);
+    }
+
+    #[test]
+    fn reads_entries_without_attempts() {
+        let entry = Entry::new(SavedRequest::default(), &Ok(Response::default()), 5);
+        let line = serde_json::to_string(&entry).unwrap();
+        assert!(!line.contains("attempts"));
+        let entry: Entry = serde_json::from_str(&line).unwrap();
+        assert!(entry.attempts.is_empty());
+    }
+
+    fn entry(method: &str, uri: &str, status: Option<u16>, time: &str) -> Entry {
+        let request = SavedRequest {
+            method: method.to_string(),
+            uri: uri.to_string(),
+            ..SavedRequest::default()
+        };
+        Entry {
+            time: time.to_string(),
+            status,
+            error: status.is_none().then(|| "timed out".to_string()),
+            ..Entry::new(request, &Ok(Response::default()), 10)
+        }
+    }
+
+    #[test]
+    fn appends_entries() {
+        let path = std::env::temp_dir().join(format!("curl-rs-history-{}", std::process::id()));
+        let mut history = History {
+            path: Some(path.join("history.jsonl")),
+            entries: vec![entry("GET", "http://earlier", Some(200), "")],
+            earlier: 1,
+        };
+        history
+            .record(entry("POST", "http://a", Some(201), ""))
+            .unwrap();
+        history.record(entry("", "http://b", None, "")).unwrap();
+        assert_eq!(history.session().len(), 2);
+
+        let text = fs::read_to_string(path.join("history.jsonl")).unwrap();
+        fs::remove_dir_all(&path).unwrap();
+        let uris: Vec<String> = text
+            .lines()
+            .map(|line| serde_json::from_str::<Entry>(line).unwrap().request.uri)
+            .collect();
+        assert_eq!(uris, ["http://a", "http://b"]);
+    }
+
+    #[test]
+    fn searches_history() {
+        let history = History {
+            entries: vec![
+                entry(
+                    "",
+                    "http://api/users",
+                    Some(200),
+                    "2026-09-30T10:00:00+00:00",
+                ),
+                entry(
+                    "post",
+                    "http://api/users",
+                    Some(422),
+                    "2026-10-01T10:00:00+00:00",
+                ),
+                entry(
+                    "DELETE",
+                    "http://API/items",
+                    None,
+                    "2026-10-14T10:00:00+00:00",
+                ),
+            ],
+            ..History::default()
+        };
+        let found = |query: &str| {
+            let view = HistoryView {
+                query: query.to_string(),
+                selected: 0,
+            };
+            view.entries(&history)
+                .iter()
+                .map(|entry| entry.time[..10].to_string())
+                .collect::<Vec<_>>()
+        };
+        // Newest first
+        assert_eq!(found(""), ["2026-10-14", "2026-10-01", "2026-09-30"]);
+        assert_eq!(found("GET"), ["2026-09-30"]);
+        assert_eq!(found("POST 4xx"), ["2026-10-01"]);
+        assert_eq!(found("err"), ["2026-10-14"]);
+        assert_eq!(found("api/ITEMS"), ["2026-10-14"]);
+        assert_eq!(found(">2026-10 users"), ["2026-10-01"]);
+        assert_eq!(found("<2026-10-01"), ["2026-09-30"]);
+        assert_eq!(found("2026-10-14 2xx"), Vec::<String>::new());
+    }
+}
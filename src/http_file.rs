@@ -0,0 +1,494 @@
+use std::path::Path;
+
+use crate::{
+    auth::AuthMode,
+    collections::{Collection, Folder},
+    environments::Variables,
+    import::{self, row, Imported},
+    models::{BodyMode, KeyValueRow, SavedRequest, Syntax},
+};
+
+/// Dynamic variables of the JetBrains and VS Code clients and the built-in
+/// ones they're read as, the first of each is what's written
+const DYNAMIC: [(&str, &str); 6] = [
+    ("{{$uuid}}", "{{uuid}}"),
+    ("{{$random.uuid}}", "{{uuid}}"),
+    ("{{$guid}}", "{{uuid}}"),
+    ("{{$timestamp}}", "{{timestamp}}"),
+    ("{{$isoTimestamp}}", "{{isoTimestamp}}"),
+    ("{{$randomInt}}", "{{randomInt}}"),
+];
+
+const METHODS: [&str; 10] = [
+    "GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS", "TRACE", "CONNECT", "GRAPHQL",
+];
+
+pub fn is_http_file(path: &str) -> bool {
+    let extension = Path::new(path).extension().and_then(|ext| ext.to_str());
+    matches!(extension, Some("http" | "rest"))
+}
+
+/// A `.http` or `.rest` file as a collection named after it. Requests are
+/// split by `###` lines and named by `# @name` or the separator's text, file
+/// variables (`@name = value`) go into an environment of the same name.
+/// Response handlers and multipart bodies are kept as written, they have no
+/// counterpart
+pub fn import(text: &str, source: &str) -> Imported {
+    let mut imported = Imported::default();
+    let collection = Path::new(source)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("http");
+    let collection = import::name(collection);
+    let mut variables = Variables::new();
+
+    let mut blocks = vec![(None, Vec::new())];
+    for line in text.lines() {
+        match line.strip_prefix("###") {
+            Some(name) => {
+                let name = name.trim().trim_start_matches('#').trim();
+                blocks.push(((!name.is_empty()).then(|| name.to_string()), Vec::new()));
+            }
+            None => blocks.last_mut().unwrap().1.push(line),
+        }
+    }
+
+    for (separator, lines) in blocks {
+        let mut name = separator;
+        let mut lines = lines.into_iter().peekable();
+        let request_line = loop {
+            let Some(line) = lines.next() else {
+                break None;
+            };
+            let trimmed = line.trim();
+            let comment = trimmed
+                .strip_prefix('#')
+                .or_else(|| trimmed.strip_prefix("//"));
+            if let Some(comment) = comment {
+                if let Some(named) = comment.trim().strip_prefix("@name") {
+                    name = Some(named.trim_start_matches([' ', '=']).trim().to_string());
+                }
+            } else if let Some((key, value)) = trimmed
+                .strip_prefix('@')
+                .and_then(|variable| variable.split_once('='))
+            {
+                variables.insert(key.trim().to_string(), text_of(value.trim()));
+            } else if !trimmed.is_empty() {
+                break Some(trimmed);
+            }
+        };
+        let Some(request_line) = request_line else {
+            continue;
+        };
+
+        let (method, mut uri) = match request_line.split_once(' ') {
+            Some((method, uri)) if METHODS.contains(&method) => (method, uri.trim().to_string()),
+            _ => ("GET", request_line.to_string()),
+        };
+        if let Some((rest, version)) = uri.rsplit_once(' ') {
+            if version.starts_with("HTTP/") {
+                uri = rest.trim().to_string();
+            }
+        }
+        // The JetBrains client allows the query on the following lines
+        while let Some(line) = lines.next_if(|line| line.trim().starts_with(['?', '&'])) {
+            uri.push_str(line.trim());
+        }
+
+        let mut headers = Vec::new();
+        for line in lines.by_ref() {
+            let line = line.trim();
+            if line.is_empty() {
+                break;
+            }
+            if line.starts_with('#') || line.starts_with("//") {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once(':') {
+                headers.push(row(key.trim(), text_of(value.trim())));
+            }
+        }
+
+        let mut body: Vec<&str> = lines
+            .take_while(|line| !line.starts_with('>') && !line.starts_with("<>"))
+            .collect();
+        while body.last().is_some_and(|line| line.trim().is_empty()) {
+            body.pop();
+        }
+        let body = text_of(&body.join("\n"));
+
+        // Folders as written by export
+        let name = name.unwrap_or_else(|| {
+            let path = uri.split('?').next().unwrap_or_default();
+            format!("{method} {}", path.rsplit('/').next().unwrap_or_default())
+        });
+        let mut path: Vec<String> = name.split('/').map(import::name).collect();
+        let name = path.pop().unwrap_or_default();
+        path.insert(0, collection.clone());
+        imported.push(&path, &name, request(method, &uri, headers, body));
+    }
+
+    if !variables.is_empty() {
+        imported.environments.push((collection, variables));
+    }
+    imported
+}
+
+/// With the other clients' dynamic variables renamed to the built-in ones
+fn text_of(text: &str) -> String {
+    let mut text = text.to_string();
+    for (theirs, own) in DYNAMIC {
+        text = text.replace(theirs, own);
+    }
+    text
+}
+
+/// `a=1&b=2`, left undecoded since values may hold placeholders
+fn pairs(text: &str) -> Vec<KeyValueRow> {
+    text.split('&')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            row(key, value.to_string())
+        })
+        .collect()
+}
+
+fn request(method: &str, uri: &str, mut headers: Vec<KeyValueRow>, body: String) -> SavedRequest {
+    let (uri, query) = uri.split_once('?').unwrap_or((uri, ""));
+    let mut request = SavedRequest {
+        method: method.to_string(),
+        uri: text_of(uri),
+        params: pairs(&text_of(query)),
+        ..SavedRequest::default()
+    };
+    let content_type = headers
+        .iter()
+        .find(|h| h.key.eq_ignore_ascii_case("content-type"))
+        .map(|h| h.value.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    // VS Code marks GraphQL with a header, JetBrains with the method. The
+    // variables follow the query after a blank line
+    let graphql = headers.iter().any(|h| {
+        h.key.eq_ignore_ascii_case("x-request-type") && h.value.eq_ignore_ascii_case("graphql")
+    });
+    if graphql || method == "GRAPHQL" {
+        headers.retain(|h| !h.key.eq_ignore_ascii_case("x-request-type"));
+        let (query, variables) = body.split_once("\n\n").unwrap_or((&body, ""));
+        request.method = "POST".to_string();
+        request.body_mode = BodyMode::GraphQl;
+        request.query = query.trim().to_string();
+        request.variables = variables.trim().to_string();
+    } else if let Some(file) = body
+        .trim()
+        .strip_prefix("< ")
+        .filter(|_| !body.contains('\n'))
+    {
+        request.body_mode = BodyMode::Binary;
+        request.file = file.trim().to_string();
+    } else if content_type.starts_with("application/x-www-form-urlencoded") {
+        request.body_mode = BodyMode::Form;
+        request.form = pairs(&body.replace('\n', ""));
+        headers.retain(|h| !h.key.eq_ignore_ascii_case("content-type"));
+    } else {
+        request.syntax = match content_type {
+            mime if mime.contains("json") => Syntax::Json,
+            mime if mime.contains("xml") => Syntax::Xml,
+            _ => Syntax::Text,
+        };
+        request.body = body;
+    }
+    request.headers = headers;
+    request
+}
+
+/// The collection as one `.http` file, every request named after its path
+/// within the collection, `variables` as file variables. Secrets aren't
+/// saved, so auth that needs them refers to variables to define
+pub fn export(collection: &Collection, variables: &Variables) -> String {
+    let mut text = String::new();
+    for (key, value) in variables {
+        text.push_str(&format!("@{key} = {}\n", export_text(value)));
+    }
+    export_folder(&collection.root, "", &mut text);
+    text
+}
+
+/// With the built-in dynamic variables renamed to the other clients'
+fn export_text(text: &str) -> String {
+    let mut text = text.to_string();
+    for (theirs, own) in DYNAMIC {
+        text = text.replace(own, theirs);
+    }
+    text
+}
+
+fn export_folder(folder: &Folder, prefix: &str, text: &mut String) {
+    for item in &folder.requests {
+        let name = format!("{prefix}{}", item.name);
+        export_request(&name, &item.request, text);
+    }
+    for child in &folder.folders {
+        export_folder(child, &format!("{prefix}{}/", child.name), text);
+    }
+}
+
+fn export_request(name: &str, request: &SavedRequest, text: &mut String) {
+    let enabled = |rows: &[KeyValueRow]| -> Vec<(String, String)> {
+        rows.iter()
+            .filter(|row| row.enabled && !row.key.is_empty())
+            .map(|row| (export_text(&row.key), export_text(&row.value)))
+            .collect()
+    };
+    let mut query = enabled(&request.params);
+    let mut headers = enabled(&request.headers);
+    let has_header = |headers: &[(String, String)], name: &str| {
+        headers
+            .iter()
+            .any(|(key, _)| key.eq_ignore_ascii_case(name))
+    };
+    let auth = |key: &str| {
+        request
+            .auth
+            .iter()
+            .find(|row| row.key == key)
+            .map(|row| row.value.trim().to_string())
+            .unwrap_or_default()
+    };
+    let mut notes = Vec::new();
+    match request.auth_mode {
+        AuthMode::None => {}
+        AuthMode::Basic => {
+            let value = format!("Basic {}:{{{{password}}}}", auth("username"));
+            headers.push(("Authorization".to_string(), value));
+        }
+        AuthMode::Bearer => {
+            headers.push(("Authorization".to_string(), "Bearer {{token}}".to_string()))
+        }
+        AuthMode::ApiKey => {
+            let entry = (auth("key"), "{{apiKey}}".to_string());
+            match auth("add to").as_str() {
+                "query" => query.push(entry),
+                _ => headers.push(entry),
+            }
+        }
+        mode => notes.push(format!("# {} auth can't be written here", mode.title())),
+    }
+
+    let body = match request.body_mode {
+        BodyMode::Raw if request.body.is_empty() => None,
+        BodyMode::Raw => {
+            if !has_header(&headers, "content-type") {
+                let mime = match request.syntax {
+                    Syntax::Json => Some("application/json"),
+                    Syntax::Xml => Some("application/xml"),
+                    Syntax::Text | Syntax::GraphQl => None,
+                };
+                if let Some(mime) = mime {
+                    headers.push(("Content-Type".to_string(), mime.to_string()));
+                }
+            }
+            Some(export_text(&request.body))
+        }
+        BodyMode::Form => {
+            headers.retain(|(key, _)| !key.eq_ignore_ascii_case("content-type"));
+            headers.push((
+                "Content-Type".to_string(),
+                "application/x-www-form-urlencoded".to_string(),
+            ));
+            let pairs: Vec<_> = enabled(&request.form)
+                .into_iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect();
+            Some(pairs.join("\n&"))
+        }
+        BodyMode::Multipart => {
+            let boundary = "curl-rs-boundary";
+            headers.retain(|(key, _)| !key.eq_ignore_ascii_case("content-type"));
+            headers.push((
+                "Content-Type".to_string(),
+                format!("multipart/form-data; boundary={boundary}"),
+            ));
+            let mut body = String::new();
+            for (key, value) in enabled(&request.multipart) {
+                body.push_str(&format!("--{boundary}\n"));
+                match value.strip_prefix('@') {
+                    Some(file) => {
+                        let file_name = Path::new(file)
+                            .file_name()
+                            .and_then(|name| name.to_str())
+                            .unwrap_or(file);
+                        body.push_str(&format!(
+                            "Content-Disposition: form-data; name=\"{key}\"; filename=\"{file_name}\"\n\n< {file}\n"
+                        ));
+                    }
+                    None => body.push_str(&format!(
+                        "Content-Disposition: form-data; name=\"{key}\"\n\n{value}\n"
+                    )),
+                }
+            }
+            body.push_str(&format!("--{boundary}--"));
+            Some(body)
+        }
+        BodyMode::Binary => Some(format!("< {}", request.file)),
+        BodyMode::GraphQl => {
+            headers.push(("X-REQUEST-TYPE".to_string(), "GraphQL".to_string()));
+            let mut body = export_text(&request.query);
+            if !request.variables.trim().is_empty() {
+                body.push_str("\n\n");
+                body.push_str(&export_text(&request.variables));
+            }
+            Some(body)
+        }
+    };
+
+    let method = match (request.body_mode, request.method.trim()) {
+        (BodyMode::GraphQl, _) => "POST",
+        (_, "") => "GET",
+        (_, method) => method,
+    };
+    let mut uri = export_text(&request.uri);
+    for (i, (key, value)) in query.iter().enumerate() {
+        let separator = match i == 0 && !uri.contains('?') {
+            true => '?',
+            false => '&',
+        };
+        uri.push_str(&format!("{separator}{key}={value}"));
+    }
+
+    text.push_str(&format!("\n### {name}\n"));
+    for note in notes {
+        text.push_str(&format!("{note}\n"));
+    }
+    text.push_str(&format!("{method} {uri}\n"));
+    for (key, value) in headers {
+        text.push_str(&format!("{key}: {value}\n"));
+    }
+    if let Some(body) = body {
+        text.push_str(&format!("\n{body}\n"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::pairs;
+
+    const FILE: &str = r#"@host = https://api.example.com
+@token = {{$uuid}}
+
+### Users/List users
+GET {{host}}/users
+    ?page=2
+    &limit={{$randomInt}}
+Accept: application/json
+# Skipped
+Authorization: Bearer {{token}}
+
+###
+# @name create
+POST {{host}}/users HTTP/1.1
+Content-Type: application/json
+
+{"name": "a"}
+
+> {% client.global.set("id", response.body.id); %}
+
+### Login
+POST {{host}}/login
+Content-Type: application/x-www-form-urlencoded
+
+user=a&
+pass=b
+
+###
+PUT {{host}}/upload
+Content-Type: image/png
+
+< ./photo.png
+
+###
+GRAPHQL {{host}}/graphql
+
+query { users { id } }
+
+{"first": 1}
+"#;
+
+    #[test]
+    fn imports_requests() {
+        let imported = import(FILE, "/tmp/api.http");
+        let paths: Vec<&str> = imported
+            .requests
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "api/Users/List users",
+                "api/create",
+                "api/Login",
+                "api/PUT upload",
+                "api/GRAPHQL graphql",
+            ]
+        );
+        assert_eq!(
+            imported.environments[0].1,
+            Variables::from([
+                ("host".to_string(), "https://api.example.com".to_string()),
+                ("token".to_string(), "{{uuid}}".to_string()),
+            ])
+        );
+
+        let list = &imported.requests[0].1;
+        assert_eq!(list.method, "GET");
+        assert_eq!(list.uri, "{{host}}/users");
+        assert_eq!(
+            pairs(&list.params),
+            [("page", "2"), ("limit", "{{randomInt}}")]
+        );
+        assert_eq!(
+            pairs(&list.headers),
+            [
+                ("Accept", "application/json"),
+                ("Authorization", "Bearer {{token}}"),
+            ]
+        );
+
+        let create = &imported.requests[1].1;
+        assert_eq!(create.uri, "{{host}}/users");
+        assert_eq!(create.syntax, Syntax::Json);
+        assert_eq!(create.body, r#"{"name": "a"}"#);
+
+        let login = &imported.requests[2].1;
+        assert_eq!(login.body_mode, BodyMode::Form);
+        assert_eq!(pairs(&login.form), [("user", "a"), ("pass", "b")]);
+        assert!(login.headers.is_empty());
+
+        let upload = &imported.requests[3].1;
+        assert_eq!(upload.body_mode, BodyMode::Binary);
+        assert_eq!(upload.file, "./photo.png");
+
+        let graphql = &imported.requests[4].1;
+        assert_eq!(graphql.method, "POST");
+        assert_eq!(graphql.body_mode, BodyMode::GraphQl);
+        assert_eq!(graphql.query, "query { users { id } }");
+        assert_eq!(graphql.variables, r#"{"first": 1}"#);
+    }
+
+    #[test]
+    fn numbers_duplicate_names() {
+        let imported = import("GET /a\n###\nGET /a\n###\n\n", "requests.rest");
+        let paths: Vec<&str> = imported
+            .requests
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect();
+        assert_eq!(paths, ["requests/GET a", "requests/GET a 2"]);
+        assert!(imported.environments.is_empty());
+        assert!(is_http_file("requests.rest") && !is_http_file("requests.json"));
+    }
+}
@@ -0,0 +1,93 @@
+use std::{collections::HashSet, fs};
+
+use serde_json::Value;
+
+use crate::{
+    environments::Variables,
+    har, http_file, insomnia,
+    models::{KeyValueRow, SavedRequest},
+    openapi, postman,
+};
+
+/// What another tool's files hold, ready to be saved into the workspace
+#[derive(Default)]
+pub struct Imported {
+    /// By `collection/folder/.../name`
+    pub requests: Vec<(String, SavedRequest)>,
+    pub environments: Vec<(String, Variables)>,
+    paths: HashSet<String>,
+}
+
+impl Imported {
+    /// Requests of the same name in the same folder are numbered, since
+    /// other tools allow that
+    pub fn push(&mut self, folders: &[String], name: &str, request: SavedRequest) {
+        let folder = folders.join("/");
+        let name = self::name(name);
+        let path = (1..)
+            .map(|n| match n {
+                1 => format!("{folder}/{name}"),
+                n => format!("{folder}/{name} {n}"),
+            })
+            .find(|path| !self.paths.contains(path))
+            .unwrap_or_default();
+        self.paths.insert(path.clone());
+        self.requests.push((path, request));
+    }
+}
+
+pub fn row(key: &str, value: String) -> KeyValueRow {
+    KeyValueRow {
+        key: key.to_string(),
+        value,
+        ..KeyValueRow::new()
+    }
+}
+
+/// The keys and values of `rows`, for comparing in tests
+#[cfg(test)]
+pub fn pairs(rows: &[KeyValueRow]) -> Vec<(&str, &str)> {
+    rows.iter()
+        .map(|row| (row.key.as_str(), row.value.as_str()))
+        .collect()
+}
+
+/// A name usable as a collection, folder or request name
+pub fn name(name: &str) -> String {
+    let name = name.replace(['/', '\\'], "-");
+    match name.trim().trim_start_matches('.').trim() {
+        "" => "untitled".to_string(),
+        name => name.to_string(),
+    }
+}
+
+pub fn read(path: &str) -> Result<Imported, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("can't read {path}: {err}"))?;
+    parse(path, &text)
+}
+
+/// Tells the format apart by what's in the text, `source` is where it was
+/// read from
+pub fn parse(source: &str, text: &str) -> Result<Imported, String> {
+    if http_file::is_http_file(source) {
+        return Ok(http_file::import(text, source));
+    }
+    let unknown = || format!("{source} isn't in a format that can be imported");
+    let json: Value = serde_json::from_str(text)
+        .ok()
+        .or_else(|| serde_yaml::from_str(text).ok())
+        .ok_or_else(unknown)?;
+    if postman::is_collection(&json) {
+        return Ok(postman::import(&json));
+    }
+    if insomnia::is_export(&json) {
+        return Ok(insomnia::import(&json));
+    }
+    if openapi::is_spec(&json) {
+        return Ok(openapi::import(&json));
+    }
+    if har::is_har(&json) {
+        return Ok(har::import(&json, source));
+    }
+    Err(unknown())
+}
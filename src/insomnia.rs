@@ -0,0 +1,412 @@
+use regex::Regex;
+use serde_json::Value;
+
+use crate::{
+    auth::AuthMode,
+    environments::Variables,
+    import::{self, row, Imported},
+    models::{BodyMode, KeyValueRow, SavedRequest, Syntax},
+};
+
+/// Version 4 exports list every resource with its parent, version 5 nests
+/// them as a tree, both come as JSON or YAML
+pub fn is_export(json: &Value) -> bool {
+    json["_type"] == "export" && json["resources"].is_array()
+        || json["type"]
+            .as_str()
+            .is_some_and(|kind| kind.starts_with("collection.insomnia.rest"))
+}
+
+/// Every workspace becomes a collection. Sub environments get the base
+/// environment's variables along with theirs, or the base environment is
+/// imported as the workspace's when there are none
+pub fn import(json: &Value) -> Imported {
+    let mut imported = Imported::default();
+    if json["resources"].is_array() {
+        import_resources(&json["resources"], &mut imported);
+    } else {
+        let name = import::name(json["name"].as_str().unwrap_or("insomnia"));
+        children(
+            &json["collection"],
+            std::slice::from_ref(&name),
+            &mut imported,
+        );
+        let base = variables(&json["environments"]["data"]);
+        environments(
+            &name,
+            base,
+            &json["environments"]["subEnvironments"],
+            &mut imported,
+        );
+    }
+    imported
+}
+
+fn import_resources(resources: &Value, imported: &mut Imported) {
+    let resources: Vec<&Value> = resources.as_array().into_iter().flatten().collect();
+    let of_type = |kind: &'static str| {
+        resources
+            .iter()
+            .copied()
+            .filter(move |resource| resource["_type"] == kind)
+    };
+    let parent = |resource: &Value| {
+        resources
+            .iter()
+            .copied()
+            .find(|parent| parent["_id"] == resource["parentId"])
+    };
+
+    for request in of_type("request") {
+        // Folder names up to the workspace's
+        let mut folders = Vec::new();
+        let mut next = parent(request);
+        while let Some(resource) = next {
+            folders.push(import::name(resource["name"].as_str().unwrap_or_default()));
+            next = parent(resource);
+        }
+        if folders.is_empty() {
+            folders.push("insomnia".to_string());
+        }
+        folders.reverse();
+        let name = request["name"].as_str().unwrap_or_default();
+        imported.push(&folders, name, self::request(request));
+    }
+
+    for workspace in of_type("workspace") {
+        let name = import::name(workspace["name"].as_str().unwrap_or_default());
+        for base in of_type("environment").filter(|env| env["parentId"] == workspace["_id"]) {
+            let subs: Vec<Value> = of_type("environment")
+                .filter(|env| env["parentId"] == base["_id"])
+                .cloned()
+                .collect();
+            environments(
+                &name,
+                variables(&base["data"]),
+                &Value::from(subs),
+                imported,
+            );
+        }
+    }
+}
+
+fn environments(workspace: &str, base: Variables, subs: &Value, imported: &mut Imported) {
+    let subs: Vec<&Value> = subs.as_array().into_iter().flatten().collect();
+    if subs.is_empty() {
+        if !base.is_empty() {
+            imported.environments.push((workspace.to_string(), base));
+        }
+        return;
+    }
+    for sub in subs {
+        let mut variables = base.clone();
+        variables.extend(self::variables(&sub["data"]));
+        let name = import::name(sub["name"].as_str().unwrap_or_default());
+        imported.environments.push((name, variables));
+    }
+}
+
+/// Nested objects are flattened into `a.b` names, which is how Insomnia
+/// refers to them
+fn variables(data: &Value) -> Variables {
+    let mut variables = Variables::new();
+    let mut stack = vec![(String::new(), data)];
+    while let Some((prefix, value)) = stack.pop() {
+        match value {
+            Value::Object(object) => stack.extend(object.iter().map(|(key, value)| {
+                let name = match prefix.is_empty() {
+                    true => key.clone(),
+                    false => format!("{prefix}.{key}"),
+                };
+                (name, value)
+            })),
+            value if !prefix.is_empty() => {
+                variables.insert(prefix, text(value));
+            }
+            _ => {}
+        }
+    }
+    variables
+}
+
+/// Version 5 folders have children, requests have a url
+fn children(list: &Value, folders: &[String], imported: &mut Imported) {
+    for item in list.as_array().into_iter().flatten() {
+        let name = item["name"].as_str().unwrap_or_default();
+        if item["children"].is_array() {
+            let mut path = folders.to_vec();
+            path.push(import::name(name));
+            children(&item["children"], &path, imported);
+        } else if item["url"].is_string() {
+            imported.push(folders, name, request(item));
+        }
+    }
+}
+
+/// Anything but a string as its JSON, with `{{ _.name }}` variables and the
+/// template tags that have a counterpart rewritten
+fn text(value: &Value) -> String {
+    let text = match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    };
+    let variable = Regex::new(r"\{\{\s*_\.([^{}]*?)\s*\}\}").unwrap();
+    let text = variable.replace_all(&text, "{{$1}}");
+    let tags = [
+        (r"\{%\s*uuid\b[^%]*%\}", "{{uuid}}"),
+        (r"\{%\s*now\s+'(unix|millis)'[^%]*%\}", "{{timestamp}}"),
+        (r"\{%\s*now\s+'iso-8601'[^%]*%\}", "{{isoTimestamp}}"),
+    ];
+    tags.into_iter()
+        .fold(text.into_owned(), |text, (tag, variable)| {
+            Regex::new(tag)
+                .unwrap()
+                .replace_all(&text, variable)
+                .into_owned()
+        })
+}
+
+/// `[{name, value, disabled}]`
+fn rows(list: &Value) -> Vec<KeyValueRow> {
+    list.as_array()
+        .into_iter()
+        .flatten()
+        .map(|entry| {
+            let value = match entry["type"].as_str() {
+                Some("file") => format!("@{}", text(&entry["fileName"])),
+                _ => text(&entry["value"]),
+            };
+            KeyValueRow {
+                enabled: !entry["disabled"].as_bool().unwrap_or(false),
+                ..row(&text(&entry["name"]), value)
+            }
+        })
+        .filter(|row| !row.key.is_empty() || !row.value.is_empty())
+        .collect()
+}
+
+fn request(json: &Value) -> SavedRequest {
+    let mut request = SavedRequest {
+        method: json["method"].as_str().unwrap_or("GET").to_string(),
+        uri: text(&json["url"]),
+        params: rows(&json["parameters"]),
+        headers: rows(&json["headers"]),
+        ..SavedRequest::default()
+    };
+    (request.auth_mode, request.auth) = auth(&json["authentication"]);
+
+    let body = &json["body"];
+    let mime = body["mimeType"].as_str().unwrap_or_default();
+    match mime {
+        "application/x-www-form-urlencoded" => {
+            request.body_mode = BodyMode::Form;
+            request.form = rows(&body["params"]);
+        }
+        "multipart/form-data" => {
+            request.body_mode = BodyMode::Multipart;
+            request.multipart = rows(&body["params"]);
+        }
+        "application/graphql" => {
+            let graphql: Value = serde_json::from_str(&text(&body["text"])).unwrap_or_default();
+            request.body_mode = BodyMode::GraphQl;
+            request.query = text(&graphql["query"]);
+            request.variables = match &graphql["variables"] {
+                Value::Null => String::new(),
+                variables => serde_json::to_string_pretty(variables).unwrap_or_default(),
+            };
+        }
+        _ if body["fileName"].is_string() => {
+            request.body_mode = BodyMode::Binary;
+            request.file = text(&body["fileName"]);
+        }
+        mime => {
+            request.body = text(&body["text"]);
+            request.syntax = match mime {
+                mime if mime.contains("json") => Syntax::Json,
+                mime if mime.contains("xml") => Syntax::Xml,
+                _ => Syntax::Text,
+            };
+        }
+    }
+    request
+}
+
+/// Secret fields are left out, saved requests never hold them
+fn auth(auth: &Value) -> (AuthMode, Vec<KeyValueRow>) {
+    if auth["disabled"].as_bool().unwrap_or(false) {
+        return (AuthMode::None, Vec::new());
+    }
+    let get = |key: &str| text(&auth[key]);
+    match auth["type"].as_str().unwrap_or_default() {
+        "basic" => (AuthMode::Basic, vec![row("username", get("username"))]),
+        "digest" => (AuthMode::Digest, vec![row("username", get("username"))]),
+        "bearer" => (AuthMode::Bearer, Vec::new()),
+        "apikey" => {
+            let add_to = match get("addTo").as_str() {
+                "queryParams" => "query",
+                _ => "header",
+            };
+            let rows = vec![row("key", get("key")), row("add to", add_to.to_string())];
+            (AuthMode::ApiKey, rows)
+        }
+        "iam" => (
+            AuthMode::AwsSigV4,
+            vec![
+                row("access key", get("accessKeyId")),
+                row("region", get("region")),
+                row("service", get("service")),
+            ],
+        ),
+        "oauth2" => {
+            let grant = match get("grantType").as_str() {
+                "authorization_code" => "authorization_code",
+                _ => "client_credentials",
+            };
+            (
+                AuthMode::OAuth2,
+                vec![
+                    row("grant type", grant.to_string()),
+                    row("token url", get("accessTokenUrl")),
+                    row("auth url", get("authorizationUrl")),
+                    row("client id", get("clientId")),
+                    row("scope", get("scope")),
+                ],
+            )
+        }
+        _ => (AuthMode::None, Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::import::pairs;
+
+    #[test]
+    fn imports_v4_resources() {
+        let export = json!({
+            "_type": "export",
+            "__export_format": 4,
+            "resources": [
+                { "_id": "wrk_1", "_type": "workspace", "name": "Shop" },
+                { "_id": "fld_1", "_type": "request_group", "parentId": "wrk_1", "name": "Users" },
+                { "_id": "req_1", "_type": "request", "parentId": "fld_1", "name": "Create user",
+                  "method": "POST", "url": "{{ _.base }}/users",
+                  "headers": [{ "name": "X-Request-Id", "value": "{% uuid 'v4' %}" }],
+                  "parameters": [{ "name": "dry", "value": "1", "disabled": true }],
+                  "authentication": { "type": "bearer", "token": "secret" },
+                  "body": { "mimeType": "application/json", "text": "{\"at\": \"{% now 'iso-8601', '' %}\"}" } },
+                { "_id": "req_2", "_type": "request", "parentId": "wrk_1", "name": "Login",
+                  "method": "POST", "url": "{{_.base}}/login",
+                  "authentication": { "type": "apikey", "key": "X-Key", "addTo": "queryParams" },
+                  "body": { "mimeType": "application/x-www-form-urlencoded",
+                            "params": [{ "name": "user", "value": "a" }] } },
+                { "_id": "req_3", "_type": "request", "parentId": "wrk_1", "name": "Query",
+                  "method": "POST", "url": "{{_.base}}/graphql",
+                  "authentication": { "type": "basic", "username": "u", "password": "p", "disabled": true },
+                  "body": { "mimeType": "application/graphql",
+                            "text": "{\"query\":\"{ me }\",\"variables\":{\"a\":1}}" } },
+                { "_id": "env_1", "_type": "environment", "parentId": "wrk_1", "name": "Base",
+                  "data": { "base": "http://localhost", "api": { "version": 2 } } },
+                { "_id": "env_2", "_type": "environment", "parentId": "env_1", "name": "Production",
+                  "data": { "base": "https://shop.example.com" } },
+            ],
+        });
+        assert!(is_export(&export));
+        let imported = import(&export);
+        let paths: Vec<&str> = imported
+            .requests
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            ["Shop/Users/Create user", "Shop/Login", "Shop/Query"]
+        );
+        assert_eq!(
+            imported.environments,
+            [(
+                "Production".to_string(),
+                Variables::from([
+                    ("api.version".to_string(), "2".to_string()),
+                    ("base".to_string(), "https://shop.example.com".to_string()),
+                ])
+            )]
+        );
+
+        let create = &imported.requests[0].1;
+        assert_eq!(create.uri, "{{base}}/users");
+        assert_eq!(pairs(&create.headers), [("X-Request-Id", "{{uuid}}")]);
+        assert!(!create.params[0].enabled);
+        assert_eq!(create.auth_mode, AuthMode::Bearer);
+        assert!(create.auth.is_empty());
+        assert_eq!(create.syntax, Syntax::Json);
+        assert_eq!(create.body, "{\"at\": \"{{isoTimestamp}}\"}");
+
+        let login = &imported.requests[1].1;
+        assert_eq!(login.body_mode, BodyMode::Form);
+        assert_eq!(pairs(&login.form), [("user", "a")]);
+        assert_eq!(pairs(&login.auth), [("key", "X-Key"), ("add to", "query")]);
+
+        let query = &imported.requests[2].1;
+        assert_eq!(query.auth_mode, AuthMode::None);
+        assert_eq!(query.body_mode, BodyMode::GraphQl);
+        assert_eq!(query.query, "{ me }");
+        assert_eq!(query.variables, "{\n  \"a\": 1\n}");
+    }
+
+    #[test]
+    fn imports_v5_yaml() {
+        let yaml = r#"
+type: collection.insomnia.rest/5.0
+name: Shop
+collection:
+  - name: Files
+    children:
+      - name: Upload
+        method: PUT
+        url: "{{ _.base }}/files"
+        body:
+          fileName: /tmp/a.bin
+      - name: Form
+        method: POST
+        url: "{{ _.base }}/form"
+        body:
+          mimeType: multipart/form-data
+          params:
+            - name: file
+              type: file
+              fileName: /tmp/b.png
+environments:
+  name: Base
+  data:
+    base: http://localhost:3000
+"#;
+        let export: Value = serde_yaml::from_str(yaml).unwrap();
+        assert!(is_export(&export));
+        let imported = import(&export);
+        let paths: Vec<&str> = imported
+            .requests
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect();
+        assert_eq!(paths, ["Shop/Files/Upload", "Shop/Files/Form"]);
+        assert_eq!(
+            imported.environments,
+            [(
+                "Shop".to_string(),
+                Variables::from([("base".to_string(), "http://localhost:3000".to_string())])
+            )]
+        );
+        let upload = &imported.requests[0].1;
+        assert_eq!(upload.body_mode, BodyMode::Binary);
+        assert_eq!(upload.file, "/tmp/a.bin");
+        assert_eq!(
+            pairs(&imported.requests[1].1.multipart),
+            [("file", "@/tmp/b.png")]
+        );
+    }
+}
@@ -0,0 +1,656 @@
+use std::collections::BTreeMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+use crate::models::AppMode;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub const fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        self.code == key.code && self.modifiers == key.modifiers
+    }
+
+    /// `ctrl+enter`, `f5`, `shift+down`, `]`, an uppercase letter is the same
+    /// as `shift+` the lowercase one
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let bad = || format!("bad key `{text}`");
+        // `+` on its own, or as the key after the modifiers
+        let (modifiers, key) = match text.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None if text == "+" => ("", "+"),
+            None => text.rsplit_once('+').unwrap_or(("", text)),
+        };
+        let mut binding = Self::new(KeyCode::Null, KeyModifiers::NONE);
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            binding.modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(bad()),
+            };
+        }
+        binding.code = match key.to_ascii_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "space" => KeyCode::Char(' '),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            lower => match (lower.strip_prefix('f').map(str::parse), key.chars().count()) {
+                (Some(Ok(n @ 1..=24)), _) => KeyCode::F(n),
+                (_, 1) => KeyCode::Char(key.chars().next().ok_or_else(bad)?),
+                _ => return Err(bad()),
+            },
+        };
+        // What terminals report for Shift and a letter
+        if let KeyCode::Char(c) = binding.code {
+            if c.is_ascii_uppercase() {
+                binding.modifiers |= KeyModifiers::SHIFT;
+            } else if c.is_ascii_lowercase() && binding.modifiers.contains(KeyModifiers::SHIFT) {
+                binding.code = KeyCode::Char(c.to_ascii_uppercase());
+            }
+        }
+        Ok(binding)
+    }
+
+    /// Shown in hints, `Ctrl+R`
+    pub fn label(&self) -> String {
+        let mut label = String::new();
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "Ctrl+"),
+            (KeyModifiers::ALT, "Alt+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                label += name;
+            }
+        }
+        let letter = matches!(self.code, KeyCode::Char(c) if c.is_ascii_alphabetic());
+        if self.modifiers.contains(KeyModifiers::SHIFT) && !letter {
+            label += "Shift+";
+        }
+        label += &match self.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            // Ctrl+R rather than Ctrl+r, as it's usually written
+            KeyCode::Char(c) if !self.modifiers.is_empty() => c.to_ascii_uppercase().to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::F(n) => format!("F{n}"),
+            KeyCode::PageUp => "PgUp".to_string(),
+            KeyCode::PageDown => "PgDn".to_string(),
+            code => format!("{code:?}"),
+        };
+        label
+    }
+}
+
+/// Where an action's keys work
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    /// In every mode, so they can't be keys that type something
+    Global,
+    /// Outside the editors, in either half
+    Normal,
+    /// Outside the editors in the request half, the same keys can mean
+    /// something else in the response
+    Request,
+    Response,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Send,
+    Quit,
+    NextMode,
+    PreviousMode,
+    History,
+    SaveAs,
+    Sidebar,
+    Workspaces,
+    NewRequestTab,
+    CloseRequestTab,
+    NextRequestTab,
+    PreviousRequestTab,
+    GraphQlVariables,
+    NextTab,
+    PreviousTab,
+    NextResponseTab,
+    PreviousResponseTab,
+    ScrollDown,
+    ScrollUp,
+    PageDown,
+    PageUp,
+    Top,
+    Bottom,
+    ScrollRight,
+    ScrollLeft,
+    Redirects,
+    RawBody,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    Filter,
+    Wrap,
+    Diff,
+    Clear,
+    Copy,
+    CopyHeaders,
+    BodyMode,
+    Syntax,
+    AuthMode,
+    NextEnvironment,
+    PreviousEnvironment,
+    Preview,
+    ImportCurl,
+    CopyCurl,
+    Snippets,
+    Save,
+    Pin,
+    Duplicate,
+    NextTheme,
+    Zoom,
+    Undo,
+    Redo,
+    ExternalEditor,
+    Paste,
+    SwitchHalf,
+    WidenRequest,
+    NarrowRequest,
+    Stop,
+    Ping,
+    CloseSocket,
+    Introspect,
+    Palette,
+}
+
+impl Action {
+    /// What it does, for the command palette
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Send => "send the request",
+            Self::Quit => "quit",
+            Self::NextMode => "focus the next pane",
+            Self::PreviousMode => "focus the previous pane",
+            Self::History => "open history",
+            Self::SaveAs => "save request as",
+            Self::Sidebar => "toggle the sidebar",
+            Self::Workspaces => "switch workspace",
+            Self::NewRequestTab => "open a new request tab",
+            Self::CloseRequestTab => "close the request tab",
+            Self::NextRequestTab => "next request tab",
+            Self::PreviousRequestTab => "previous request tab",
+            Self::GraphQlVariables => "toggle GraphQL query and variables",
+            Self::NextTab => "next payload tab",
+            Self::PreviousTab => "previous payload tab",
+            Self::NextResponseTab => "next response tab",
+            Self::PreviousResponseTab => "previous response tab",
+            Self::ScrollDown => "scroll the response down",
+            Self::ScrollUp => "scroll the response up",
+            Self::PageDown => "page the response down",
+            Self::PageUp => "page the response up",
+            Self::Top => "go to the top of the response",
+            Self::Bottom => "go to the bottom of the response",
+            Self::ScrollRight => "scroll the response right",
+            Self::ScrollLeft => "scroll the response left",
+            Self::Redirects => "toggle redirects",
+            Self::RawBody => "toggle the raw body",
+            Self::Search => "search the response",
+            Self::NextMatch => "next search match",
+            Self::PreviousMatch => "previous search match",
+            Self::Filter => "filter the response",
+            Self::Wrap => "toggle line wrapping",
+            Self::Diff => "diff against the pinned response",
+            Self::Clear => "clear search and filter",
+            Self::Copy => "copy the response body",
+            Self::CopyHeaders => "copy the response status and headers",
+            Self::BodyMode => "change the body mode",
+            Self::Syntax => "change the body syntax",
+            Self::AuthMode => "change the auth mode",
+            Self::NextEnvironment => "next environment",
+            Self::PreviousEnvironment => "previous environment",
+            Self::Preview => "preview the resolved request",
+            Self::ImportCurl => "import a curl command",
+            Self::CopyCurl => "copy as curl",
+            Self::Snippets => "generate code",
+            Self::Save => "save the response body",
+            Self::Pin => "pin the response",
+            Self::Duplicate => "duplicate the request",
+            Self::NextTheme => "next theme",
+            Self::Zoom => "zoom the response or body pane",
+            Self::Undo => "undo the last edit",
+            Self::Redo => "redo the last undone edit",
+            Self::ExternalEditor => "edit the body or headers in $EDITOR",
+            Self::Paste => "paste from the clipboard",
+            Self::SwitchHalf => "switch between the request and the response",
+            Self::WidenRequest => "widen the request pane",
+            Self::NarrowRequest => "narrow the request pane",
+            Self::Stop => "stop receiving the response",
+            Self::Ping => "ping the WebSocket",
+            Self::CloseSocket => "close the WebSocket",
+            Self::Introspect => "fetch the GraphQL schema again",
+            Self::Palette => "command palette",
+        }
+    }
+}
+
+/// Name in the config file, where it works and the default keys
+const ACTIONS: [(&str, Action, Scope, &[&str]); 62] = [
+    ("send", Action::Send, Scope::Global, &["ctrl+enter", "f5"]),
+    ("quit", Action::Quit, Scope::Global, &["alt+q"]),
+    ("next_mode", Action::NextMode, Scope::Global, &["tab"]),
+    (
+        "previous_mode",
+        Action::PreviousMode,
+        Scope::Global,
+        &["shift+backtab"],
+    ),
+    ("history", Action::History, Scope::Global, &["ctrl+r"]),
+    ("save_as", Action::SaveAs, Scope::Global, &["ctrl+s"]),
+    ("sidebar", Action::Sidebar, Scope::Global, &["ctrl+o"]),
+    ("workspaces", Action::Workspaces, Scope::Global, &["ctrl+l"]),
+    ("palette", Action::Palette, Scope::Global, &["ctrl+p"]),
+    (
+        "new_request_tab",
+        Action::NewRequestTab,
+        Scope::Global,
+        &["ctrl+t"],
+    ),
+    (
+        "close_request_tab",
+        Action::CloseRequestTab,
+        Scope::Global,
+        &["ctrl+w"],
+    ),
+    (
+        "next_request_tab",
+        Action::NextRequestTab,
+        Scope::Global,
+        &["ctrl+tab", "ctrl+pagedown"],
+    ),
+    (
+        "previous_request_tab",
+        Action::PreviousRequestTab,
+        Scope::Global,
+        &["ctrl+shift+backtab", "ctrl+pageup"],
+    ),
+    ("zoom", Action::Zoom, Scope::Global, &["f11", "alt+z"]),
+    ("undo", Action::Undo, Scope::Global, &["ctrl+z"]),
+    ("redo", Action::Redo, Scope::Global, &["ctrl+y"]),
+    (
+        "external_editor",
+        Action::ExternalEditor,
+        Scope::Global,
+        &["alt+e"],
+    ),
+    ("paste", Action::Paste, Scope::Global, &["ctrl+v"]),
+    ("switch_half", Action::SwitchHalf, Scope::Global, &["f6"]),
+    ("stop", Action::Stop, Scope::Global, &["alt+s"]),
+    ("ping", Action::Ping, Scope::Global, &["alt+p"]),
+    (
+        "close_socket",
+        Action::CloseSocket,
+        Scope::Global,
+        &["alt+x"],
+    ),
+    ("introspect", Action::Introspect, Scope::Global, &["alt+i"]),
+    (
+        "graphql_variables",
+        Action::GraphQlVariables,
+        Scope::Global,
+        &["ctrl+g"],
+    ),
+    ("next_tab", Action::NextTab, Scope::Request, &["right"]),
+    (
+        "previous_tab",
+        Action::PreviousTab,
+        Scope::Request,
+        &["left"],
+    ),
+    (
+        "next_response_tab",
+        Action::NextResponseTab,
+        Scope::Response,
+        &["]", "right"],
+    ),
+    (
+        "previous_response_tab",
+        Action::PreviousResponseTab,
+        Scope::Response,
+        &["[", "left"],
+    ),
+    (
+        "scroll_down",
+        Action::ScrollDown,
+        Scope::Response,
+        &["down"],
+    ),
+    ("scroll_up", Action::ScrollUp, Scope::Response, &["up"]),
+    (
+        "page_down",
+        Action::PageDown,
+        Scope::Response,
+        &["pagedown"],
+    ),
+    ("page_up", Action::PageUp, Scope::Response, &["pageup"]),
+    ("top", Action::Top, Scope::Response, &["home"]),
+    ("bottom", Action::Bottom, Scope::Response, &["end"]),
+    (
+        "scroll_right",
+        Action::ScrollRight,
+        Scope::Response,
+        &["shift+right"],
+    ),
+    (
+        "scroll_left",
+        Action::ScrollLeft,
+        Scope::Response,
+        &["shift+left"],
+    ),
+    ("redirects", Action::Redirects, Scope::Response, &["r"]),
+    ("raw_body", Action::RawBody, Scope::Response, &["p"]),
+    ("search", Action::Search, Scope::Response, &["/"]),
+    ("next_match", Action::NextMatch, Scope::Response, &["n"]),
+    (
+        "previous_match",
+        Action::PreviousMatch,
+        Scope::Response,
+        &["N"],
+    ),
+    ("filter", Action::Filter, Scope::Response, &["f"]),
+    ("wrap", Action::Wrap, Scope::Response, &["w"]),
+    ("diff", Action::Diff, Scope::Response, &["d"]),
+    ("clear", Action::Clear, Scope::Response, &["esc"]),
+    ("copy", Action::Copy, Scope::Response, &["y"]),
+    ("copy_headers", Action::CopyHeaders, Scope::Response, &["Y"]),
+    ("body_mode", Action::BodyMode, Scope::Request, &["b"]),
+    ("syntax", Action::Syntax, Scope::Request, &["s"]),
+    ("auth_mode", Action::AuthMode, Scope::Request, &["a"]),
+    (
+        "next_environment",
+        Action::NextEnvironment,
+        Scope::Normal,
+        &["e"],
+    ),
+    (
+        "previous_environment",
+        Action::PreviousEnvironment,
+        Scope::Normal,
+        &["E"],
+    ),
+    ("preview", Action::Preview, Scope::Normal, &["v"]),
+    ("import_curl", Action::ImportCurl, Scope::Normal, &["i"]),
+    ("copy_curl", Action::CopyCurl, Scope::Normal, &["c"]),
+    ("snippets", Action::Snippets, Scope::Normal, &["g"]),
+    ("save", Action::Save, Scope::Response, &["S"]),
+    ("pin", Action::Pin, Scope::Response, &["P"]),
+    ("duplicate", Action::Duplicate, Scope::Normal, &["D"]),
+    ("next_theme", Action::NextTheme, Scope::Normal, &["T"]),
+    ("widen_request", Action::WidenRequest, Scope::Normal, &[">"]),
+    (
+        "narrow_request",
+        Action::NarrowRequest,
+        Scope::Normal,
+        &["<"],
+    ),
+];
+
+/// `[keys]` in the config, one key or a list of them for an action
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
+pub struct KeyMap {
+    bindings: Vec<(Action, Scope, Vec<KeyBinding>)>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let bindings = ACTIONS
+            .iter()
+            .map(|(_, action, scope, keys)| {
+                let keys = keys.iter().filter_map(|key| KeyBinding::parse(key).ok());
+                (*action, *scope, keys.collect())
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl KeyMap {
+    /// The defaults with the configured actions replaced, and what was wrong
+    pub fn new(config: BTreeMap<String, Keys>) -> (Self, Vec<String>) {
+        let mut map = Self::default();
+        let mut errors = Vec::new();
+        for (name, keys) in config {
+            let Some(index) = ACTIONS.iter().position(|(known, ..)| *known == name) else {
+                errors.push(format!("unknown action `{name}` in [keys]"));
+                continue;
+            };
+            let keys = match keys {
+                Keys::One(key) => vec![key],
+                Keys::Many(keys) => keys,
+            };
+            let scope = map.bindings[index].1;
+            let mut bindings = Vec::new();
+            for key in keys {
+                match KeyBinding::parse(&key) {
+                    // An action that works while typing can't take a key that types
+                    Ok(binding) if scope == Scope::Global && types(&binding) => errors.push(
+                        format!("`{key}` for {name} would be typed into the editors"),
+                    ),
+                    Ok(binding) => bindings.push(binding),
+                    Err(err) => errors.push(format!("{err} for {name}")),
+                }
+            }
+            map.bindings[index].2 = bindings;
+        }
+        errors.extend(map.conflicts());
+        (map, errors)
+    }
+
+    /// Keys bound to two actions that work at the same time
+    fn conflicts(&self) -> Vec<String> {
+        let mut conflicts = Vec::new();
+        for (index, (action, scope, keys)) in self.bindings.iter().enumerate() {
+            for (other, other_scope, other_keys) in &self.bindings[index + 1..] {
+                let overlap = !matches!(
+                    (scope, other_scope),
+                    (Scope::Request, Scope::Response) | (Scope::Response, Scope::Request)
+                );
+                if let Some(key) = keys.iter().find(|key| overlap && other_keys.contains(key)) {
+                    conflicts.push(format!(
+                        "{} is bound to both {} and {}",
+                        key.label(),
+                        name(*action),
+                        name(*other)
+                    ));
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// An action that works in every mode
+    pub fn global(&self, key: &KeyEvent) -> Option<Action> {
+        self.find(key, Scope::Global)
+    }
+
+    /// An action of normal mode in the focused half
+    pub fn normal(&self, key: &KeyEvent, half: AppMode) -> Option<Action> {
+        let scope = match half {
+            AppMode::Request => Scope::Request,
+            AppMode::Response => Scope::Response,
+        };
+        self.find(key, scope)
+            .or_else(|| self.find(key, Scope::Normal))
+    }
+
+    fn find(&self, key: &KeyEvent, scope: Scope) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, s, keys)| *s == scope && keys.iter().any(|binding| binding.matches(key)))
+            .map(|(action, ..)| *action)
+    }
+
+    /// Every action, in the order of the defaults
+    pub fn actions(&self) -> impl Iterator<Item = Action> + '_ {
+        self.bindings.iter().map(|(action, ..)| *action)
+    }
+
+    /// The first key of an action for hints, `unbound` without any
+    pub fn label(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .find(|(a, ..)| *a == action)
+            .and_then(|(_, _, keys)| keys.first())
+            .map(KeyBinding::label)
+            .unwrap_or_else(|| "unbound".to_string())
+    }
+}
+
+fn name(action: Action) -> &'static str {
+    ACTIONS
+        .iter()
+        .find(|(_, a, ..)| *a == action)
+        .map(|(name, ..)| *name)
+        .unwrap_or_default()
+}
+
+/// Keys a text area would insert something for
+fn types(binding: &KeyBinding) -> bool {
+    let typing = (binding.modifiers - KeyModifiers::SHIFT).is_empty();
+    typing
+        && matches!(
+            binding.code,
+            KeyCode::Char(_) | KeyCode::Enter | KeyCode::Tab | KeyCode::Backspace | KeyCode::Delete
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn titles_are_distinct() {
+        let mut titles = HashSet::new();
+        for (_, action, ..) in ACTIONS {
+            assert!(titles.insert(action.title()), "{}", action.title());
+        }
+    }
+
+    #[test]
+    fn sends_with_ctrl_enter_or_f5() {
+        let map = KeyMap::default();
+        let key = |code, modifiers| map.global(&KeyEvent::new(code, modifiers));
+        assert_eq!(
+            key(KeyCode::Enter, KeyModifiers::CONTROL),
+            Some(Action::Send)
+        );
+        assert_eq!(key(KeyCode::F(5), KeyModifiers::NONE), Some(Action::Send));
+        assert_eq!(key(KeyCode::Enter, KeyModifiers::NONE), None);
+    }
+
+    #[test]
+    fn parses_keys() {
+        let key = |text| KeyBinding::parse(text).unwrap();
+        assert_eq!(
+            key("Ctrl+Enter"),
+            KeyBinding::new(KeyCode::Enter, KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            key("f12"),
+            KeyBinding::new(KeyCode::F(12), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            key("ctrl++"),
+            KeyBinding::new(KeyCode::Char('+'), KeyModifiers::CONTROL)
+        );
+        // Uppercase letters and shift are the same
+        assert_eq!(key("shift+d"), key("D"));
+        assert_eq!(key("D").label(), "D");
+        assert_eq!(key("alt+shift+pageup").label(), "Alt+Shift+PgUp");
+        assert!(KeyBinding::parse("hyper+x").is_err());
+        assert!(KeyBinding::parse("f25").is_err());
+        assert!(KeyBinding::parse("xy").is_err());
+    }
+
+    #[test]
+    fn rebinds_actions() {
+        let config = BTreeMap::from([
+            ("send".to_string(), Keys::One("ctrl+x".to_string())),
+            (
+                "quit".to_string(),
+                Keys::Many(vec!["q".to_string(), "ctrl+c".to_string()]),
+            ),
+            ("fly".to_string(), Keys::One("f".to_string())),
+        ]);
+        let (map, errors) = KeyMap::new(config);
+        assert_eq!(
+            errors,
+            [
+                "unknown action `fly` in [keys]",
+                "`q` for quit would be typed into the editors",
+            ]
+        );
+        let key = |code| KeyEvent::new(code, KeyModifiers::CONTROL);
+        assert_eq!(map.global(&key(KeyCode::Char('x'))), Some(Action::Send));
+        assert_eq!(map.global(&key(KeyCode::Enter)), None);
+        assert_eq!(map.global(&key(KeyCode::Char('c'))), Some(Action::Quit));
+        assert_eq!(map.label(Action::Send), "Ctrl+X");
+    }
+
+    #[test]
+    fn finds_conflicts() {
+        assert!(KeyMap::default().conflicts().is_empty());
+        // The request and response halves can share keys
+        let config = BTreeMap::from([
+            ("next_tab".to_string(), Keys::One("x".to_string())),
+            ("scroll_down".to_string(), Keys::One("x".to_string())),
+            ("history".to_string(), Keys::One("ctrl+o".to_string())),
+        ]);
+        let (_, errors) = KeyMap::new(config);
+        assert_eq!(errors, ["Ctrl+O is bound to both history and sidebar"]);
+    }
+
+    #[test]
+    fn keys_per_half() {
+        let map = KeyMap::default();
+        let key = |code, half| map.normal(&KeyEvent::new(code, KeyModifiers::NONE), half);
+        assert_eq!(key(KeyCode::Right, AppMode::Request), Some(Action::NextTab));
+        assert_eq!(
+            key(KeyCode::Right, AppMode::Response),
+            Some(Action::NextResponseTab)
+        );
+        assert_eq!(key(KeyCode::Char('/'), AppMode::Request), None);
+        assert_eq!(key(KeyCode::Char('b'), AppMode::Response), None);
+        // Either half
+        assert_eq!(
+            key(KeyCode::Char('c'), AppMode::Response),
+            Some(Action::CopyCurl)
+        );
+    }
+}
@@ -1,224 +1,3554 @@
+use chrono::{DateTime, Local};
+use clipboard::Clipboard;
+use codegen::{Language, Snippets};
+use collections::{Collections, NodeKind, DEFAULT_COLLECTION};
+use completion::{Completion, Target};
+use config::Config;
 use crossterm::{
     event::{
-        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+        MouseButton, MouseEvent, MouseEventKind, PopKeyboardEnhancementFlags,
+        PushKeyboardEnhancementFlags,
     },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use models::{Editor, InputMode, State};
+use draft::{Draft, AUTOSAVE_INTERVAL};
+use environments::Variables;
+use graphics::{Graphics, Protocol};
+use history::{Entry, History, HistoryView};
+use keys::{Action, KeyMap};
+use models::{
+    is_valid_uri, Body, BodyMode, Editor, Editors, Export, Filter, InputMode, KeyValueEditor,
+    Message, Panes, PayloadTab, Request, RequestError, Response, ResponseTab, SavedRequest,
+    SidebarTab, State, Syntax, Toast, Zoom,
+};
+use palette::{Command, Palette};
+
+mod assertions;
+mod auth;
+mod certs;
+mod cli;
+mod clipboard;
+mod codegen;
+mod collections;
+mod completion;
+mod config;
+mod cookies;
+mod curl;
+mod diff;
+mod digest;
+mod draft;
+mod environments;
+mod external;
+mod graphics;
+mod graphql;
+mod grpc;
+mod har;
+mod highlight;
+mod history;
+mod http_file;
+mod import;
+mod insomnia;
+mod keys;
+mod models;
+mod net;
+mod oauth;
+mod openapi;
+mod palette;
+mod postman;
+mod protobuf;
+mod sigv4;
+mod sse;
+mod theme;
+mod timing;
+mod tls;
+mod traits;
+mod utils;
+mod vim;
+mod websocket;
+mod widgets;
+mod workspaces;
+
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs, Wrap,
+    },
+    Frame, Terminal,
+};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    io,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::AsyncWriteExt,
+    runtime::Runtime,
+    sync::{
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        Notify,
+    },
+};
+use traits::Tab;
+use tui_textarea::CursorMove;
+use unicode_width::UnicodeWidthStr;
+use utils::{format_duration, format_size, join_lines, wrap_words};
+use websocket::Outgoing;
+use widgets::{resolved_request, resolved_spans, KeyValueTable, LineHighlight};
+use workspaces::WorkspaceView;
+
+const TICK_RATE: Duration = Duration::from_millis(100);
+const MOUSE_SCROLL: i32 = 3;
+const HORIZONTAL_SCROLL: i32 = 8;
+const HISTORY_PAGE: i32 = 10;
+/// Widest a toast gets
+const TOAST_WIDTH: u16 = 50;
+/// Percent the request pane grows or shrinks by
+const SPLIT_STEP: i32 = 5;
+const PREVIEW_PAGE: u16 = 10;
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Where the keys go while editing
+enum Focused<'e, 'a> {
+    Text(&'e mut Editor<'a>),
+    Table(&'e mut KeyValueEditor<'a>),
+    None,
+}
+
+struct App<'a> {
+    editors: Editors<'a>,
+    state: State<'a>,
+    terminal: Terminal<CrosstermBackend<io::Stdout>>,
+    config: Config,
+    runtime: Runtime,
+    tx: UnboundedSender<Message>,
+    rx: UnboundedReceiver<Message>,
+    clipboard: Clipboard,
+    autosaved: Instant,
+    /// Saved path and JSON of the request as last autosaved
+    draft: (Option<String>, String),
+    graphics: Graphics,
+}
+
+impl<'a> App<'a> {
+    fn new() -> io::Result<Self> {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableBracketedPaste
+        )?;
+        // Needed to tell e.g. Ctrl+Enter apart from Enter, unsupported terminals ignore it
+        let _ = execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        );
+        let backend = CrosstermBackend::new(stdout);
+        let terminal = Terminal::new(backend)?;
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        let (config, errors) = Config::load();
+        theme::set(config.theme);
+        let editors = Editors::new();
+        let mut state = State::new(&config);
+        let graphics = Graphics::new(Protocol::detect());
+        state.graphics = graphics.protocol != Protocol::HalfBlocks;
+        if !errors.is_empty() {
+            state.notify(Err(errors.join("; ")));
+        }
+        let empty = Self::draft_json(&editors.snapshot());
+        state.restore = draft::load().filter(|draft| {
+            let request = Self::draft_json(&draft.request);
+            // Nothing to offer when it's what was last saved in the collections
+            let saved = draft
+                .saved_path
+                .as_ref()
+                .filter(|_| draft.workspace == state.workspace)
+                .and_then(|path| state.collections.find(path))
+                .map(|item| Self::draft_json(&item.request));
+            request != empty && Some(request) != saved
+        });
+
+        Ok(Self {
+            editors,
+            state,
+            terminal,
+            config,
+            runtime: Runtime::new()?,
+            tx,
+            rx,
+            clipboard: Clipboard::new(),
+            autosaved: Instant::now(),
+            draft: (None, empty),
+            graphics,
+        })
+    }
+
+    fn draft_json(request: &SavedRequest) -> String {
+        serde_json::to_string(request).unwrap_or_default()
+    }
+
+    /// Writes the editors out when they changed since the last time, but not
+    /// over a draft that's still offered for restoring
+    fn autosave(&mut self) {
+        self.autosaved = Instant::now();
+        if self.state.restore.is_some() {
+            return;
+        }
+        let request = self.editors.snapshot();
+        let current = (self.state.saved_path.clone(), Self::draft_json(&request));
+        if current == self.draft {
+            return;
+        }
+        let draft = Draft::new(
+            &self.state.workspace,
+            self.state.saved_path.as_deref(),
+            request,
+        );
+        match draft::save(&draft) {
+            Ok(()) => self.draft = current,
+            Err(err) => self.state.notify(Err(err)),
+        }
+    }
+
+    fn send_request(&mut self) {
+        if self.state.socket.is_some() {
+            self.send_message();
+            return;
+        }
+        if self.state.pending.is_some() {
+            return;
+        }
+
+        if !self.editors.uri.validate_uri() {
+            self.state.response = Some(Err(RequestError::InvalidUri));
+            return;
+        }
+
+        let variables = self.state.environments.variables();
+        let request = Request::new(&self.editors, &self.config).and_then(|mut request| {
+            request.resolve(&variables)?;
+            match is_valid_uri(&request.uri) {
+                true => Ok(request),
+                false => Err(RequestError::InvalidUri),
+            }
+        });
+        let mut request = match request {
+            Ok(request) => request,
+            Err(err) => {
+                self.state.response = Some(Err(err));
+                return;
+            }
+        };
+        let tx = self.tx.clone();
+        let id = self.state.tabs[self.state.active_tab].id;
+        let (progress, mut forwarded) = mpsc::unbounded_channel();
+        request.progress = Some(progress);
+        let socket = websocket::is_websocket(&request.uri).then(|| {
+            let (socket, outgoing) = mpsc::unbounded_channel();
+            self.state.socket = Some(socket);
+            outgoing
+        });
+        if socket.is_none() {
+            let stop = Arc::new(Notify::new());
+            request.stop = Some(stop.clone());
+            self.state.stop = Some(stop);
+        }
+        self.state.sent = Some(self.editors.snapshot());
+        self.state.pending = Some(Instant::now());
+        self.runtime.spawn(async move {
+            // Tagged with the tab, which may not be open by the time they arrive
+            let forward = async {
+                while let Some(message) = forwarded.recv().await {
+                    let _ = tx.send(Message::Tab(id, Box::new(message)));
+                }
+            };
+            // Moved in so the progress sender is dropped once it's done
+            let fetch = async move {
+                match socket {
+                    Some(outgoing) => websocket::connect(&request, outgoing).await,
+                    None if grpc::is_grpc(&request.uri) => grpc::call(&request).await,
+                    None => request.fetch().await,
+                }
+            };
+            let (response, ()) = tokio::join!(fetch, forward);
+            // The receiver only goes away once the app is shutting down
+            let message = Message::ResponseReceived(Box::new(response));
+            let _ = tx.send(Message::Tab(id, Box::new(message)));
+        });
+    }
+
+    /// Sends the raw body as text, or the binary body's file, over the open
+    /// WebSocket
+    fn send_message(&mut self) {
+        let message = match self.editors.body_mode {
+            BodyMode::Raw => {
+                let variables = self.state.environments.variables();
+                environments::resolve(&self.editors.body.text(), &variables)
+                    .map(Outgoing::Text)
+                    .map_err(|err| err.to_string())
+            }
+            BodyMode::Binary => match self.editors.file_path() {
+                Some(path) => std::fs::read(&path)
+                    .map(Outgoing::Binary)
+                    .map_err(|err| format!("{}: {err}", path.display())),
+                None => Err("no file to send".to_string()),
+            },
+            _ => Err("WebSocket messages are sent from the raw or binary body".to_string()),
+        };
+        match message {
+            Ok(message) => self.state.send_socket(message),
+            Err(err) => self.state.notify(Err(err)),
+        }
+    }
+
+    /// The URI with variables filled in, which GraphQL schemas are kept by
+    fn graphql_endpoint(editors: &Editors, variables: &Variables) -> String {
+        let uri = editors.uri.text();
+        environments::resolve(uri.trim(), variables).unwrap_or_else(|_| uri.trim().to_string())
+    }
+
+    /// Sends the introspection query to the GraphQL endpoint with the
+    /// request's headers and auth
+    fn introspect(&mut self) {
+        let variables = self.state.environments.variables();
+        let endpoint = Self::graphql_endpoint(&self.editors, &variables);
+        let request = Request::new(&self.editors, &self.config).and_then(|mut request| {
+            request.resolve(&variables)?;
+            match is_valid_uri(&request.uri) && request.uri.starts_with("http") {
+                true => Ok(request),
+                false => Err(RequestError::InvalidUri),
+            }
+        });
+        // Not tried again on every edit after it failed
+        self.state.schemas.insert(endpoint.clone(), None);
+        let mut request = match request {
+            Ok(request) => request,
+            Err(err) => {
+                self.state.notify(Err(format!("can't introspect: {err}")));
+                return;
+            }
+        };
+        request.method = "POST".to_string();
+        request.body = Some(Body::Raw(
+            serde_json::json!({ "query": graphql::INTROSPECTION }).to_string(),
+        ));
+        request.content_type = Some(Syntax::Json.content_type());
+        self.state.notify(Ok(format!("introspecting {endpoint}")));
+        let tx = self.tx.clone();
+        self.runtime.spawn(async move {
+            let schema = graphql::introspect(&request, &endpoint).await;
+            let _ = tx.send(Message::Introspected(endpoint, Box::new(schema)));
+        });
+    }
+
+    fn run(&mut self) -> io::Result<()> {
+        loop {
+            // Try to make ui() a struct method and not an assoc method
+            self.terminal
+                .draw(|f| Self::ui(f, &mut self.state, &mut self.editors, &self.config.keys))?;
+            if self.show_image()? {
+                // Drawn again without the sixels
+                self.terminal.clear()?;
+                continue;
+            }
+
+            while let Ok(message) = self.rx.try_recv() {
+                self.state.handle_message(message);
+            }
+            self.state.flush_stream();
+            if self.autosaved.elapsed() >= AUTOSAVE_INTERVAL {
+                self.autosave();
+            }
+
+            if !event::poll(TICK_RATE)? {
+                self.state.tick();
+                continue;
+            }
+
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press && self.handle_key(key) => {
+                    return Ok(());
+                }
+                Event::Mouse(mouse) => self.handle_mouse(mouse),
+                Event::Paste(text) if !self.state.modal() => {
+                    self.completing(|app| Self::paste(&mut app.state, &mut app.editors, &text))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Puts the image preview on screen when the terminal has graphics,
+    /// true when the screen has to be drawn again first
+    fn show_image(&mut self) -> io::Result<bool> {
+        let image = self.state.image_area.zip(
+            self.state
+                .response
+                .as_ref()
+                .and_then(|resp| resp.as_ref().ok())
+                .and_then(|resp| resp.image.as_ref())
+                .map(|preview| (preview.id, &preview.image)),
+        );
+        self.graphics.show(self.terminal.backend_mut(), image)
+    }
+
+    /// Returns true when the app should quit
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if self.state.restore.is_some() {
+            self.restore_input(key);
+            return false;
+        }
+        if self.state.quit_prompt.is_some() {
+            return self.quit_input(key);
+        }
+        if self
+            .state
+            .search
+            .as_ref()
+            .is_some_and(|search| search.editing)
+        {
+            self.search_input(key);
+            return false;
+        }
+        if self
+            .state
+            .filter
+            .as_ref()
+            .is_some_and(|filter| filter.editing)
+        {
+            self.filter_input(key);
+            return false;
+        }
+        if self.state.save_path.is_some() {
+            self.save_input(key);
+            return false;
+        }
+        if self.state.save_as.is_some() {
+            self.save_as_input(key);
+            return false;
+        }
+        if self.state.history_view.is_some() {
+            self.history_input(key);
+            return false;
+        }
+        if self.state.move_to.is_some() {
+            self.move_input(key);
+            return false;
+        }
+        if self.state.import_path.is_some() {
+            self.import_input(key);
+            return false;
+        }
+        if self.state.export_path.is_some() {
+            self.export_input(key);
+            return false;
+        }
+        if self.state.workspace_view.is_some() {
+            self.workspace_input(key);
+            return false;
+        }
+        if self.state.new_environment.is_some() {
+            self.new_environment_input(key);
+            return false;
+        }
+        if self.state.env_file_prompt.is_some() {
+            self.env_file_input(key);
+            return false;
+        }
+        if self.state.environment_editor.is_some() {
+            self.environment_editor_input(key);
+            return false;
+        }
+        if self.state.preview.is_some() {
+            self.preview_input(key);
+            return false;
+        }
+        if self.state.curl_import.is_some() {
+            self.curl_import_input(key);
+            return false;
+        }
+        if self.state.snippets.is_some() {
+            self.snippets_input(key);
+            return false;
+        }
+        if self.state.palette.is_some() {
+            return self.palette_input(key);
+        }
+        // Tab takes a suggestion rather than moving on while they're shown
+        if self.completion_input(key) {
+            return false;
+        }
+        // Before the editors, which bind Ctrl+R to redo and would insert a
+        // newline for Ctrl+Enter
+        if let Some(action) = self.config.keys.global(&key) {
+            return self.perform(action);
+        }
+        self.completing(|app| app.input_editor(key));
+
+        match self.state.input_mode {
+            InputMode::Sidebar if key.modifiers == KeyModifiers::NONE => {
+                self.sidebar_input(key);
+                return false;
+            }
+            InputMode::Normal | InputMode::Response => {}
+            _ => return false,
+        }
+        match self.config.keys.normal(&key, self.state.app_mode()) {
+            Some(action) => self.perform(action),
+            None => false,
+        }
+    }
+
+    /// Runs an action from its key or the command palette, true to quit
+    fn perform(&mut self, action: Action) -> bool {
+        match action {
+            Action::Send => self.send_request(),
+            Action::Quit => match self.state.unsaved_tabs(&self.editors).len() {
+                0 => return true,
+                unsaved => self.state.quit_prompt = Some(unsaved),
+            },
+            Action::NextMode => self.state.cycle_input_mode(true),
+            Action::PreviousMode => self.state.cycle_input_mode(false),
+            Action::History => self.state.toggle_history(),
+            Action::SaveAs => self.state.start_save_as(),
+            Action::Sidebar => self.state.toggle_sidebar(),
+            Action::Zoom => self.state.toggle_zoom(),
+            Action::Undo => self.undo(false),
+            Action::Redo => self.undo(true),
+            Action::ExternalEditor => self.edit_externally(),
+            Action::SwitchHalf => self.state.switch_half(),
+            Action::Stop => match (&self.state.socket, &self.state.stop) {
+                (Some(_), _) => self.state.send_socket(Outgoing::Close),
+                (None, Some(stop)) => stop.notify_one(),
+                (None, None) => {}
+            },
+            Action::Ping => self.state.send_socket(Outgoing::Ping),
+            Action::CloseSocket => self.state.send_socket(Outgoing::Close),
+            Action::Introspect => self.introspect(),
+            Action::Paste => match self.clipboard.paste() {
+                Ok(text) => {
+                    self.completing(|app| Self::paste(&mut app.state, &mut app.editors, &text))
+                }
+                Err(err) => self.state.notify(Err(err)),
+            },
+            Action::Workspaces => self.state.toggle_workspaces(),
+            Action::GraphQlVariables => {
+                self.editors.variables_focused = !self.editors.variables_focused
+            }
+            Action::NewRequestTab => {
+                let index = self.state.add_tab();
+                self.switch_tab(index);
+                self.state.input_mode = InputMode::UriEditing;
+            }
+            Action::CloseRequestTab => self.close_tab(),
+            Action::NextRequestTab => {
+                self.switch_tab((self.state.active_tab + 1) % self.state.tabs.len())
+            }
+            Action::PreviousRequestTab => {
+                let count = self.state.tabs.len();
+                self.switch_tab((self.state.active_tab + count - 1) % count)
+            }
+            Action::Palette => {
+                let environments = &self.state.environments.list;
+                let names: Vec<String> = environments.iter().map(|env| env.name.clone()).collect();
+                self.state.palette = Some(Palette::new(&self.config.keys, &names));
+            }
+            Action::NextTab => self.state.next_payload(),
+            Action::PreviousTab => self.state.previous_payload(),
+            Action::ScrollDown => self.state.scroll_down(),
+            Action::ScrollUp => self.state.scroll_up(),
+            Action::PageDown => self.state.page_down(),
+            Action::PageUp => self.state.page_up(),
+            Action::Top => self.state.scroll_to_top(),
+            Action::Bottom => self.state.scroll_to_bottom(),
+            Action::ScrollRight => self.state.scroll_horizontal(HORIZONTAL_SCROLL),
+            Action::ScrollLeft => self.state.scroll_horizontal(-HORIZONTAL_SCROLL),
+            Action::Redirects => self.state.toggle_redirects(),
+            Action::RawBody => self.state.toggle_raw_body(),
+            Action::Search => self.state.start_search(),
+            Action::NextMatch => self.state.next_match(),
+            Action::PreviousMatch => self.state.previous_match(),
+            Action::Filter => self.state.start_filter(),
+            Action::Wrap => self.state.toggle_wrap(),
+            Action::Diff => self.state.toggle_diff(),
+            Action::Copy => self.copy(false),
+            Action::CopyHeaders => self.copy(true),
+            Action::Clear => {
+                self.state.search = None;
+                self.state.filter = None;
+                self.state.refresh_filter();
+            }
+            Action::NextResponseTab => self.state.next_response_tab(),
+            Action::PreviousResponseTab => self.state.previous_response_tab(),
+            Action::BodyMode => self.editors.body_mode = self.editors.body_mode.next(),
+            Action::Syntax => self.editors.body.syntax = self.editors.body.syntax.next(),
+            Action::AuthMode => self.editors.auth.mode = self.editors.auth.mode.next(),
+            Action::NextEnvironment => self.state.cycle_environment(true),
+            Action::PreviousEnvironment => self.state.cycle_environment(false),
+            Action::Preview => self.state.preview = Some(0),
+            Action::ImportCurl => self.state.curl_import = Some(Editor::default("import curl")),
+            Action::CopyCurl => self.copy_curl(),
+            Action::Snippets => self.open_snippets(),
+            Action::Save => self.state.start_save(),
+            Action::Pin => self.state.pin_response(),
+            Action::Duplicate => {
+                let path = self
+                    .state
+                    .saved_path
+                    .clone()
+                    .unwrap_or_else(|| format!("{DEFAULT_COLLECTION}/untitled"));
+                self.duplicate(&path, self.editors.snapshot());
+            }
+            Action::WidenRequest => self.resize(self.state.split as i32 + SPLIT_STEP),
+            Action::NarrowRequest => self.resize(self.state.split as i32 - SPLIT_STEP),
+            Action::NextTheme => {
+                let name = theme::next().name;
+                self.state.notify(Ok(format!("using the {name} theme")));
+            }
+        }
+        false
+    }
+
+    /// Moves the divider and remembers where in the config
+    fn resize(&mut self, split: i32) {
+        self.state.resize(split);
+        if let Err(err) = config::save_split(self.state.split) {
+            self.state.notify(Err(err));
+        }
+    }
+
+    /// Puts away the editors and state of the open tab and brings out the
+    /// ones of tab `index`
+    fn switch_tab(&mut self, index: usize) {
+        let open = self.state.active_tab;
+        if index == open {
+            return;
+        }
+        std::mem::swap(&mut self.editors, &mut self.state.tabs[open].editors);
+        self.state.swap_tab_state(open);
+        std::mem::swap(&mut self.editors, &mut self.state.tabs[index].editors);
+        self.state.swap_tab_state(index);
+        self.state.active_tab = index;
+    }
+
+    /// The last tab stays, there has to be a request to edit
+    fn close_tab(&mut self) {
+        let closing = self.state.active_tab;
+        let count = self.state.tabs.len();
+        if count == 1 {
+            self.state
+                .notify(Err("can't close the only tab".to_string()));
+            return;
+        }
+        self.switch_tab(if closing + 1 < count {
+            closing + 1
+        } else {
+            closing - 1
+        });
+        self.state.tabs.remove(closing);
+        if self.state.active_tab > closing {
+            self.state.active_tab -= 1;
+        }
+    }
+
+    fn palette_input(&mut self, key: KeyEvent) -> bool {
+        let Some(palette) = &mut self.state.palette else {
+            return false;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.state.palette = None,
+            KeyCode::Char('p') if key.modifiers == KeyModifiers::CONTROL => {
+                self.state.palette = None
+            }
+            KeyCode::Down => palette.move_selection(1),
+            KeyCode::Up => palette.move_selection(-1),
+            KeyCode::Backspace => {
+                palette.query.pop();
+                palette.selected = 0;
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                palette.query.push(c);
+                palette.selected = 0;
+            }
+            KeyCode::Enter => {
+                let choice = palette.choice();
+                self.state.palette = None;
+                match choice {
+                    Some(Command::Action(action)) => return self.perform(action),
+                    Some(Command::Environment(name)) => {
+                        let activated = self.state.environments.activate(Some(name));
+                        self.state.notify(activated)
+                    }
+                    Some(Command::Theme(index)) => {
+                        theme::set(index);
+                        let name = theme::current().name;
+                        self.state.notify(Ok(format!("using the {name} theme")));
+                    }
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn restore_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                if let Some(draft) = self.state.restore.take() {
+                    if draft.workspace != self.state.workspace {
+                        self.state.switch_workspace(draft.workspace);
+                    }
+                    self.editors.load(&draft.request);
+                    self.state.saved_path = draft.saved_path;
+                    self.state
+                        .notify(Ok("restored the unsaved request".to_string()));
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                self.state.restore = None;
+                draft::discard();
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns true when the app should quit
+    fn quit_input(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char('s') => return self.save_all(),
+            KeyCode::Char('d') => {
+                // Nothing to offer restoring next time either
+                draft::discard();
+                return true;
+            }
+            KeyCode::Char('c') | KeyCode::Esc => self.state.quit_prompt = None,
+            _ => {}
+        }
+        false
+    }
+
+    /// Saves the unsaved requests over where they were saved, true when that
+    /// was all of them. A request that never was is opened to be named
+    fn save_all(&mut self) -> bool {
+        self.state.quit_prompt = None;
+        for index in self.state.unsaved_tabs(&self.editors) {
+            let (editors, saved_path) = self.state.tab_request(index, &self.editors);
+            let request = editors.snapshot();
+            let Some(path) = saved_path.map(str::to_string) else {
+                self.switch_tab(index);
+                self.state.start_save_as();
+                self.state
+                    .notify(Ok("name the request, then quit again".to_string()));
+                return false;
+            };
+            if let Err(err) = self.state.collections.save(&path, request) {
+                self.state.notify(Err(err));
+                return false;
+            }
+        }
+        true
+    }
+
+    fn search_input(&mut self, key: KeyEvent) {
+        let Some(search) = &mut self.state.search else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.state.search = None,
+            KeyCode::Enter => search.editing = false,
+            KeyCode::Backspace => {
+                search.query.pop();
+                self.state.refresh_search();
+            }
+            KeyCode::Char(c) => {
+                search.query.push(c);
+                self.state.refresh_search();
+            }
+            _ => {}
+        }
+    }
+
+    /// Copies the body, or the filtered fragment when a filter is active
+    fn copy(&mut self, headers: bool) {
+        let Some(text) = self.state.clipboard_text(headers) else {
+            return;
+        };
+
+        let what = if headers {
+            "status and headers"
+        } else {
+            "body"
+        };
+        self.state.notify(match self.clipboard.copy(&text) {
+            Ok(()) => Ok(format!("copied {what}")),
+            Err(err) => Err(format!("can't copy {what}: {err}")),
+        });
+    }
+
+    /// With the variables filled in and the secrets as typed, so it can be run
+    /// as it is
+    fn copy_curl(&mut self) {
+        let variables = self.state.environments.variables();
+        let request = Request::new(&self.editors, &self.config).and_then(|mut request| {
+            request.resolve(&variables)?;
+            Ok(request)
+        });
+        let (command, note) = match request {
+            Ok(request) => curl::command(&request),
+            Err(err) => {
+                self.state.notify(Err(format!("can't export: {err}")));
+                return;
+            }
+        };
+        self.state.notify(match self.clipboard.copy(&command) {
+            Ok(()) => Ok(match note {
+                Some(note) => format!("copied curl command, {note}"),
+                None => "copied curl command".to_string(),
+            }),
+            Err(err) => Err(format!("can't copy curl command: {err}")),
+        });
+    }
+
+    fn open_snippets(&mut self) {
+        let variables = self.state.environments.variables();
+        let request = Request::new(&self.editors, &self.config).and_then(|mut request| {
+            request.resolve(&variables)?;
+            Ok(request)
+        });
+        match request {
+            Ok(request) => self.state.snippets = Some(Snippets::new(&request)),
+            Err(err) => self
+                .state
+                .notify(Err(format!("can't generate code: {err}"))),
+        }
+    }
+
+    fn snippets_input(&mut self, key: KeyEvent) {
+        let Some(snippets) = &mut self.state.snippets else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('g') | KeyCode::Char('q') => self.state.snippets = None,
+            KeyCode::Right | KeyCode::Tab => {
+                snippets.language = snippets.language.next();
+                snippets.scroll = 0;
+            }
+            KeyCode::Left | KeyCode::BackTab => {
+                snippets.language = snippets.language.previous();
+                snippets.scroll = 0;
+            }
+            KeyCode::Down => snippets.scroll = snippets.scroll.saturating_add(1),
+            KeyCode::Up => snippets.scroll = snippets.scroll.saturating_sub(1),
+            KeyCode::PageDown => snippets.scroll = snippets.scroll.saturating_add(PREVIEW_PAGE),
+            KeyCode::PageUp => snippets.scroll = snippets.scroll.saturating_sub(PREVIEW_PAGE),
+            KeyCode::Char('c') | KeyCode::Char('y') => {
+                let language = Language::TITLES[snippets.language.as_int() as usize];
+                let copied = match self.clipboard.copy(snippets.current()) {
+                    Ok(()) => Ok(match snippets.note {
+                        Some(note) => format!("copied {language} code, {note}"),
+                        None => format!("copied {language} code"),
+                    }),
+                    Err(err) => Err(format!("can't copy code: {err}")),
+                };
+                self.state.notify(copied);
+            }
+            _ => {}
+        }
+    }
+
+    fn save_input(&mut self, key: KeyEvent) {
+        let Some(path) = &mut self.state.save_path else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.state.save_path = None,
+            KeyCode::Enter => {
+                let path = std::mem::take(path);
+                self.state.save_path = None;
+                self.save_response(path);
+            }
+            KeyCode::Backspace => {
+                path.pop();
+            }
+            KeyCode::Char(c) => path.push(c),
+            _ => {}
+        }
+    }
+
+    /// Writes the body as received, off the ui thread
+    fn save_response(&mut self, path: String) {
+        let Some(Ok(resp)) = &self.state.response else {
+            return;
+        };
+
+        let bytes = resp.bytes.clone();
+        let tx = self.tx.clone();
+        self.runtime.spawn(async move {
+            let write = async {
+                let mut file = tokio::fs::File::create(&path).await?;
+                file.write_all(&bytes).await?;
+                file.flush().await
+            };
+            let result = match write.await {
+                Ok(()) => Ok(format!(
+                    "saved {} to {path}",
+                    format_size(bytes.len() as u64)
+                )),
+                Err(err) => Err(format!("can't save to {path}: {err}")),
+            };
+            let _ = tx.send(Message::Saved(result));
+        });
+    }
+
+    fn save_as_input(&mut self, key: KeyEvent) {
+        let Some(path) = &mut self.state.save_as else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.state.save_as = None,
+            KeyCode::Enter => {
+                let path = std::mem::take(path);
+                self.state.save_as = None;
+                let result = self.state.collections.save(&path, self.editors.snapshot());
+                if result.is_ok() {
+                    self.state.saved_path = Some(path);
+                }
+                self.state.notify(result);
+            }
+            KeyCode::Backspace => {
+                path.pop();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => path.push(c),
+            _ => {}
+        }
+    }
+
+    fn import_input(&mut self, key: KeyEvent) {
+        let Some(path) = &mut self.state.import_path else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.state.import_path = None,
+            KeyCode::Enter => {
+                let path = std::mem::take(path).trim().to_string();
+                self.state.import_path = None;
+                match path.starts_with("http://") || path.starts_with("https://") {
+                    true => self.fetch_import(path),
+                    false => self.state.import(&path, import::read(&path)),
+                }
+            }
+            KeyCode::Backspace => {
+                path.pop();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => path.push(c),
+            _ => {}
+        }
+    }
+
+    /// Specs are often only published at a URL
+    fn fetch_import(&mut self, url: String) {
+        let tx = self.tx.clone();
+        self.state.notify(Ok(format!("fetching {url}")));
+        self.runtime.spawn(async move {
+            let text = async { reqwest::get(&url).await?.error_for_status()?.text().await };
+            let imported = match text.await {
+                Ok(text) => import::parse(&url, &text),
+                Err(err) => Err(format!("can't fetch {url}: {err}")),
+            };
+            let _ = tx.send(Message::Imported(url, Box::new(imported)));
+        });
+    }
+
+    fn export_input(&mut self, key: KeyEvent) {
+        let Some((_, path)) = &mut self.state.export_path else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.state.export_path = None,
+            KeyCode::Enter => {
+                if let Some((export, path)) = self.state.export_path.take() {
+                    self.state.export(export, &path);
+                }
+            }
+            KeyCode::Backspace => {
+                path.pop();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => path.push(c),
+            _ => {}
+        }
+    }
+
+    fn sidebar_input(&mut self, key: KeyEvent) {
+        let state = &mut self.state;
+        match key.code {
+            KeyCode::Right => state.sidebar_tab = state.sidebar_tab.next(),
+            KeyCode::Left => state.sidebar_tab = state.sidebar_tab.previous(),
+            KeyCode::Esc => state.input_mode = InputMode::Normal,
+            KeyCode::Down => state.move_sidebar(1),
+            KeyCode::Up => state.move_sidebar(-1),
+            _ if state.sidebar_tab == SidebarTab::Environments => self.environments_input(key),
+            KeyCode::Char('i') if state.sidebar_tab == SidebarTab::Collections => {
+                state.import_path = Some(String::new())
+            }
+            _ if state.sidebar_tab == SidebarTab::History => {
+                let entry = state
+                    .history
+                    .entries
+                    .iter()
+                    .rev()
+                    .nth(state.sidebar_history);
+                match (key.code, entry) {
+                    (KeyCode::Enter, Some(entry)) => {
+                        let request = entry.request.clone();
+                        self.editors.load(&request);
+                        state.notify(Ok(format!("loaded {}", request.uri)));
+                    }
+                    (KeyCode::Char('x'), _) => {
+                        let file = format!("{}.har", Local::now().format("curl-rs-%Y-%m-%d"));
+                        state.export_path = Some((Export::History, file));
+                    }
+                    _ => {}
+                }
+            }
+            _ => self.collections_input(key),
+        }
+    }
+
+    fn environments_input(&mut self, key: KeyEvent) {
+        let state = &mut self.state;
+        let name = state
+            .environments
+            .list
+            .get(state.environment_selected)
+            .map(|env| env.name.clone());
+
+        match (key.code, name) {
+            (KeyCode::Char('a'), _) => state.new_environment = Some(String::new()),
+            (KeyCode::Enter, Some(name)) => {
+                let name = match state.environments.active.as_ref() == Some(&name) {
+                    true => None,
+                    false => Some(name),
+                };
+                let activated = state.environments.activate(name);
+                state.notify(activated);
+            }
+            (KeyCode::Char('e'), Some(name)) => state.edit_environment(&name),
+            (KeyCode::Char('f'), Some(name)) => {
+                let path = state
+                    .environments
+                    .list
+                    .iter()
+                    .find(|env| env.name == name)
+                    .and_then(|env| env.env_file.clone())
+                    .unwrap_or_else(|| ".env".to_string());
+                state.env_file_prompt = Some((name, path));
+            }
+            (KeyCode::Char('p'), Some(name)) => {
+                let toggled = state.environments.toggle_process_env(&name);
+                state.notify(toggled)
+            }
+            _ => {}
+        }
+    }
+
+    fn preview_input(&mut self, key: KeyEvent) {
+        let Some(scroll) = &mut self.state.preview else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('v') | KeyCode::Char('q') => self.state.preview = None,
+            KeyCode::Down => *scroll = scroll.saturating_add(1),
+            KeyCode::Up => *scroll = scroll.saturating_sub(1),
+            KeyCode::PageDown => *scroll = scroll.saturating_add(PREVIEW_PAGE),
+            KeyCode::PageUp => *scroll = scroll.saturating_sub(PREVIEW_PAGE),
+            KeyCode::Enter => {
+                self.state.preview = None;
+                self.send_request();
+            }
+            _ => {}
+        }
+    }
+
+    /// Enter after a trailing backslash continues the command on a new line,
+    /// so pasted multi-line commands come through whole
+    fn curl_import_input(&mut self, key: KeyEvent) {
+        let Some(editor) = &mut self.state.curl_import else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.state.curl_import = None,
+            KeyCode::Enter if !editor.text().trim_end_matches(' ').ends_with('\\') => {
+                self.import_curl()
+            }
+            _ => {
+                editor.text_area.input(key);
+            }
+        }
+    }
+
+    fn import_curl(&mut self) {
+        let Some(editor) = self.state.curl_import.take() else {
+            return;
+        };
+        match curl::parse(&editor.text()) {
+            Ok((request, ignored)) => self.load_curl(&request, &ignored),
+            Err(err) => {
+                self.state.notify(Err(format!("can't import: {err}")));
+                self.state.curl_import = Some(editor);
+            }
+        }
+    }
+
+    fn load_curl(&mut self, request: &SavedRequest, ignored: &[String]) {
+        self.editors.load(request);
+        if let Some(fields) = self.editors.auth.fields_mut() {
+            // Loading leaves secret fields alone, the imported ones are wanted
+            for row in &request.auth {
+                fields.set_value(&row.key, &row.value);
+            }
+        }
+        self.state.saved_path = None;
+        let notice = match ignored.is_empty() {
+            true => format!("imported {}", request.uri),
+            false => format!("imported {}, ignoring {}", request.uri, ignored.join(" ")),
+        };
+        self.state.notify(Ok(notice));
+    }
+
+    fn env_file_input(&mut self, key: KeyEvent) {
+        let Some((_, path)) = &mut self.state.env_file_prompt else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.state.env_file_prompt = None,
+            KeyCode::Enter => {
+                if let Some((name, path)) = self.state.env_file_prompt.take() {
+                    let result = self.state.environments.set_env_file(&name, &path);
+                    self.state.notify(result);
+                }
+            }
+            KeyCode::Backspace => {
+                path.pop();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => path.push(c),
+            _ => {}
+        }
+    }
+
+    fn new_environment_input(&mut self, key: KeyEvent) {
+        let Some(name) = &mut self.state.new_environment else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.state.new_environment = None,
+            KeyCode::Enter => {
+                let name = std::mem::take(name);
+                self.state.new_environment = None;
+                let result = self
+                    .state
+                    .environments
+                    .save(&name, Variables::new(), BTreeSet::new());
+                if result.is_ok() {
+                    let name = name.trim();
+                    self.state.environment_selected = self
+                        .state
+                        .environments
+                        .list
+                        .iter()
+                        .position(|env| env.name == name)
+                        .unwrap_or_default();
+                    self.state.edit_environment(name);
+                }
+                self.state.notify(result);
+            }
+            KeyCode::Backspace => {
+                name.pop();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => name.push(c),
+            _ => {}
+        }
+    }
+
+    /// Esc saves the variables and closes the editor
+    fn environment_editor_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => self.state.close_environment_editor(),
+            KeyCode::Char('s') if key.modifiers == KeyModifiers::CONTROL => {
+                if let Some((_, editor)) = &mut self.state.environment_editor {
+                    let row = &mut editor.rows[editor.selected];
+                    row.secret = !row.secret;
+                }
+            }
+            _ => {
+                if let Some((_, editor)) = &mut self.state.environment_editor {
+                    editor.input(key);
+                }
+            }
+        }
+    }
+
+    fn collections_input(&mut self, key: KeyEvent) {
+        let state = &mut self.state;
+        let nodes = state.collections.nodes();
+        let Some(node) = nodes.get(state.collection_selected) else {
+            return;
+        };
+        let path = node.path.clone();
+
+        match (key.code, &node.kind) {
+            (KeyCode::Enter, NodeKind::Folder { .. })
+            | (KeyCode::Left, NodeKind::Folder { expanded: true })
+            | (KeyCode::Right, NodeKind::Folder { expanded: false }) => {
+                state.collections.toggle(&path)
+            }
+            (KeyCode::Enter, NodeKind::Request(item)) => {
+                self.editors.load(&item.request);
+                state.notify(Ok(format!("loaded {path}")));
+                state.saved_path = Some(path);
+            }
+            (KeyCode::Char('c'), NodeKind::Request(item)) => {
+                let request = item.request.clone();
+                self.duplicate(&path, request);
+            }
+            (KeyCode::Char('x'), _) => {
+                let collection = path.split('/').next().unwrap_or_default().to_string();
+                let file = format!("{collection}.postman_collection.json");
+                state.export_path = Some((Export::Collection(collection), file));
+            }
+            (KeyCode::Char('m'), NodeKind::Request(_)) => {
+                let folder = path.rsplit_once('/').map_or("", |(folder, _)| folder);
+                state.move_to = Some((path.clone(), folder.to_string()));
+            }
+            _ => {}
+        }
+    }
+
+    /// Saves a copy next to `path` and switches to it, so edits go to the copy
+    fn duplicate(&mut self, path: &str, request: SavedRequest) {
+        let state = &mut self.state;
+        let copy = state.collections.copy_path(path);
+        let result = state.collections.save(&copy, request);
+        if result.is_ok() {
+            // Leaves the secret auth fields that aren't saved as they are
+            let request = state
+                .collections
+                .find(&copy)
+                .map(|item| item.request.clone());
+            if let Some(request) = request {
+                self.editors.load(&request);
+            }
+            state.collection_selected = state
+                .collections
+                .nodes()
+                .iter()
+                .position(|node| node.path == copy)
+                .unwrap_or(state.collection_selected);
+            state.saved_path = Some(copy);
+        }
+        state.notify(result);
+    }
+
+    fn move_input(&mut self, key: KeyEvent) {
+        let Some((_, folder)) = &mut self.state.move_to else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.state.move_to = None,
+            KeyCode::Enter => {
+                if let Some((from, to)) = self.state.move_to.take() {
+                    let result = self.state.collections.move_request(&from, &to);
+                    self.state.notify(result);
+                }
+            }
+            KeyCode::Backspace => {
+                folder.pop();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => folder.push(c),
+            _ => {}
+        }
+    }
+
+    fn history_input(&mut self, key: KeyEvent) {
+        let Some(view) = &mut self.state.history_view else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.state.history_view = None,
+            KeyCode::Down => self.state.move_history(1),
+            KeyCode::Up => self.state.move_history(-1),
+            KeyCode::PageDown => self.state.move_history(HISTORY_PAGE),
+            KeyCode::PageUp => self.state.move_history(-HISTORY_PAGE),
+            KeyCode::Char('r') if key.modifiers == KeyModifiers::CONTROL => {
+                self.state.history_view = None
+            }
+            KeyCode::Backspace => {
+                view.query.pop();
+                view.selected = 0;
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                view.query.push(c);
+                view.selected = 0;
+            }
+            KeyCode::Enter => {
+                if let Some(entry) = self.state.history_entry() {
+                    let request = entry.request.clone();
+                    self.editors.load(&request);
+                    self.state.notify(Ok(format!("loaded {}", request.uri)));
+                }
+                self.state.history_view = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn workspace_input(&mut self, key: KeyEvent) {
+        let Some(view) = &mut self.state.workspace_view else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.state.workspace_view = None,
+            KeyCode::Down => self.state.move_workspace(1),
+            KeyCode::Up => self.state.move_workspace(-1),
+            KeyCode::Char('l') if key.modifiers == KeyModifiers::CONTROL => {
+                self.state.workspace_view = None
+            }
+            KeyCode::Backspace => {
+                view.query.pop();
+                view.selected = 0;
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                view.query.push(c);
+                view.selected = 0;
+            }
+            KeyCode::Enter => {
+                if let Some(name) = view.choice() {
+                    self.state.switch_workspace(name);
+                }
+                self.state.workspace_view = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn filter_input(&mut self, key: KeyEvent) {
+        let Some(filter) = &mut self.state.filter else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => {
+                self.state.filter = None;
+                self.state.refresh_filter();
+            }
+            KeyCode::Enter => filter.editing = false,
+            KeyCode::Backspace => {
+                filter.expr.pop();
+                self.state.refresh_filter();
+            }
+            KeyCode::Char(c) => {
+                filter.expr.push(c);
+                self.state.refresh_filter();
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let over_response = self.state.zoom != Some(Zoom::Payload)
+            && contains(self.state.resp_area, (mouse.column, mouse.row));
+        match mouse.kind {
+            MouseEventKind::ScrollDown if over_response => self.state.scroll_by(MOUSE_SCROLL),
+            MouseEventKind::ScrollUp if over_response => self.state.scroll_by(-MOUSE_SCROLL),
+            _ if self.state.modal() => {}
+            MouseEventKind::ScrollDown => self.scroll_editor(mouse, MOUSE_SCROLL as i16),
+            MouseEventKind::ScrollUp => self.scroll_editor(mouse, -MOUSE_SCROLL as i16),
+            MouseEventKind::Down(MouseButton::Left) if self.on_divider(mouse) => {
+                self.state.dragging_split = true
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.state.dragging_split => {
+                let columns = self.state.panes.columns;
+                let offset = mouse.column.saturating_sub(columns.x) as i32;
+                self.state
+                    .resize(offset * 100 / columns.width.max(1) as i32);
+            }
+            MouseEventKind::Up(MouseButton::Left) if self.state.dragging_split => {
+                self.state.dragging_split = false;
+                self.resize(self.state.split as i32);
+            }
+            MouseEventKind::Down(MouseButton::Left) => self.click(mouse),
+            _ => {}
+        }
+    }
+
+    /// The borders of the request and response panes either side of the split
+    fn on_divider(&self, mouse: MouseEvent) -> bool {
+        let panes = &self.state.panes;
+        let rows = panes.columns.y..panes.columns.y + panes.columns.height;
+        let divider = panes.divider;
+        rows.contains(&mouse.row) && (divider.saturating_sub(1)..=divider).contains(&mouse.column)
+    }
+
+    /// Focuses the pane under the mouse, or switches to the tab
+    fn click(&mut self, mouse: MouseEvent) {
+        let at = (mouse.column, mouse.row);
+        let panes = self.state.panes;
+        // Titles of bordered tabs are on the row inside the border
+        let titles = |area: Rect| Rect {
+            x: area.x + 1,
+            y: area.y + 1,
+            ..area
+        };
+        let widths = |titles: &[&str]| titles.iter().map(|title| title.len()).collect::<Vec<_>>();
+        if contains(panes.request_tabs, at) {
+            let widths: Vec<usize> = (0..self.state.tabs.len())
+                .map(|index| Self::request_tab_title(&self.state, &self.editors, index).len())
+                .collect();
+            if let Some(index) = Self::tab_at(widths.into_iter(), panes.request_tabs, at) {
+                self.switch_tab(index);
+            }
+            return;
+        }
+        let state = &mut self.state;
+        if contains(panes.sidebar, at) {
+            let widths = widths(&state.sidebar_titles);
+            if let Some(index) = Self::tab_at(widths.into_iter(), titles(panes.sidebar), at) {
+                state.sidebar_tab = state.sidebar_tab.to_enum(index as u8);
+            }
+            state.input_mode = InputMode::Sidebar;
+        } else if contains(panes.method, at) {
+            state.input_mode = InputMode::MethodEditing;
+        } else if contains(panes.uri, at) {
+            state.input_mode = InputMode::UriEditing;
+        } else if contains(panes.payload_tabs, at) {
+            let widths = widths(&state.payload_titles);
+            if let Some(index) = Self::tab_at(widths.into_iter(), titles(panes.payload_tabs), at) {
+                state.payload_tab = state.payload_tab.to_enum(index as u8);
+            }
+            state.input_mode = InputMode::Normal;
+        } else if contains(panes.payload, at) {
+            state.input_mode = InputMode::PayloadEditing;
+        } else if contains(panes.response_tabs, at) {
+            let widths = widths(&state.response_titles);
+            let row = titles(panes.response_tabs);
+            if let Some(index) = Self::tab_at(widths.into_iter(), row, at) {
+                state.response_tab = state.response_tab.to_enum(index as u8);
+            }
+            state.input_mode = InputMode::Response;
+        } else if contains(panes.response, at) {
+            state.input_mode = InputMode::Response;
+        }
+    }
+
+    /// Numbered, so the tabs of two new requests can be told apart
+    fn request_tab_title(state: &State, editors: &Editors, index: usize) -> String {
+        let (title, pending) = state.tab_title(index, editors);
+        let pending = if pending { " ..." } else { "" };
+        format!("{} {title}{pending}", index + 1)
+    }
+
+    /// The tab whose title was clicked, going by how `Tabs` lays out a row of
+    /// titles: a space either side and a one column divider between
+    fn tab_at(
+        widths: impl Iterator<Item = usize>,
+        row: Rect,
+        (column, at_row): (u16, u16),
+    ) -> Option<usize> {
+        if at_row != row.y {
+            return None;
+        }
+        let mut start = row.x;
+        for (index, width) in widths.enumerate() {
+            let end = start + 1 + width as u16;
+            if (start..=end).contains(&column) {
+                return Some(index);
+            }
+            start = end + 2;
+        }
+        None
+    }
+
+    /// Wheel over the editors, tables move their selection instead
+    fn scroll_editor(&mut self, mouse: MouseEvent, delta: i16) {
+        let at = (mouse.column, mouse.row);
+        let editors = &mut self.editors;
+        let text_area = if contains(self.state.panes.method, at) {
+            &mut editors.method.text_area
+        } else if contains(self.state.panes.uri, at) {
+            &mut editors.uri.text_area
+        } else if contains(self.state.panes.payload, at) {
+            let table = match self.state.payload_tab {
+                PayloadTab::Params => &mut editors.params,
+                PayloadTab::Headers => &mut editors.headers,
+                PayloadTab::Body => match editors.body_mode {
+                    BodyMode::Raw => return editors.body.text_area.scroll((delta, 0)),
+                    BodyMode::Form => &mut editors.form,
+                    BodyMode::Multipart => &mut editors.multipart,
+                    BodyMode::Binary => return editors.file.text_area.scroll((delta, 0)),
+                    BodyMode::GraphQl if editors.variables_focused => {
+                        return editors.variables.text_area.scroll((delta, 0))
+                    }
+                    BodyMode::GraphQl => return editors.query.text_area.scroll((delta, 0)),
+                },
+                PayloadTab::Auth => match editors.auth.fields_mut() {
+                    Some(fields) => fields,
+                    None => return,
+                },
+                PayloadTab::Captures => &mut editors.captures,
+                PayloadTab::Assertions => &mut editors.assertions,
+                PayloadTab::Options => return editors.options.text_area.scroll((delta, 0)),
+            };
+            let code = if delta > 0 {
+                KeyCode::Down
+            } else {
+                KeyCode::Up
+            };
+            for _ in 0..delta.unsigned_abs() {
+                table.input(KeyEvent::new(code, KeyModifiers::NONE));
+            }
+            return;
+        } else {
+            return;
+        };
+        text_area.scroll((delta, 0));
+    }
+
+    fn input_editor(&mut self, key: KeyEvent) {
+        let enter = key.code == KeyCode::Enter
+            || (key.modifiers == KeyModifiers::CONTROL && key.code == KeyCode::Char('m'));
+        match Self::focused(&self.state, &mut self.editors) {
+            // Sends from the URI, and does nothing in the other one line fields
+            Focused::Text(editor) if editor.single_line && enter => {
+                if self.state.input_mode == InputMode::UriEditing {
+                    self.send_request();
+                }
+            }
+            Focused::Text(editor) => {
+                match &mut self.state.vim {
+                    Some(vim) => vim.input(&mut editor.text_area, key),
+                    None => {
+                        editor.text_area.input(key);
+                    }
+                }
+                editor.keep_single_line();
+            }
+            Focused::Table(table) => table.input(key),
+            Focused::None => {}
+        }
+    }
+
+    /// Runs `edit`, then updates the suggestions when it changed what's typed
+    /// in the field that has them
+    fn completing(&mut self, edit: impl FnOnce(&mut Self)) {
+        let before = Self::completable(&self.state, &self.editors);
+        edit(self);
+        match (Self::completable(&self.state, &self.editors), before) {
+            // Only on edits, not on moving to another cell
+            (Some((target, typed)), Some((before, typed_before)))
+                if target == before && typed != typed_before =>
+            {
+                self.complete(target, &typed)
+            }
+            _ => {}
+        }
+    }
+
+    /// Inserts `text` into the editor in focus, on one line for the fields that
+    /// only have one
+    fn paste(state: &mut State, editors: &mut Editors<'a>, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        match Self::focused(state, editors) {
+            Focused::Text(editor) if editor.single_line => {
+                editor.text_area.insert_str(join_lines(&text));
+            }
+            Focused::Text(editor) => {
+                for (index, line) in text.split('\n').enumerate() {
+                    if index > 0 {
+                        editor.text_area.insert_newline();
+                    }
+                    editor.text_area.insert_str(line);
+                }
+            }
+            Focused::Table(table) => table.insert_str(&join_lines(&text)),
+            Focused::None => state.notify(Err("open an editor to paste into".to_string())),
+        }
+    }
+
+    /// The field in focus that has suggestions, and what's typed in it
+    fn completable(state: &State, editors: &Editors) -> Option<(Target, String)> {
+        match state.input_mode {
+            InputMode::UriEditing => Some((Target::Uri, editors.uri.text())),
+            InputMode::PayloadEditing if state.payload_tab == PayloadTab::Headers => {
+                let headers = &editors.headers;
+                let row = &headers.rows[headers.selected];
+                let typed = if headers.column == 0 {
+                    &row.key
+                } else {
+                    &row.value
+                };
+                let target = Target::Header {
+                    row: headers.selected,
+                    column: headers.column,
+                };
+                Some((target, typed.clone()))
+            }
+            InputMode::PayloadEditing
+                if state.payload_tab == PayloadTab::Body
+                    && editors.body_mode == BodyMode::GraphQl
+                    && !editors.variables_focused =>
+            {
+                let query = &editors.query;
+                let typed = graphql::typed(&query.text(), query.text_area.cursor());
+                Some((Target::Query, typed))
+            }
+            _ => None,
+        }
+    }
+
+    fn complete(&mut self, target: Target, typed: &str) {
+        match target {
+            Target::Uri => self.state.complete_uri(typed),
+            Target::Header { row, column } => {
+                let name = &self.editors.headers.rows[row].key;
+                let candidates = completion::header_candidates(column, name);
+                self.state.completion = Completion::new(target, typed, candidates);
+            }
+            Target::Query => {
+                let variables = self.state.environments.variables();
+                let endpoint = Self::graphql_endpoint(&self.editors, &variables);
+                // Read from the cache, introspected the first time otherwise
+                if !self.state.schemas.contains_key(&endpoint) {
+                    match graphql::cached(&endpoint) {
+                        Some(schema) => {
+                            self.state.schemas.insert(endpoint.clone(), Some(schema));
+                        }
+                        None => self.introspect(),
+                    }
+                }
+                let query = &self.editors.query;
+                let candidates = match self.state.schemas.get(&endpoint) {
+                    Some(Some(schema)) => {
+                        schema.candidates(&query.text(), query.text_area.cursor())
+                    }
+                    _ => Vec::new(),
+                };
+                self.state.completion = Completion::new(target, typed, candidates);
+            }
+        }
+    }
+
+    /// Whether the suggestions are for what's in the field in focus now
+    fn completion_shown(state: &State, editors: &Editors) -> bool {
+        let Some(completion) = &state.completion else {
+            return false;
+        };
+        Self::completable(state, editors) == Some((completion.target, completion.typed.clone()))
+    }
+
+    /// Up and Down pick a suggestion while they're shown, Tab takes it and
+    /// Esc hides them. Returns true when the key was used
+    fn completion_input(&mut self, key: KeyEvent) -> bool {
+        if key.modifiers != KeyModifiers::NONE
+            || !Self::completion_shown(&self.state, &self.editors)
+        {
+            return false;
+        }
+        let Some(completion) = &mut self.state.completion else {
+            return false;
+        };
+        match key.code {
+            KeyCode::Down => completion.move_selection(1),
+            KeyCode::Up => completion.move_selection(-1),
+            KeyCode::Tab => {
+                let choice = completion.choice().to_string();
+                let target = completion.target;
+                let typed = completion.typed.chars().count();
+                self.state.completion = None;
+                match target {
+                    Target::Uri => {
+                        // Over what's typed, so it's one edit to undo
+                        let text_area = &mut self.editors.uri.text_area;
+                        text_area.move_cursor(CursorMove::Head);
+                        text_area.delete_line_by_end();
+                        text_area.insert_str(choice);
+                    }
+                    Target::Header { .. } => self.editors.headers.set_cell(&choice),
+                    Target::Query => {
+                        let text_area = &mut self.editors.query.text_area;
+                        let (_, column) = text_area.cursor();
+                        text_area.delete_str(column - typed, typed);
+                        text_area.insert_str(choice);
+                    }
+                }
+            }
+            KeyCode::Esc => self.state.completion = None,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Undoes or redoes the last edit in the focused editor
+    fn undo(&mut self, redo: bool) {
+        let changed = match Self::focused(&self.state, &mut self.editors) {
+            Focused::Text(editor) if redo => editor.text_area.redo(),
+            Focused::Text(editor) => editor.text_area.undo(),
+            Focused::Table(table) if redo => table.redo(),
+            Focused::Table(table) => table.undo(),
+            Focused::None => return,
+        };
+        if !changed {
+            let what = if redo { "redo" } else { "undo" };
+            self.state.notify(Err(format!("nothing to {what}")));
+        }
+    }
+
+    /// Hands the terminal to `$EDITOR` for the body, or the headers on their
+    /// tab, and takes back what was saved
+    fn edit_externally(&mut self) {
+        let editors = &mut self.editors;
+        let text = match (self.state.payload_tab, editors.body_mode) {
+            (PayloadTab::Headers, _) => editors.headers.to_text(),
+            (PayloadTab::Body, BodyMode::Raw) => editors.body.text(),
+            (PayloadTab::Body, BodyMode::GraphQl) if editors.variables_focused => {
+                editors.variables.text()
+            }
+            (PayloadTab::Body, BodyMode::GraphQl) => editors.query.text(),
+            _ => {
+                let message = "open the headers or a text body to edit it externally";
+                self.state.notify(Err(message.to_string()));
+                return;
+            }
+        };
+        let extension = match (self.state.payload_tab, editors.variables_focused) {
+            (PayloadTab::Headers, _) => "txt",
+            _ if editors.body_mode == BodyMode::Raw => editors.body.syntax.extension(),
+            (_, true) => Syntax::Json.extension(),
+            (_, false) => Syntax::GraphQl.extension(),
+        };
+
+        let edited = match self.suspend(|| external::edit(&text, extension)) {
+            Ok(edited) => edited,
+            Err(err) => Err(format!("can't hand over the terminal: {err}")),
+        };
+        let edited = match edited {
+            Ok(edited) if edited != text => edited,
+            Ok(_) => return,
+            Err(err) => {
+                self.state.notify(Err(err));
+                return;
+            }
+        };
+        let editors = &mut self.editors;
+        match (self.state.payload_tab, editors.body_mode) {
+            (PayloadTab::Headers, _) => editors.headers.set_text(&edited),
+            (_, BodyMode::Raw) => editors.body.set_text(&edited),
+            (_, _) if editors.variables_focused => editors.variables.set_text(&edited),
+            (_, _) => editors.query.set_text(&edited),
+        }
+    }
+
+    /// Runs `run` with the terminal back to how it was before starting, for
+    /// programs that draw on it themselves
+    fn suspend<T>(&mut self, run: impl FnOnce() -> T) -> io::Result<T> {
+        self.graphics.show(self.terminal.backend_mut(), None)?;
+        let _ = execute!(self.terminal.backend_mut(), PopKeyboardEnhancementFlags);
+        disable_raw_mode()?;
+        execute!(
+            self.terminal.backend_mut(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            DisableBracketedPaste
+        )?;
+        let result = run();
+        enable_raw_mode()?;
+        execute!(
+            self.terminal.backend_mut(),
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableBracketedPaste
+        )?;
+        let _ = execute!(
+            self.terminal.backend_mut(),
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        );
+        // Whatever was on screen is gone
+        self.terminal.clear()?;
+        Ok(result)
+    }
+
+    /// The editor keys go to in the current mode
+    fn focused<'e>(state: &State, editors: &'e mut Editors<'a>) -> Focused<'e, 'a> {
+        match state.input_mode {
+            InputMode::PayloadEditing => match state.payload_tab {
+                PayloadTab::Params => Focused::Table(&mut editors.params),
+                PayloadTab::Headers => Focused::Table(&mut editors.headers),
+                PayloadTab::Body => match editors.body_mode {
+                    BodyMode::Raw => Focused::Text(&mut editors.body),
+                    BodyMode::Form => Focused::Table(&mut editors.form),
+                    BodyMode::Multipart => Focused::Table(&mut editors.multipart),
+                    BodyMode::Binary => Focused::Text(&mut editors.file),
+                    BodyMode::GraphQl if editors.variables_focused => {
+                        Focused::Text(&mut editors.variables)
+                    }
+                    BodyMode::GraphQl => Focused::Text(&mut editors.query),
+                },
+                PayloadTab::Auth => match editors.auth.fields_mut() {
+                    Some(fields) => Focused::Table(fields),
+                    None => Focused::None,
+                },
+                PayloadTab::Captures => Focused::Table(&mut editors.captures),
+                PayloadTab::Assertions => Focused::Table(&mut editors.assertions),
+                PayloadTab::Options => Focused::Text(&mut editors.options),
+            },
+            InputMode::MethodEditing => Focused::Text(&mut editors.method),
+            InputMode::UriEditing => Focused::Text(&mut editors.uri),
+            InputMode::Normal | InputMode::Response | InputMode::Sidebar => Focused::None,
+        }
+    }
+
+    /// Whether keys go to one of the text editors, and so through vim when
+    /// it's on
+    fn text_focused(state: &State, editors: &Editors) -> bool {
+        match state.input_mode {
+            InputMode::PayloadEditing => match state.payload_tab {
+                PayloadTab::Body => {
+                    !matches!(editors.body_mode, BodyMode::Form | BodyMode::Multipart)
+                }
+                PayloadTab::Options => true,
+                _ => false,
+            },
+            InputMode::MethodEditing | InputMode::UriEditing => true,
+            InputMode::Normal | InputMode::Response | InputMode::Sidebar => false,
+        }
+    }
+
+    fn ui(
+        f: &mut Frame<CrosstermBackend<io::Stdout>>,
+        state: &mut State<'a>,
+        editors: &mut Editors<'a>,
+        keys: &KeyMap,
+    ) {
+        let theme = theme::current();
+        let size = f.size();
+        let variables = state.environments.masked_variables();
+        for editor in [
+            &mut editors.uri,
+            &mut editors.body,
+            &mut editors.file,
+            &mut editors.query,
+            &mut editors.variables,
+        ] {
+            editor.highlight_variables(&variables);
+        }
+
+        // Layouts
+        let columns = match state.sidebar {
+            true => vec![
+                Constraint::Percentage(20),
+                Constraint::Percentage(state.split * 4 / 5),
+                Constraint::Percentage((100 - state.split) * 4 / 5),
+            ],
+            false => vec![
+                Constraint::Percentage(state.split),
+                Constraint::Percentage(100 - state.split),
+            ],
+        };
+        let main_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .margin(1)
+            .constraints(columns)
+            .split(size);
+        let (req_column, resp_column) = match state.sidebar {
+            true => (main_layout[1], main_layout[2]),
+            false => (main_layout[0], main_layout[1]),
+        };
+        // Inside the margin, where the zoomed pane goes
+        let full = Rect {
+            x: 1,
+            y: 1,
+            width: size.width.saturating_sub(2),
+            height: size.height.saturating_sub(2),
+        };
+        let resp_column = match state.zoom {
+            Some(Zoom::Response) => full,
+            _ => resp_column,
+        };
+
+        let req_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Min(0),
+                ]
+                .as_ref(),
+            )
+            .split(req_column);
+
+        let resp_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(3),
+            ])
+            .split(resp_column);
+        let body_area = match &state.filter {
+            Some(filter) => {
+                let layout = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(3), Constraint::Min(0)])
+                    .split(resp_layout[1]);
+                Self::render_filter(f, filter, layout[0]);
+                layout[1]
+            }
+            None => resp_layout[1],
+        };
+        state.resp_area = body_area;
+
+        // Main block
+        let block = Block::default().style(Style::default().bg(theme.background).fg(theme.text));
+        f.render_widget(block, size);
+
+        // Request tabs, in the top margin once there's more than one
+        let mut request_tabs = Rect::default();
+        if state.tabs.len() > 1 {
+            let titles: Vec<String> = (0..state.tabs.len())
+                .map(|index| Self::request_tab_title(state, editors, index))
+                .collect();
+            let area = Rect {
+                x: 1,
+                y: 0,
+                width: size.width.saturating_sub(2),
+                height: 1,
+            };
+            let tabs = Tabs::new(titles.into_iter().map(Line::from).collect())
+                .select(state.active_tab)
+                .style(Style::default().fg(theme.muted))
+                .highlight_style(
+                    Style::default()
+                        .fg(theme.text)
+                        .add_modifier(Modifier::BOLD)
+                        .bg(theme.selection),
+                );
+            f.render_widget(tabs, area);
+            request_tabs = area;
+        }
+
+        // Response block
+        let response = state.response.as_ref().and_then(|resp| resp.as_ref().ok());
+        let resp_focused = state.input_mode == InputMode::Response;
+        let resp_color = if resp_focused {
+            theme.focus
+        } else {
+            theme.text
+        };
+        let resp_tabs = Tabs::new(Self::tab_titles(&state.response_titles))
+            .block(Self::focus_ring(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("response ([ ])"),
+                resp_focused,
+            ))
+            .select(state.response_tab.as_int() as usize)
+            .style(Style::default().fg(resp_color))
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .bg(theme.selection),
+            );
+        f.render_widget(resp_tabs, resp_layout[0]);
+
+        let resp_title = match (response, state.response_tab) {
+            (Some(resp), ResponseTab::Headers) => format!("headers ({})", resp.headers.len()),
+            (Some(resp), ResponseTab::Cookies) => format!("cookies ({})", resp.cookies.len()),
+            (Some(_), ResponseTab::Timing) => "timing".to_string(),
+            (Some(resp), ResponseTab::Certificates) => match &resp.certificates {
+                Some(Ok(chain)) => format!("certificates ({})", chain.len()),
+                _ => "certificates".to_string(),
+            },
+            (Some(_), ResponseTab::Body)
+                if state.filter.as_ref().and_then(Filter::output).is_some() =>
+            {
+                "body (filtered)".to_string()
+            }
+            (Some(_), ResponseTab::Body) if state.diff.is_some() => {
+                "body (diff against pinned, d to close)".to_string()
+            }
+            (
+                Some(Response {
+                    image: Some(image), ..
+                }),
+                ResponseTab::Body,
+            ) if !state.raw_body => {
+                format!("body (image {}x{}, p for hex)", image.width, image.height)
+            }
+            (Some(resp), ResponseTab::Body) if resp.hex.is_some() => "body (hex)".to_string(),
+            (Some(resp), ResponseTab::Body) if resp.pretty.is_some() && state.raw_body => {
+                "body (raw)".to_string()
+            }
+            (Some(resp), ResponseTab::Body) if resp.is_event_stream() => {
+                "events (p for raw)".to_string()
+            }
+            (Some(resp), ResponseTab::Body) if resp.pretty.is_some() => {
+                "body (pretty, p for raw)".to_string()
+            }
+            _ => "body".to_string(),
+        };
+        let lines = state.resp_lines();
+        let page = state.resp_page() as usize;
+        let resp_title = if lines > page {
+            let first = state.resp_scroll as usize + 1;
+            let last = (first + page - 1).min(lines);
+            format!("{resp_title} [{first}-{last}/{lines}]")
+        } else {
+            resp_title
+        };
+        let resp_block = Self::focus_ring(
+            Block::default()
+                .borders(Borders::all())
+                .style(Style::default().fg(theme.text))
+                .border_style(Style::default().fg(resp_color))
+                .title(resp_title),
+            resp_focused,
+        );
+        let view = state.response_view().block(resp_block);
+        let image_area = view.image(body_area).map(|(_, area)| area);
+        f.render_widget(view, body_area);
+
+        // Status block
+        let status = match &state.response {
+            _ if state.pending.is_some() => {
+                let elapsed = state
+                    .pending
+                    .map(|start| start.elapsed())
+                    .unwrap_or_default();
+                let progress = match &state.response {
+                    Some(Ok(resp)) if state.socket.is_some() => {
+                        format!("open, {} logged", format_size(resp.body_size as u64))
+                    }
+                    Some(Ok(resp)) => format!("receiving {}", format_size(resp.body_size as u64)),
+                    _ => "sending".to_string(),
+                };
+                Line::from(Span::styled(
+                    format!(
+                        "{} {progress} {:.1}s",
+                        SPINNER[state.spinner_index % SPINNER.len()],
+                        elapsed.as_secs_f64()
+                    ),
+                    Style::default().fg(theme.accent),
+                ))
+            }
+            Some(Ok(resp)) => {
+                let color = match resp.status {
+                    200..=299 => theme.success,
+                    300..=399 => theme.accent,
+                    400..=599 => theme.error,
+                    _ => theme.text,
+                };
+                let mut status = vec![
+                    Span::styled(
+                        format!("{} {}", resp.status, resp.reason()),
+                        Style::default().fg(color).add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(
+                        format!("  {}", resp.version),
+                        Style::default().fg(theme.muted),
+                    ),
+                    Span::raw(format!(
+                        "  {} body, {} headers",
+                        format_size(resp.body_size as u64),
+                        format_size(resp.header_size() as u64)
+                    )),
+                ];
+                let encoding = match (&resp.encoding, resp.header("content-encoding")) {
+                    (Some(encoding), _) => Some(format!(
+                        " ({encoding}, {} decoded)",
+                        format_size(resp.bytes.len() as u64)
+                    )),
+                    (None, Some(encoding)) => Some(format!(" ({encoding}, not decoded)")),
+                    (None, None) => None,
+                };
+                if let Some(encoding) = encoding {
+                    status.push(Span::styled(encoding, Style::default().fg(theme.muted)));
+                }
+                if let Some(charset) = resp.charset {
+                    status.push(Span::styled(
+                        format!("  {charset}"),
+                        Style::default().fg(theme.muted),
+                    ));
+                }
+                if resp.attempts > 1 {
+                    status.push(Span::raw(format!("  {} attempts", resp.attempts)));
+                }
+                if let Some(challenged) = &resp.challenged {
+                    let reason = format!("  reauthenticated after {}", challenged.status);
+                    status.push(Span::raw(reason));
+                }
+                if let Some(failure) = grpc::failure(resp) {
+                    status.push(Span::styled(
+                        format!("  grpc {failure}"),
+                        Style::default().fg(theme.error),
+                    ));
+                }
+                if resp.stopped {
+                    status.push(Span::styled("  stopped", Style::default().fg(theme.accent)));
+                }
+                Line::from(status)
+            }
+            Some(Err(err)) => Line::from(Span::styled(
+                err.to_string(),
+                Style::default().fg(theme.error),
+            )),
+            None => Line::default(),
+        };
+        let status = match &state.search {
+            _ if state.quit_prompt.is_some() => {
+                let unsaved = match state.quit_prompt {
+                    Some(1) => "an unsaved request".to_string(),
+                    count => format!("{} unsaved requests", count.unwrap_or_default()),
+                };
+                Line::from(Span::styled(
+                    format!("quit with {unsaved}? (s)ave, (d)iscard, (c)ancel"),
+                    Style::default().fg(theme.accent),
+                ))
+            }
+            _ if state.restore.is_some() => {
+                let time = state
+                    .restore
+                    .as_ref()
+                    .and_then(|draft| DateTime::parse_from_rfc3339(&draft.time).ok())
+                    .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default();
+                Line::from(Span::styled(
+                    format!("restore the request left unsaved at {time}? (y/n)"),
+                    Style::default().fg(theme.accent),
+                ))
+            }
+            _ if state.env_file_prompt.is_some() => {
+                let (name, path) = state
+                    .env_file_prompt
+                    .as_ref()
+                    .map(|(name, path)| (name.as_str(), path.as_str()))
+                    .unwrap_or_default();
+                Line::from(vec![
+                    Span::styled(
+                        format!("read {name} variables from .env file (empty for none): "),
+                        Style::default().fg(theme.accent),
+                    ),
+                    Span::raw(path),
+                    Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+                ])
+            }
+            _ if state.new_environment.is_some() => Line::from(vec![
+                Span::styled("new environment: ", Style::default().fg(theme.accent)),
+                Span::raw(state.new_environment.as_deref().unwrap_or_default()),
+                Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+            ]),
+            _ if state.save_as.is_some() => Line::from(vec![
+                Span::styled(
+                    "save request as collection/name: ",
+                    Style::default().fg(theme.accent),
+                ),
+                Span::raw(state.save_as.as_deref().unwrap_or_default()),
+                Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+            ]),
+            _ if state.import_path.is_some() => Line::from(vec![
+                Span::styled("import file or URL: ", Style::default().fg(theme.accent)),
+                Span::raw(state.import_path.as_deref().unwrap_or_default()),
+                Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+            ]),
+            _ if state.export_path.is_some() => {
+                let (title, path) = state
+                    .export_path
+                    .as_ref()
+                    .map(|(export, path)| (export.title(), path.as_str()))
+                    .unwrap_or_default();
+                Line::from(vec![
+                    Span::styled(
+                        format!("export {title} file: "),
+                        Style::default().fg(theme.accent),
+                    ),
+                    Span::raw(path),
+                    Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+                ])
+            }
+            _ if state.move_to.is_some() => {
+                let (from, folder) = state
+                    .move_to
+                    .as_ref()
+                    .map(|(from, folder)| (from.as_str(), folder.as_str()))
+                    .unwrap_or_default();
+                Line::from(vec![
+                    Span::styled(
+                        format!("move {from} to collection/folder: "),
+                        Style::default().fg(theme.accent),
+                    ),
+                    Span::raw(folder),
+                    Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+                ])
+            }
+            _ if state.save_path.is_some() => Line::from(vec![
+                Span::styled("save to: ", Style::default().fg(theme.accent)),
+                Span::raw(state.save_path.as_deref().unwrap_or_default()),
+                Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+            ]),
+            Some(search) if search.editing => Line::from(vec![
+                Span::styled("/", Style::default().fg(theme.accent)),
+                Span::raw(search.query.as_str()),
+                Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+            ]),
+            Some(search) => {
+                let found = match search.matches.len() {
+                    0 => format!("  no matches for `{}`", search.query),
+                    len => format!(
+                        "  match {}/{len} for `{}`",
+                        search.current + 1,
+                        search.query
+                    ),
+                };
+                let mut status = status;
+                status
+                    .spans
+                    .push(Span::styled(found, Style::default().fg(theme.accent)));
+                status
+            }
+            None => status,
+        };
+        let status_block = Block::default()
+            .borders(Borders::all())
+            .style(Style::default().fg(theme.text))
+            .title("status");
+        f.render_widget(Paragraph::new(status).block(status_block), resp_layout[2]);
+
+        // Status bar, in the bottom margin
+        if size.height > 2 {
+            let area = Rect {
+                x: 1,
+                y: size.height - 1,
+                width: size.width.saturating_sub(2),
+                height: 1,
+            };
+            let status = Self::status_bar(state, editors, keys);
+            f.render_widget(Paragraph::new(status), area);
+        }
+
+        let uri_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(12), Constraint::Min(0)])
+            .split(req_layout[0]);
+        let payload_area = match state.zoom {
+            Some(Zoom::Payload) => full,
+            _ => req_layout[2],
+        };
+        // Only what's on screen takes clicks
+        state.panes = match state.zoom {
+            Some(Zoom::Response) => Panes {
+                request_tabs,
+                response_tabs: resp_layout[0],
+                response: resp_layout[1],
+                ..Panes::default()
+            },
+            Some(Zoom::Payload) => Panes {
+                request_tabs,
+                payload: full,
+                ..Panes::default()
+            },
+            None => Panes {
+                request_tabs,
+                sidebar: if state.sidebar {
+                    main_layout[0]
+                } else {
+                    Rect::default()
+                },
+                method: uri_layout[0],
+                uri: uri_layout[1],
+                payload_tabs: req_layout[1],
+                payload: req_layout[2],
+                response_tabs: resp_layout[0],
+                response: resp_layout[1],
+                columns: req_column.union(resp_column),
+                divider: resp_column.x,
+            },
+        };
+
+        let text_style = Style::default().bg(theme.background).fg(theme.text);
+        let method_editor = &mut editors.method;
+        method_editor.text_area.set_style(text_style);
+        let method_focused = state.input_mode == InputMode::MethodEditing;
+        method_editor.text_area.set_block(Self::focus_ring(
+            Block::default()
+                .borders(Borders::all())
+                .border_style(Style::default().fg(Self::border_color(
+                    method_focused,
+                    method_editor.validate_method(),
+                )))
+                .title(method_editor.title),
+            method_focused,
+        ));
+
+        let uri_editor = &mut editors.uri;
+        let uri = uri_editor.text();
+        let uri_title = match uri.contains("{{") {
+            true => {
+                let mut title = vec![Span::raw(format!("{} = ", uri_editor.title))];
+                title.extend(resolved_spans(&uri, &variables).spans);
+                Line::from(title)
+            }
+            false => Line::from(uri_editor.title),
+        };
+        uri_editor.text_area.set_style(text_style);
+        let uri_focused = state.input_mode == InputMode::UriEditing;
+        uri_editor.text_area.set_block(Self::focus_ring(
+            Block::default()
+                .borders(Borders::all())
+                .border_style(
+                    Style::default().fg(Self::border_color(uri_focused, uri_editor.validate_uri())),
+                )
+                .title(uri_title),
+            uri_focused,
+        ));
+
+        // Payload tabs
+        let tabs_focused = state.input_mode == InputMode::Normal;
+        let tabs = Tabs::new(Self::tab_titles(&state.payload_titles))
+            .block(Self::focus_ring(
+                Block::default().borders(Borders::ALL).title("option"),
+                tabs_focused,
+            ))
+            .select(state.payload_tab.as_int() as usize)
+            .style(Style::default().fg(if tabs_focused {
+                theme.focus
+            } else {
+                theme.text
+            }))
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .bg(theme.selection),
+            );
+
+        match state.zoom {
+            Some(Zoom::Payload) => {
+                f.render_widget(Clear, payload_area);
+                Self::render_payload(f, state, editors, &variables, payload_area);
+            }
+            Some(Zoom::Response) => {}
+            None => {
+                f.render_widget(editors.method.text_area.widget(), uri_layout[0]);
+                f.render_widget(editors.uri.text_area.widget(), uri_layout[1]);
+                f.render_widget(tabs, req_layout[1]);
+                Self::render_payload(f, state, editors, &variables, payload_area);
+            }
+        }
 
-mod models;
-mod traits;
+        if state.sidebar && state.zoom.is_none() {
+            Self::render_sidebar(f, state, keys, main_layout[0]);
+        }
+        if let Some(completion) = &state.completion {
+            let under = match completion.target {
+                _ if !Self::completion_shown(state, editors) => None,
+                Target::Uri if state.zoom.is_none() => Some(uri_layout[1]),
+                Target::Header { row, column } if state.zoom != Some(Zoom::Response) => {
+                    // Roughly where the table puts the cell, past the
+                    // checkbox column
+                    let width = payload_area.width.saturating_sub(6);
+                    let (x, width) = match column {
+                        0 => (payload_area.x + 4, width * 2 / 5),
+                        _ => (payload_area.x + 5 + width * 2 / 5, width - width * 2 / 5),
+                    };
+                    let y = payload_area.y + 2 + row as u16;
+                    (y + 1 < payload_area.y + payload_area.height).then_some(Rect {
+                        x,
+                        y,
+                        width: width.max(24).min(size.width.saturating_sub(x)),
+                        height: 1,
+                    })
+                }
+                Target::Query if state.zoom != Some(Zoom::Response) => {
+                    // Where the cursor is as long as the query fits, past the
+                    // line numbers
+                    let query = &editors.query.text_area;
+                    let (row, column) = query.cursor();
+                    let height = payload_area.height * 3 / 5;
+                    let numbers = query.lines().len().to_string().len() as u16 + 2;
+                    let x = (payload_area.x + 1 + numbers + column as u16)
+                        .min(size.width.saturating_sub(24));
+                    let y = payload_area.y + 1 + (row as u16).min(height.saturating_sub(3));
+                    Some(Rect {
+                        x,
+                        y,
+                        width: 24.min(size.width.saturating_sub(x)),
+                        height: 1,
+                    })
+                }
+                _ => None,
+            };
+            if let Some(under) = under {
+                Self::render_completion(f, completion, under, size);
+            }
+        }
+        if let Some(view) = &state.history_view {
+            Self::render_history(f, &state.history, view, size);
+        }
+        if let Some(view) = &state.workspace_view {
+            Self::render_workspaces(f, view, &state.workspace, size);
+        }
+        if let Some(palette) = &state.palette {
+            Self::render_palette(f, palette, size);
+        }
+        if let Some((name, editor)) = &state.environment_editor {
+            let area = Rect {
+                x: size.width / 6,
+                y: size.height / 6,
+                width: size.width - size.width / 3,
+                height: size.height - size.height / 3,
+            };
+            f.render_widget(Clear, area);
+            Self::render_table(
+                f,
+                editor,
+                format!("environment {name} (use as {{{{key}}}}, Ctrl+S secret, Esc to save)"),
+                true,
+                None,
+                area,
+            );
+        }
+        if let Some(scroll) = state.preview {
+            Self::render_preview(f, &editors.snapshot(), &variables, scroll, size);
+        }
+        if let Some(snippets) = &state.snippets {
+            Self::render_snippets(f, snippets, size);
+        }
+        if let Some(editor) = &mut state.curl_import {
+            let area = Rect {
+                x: size.width / 6,
+                y: size.height / 4,
+                width: size.width - size.width / 3,
+                height: size.height / 2,
+            };
+            let title = format!(
+                "{} (paste a curl command, Enter to import, Esc to cancel)",
+                editor.title
+            );
+            f.render_widget(Clear, area);
+            Self::render_editor(f, editor, title, true, true, area);
+        }
+        let toasts = Self::render_toasts(f, &state.toasts, size);
+        state.image_area = image_area.filter(|area| {
+            state.zoom != Some(Zoom::Payload)
+                && !state.modal()
+                && state.completion.is_none()
+                && !toasts.iter().any(|toast| toast.intersects(*area))
+        });
+    }
 
-use std::io;
-use traits::Tab;
-use tui::{
-    backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    text::{Span, Spans},
-    widgets::{Block, Borders, Tabs},
-    Frame, Terminal,
-};
+    /// Dropped down under the editor at `under`
+    fn render_completion(
+        f: &mut Frame<CrosstermBackend<io::Stdout>>,
+        completion: &Completion,
+        under: Rect,
+        size: Rect,
+    ) {
+        let theme = theme::current();
+        let y = under.y + under.height;
+        let height = (completion.items.len() as u16 + 2).min(size.height.saturating_sub(y + 1));
+        if height < 3 {
+            return;
+        }
+        let area = Rect {
+            x: under.x,
+            y,
+            width: under.width,
+            height,
+        };
+        let items: Vec<ListItem> = completion
+            .items
+            .iter()
+            .map(|item| ListItem::new(item.as_str()))
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.focus))
+                    .title("Tab to complete"),
+            )
+            .style(Style::default().fg(theme.text).bg(theme.background))
+            .highlight_style(Style::default().bg(theme.selection));
+        let mut list_state = ListState::default();
+        list_state.select(Some(completion.selected));
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(list, area, &mut list_state);
+    }
 
-struct App<'a> {
-    uri_editor: Editor<'a>,
-    payload_editors: Vec<Editor<'a>>,
-    state: State<'a>,
-    terminal: Terminal<CrosstermBackend<io::Stdout>>,
-}
+    /// Stacked down the top right corner, over everything else. Returns the
+    /// areas they cover
+    fn render_toasts(
+        f: &mut Frame<CrosstermBackend<io::Stdout>>,
+        toasts: &VecDeque<Toast>,
+        size: Rect,
+    ) -> Vec<Rect> {
+        let theme = theme::current();
+        let widest = (size.width / 2)
+            .clamp(20, TOAST_WIDTH)
+            .min(size.width.saturating_sub(2));
+        let mut y = 1;
+        let mut covered = Vec::new();
+        for toast in toasts {
+            let (text, color) = match &toast.message {
+                Ok(message) => (message, theme.success),
+                Err(err) => (err, theme.error),
+            };
+            let lines = wrap_words(text, widest.saturating_sub(4) as usize);
+            let longest = lines
+                .iter()
+                .map(|line| line.width())
+                .max()
+                .unwrap_or_default();
+            let width = (longest as u16 + 4).min(widest);
+            let lines: Vec<Line> = lines
+                .into_iter()
+                .map(|line| Line::from(format!(" {line}")))
+                .collect();
+            let height = lines.len() as u16 + 2;
+            if y + height > size.height {
+                break;
+            }
+            let area = Rect {
+                x: size.width - width - 1,
+                y,
+                width,
+                height,
+            };
+            let toast = Paragraph::new(lines)
+                .style(Style::default().fg(theme.text).bg(theme.background))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(color)),
+                );
+            f.render_widget(Clear, area);
+            f.render_widget(toast, area);
+            covered.push(area);
+            y += height;
+        }
+        covered
+    }
 
-impl<'a> App<'a> {
-    fn new() -> io::Result<Self> {
-        enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-        let backend = CrosstermBackend::new(stdout);
-        let terminal = Terminal::new(backend)?;
+    /// The editors with the placeholders filled in, secrets stay masked
+    fn render_preview(
+        f: &mut Frame<CrosstermBackend<io::Stdout>>,
+        request: &SavedRequest,
+        variables: &Variables,
+        scroll: u16,
+        size: Rect,
+    ) {
+        let theme = theme::current();
+        let area = Rect {
+            x: size.width / 6,
+            y: size.height / 6,
+            width: size.width - size.width / 3,
+            height: size.height - size.height / 3,
+        };
+        let (text, unknown) = resolved_request(request, variables);
+        let (title, color) = match unknown.is_empty() {
+            true => ("resolved request".to_string(), theme.focus),
+            false => (format!("unknown {}", unknown.join(", ")), theme.error),
+        };
+        let block = Block::default()
+            .borders(Borders::all())
+            .border_style(Style::default().fg(color))
+            .title(format!("{title} (Enter to send, Esc to close)"));
+        f.render_widget(Clear, area);
+        f.render_widget(
+            Paragraph::new(text)
+                .block(block)
+                .wrap(Wrap { trim: false })
+                .scroll((scroll, 0)),
+            area,
+        );
+    }
 
-        Ok(Self {
-            uri_editor: Editor::default("uri"),
-            payload_editors: vec![Editor::default("headers"), Editor::default("body")],
-            state: State::new(),
-            terminal,
-        })
+    fn render_snippets(
+        f: &mut Frame<CrosstermBackend<io::Stdout>>,
+        snippets: &Snippets,
+        size: Rect,
+    ) {
+        let theme = theme::current();
+        let area = Rect {
+            x: size.width / 6,
+            y: size.height / 6,
+            width: size.width - size.width / 3,
+            height: size.height - size.height / 3,
+        };
+        let mut title =
+            "generate code (Left/Right for language, c to copy, Esc to close)".to_string();
+        if let Some(note) = snippets.note {
+            title = format!("{note}, {title}");
+        }
+        let block = Block::default()
+            .borders(Borders::all())
+            .border_style(Style::default().fg(theme.focus))
+            .title(title);
+        let inner = block.inner(area);
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(2), Constraint::Min(0)])
+            .split(inner);
+        let tabs = Tabs::new(Self::tab_titles(&Language::TITLES))
+            .block(Block::default().borders(Borders::BOTTOM))
+            .select(snippets.language.as_int() as usize)
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .bg(theme.selection),
+            );
+        f.render_widget(tabs, layout[0]);
+        f.render_widget(
+            Paragraph::new(snippets.current()).scroll((snippets.scroll, 0)),
+            layout[1],
+        );
     }
 
-    fn run(&mut self) -> io::Result<()> {
-        loop {
-            // Try to make ui() a struct method and not an assoc method
-            self.terminal.draw(|f| {
-                Self::ui(
-                    f,
-                    &self.state,
-                    &mut self.uri_editor,
-                    &mut self.payload_editors,
-                )
-            })?;
-
-            let event = event::read()?;
-            if let Event::Key(key) = event.into() {
-                if key.kind == KeyEventKind::Press {
-                    match self.state.input_mode {
-                        InputMode::PayloadEditing => {
-                            self.payload_editors[self.state.req_tab_index]
-                                .text_area
-                                .input(key);
+    fn render_sidebar(
+        f: &mut Frame<CrosstermBackend<io::Stdout>>,
+        state: &State,
+        keys: &KeyMap,
+        area: Rect,
+    ) {
+        let theme = theme::current();
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(area);
+        let focused = state.input_mode == InputMode::Sidebar;
+        let color = if focused { theme.focus } else { theme.text };
+
+        let tabs = Tabs::new(Self::tab_titles(&state.sidebar_titles))
+            .block(Self::focus_ring(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(color))
+                    .title(format!(
+                        "workspace {} (Left/Right, {})",
+                        state.workspace,
+                        keys.label(Action::Workspaces)
+                    )),
+                focused,
+            ))
+            .select(state.sidebar_tab.as_int() as usize)
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .bg(theme.selection),
+            );
+        f.render_widget(tabs, layout[0]);
+
+        let (items, selected, title) = match state.sidebar_tab {
+            SidebarTab::Collections => (
+                Self::collection_items(&state.collections),
+                state.collection_selected,
+                "collections (Enter, c to copy, m to move, i to import, x to export)".to_string(),
+            ),
+            SidebarTab::History => (
+                state
+                    .history
+                    .entries
+                    .iter()
+                    .rev()
+                    .map(|entry| Self::history_item(entry, false))
+                    .collect(),
+                state.sidebar_history,
+                format!(
+                    "history ({} to search, x to export as HAR)",
+                    keys.label(Action::History)
+                ),
+            ),
+            SidebarTab::Environments => (
+                state
+                    .environments
+                    .list
+                    .iter()
+                    .map(|env| {
+                        let active = state.environments.active.as_ref() == Some(&env.name);
+                        let (marker, style) = match active {
+                            true => ("● ", Style::default().fg(theme.success)),
+                            false => ("  ", Style::default()),
+                        };
+                        let mut sources = format!(" {}", env.variables.len());
+                        if let Some(path) = &env.env_file {
+                            sources.push_str(&format!(" +{path}"));
                         }
-                        InputMode::UriEditing => {
-                            self.uri_editor.text_area.input(key);
+                        if env.process_env {
+                            sources.push_str(" +$env");
                         }
-                        _ => {}
-                    }
+                        ListItem::new(Line::from(vec![
+                            Span::styled(marker, style),
+                            Span::styled(env.name.as_str(), style),
+                            Span::styled(sources, Style::default().fg(theme.muted)),
+                        ]))
+                    })
+                    .collect(),
+                state.environment_selected,
+                "environments (Enter to use, e to edit, a to add, f .env file, p process env)"
+                    .to_string(),
+            ),
+        };
+        let block = Block::default()
+            .borders(Borders::all())
+            .border_style(Style::default().fg(color))
+            .title(title);
+        let mut list = List::new(items).block(Self::focus_ring(block, focused));
+        if focused {
+            list = list.highlight_style(
+                Style::default()
+                    .bg(theme.selection)
+                    .add_modifier(Modifier::BOLD),
+            );
+        }
+        let mut list_state = ListState::default();
+        list_state.select(Some(selected));
+        f.render_stateful_widget(list, layout[1], &mut list_state);
+    }
 
-                    match key.modifiers {
-                        KeyModifiers::NONE => match self.state.input_mode {
-                            InputMode::Normal => match key.code {
-                                KeyCode::Right => self.state.next_payload(),
-                                KeyCode::Left => self.state.previous_payload(),
-                                _ => {}
-                            },
-                            _ => {}
-                        },
-                        KeyModifiers::ALT => match key.code {
-                            KeyCode::Char('q') => return Ok(()),
-                            _ => {}
-                        },
-                        KeyModifiers::SHIFT => match key.code {
-                            KeyCode::Down => self.state.input_mode = self.state.input_mode.next(),
-                            KeyCode::Up => self.state.input_mode = self.state.input_mode.previous(),
-                            KeyCode::Enter => {}
-                            _ => {}
-                        },
-                        _ => {}
+    fn collection_items(collections: &Collections) -> Vec<ListItem<'_>> {
+        let theme = theme::current();
+        collections
+            .nodes()
+            .into_iter()
+            .map(|node| {
+                let indent = "  ".repeat(node.depth);
+                ListItem::new(Line::from(match node.kind {
+                    NodeKind::Folder { expanded } => vec![Span::styled(
+                        format!("{indent}{} {}", if expanded { "▾" } else { "▸" }, node.name),
+                        Style::default().fg(theme.accent),
+                    )],
+                    NodeKind::Request(item) => {
+                        let method = match item.request.method.trim() {
+                            "" => "GET",
+                            method => method,
+                        };
+                        vec![
+                            Span::styled(
+                                format!("{indent}{method:<7}"),
+                                Style::default().fg(theme.focus),
+                            ),
+                            Span::styled(node.name, Style::default().add_modifier(Modifier::BOLD)),
+                        ]
                     }
-                }
-            }
+                }))
+            })
+            .collect()
+    }
+
+    fn history_item(entry: &Entry, with_time: bool) -> ListItem<'_> {
+        let theme = theme::current();
+        let (status, color) = match entry.status {
+            Some(status) => (
+                status.to_string(),
+                match status {
+                    200..=299 => theme.success,
+                    300..=399 => theme.accent,
+                    _ => theme.error,
+                },
+            ),
+            None => ("ERR".to_string(), theme.error),
+        };
+        let method = match entry.request.method.trim() {
+            "" => "GET",
+            method => method,
+        };
+
+        let mut spans = Vec::new();
+        if with_time {
+            let time = DateTime::parse_from_rfc3339(&entry.time)
+                .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default();
+            spans.push(Span::styled(
+                format!("{time}  "),
+                Style::default().fg(theme.muted),
+            ));
+        }
+        spans.extend([
+            Span::styled(format!("{status:<4}"), Style::default().fg(color)),
+            Span::styled(format!("{method:<8}"), Style::default().fg(theme.focus)),
+            Span::raw(entry.request.uri.as_str()),
+        ]);
+        for attempt in &entry.attempts {
+            spans.push(Span::styled(
+                format!("  after {}", attempt.status),
+                Style::default().fg(theme.muted),
+            ));
+        }
+        if with_time {
+            spans.push(Span::styled(
+                format!("  {}ms", entry.duration_ms),
+                Style::default().fg(theme.muted),
+            ));
         }
+        ListItem::new(Line::from(spans))
     }
 
-    fn ui(
+    /// Newest first, over the middle of the screen
+    fn render_history(
         f: &mut Frame<CrosstermBackend<io::Stdout>>,
-        state: &State,
-        uri_editor: &mut Editor<'a>,
-        payload_editors: &mut Vec<Editor<'a>>,
+        history: &History,
+        view: &HistoryView,
+        size: Rect,
     ) {
-        let size = f.size();
+        let theme = theme::current();
+        let area = Rect {
+            x: size.width / 10,
+            y: size.height / 10,
+            width: size.width - size.width / 5,
+            height: size.height - size.height / 5,
+        };
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(area);
+        let entries = view.entries(history);
+        let items: Vec<ListItem> = entries
+            .iter()
+            .map(|entry| Self::history_item(entry, true))
+            .collect();
 
-        // Layouts
-        let main_layout = Layout::default()
-            .direction(Direction::Horizontal)
-            .margin(1)
-            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-            .split(size);
+        let block = Block::default()
+            .borders(Borders::all())
+            .border_style(Style::default().fg(theme.focus))
+            .title(format!(
+                "history ({}/{}, Enter to load, Esc to close)",
+                entries.len(),
+                history.entries.len()
+            ));
+        let list = List::new(items).block(block).highlight_style(
+            Style::default()
+                .bg(theme.selection)
+                .add_modifier(Modifier::BOLD),
+        );
+        let mut list_state = ListState::default();
+        list_state.select(Some(view.selected));
 
-        let req_layout = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints(
-                [
-                    Constraint::Length(3),
-                    Constraint::Length(3),
-                    Constraint::Min(0),
-                ]
-                .as_ref(),
-            )
-            .split(main_layout[0]);
+        let search = Paragraph::new(Line::from(vec![
+            Span::raw(view.query.as_str()),
+            Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+        ]))
+        .block(
+            Block::default()
+                .borders(Borders::all())
+                .border_style(Style::default().fg(theme.focus))
+                .title("search (url text, GET, 4xx, err, 2026-10-14, >2026-10, <2026-10-01)"),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(search, layout[0]);
+        f.render_stateful_widget(list, layout[1], &mut list_state);
+    }
 
-        let resp_layout = Layout::default()
+    fn render_palette(f: &mut Frame<CrosstermBackend<io::Stdout>>, palette: &Palette, size: Rect) {
+        let theme = theme::current();
+        let area = Rect {
+            x: size.width / 4,
+            y: size.height / 6,
+            width: size.width / 2,
+            height: size.height - size.height / 3,
+        };
+        let layout = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Min(0), Constraint::Length(3)])
-            .split(main_layout[1]);
-
-        // Main block
-        let block = Block::default().style(Style::default().bg(Color::Black).fg(Color::White));
-        f.render_widget(block, size);
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(area);
+        let width = layout[1].width.saturating_sub(2) as usize;
+        let items: Vec<ListItem> = palette
+            .matches()
+            .into_iter()
+            .map(|entry| {
+                let gap = width.saturating_sub(entry.title.len() + entry.key.len());
+                ListItem::new(Line::from(vec![
+                    Span::raw(entry.title.clone()),
+                    Span::raw(" ".repeat(gap)),
+                    Span::styled(entry.key.clone(), Style::default().fg(theme.muted)),
+                ]))
+            })
+            .collect();
 
-        // Response block
-        let resp_block = Block::default()
-            .borders(Borders::all())
-            .style(Style::default().fg(Color::White));
-        f.render_widget(resp_block, main_layout[1]);
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::all())
+                    .border_style(Style::default().fg(theme.focus))
+                    .title("commands (Enter to run, Esc to close)"),
+            )
+            .highlight_style(
+                Style::default()
+                    .bg(theme.selection)
+                    .add_modifier(Modifier::BOLD),
+            );
+        let mut list_state = ListState::default();
+        list_state.select(Some(palette.selected));
 
-        uri_editor.text_area.set_block(
+        let query = Paragraph::new(Line::from(vec![
+            Span::raw(palette.query.as_str()),
+            Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+        ]))
+        .block(
             Block::default()
                 .borders(Borders::all())
-                .border_style(
-                    Style::default().fg(if state.input_mode == InputMode::UriEditing {
-                        Color::Cyan
-                    } else {
-                        Color::White
-                    }),
-                )
-                .title(uri_editor.title),
+                .border_style(Style::default().fg(theme.focus))
+                .title("find a command"),
         );
+        f.render_widget(Clear, area);
+        f.render_widget(query, layout[0]);
+        f.render_stateful_widget(list, layout[1], &mut list_state);
+    }
 
-        // Payload tabs
-        let payload_titles = state
-            .payload_titles
-            .iter()
-            .map(|t| {
-                let (first, rest) = t.split_at(1);
-                Spans::from(vec![
-                    Span::styled(first, Style::default().fg(Color::Yellow)),
-                    Span::styled(rest, Style::default().fg(Color::Green)),
-                ])
+    fn render_workspaces(
+        f: &mut Frame<CrosstermBackend<io::Stdout>>,
+        view: &WorkspaceView,
+        current: &str,
+        size: Rect,
+    ) {
+        let theme = theme::current();
+        let area = Rect {
+            x: size.width / 4,
+            y: size.height / 4,
+            width: size.width / 2,
+            height: size.height / 2,
+        };
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(area);
+        let items: Vec<ListItem> = view
+            .matches()
+            .into_iter()
+            .map(|name| {
+                let note = match name {
+                    _ if name == current => " (current)",
+                    _ if !view.exists(name) => " (new)",
+                    _ => "",
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(name.to_string()),
+                    Span::styled(note, Style::default().fg(theme.muted)),
+                ]))
             })
             .collect();
 
-        let tabs = Tabs::new(payload_titles)
-            .block(Block::default().borders(Borders::ALL).title("option"))
-            .select(state.req_tab_index)
-            .style(
-                Style::default().fg(if state.input_mode == InputMode::Normal {
-                    Color::Cyan
-                } else {
-                    Color::White
-                }),
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::all())
+                    .border_style(Style::default().fg(theme.focus))
+                    .title("workspaces (Enter to switch, Esc to close)"),
             )
             .highlight_style(
                 Style::default()
-                    .add_modifier(Modifier::BOLD)
-                    .bg(Color::Blue),
+                    .bg(theme.selection)
+                    .add_modifier(Modifier::BOLD),
             );
+        let mut list_state = ListState::default();
+        list_state.select(Some(view.selected));
 
-        // Payload editor
-        let inner = &mut payload_editors[state.req_tab_index];
-        inner.text_area.set_block(
+        let name = Paragraph::new(Line::from(vec![
+            Span::raw(view.query.as_str()),
+            Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+        ]))
+        .block(
             Block::default()
                 .borders(Borders::all())
-                .border_style(Style::default().fg(
-                    if state.input_mode == InputMode::PayloadEditing {
-                        if inner.validate_json() {
-                            Color::Cyan
-                        } else {
-                            Color::Red
-                        }
-                    } else {
-                        Color::White
-                    },
-                ))
-                .title(inner.title),
+                .border_style(Style::default().fg(theme.focus))
+                .title("find or name a new workspace"),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(name, layout[0]);
+        f.render_stateful_widget(list, layout[1], &mut list_state);
+    }
+
+    fn render_filter(f: &mut Frame<CrosstermBackend<io::Stdout>>, filter: &Filter, area: Rect) {
+        let theme = theme::current();
+        let (title, color) = match filter.error() {
+            Some(err) => (
+                Span::styled(err, Style::default().fg(theme.error)),
+                theme.error,
+            ),
+            None => (
+                Span::raw("filter (JSONPath or .jq.path, Esc to clear)"),
+                if filter.editing {
+                    theme.focus
+                } else {
+                    theme.text
+                },
+            ),
+        };
+
+        let mut line = vec![Span::raw(filter.expr.as_str())];
+        if filter.editing {
+            line.push(Span::styled(
+                " ",
+                Style::default().add_modifier(Modifier::REVERSED),
+            ));
+        }
+        let block = Block::default()
+            .borders(Borders::all())
+            .border_style(Style::default().fg(color))
+            .title(title);
+        f.render_widget(Paragraph::new(Line::from(line)).block(block), area);
+    }
+
+    fn tab_titles<'b>(titles: &[&'b str]) -> Vec<Line<'b>> {
+        let theme = theme::current();
+        titles
+            .iter()
+            .map(|t| {
+                let (first, rest) = t.split_at(1);
+                Line::from(vec![
+                    Span::styled(first, Style::default().fg(theme.accent)),
+                    Span::styled(rest, Style::default().fg(theme.success)),
+                ])
+            })
+            .collect()
+    }
+
+    /// Thick borders around what keys go to
+    fn focus_ring(block: Block, focused: bool) -> Block {
+        match focused {
+            true => block.border_type(BorderType::Thick),
+            false => block,
+        }
+    }
+
+    fn border_color(editing: bool, valid: bool) -> Color {
+        let theme = theme::current();
+        match (editing, valid) {
+            (false, _) => theme.text,
+            (true, true) => theme.focus,
+            (true, false) => theme.error,
+        }
+    }
+
+    /// Mode, environment, how the request is doing and keys for the mode
+    fn status_bar(state: &State, editors: &Editors, keys: &KeyMap) -> Line<'static> {
+        let theme = theme::current();
+        let mut spans = vec![Span::styled(
+            format!(" {} ", state.input_mode.title().to_uppercase()),
+            Style::default()
+                .fg(theme.text)
+                .bg(theme.selection)
+                .add_modifier(Modifier::BOLD),
+        )];
+        if let Some(vim) = state
+            .vim
+            .as_ref()
+            .filter(|_| Self::text_focused(state, editors))
+        {
+            spans.push(Span::styled(
+                format!(" -- {} --", vim.mode.title()),
+                Style::default().fg(theme.accent),
+            ));
+        }
+        spans.push(match &state.environments.active {
+            Some(name) => Span::styled(format!("  env {name}"), Style::default().fg(theme.success)),
+            None => Span::styled("  no env", Style::default().fg(theme.muted)),
+        });
+        spans.push(match (&state.response, state.pending) {
+            (Some(Ok(_)), Some(start)) if state.socket.is_some() => Span::styled(
+                format!("  websocket open {:.0}s", start.elapsed().as_secs_f64()),
+                Style::default().fg(theme.accent),
+            ),
+            (_, Some(start)) => Span::styled(
+                format!("  sending {:.1}s", start.elapsed().as_secs_f64()),
+                Style::default().fg(theme.accent),
+            ),
+            (Some(Ok(resp)), None) => {
+                Span::raw(format!("  done in {}", format_duration(resp.timing.total)))
+            }
+            (Some(Err(_)), None) => Span::styled("  failed", Style::default().fg(theme.error)),
+            (None, None) => Span::styled("  idle", Style::default().fg(theme.muted)),
+        });
+
+        if let Some(err) =
+            Self::json_error(editors).filter(|_| state.payload_tab == PayloadTab::Body)
+        {
+            spans.push(Span::styled(
+                format!("  invalid JSON: {err}"),
+                Style::default().fg(theme.error),
+            ));
+        }
+
+        let actions: &[(Action, &str)] = match state.input_mode {
+            _ if state.modal() => &[],
+            InputMode::Normal => &[
+                (Action::Send, "send"),
+                (Action::NextMode, "next pane"),
+                (Action::NextTab, "next tab"),
+                (Action::NextEnvironment, "env"),
+                (Action::Palette, "commands"),
+                (Action::Quit, "quit"),
+            ],
+            InputMode::Response => &[
+                (Action::Send, "send"),
+                (Action::NextMode, "next pane"),
+                (Action::NextResponseTab, "next tab"),
+                (Action::Search, "search"),
+                (Action::Zoom, "zoom"),
+                (Action::Palette, "commands"),
+            ],
+            InputMode::PayloadEditing => &[
+                (Action::Send, "send"),
+                (Action::NextMode, "next pane"),
+                (Action::Zoom, "zoom"),
+                (Action::Palette, "commands"),
+            ],
+            InputMode::MethodEditing | InputMode::UriEditing => &[
+                (Action::Send, "send"),
+                (Action::NextMode, "next pane"),
+                (Action::Palette, "commands"),
+            ],
+            InputMode::Sidebar => &[(Action::Sidebar, "close"), (Action::Palette, "commands")],
+        };
+        let mut hints: Vec<(String, &str)> = actions
+            .iter()
+            .map(|&(action, title)| (keys.label(action), title))
+            .collect();
+        match state.input_mode {
+            // Answered with the letters they show
+            _ if state.restore.is_some() || state.quit_prompt.is_some() => {}
+            _ if state.modal() => {
+                hints.push(("Enter".to_string(), "confirm"));
+                hints.push(("Esc".to_string(), "cancel"));
+            }
+            InputMode::Sidebar => hints.insert(0, ("Left/Right".to_string(), "lists")),
+            _ => {}
+        }
+        spans.push(Span::raw("   "));
+        for (key, title) in hints {
+            spans.push(Span::styled(key, Style::default().fg(theme.focus)));
+            spans.push(Span::styled(
+                format!(" {title}  "),
+                Style::default().fg(theme.muted),
+            ));
+        }
+        Line::from(spans)
+    }
+
+    /// Why the raw JSON body doesn't parse, nothing for an empty one
+    fn json_error(editors: &Editors) -> Option<serde_json::Error> {
+        let body = &editors.body;
+        let json = editors.body_mode == BodyMode::Raw && body.syntax == Syntax::Json;
+        match json && !body.text().trim().is_empty() {
+            true => body.json_error(),
+            false => None,
+        }
+    }
+
+    fn render_payload(
+        f: &mut Frame<CrosstermBackend<io::Stdout>>,
+        state: &State,
+        editors: &mut Editors<'a>,
+        variables: &Variables,
+        area: Rect,
+    ) {
+        let editing = state.input_mode == InputMode::PayloadEditing;
+        let numbers = Style::default().fg(theme::current().muted);
+        let body_title = format!("{} ({})", editors.body.title, editors.body_mode.title());
+
+        match (state.payload_tab, editors.body_mode) {
+            (PayloadTab::Params, _) => {
+                let title = editors.params.title.to_string();
+                Self::render_table(f, &editors.params, title, editing, Some(variables), area);
+            }
+            (PayloadTab::Headers, _) => {
+                let title = editors.headers.title.to_string();
+                Self::render_table(f, &editors.headers, title, editing, Some(variables), area);
+            }
+            (PayloadTab::Body, BodyMode::Raw) => {
+                let title = format!(
+                    "{} (raw {}) [{}]",
+                    editors.body.title,
+                    editors.body.syntax.title(),
+                    editors.body.position()
+                );
+                let valid = editors.body.validate();
+                editors.body.text_area.set_line_number_style(numbers);
+                Self::render_editor(f, &mut editors.body, title, valid, editing, area);
+                if let Some(err) = Self::json_error(editors) {
+                    let lines = editors.body.text_area.lines().len();
+                    let highlight = LineHighlight::new(err.line(), lines, theme::current().error);
+                    f.render_widget(
+                        highlight,
+                        Block::default().borders(Borders::all()).inner(area),
+                    );
+                }
+            }
+            (PayloadTab::Body, BodyMode::Form) => {
+                Self::render_table(f, &editors.form, body_title, editing, Some(variables), area);
+            }
+            (PayloadTab::Body, BodyMode::Multipart) => {
+                Self::render_table(
+                    f,
+                    &editors.multipart,
+                    body_title,
+                    editing,
+                    Some(variables),
+                    area,
+                );
+            }
+            (PayloadTab::Body, BodyMode::Binary) => {
+                let metadata = editors.file_path().map(std::fs::metadata);
+                let (title, valid) = match metadata {
+                    Some(Ok(metadata)) => (
+                        format!("{body_title}, {}", format_size(metadata.len())),
+                        metadata.is_file(),
+                    ),
+                    Some(Err(_)) => (format!("{body_title}, file not found"), false),
+                    None => (body_title, false),
+                };
+                Self::render_editor(f, &mut editors.file, title, valid, editing, area);
+            }
+            (PayloadTab::Body, BodyMode::GraphQl) => {
+                let layout = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                    .split(area);
+                let focused = editors.variables_focused;
+
+                let (query_editing, variables_editing) = (editing && !focused, editing && focused);
+                let mut title = format!("{body_title} [{}]", editors.query.position());
+                // Checked against the schema once it's been introspected
+                let endpoint = Self::graphql_endpoint(editors, variables);
+                let problem = match state.schemas.get(&endpoint) {
+                    Some(Some(schema)) => schema.problems(&editors.query.text()).into_iter().next(),
+                    _ => None,
+                };
+                if let Some((line, problem)) = &problem {
+                    title.push_str(&format!(", line {line}: {problem}"));
+                }
+                let valid = editors.query.validate() && problem.is_none();
+                editors.query.text_area.set_line_number_style(numbers);
+                Self::render_editor(
+                    f,
+                    &mut editors.query,
+                    title,
+                    valid,
+                    query_editing,
+                    layout[0],
+                );
+                if let Some((line, _)) = problem {
+                    let lines = editors.query.text_area.lines().len();
+                    let highlight = LineHighlight::new(line, lines, theme::current().error);
+                    f.render_widget(
+                        highlight,
+                        Block::default().borders(Borders::all()).inner(layout[0]),
+                    );
+                }
+
+                let title = format!(
+                    "{} [{}]",
+                    editors.variables.title,
+                    editors.variables.position()
+                );
+                let valid =
+                    editors.variables.text().trim().is_empty() || editors.variables.validate();
+                editors.variables.text_area.set_line_number_style(numbers);
+                Self::render_editor(
+                    f,
+                    &mut editors.variables,
+                    title,
+                    valid,
+                    variables_editing,
+                    layout[1],
+                );
+            }
+            (PayloadTab::Auth, _) => {
+                let auth = &editors.auth;
+                let title = format!("{} ({})", auth.title, auth.mode.title());
+                match auth.fields() {
+                    Some(fields) => {
+                        Self::render_table(f, fields, title, editing, Some(variables), area)
+                    }
+                    None => f.render_widget(
+                        Paragraph::new("No authorization, press a in normal mode to switch")
+                            .block(Block::default().borders(Borders::all()).title(title)),
+                        area,
+                    ),
+                }
+            }
+            (PayloadTab::Captures, _) => {
+                let title = format!(
+                    "{} (variable = JSONPath into the response, set when received)",
+                    editors.captures.title
+                );
+                Self::render_table(f, &editors.captures, title, editing, None, area);
+            }
+            (PayloadTab::Assertions, _) => {
+                let title = format!(
+                    "{} (checked when received, e.g. status = 2xx, $.id = exists)",
+                    editors.assertions.title
+                );
+                Self::render_table(
+                    f,
+                    &editors.assertions,
+                    title,
+                    editing,
+                    Some(variables),
+                    area,
+                );
+            }
+            (PayloadTab::Options, _) => {
+                let title = editors.options.title.to_string();
+                let valid = editors.options.validate();
+                Self::render_editor(f, &mut editors.options, title, valid, editing, area);
+            }
+        }
+    }
+
+    fn render_table(
+        f: &mut Frame<CrosstermBackend<io::Stdout>>,
+        editor: &KeyValueEditor,
+        title: String,
+        editing: bool,
+        variables: Option<&Variables>,
+        area: Rect,
+    ) {
+        let block = Block::default()
+            .borders(Borders::all())
+            .border_style(Style::default().fg(Self::border_color(editing, editor.validate())))
+            .title(title);
+        f.render_widget(
+            KeyValueTable::new(editor)
+                .block(Self::focus_ring(block, editing))
+                .focused(editing)
+                .variables(variables),
+            area,
         );
+    }
 
-        f.render_widget(uri_editor.text_area.widget(), req_layout[0]);
-        f.render_widget(tabs, req_layout[1]);
-        f.render_widget(inner.text_area.widget(), req_layout[2]);
+    fn render_editor(
+        f: &mut Frame<CrosstermBackend<io::Stdout>>,
+        editor: &mut Editor<'a>,
+        title: String,
+        valid: bool,
+        editing: bool,
+        area: Rect,
+    ) {
+        let theme = theme::current();
+        let color = Self::border_color(editing, valid);
+        editor
+            .text_area
+            .set_style(Style::default().bg(theme.background).fg(theme.text));
+        editor.text_area.set_block(Self::focus_ring(
+            Block::default()
+                .borders(Borders::all())
+                .border_style(Style::default().fg(color))
+                .title(title),
+            editing,
+        ));
+        f.render_widget(editor.text_area.widget(), area);
     }
 }
 
 impl<'a> Drop for App<'a> {
     fn drop(&mut self) {
+        let _ = self.graphics.show(self.terminal.backend_mut(), None);
+        let _ = execute!(self.terminal.backend_mut(), PopKeyboardEnhancementFlags);
         disable_raw_mode().unwrap();
         execute!(
             self.terminal.backend_mut(),
             LeaveAlternateScreen,
-            DisableMouseCapture
+            DisableMouseCapture,
+            DisableBracketedPaste
         )
         .unwrap();
         self.terminal.show_cursor().unwrap();
     }
 }
 
+fn contains(area: Rect, (column, row): (u16, u16)) -> bool {
+    (area.x..area.x + area.width).contains(&column) && (area.y..area.y + area.height).contains(&row)
+}
+
 fn main() -> io::Result<()> {
-    App::new()?.run()
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("run") => std::process::exit(cli::run(&args[1..])),
+        Some("test") => std::process::exit(cli::test(&args[1..])),
+        Some("-h" | "--help") => {
+            println!("{}", cli::USAGE);
+            Ok(())
+        }
+        None => App::new()?.run(),
+        Some(_) => {
+            // Checked before taking over the terminal, so the error stays readable
+            let (request, ignored) = match curl::from_args(args.into_iter()) {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    eprintln!("curl-rs: {err}, see curl-rs --help");
+                    std::process::exit(2);
+                }
+            };
+            let mut app = App::new()?;
+            app.load_curl(&request, &ignored);
+            app.run()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{buffer::Buffer, symbols, widgets::Widget};
+
+    use super::*;
+    use crate::models::KeyValueRow;
+
+    #[test]
+    fn clicks_tab_titles() {
+        let titles = ["Params", "Headers", "Body"];
+        let row = Rect::new(3, 2, 40, 1);
+        let mut buffer = Buffer::empty(row);
+        Tabs::new(titles.iter().map(|title| Line::from(*title)).collect()).render(row, &mut buffer);
+
+        // Every letter of a title picks its tab, titles are split by dividers
+        let widths = || titles.iter().map(|title| title.len());
+        let mut tab = 0;
+        for column in row.x..row.x + row.width {
+            match buffer.get(column, row.y).symbol.as_str() {
+                " " => {}
+                symbols::line::VERTICAL => tab += 1,
+                letter => assert_eq!(
+                    App::tab_at(widths(), row, (column, row.y)),
+                    Some(tab),
+                    "column {column} is `{letter}`"
+                ),
+            }
+        }
+        assert_eq!(tab, titles.len() - 1);
+        assert_eq!(App::tab_at(widths(), row, (4, 2)), Some(0));
+        assert_eq!(App::tab_at(widths(), row, (4, 3)), None);
+        assert_eq!(App::tab_at(widths(), row, (39, 2)), None);
+        assert!(contains(row, (42, 2)));
+        assert!(!contains(row, (43, 2)));
+    }
+
+    fn state() -> State<'static> {
+        let dir = std::env::temp_dir().join(format!("curl-rs-tests-{}", std::process::id()));
+        history::set_data_dir(dir);
+        State::new(&Config::default())
+    }
+
+    #[test]
+    fn shows_status() {
+        let text = |line: Line| -> String {
+            line.spans
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect()
+        };
+        let (mut state, editors, keys) = (state(), Editors::new(), KeyMap::default());
+        state.environments.active = None;
+        state.input_mode = InputMode::Normal;
+        assert_eq!(
+            text(App::status_bar(&state, &editors, &keys)),
+            " NORMAL   no env  idle   Ctrl+Enter send  Tab next pane  Right next tab  e env  Ctrl+P commands  Alt+Q quit  "
+        );
+
+        state.environments.active = Some("dev".to_string());
+        state.response = Some(Err(RequestError::InvalidUri));
+        state.save_as = Some(String::new());
+        assert_eq!(
+            text(App::status_bar(&state, &editors, &keys)),
+            " NORMAL   env dev  failed   Enter confirm  Esc cancel  "
+        );
+    }
+
+    #[test]
+    fn completes_header_cells() {
+        let (mut state, mut editors) = (state(), Editors::new());
+        state.input_mode = InputMode::PayloadEditing;
+        state.payload_tab = PayloadTab::Headers;
+        editors.headers.set_rows(&[KeyValueRow {
+            key: "Content-Type".to_string(),
+            value: "app".to_string(),
+            ..KeyValueRow::new()
+        }]);
+        editors.headers.column = 1;
+        let (target, typed) = App::completable(&state, &editors).unwrap();
+        assert_eq!(target, Target::Header { row: 0, column: 1 });
+        assert_eq!(typed, "app");
+
+        // Taken as one edit
+        editors.headers.set_cell("application/json");
+        assert_eq!(editors.headers.to_text(), "Content-Type: application/json");
+        assert!(editors.headers.undo());
+        assert_eq!(editors.headers.to_text(), "Content-Type: app");
+
+        state.payload_tab = PayloadTab::Params;
+        assert!(App::completable(&state, &editors).is_none());
+    }
+
+    #[test]
+    fn finds_json_errors() {
+        let mut editors = Editors::new();
+        editors.body_mode = BodyMode::Raw;
+        editors.body.set_text("{\n  \"a\": 1,\n}");
+        let err = App::json_error(&editors).unwrap();
+        assert_eq!((err.line(), err.column()), (3, 1));
+
+        editors.body.set_text("  \n");
+        assert!(App::json_error(&editors).is_none());
+        // Only JSON bodies are checked
+        editors.body.set_text("<a>");
+        editors.body.syntax = Syntax::Xml;
+        assert!(App::json_error(&editors).is_none());
+    }
+
+    #[test]
+    fn pastes_into_focus() {
+        let (mut state, mut editors) = (state(), Editors::new());
+        state.input_mode = InputMode::UriEditing;
+        // Put back together when wrapped over lines
+        App::paste(
+            &mut state,
+            &mut editors,
+            "http://localhost/\r\n  users?page=1\n",
+        );
+        assert_eq!(editors.uri.text(), "http://localhost/users?page=1");
+
+        state.input_mode = InputMode::PayloadEditing;
+        state.payload_tab = PayloadTab::Body;
+        editors.body_mode = BodyMode::Raw;
+        App::paste(&mut state, &mut editors, "{\r\n  \"a\": 1\r}");
+        assert_eq!(editors.body.text(), "{\n  \"a\": 1\n}");
+
+        state.toasts.clear();
+        state.input_mode = InputMode::Normal;
+        App::paste(&mut state, &mut editors, "lost");
+        assert_eq!(
+            state.toasts[0].message,
+            Err("open an editor to paste into".to_string())
+        );
+    }
 }
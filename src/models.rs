@@ -1,10 +1,78 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use image::RgbaImage;
+use openssl::ssl::SslConnector;
+use ratatui::{layout::Rect, style::Style};
 use regex::Regex;
+use reqwest::{
+    header::{
+        HeaderMap, HeaderName, HeaderValue, ACCEPT_ENCODING, AUTHORIZATION, CONTENT_ENCODING,
+        CONTENT_LENGTH, CONTENT_TYPE, COOKIE, LOCATION, PROXY_AUTHORIZATION, TRANSFER_ENCODING,
+        WWW_AUTHENTICATE,
+    },
+    Method, Url,
+};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tui::style::{Color, Style};
-use tui_textarea::TextArea;
+use serde_json_path::JsonPath;
+use tokio::sync::{mpsc::UnboundedSender, Notify};
+use tui_textarea::{CursorMove, TextArea};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    assertions,
+    auth::{Auth, AuthEditor, AuthMode},
+    certs::Certificate,
+    codegen::Snippets,
+    collections::{Collections, DEFAULT_COLLECTION},
+    completion::{Completion, Target},
+    config::{Config, RetryPolicy, MAX_DELAY, MAX_RETRIES, SPLIT_RANGE},
+    cookies::Cookie,
+    diff,
+    draft::Draft,
+    environments::{self, Environments, Variables},
+    graphql::Schema,
+    har, highlight,
+    history::{Attempt, Entry, History, HistoryView},
+    http_file,
+    import::Imported,
+    net::{self, Chunk},
+    palette::Palette,
+    postman, sse, theme,
+    timing::Timing,
+    tls::{self, Identity},
+    traits::Tab,
+    utils::{extension_for, hex_dump, join_lines},
+    vim::Vim,
+    websocket::Outgoing,
+    widgets::ResponseView,
+    workspaces::{self, WorkspaceView},
+};
 
-use crate::traits::Tab;
+/// Longest request tab title before it's cut short
+const TAB_TITLE_WIDTH: usize = 24;
+/// Edits a table remembers, as many as the text editors do
+const UNDO_LIMIT: usize = 50;
+/// How long a toast stays up, errors twice as long
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+/// More at once and the oldest goes early
+const MAX_TOASTS: usize = 4;
 
+/// The half of the screen keys go to outside the editors
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum AppMode {
     Request = 0,
@@ -34,10 +102,28 @@ impl Tab for AppMode {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// What has focus, in the order Tab moves it. `Normal` is the payload tabs,
+/// the keys of normal mode work there and in the response
 pub enum InputMode {
-    UriEditing = 0,
-    Normal = 1,
-    PayloadEditing = 2,
+    MethodEditing = 0,
+    UriEditing = 1,
+    Normal = 2,
+    PayloadEditing = 3,
+    Response = 4,
+    Sidebar = 5,
+}
+
+impl InputMode {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::MethodEditing => "method",
+            Self::UriEditing => "uri",
+            Self::Normal => "normal",
+            Self::PayloadEditing => "payload",
+            Self::Response => "response",
+            Self::Sidebar => "sidebar",
+        }
+    }
 }
 
 impl Tab for InputMode {
@@ -47,13 +133,53 @@ impl Tab for InputMode {
 
     fn to_enum(&self, num: u8) -> Self {
         match num {
-            0 => Self::UriEditing,
-            1 => Self::Normal,
-            2 => Self::PayloadEditing,
+            0 => Self::MethodEditing,
+            1 => Self::UriEditing,
+            2 => Self::Normal,
+            3 => Self::PayloadEditing,
+            4 => Self::Response,
+            5 => Self::Sidebar,
             _ => Self::Normal,
         }
     }
 
+    fn next(&self) -> Self {
+        self.to_enum((self.as_int() + 1) % 6)
+    }
+
+    fn previous(&self) -> Self {
+        self.to_enum((self.as_int() + 5) % 6)
+    }
+}
+
+/// A pane drawn over the whole terminal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Zoom {
+    Response,
+    Payload,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SidebarTab {
+    Collections = 0,
+    History = 1,
+    Environments = 2,
+}
+
+impl Tab for SidebarTab {
+    fn as_int(&self) -> u8 {
+        *self as u8
+    }
+
+    fn to_enum(&self, num: u8) -> Self {
+        match num {
+            0 => Self::Collections,
+            1 => Self::History,
+            2 => Self::Environments,
+            _ => Self::Collections,
+        }
+    }
+
     fn next(&self) -> Self {
         self.to_enum((self.as_int() + 1) % 3)
     }
@@ -63,79 +189,4353 @@ impl Tab for InputMode {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PayloadTab {
+    Params = 0,
+    Headers = 1,
+    Body = 2,
+    Auth = 3,
+    Captures = 4,
+    Assertions = 5,
+    Options = 6,
+}
+
+impl Tab for PayloadTab {
+    fn as_int(&self) -> u8 {
+        *self as u8
+    }
+
+    fn to_enum(&self, num: u8) -> Self {
+        match num {
+            0 => Self::Params,
+            1 => Self::Headers,
+            2 => Self::Body,
+            3 => Self::Auth,
+            4 => Self::Captures,
+            5 => Self::Assertions,
+            6 => Self::Options,
+            _ => Self::Params,
+        }
+    }
+
+    fn next(&self) -> Self {
+        self.to_enum((self.as_int() + 1) % 7)
+    }
+
+    fn previous(&self) -> Self {
+        self.to_enum((self.as_int() + 6) % 7)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResponseTab {
+    Body = 0,
+    Headers = 1,
+    Cookies = 2,
+    Timing = 3,
+    Certificates = 4,
+}
+
+impl Tab for ResponseTab {
+    fn as_int(&self) -> u8 {
+        *self as u8
+    }
+
+    fn to_enum(&self, num: u8) -> Self {
+        match num {
+            0 => Self::Body,
+            1 => Self::Headers,
+            2 => Self::Cookies,
+            3 => Self::Timing,
+            4 => Self::Certificates,
+            _ => Self::Body,
+        }
+    }
+
+    fn next(&self) -> Self {
+        self.to_enum((self.as_int() + 1) % 5)
+    }
+
+    fn previous(&self) -> Self {
+        self.to_enum((self.as_int() + 4) % 5)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyMode {
+    #[default]
+    Raw = 0,
+    Form = 1,
+    Multipart = 2,
+    Binary = 3,
+    GraphQl = 4,
+}
+
+impl BodyMode {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Raw => "raw",
+            Self::Form => "x-www-form-urlencoded",
+            Self::Multipart => "form-data, prefix values with @ to send a file",
+            Self::Binary => "binary file",
+            Self::GraphQl => "graphql, Ctrl+G to switch editor",
+        }
+    }
+}
+
+impl Tab for BodyMode {
+    fn as_int(&self) -> u8 {
+        *self as u8
+    }
+
+    fn to_enum(&self, num: u8) -> Self {
+        match num {
+            0 => Self::Raw,
+            1 => Self::Form,
+            2 => Self::Multipart,
+            3 => Self::Binary,
+            4 => Self::GraphQl,
+            _ => Self::Raw,
+        }
+    }
+
+    fn next(&self) -> Self {
+        self.to_enum((self.as_int() + 1) % 5)
+    }
+
+    fn previous(&self) -> Self {
+        self.to_enum((self.as_int() + 4) % 5)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Syntax {
+    #[default]
+    Json = 0,
+    Xml = 1,
+    Text = 2,
+    GraphQl = 3,
+}
+
+impl Syntax {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Xml => "xml",
+            Self::Text => "text",
+            Self::GraphQl => "graphql",
+        }
+    }
+
+    /// What the file's named for an external editor
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Xml => "xml",
+            Self::Text => "txt",
+            Self::GraphQl => "graphql",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Xml => "application/xml",
+            Self::Text => "text/plain",
+            Self::GraphQl => "application/graphql",
+        }
+    }
+}
+
+impl Tab for Syntax {
+    fn as_int(&self) -> u8 {
+        *self as u8
+    }
+
+    fn to_enum(&self, num: u8) -> Self {
+        match num {
+            0 => Self::Json,
+            1 => Self::Xml,
+            2 => Self::Text,
+            3 => Self::GraphQl,
+            _ => Self::Json,
+        }
+    }
+
+    fn next(&self) -> Self {
+        self.to_enum((self.as_int() + 1) % 4)
+    }
+
+    fn previous(&self) -> Self {
+        self.to_enum((self.as_int() + 3) % 4)
+    }
+}
+
+pub struct Redirect {
+    pub status: u16,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Default)]
 pub struct Response {
-    pub json: String,
-    pub status: u32,
+    pub status: u16,
+    pub version: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    /// The body as received
+    pub bytes: Bytes,
+    /// Bytes received for the body, before decompression
+    pub body_size: usize,
+    /// Content-Encoding the body was decompressed from
+    pub encoding: Option<String>,
+    /// Server certificate of HTTPS responses, from the connection they came
+    /// over
+    pub certificates: Option<Result<Vec<Certificate>, String>>,
+    /// Charset the body was decoded from when it isn't UTF-8
+    pub charset: Option<&'static str>,
+    pub redirects: Vec<Redirect>,
+    pub attempts: u32,
+    /// The 401 answered by sending again with new credentials
+    pub challenged: Option<Attempt>,
+    /// Indented copy of a JSON body
+    pub pretty: Option<String>,
+    /// Dump shown instead of binary bodies
+    pub hex: Option<String>,
+    pub image: Option<Preview>,
+    pub cookies: Vec<Cookie>,
+    pub timing: Timing,
+    /// The body was cut short by stopping the request
+    pub stopped: bool,
+}
+
+impl Response {
+    pub fn reason(&self) -> &'static str {
+        reqwest::StatusCode::from_u16(self.status)
+            .ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or_default()
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_json(&self) -> bool {
+        self.header("content-type").is_some_and(|content_type| {
+            let mime = content_type.split(';').next().unwrap_or_default().trim();
+            mime == "application/json" || mime.ends_with("+json")
+        })
+    }
+
+    pub fn is_event_stream(&self) -> bool {
+        self.header("content-type").is_some_and(|content_type| {
+            let mime = content_type.split(';').next().unwrap_or_default().trim();
+            mime.eq_ignore_ascii_case("text/event-stream")
+        })
+    }
+
+    /// Size of the status line and headers as sent over HTTP/1.1
+    pub fn header_size(&self) -> usize {
+        let status_line = format!("{} {} {}\r\n", self.version, self.status, self.reason());
+        let headers: usize = self
+            .headers
+            .iter()
+            .map(|(name, value)| name.len() + value.len() + 4)
+            .sum();
+        status_line.len() + headers + 2
+    }
+
+    fn parse_cookies(&self) -> Vec<Cookie> {
+        self.headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("set-cookie"))
+            .filter_map(|(_, value)| Cookie::parse(value))
+            .collect()
+    }
+
+    /// Binary content types, or untyped bodies that aren't valid UTF-8
+    pub fn is_binary(&self) -> bool {
+        let content_type = self.header("content-type").unwrap_or_default();
+        let mime = content_type.split(';').next().unwrap_or_default().trim();
+        let binary_mime = ["image/", "audio/", "video/", "font/"]
+            .iter()
+            .any(|prefix| mime.starts_with(prefix))
+            && mime != "image/svg+xml"
+            || matches!(
+                mime,
+                "application/octet-stream"
+                    | "application/pdf"
+                    | "application/zip"
+                    | "application/gzip"
+                    | "application/wasm"
+            );
+        // Text in a legacy charset still decodes, see `charset`
+        let text_mime = mime.starts_with("text/")
+            || ["json", "xml", "javascript", "html"]
+                .iter()
+                .any(|kind| mime.contains(kind));
+        let has_charset = content_type.to_ascii_lowercase().contains("charset=");
+
+        binary_mime || !text_mime && !has_charset && std::str::from_utf8(&self.bytes).is_err()
+    }
+
+    /// Falls back to the raw body when it isn't JSON
+    pub fn body_text(&self, raw: bool) -> &str {
+        match (&self.hex, &self.pretty) {
+            (Some(hex), _) => hex,
+            (None, Some(pretty)) if !raw => pretty,
+            _ => &self.body,
+        }
+    }
+}
+
+pub struct Preview {
+    /// Tells images apart once they're on screen, new for every decode
+    pub id: u64,
+    /// Downscaled copy, the terminal can't show more detail anyway
+    pub image: RgbaImage,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Preview {
+    const MAX_SIZE: u32 = 512;
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let image = image::load_from_memory(bytes).ok()?;
+        let (width, height) = (image.width(), image.height());
+        Some(Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            image: image.thumbnail(Self::MAX_SIZE, Self::MAX_SIZE).to_rgba8(),
+            width,
+            height,
+        })
+    }
+}
+
+/// Waits for `stop` to be notified, forever without one
+pub async fn notified(stop: Option<&Notify>) {
+    match stop {
+        Some(stop) => stop.notified().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Undoes a Content-Encoding, codings are listed in the order they were applied
+fn decompress(encoding: &str, bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut bytes = bytes.to_vec();
+    for coding in encoding.split(',').map(str::trim).rev() {
+        let mut decoded = Vec::new();
+        match coding {
+            "gzip" | "x-gzip" => {
+                GzDecoder::new(bytes.as_slice()).read_to_end(&mut decoded)?;
+            }
+            // Servers disagree on whether deflate means zlib or raw deflate
+            "deflate" => {
+                if ZlibDecoder::new(bytes.as_slice())
+                    .read_to_end(&mut decoded)
+                    .is_err()
+                {
+                    decoded.clear();
+                    DeflateDecoder::new(bytes.as_slice()).read_to_end(&mut decoded)?;
+                }
+            }
+            "br" => {
+                brotli::Decompressor::new(bytes.as_slice(), 4096).read_to_end(&mut decoded)?;
+            }
+            "identity" | "" => continue,
+            other => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    format!("unsupported content encoding `{other}`"),
+                ))
+            }
+        }
+        bytes = decoded;
+    }
+    Ok(bytes)
+}
+
+/// Picks the charset from a byte order mark, then the Content-Type, then a
+/// `<meta charset>` or `<?xml encoding>` near the start, and finally guesses
+/// Windows-1252 (the web's stand-in for ISO-8859-1) for anything not UTF-8
+fn charset(bytes: &[u8], content_type: Option<&str>) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+
+    let declared = content_type.and_then(|content_type| {
+        content_type
+            .split(';')
+            .filter_map(|param| param.trim().split_once('='))
+            .find(|(name, _)| name.eq_ignore_ascii_case("charset"))
+            .and_then(|(_, charset)| Encoding::for_label(charset.trim_matches('"').as_bytes()))
+    });
+    if let Some(encoding) = declared.or_else(|| sniff_charset(bytes)) {
+        return encoding;
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(_) => UTF_8,
+        Err(_) => WINDOWS_1252,
+    }
+}
+
+fn sniff_charset(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]).to_ascii_lowercase();
+    ["charset=", "encoding="].iter().find_map(|key| {
+        let start = head.find(key)? + key.len();
+        let label = head[start..]
+            .trim_start_matches(['"', '\''])
+            .split(|c: char| !(c.is_ascii_alphanumeric() || "-_.:".contains(c)))
+            .next()?;
+        Encoding::for_label(label.as_bytes())
+    })
+}
+
+#[derive(Debug)]
+pub enum RequestError {
+    InvalidUri,
+    InvalidMethod(String),
+    InvalidHeaders(String),
+    InvalidOptions(String),
+    InvalidBody(String),
+    InvalidAuth(String),
+    InvalidCertificate(String),
+    UnknownVariable(String),
+    Timeout(Duration),
+    Http(reqwest::Error),
+    WebSocket(String),
+    Grpc(String),
+    /// Connecting or talking over the connection failed, which may not
+    /// happen again, unlike requests that can't be sent at all
+    Connection(String),
+    TooManyRedirects(usize),
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidUri => write!(f, "invalid uri"),
+            Self::InvalidMethod(method) => write!(f, "invalid method `{method}`"),
+            Self::InvalidHeaders(reason) => write!(f, "invalid headers: {reason}"),
+            Self::InvalidOptions(reason) => write!(f, "invalid options: {reason}"),
+            Self::InvalidBody(reason) => write!(f, "invalid body: {reason}"),
+            Self::InvalidAuth(reason) => write!(f, "invalid auth: {reason}"),
+            Self::InvalidCertificate(reason) => write!(f, "invalid client certificate: {reason}"),
+            Self::UnknownVariable(name) => write!(f, "unknown variable `{{{{{name}}}}}`"),
+            Self::Timeout(timeout) => {
+                write!(f, "request timed out after {:.1}s", timeout.as_secs_f64())
+            }
+            Self::Http(err) => write!(f, "{err}"),
+            Self::WebSocket(reason) => write!(f, "websocket: {reason}"),
+            Self::Grpc(reason) => write!(f, "grpc: {reason}"),
+            Self::Connection(reason) => write!(f, "connection: {reason}"),
+            Self::TooManyRedirects(max) => write!(f, "too many redirects (max {max})"),
+        }
+    }
+}
+
+impl From<reqwest::Error> for RequestError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Http(err)
+    }
+}
+
+pub enum Message {
+    ResponseReceived(Box<Result<Response, RequestError>>),
+    /// Status and headers of a response whose body is still arriving
+    Started(Box<Response>),
+    Chunk(Bytes),
+    /// Outcome of writing the response body to disk
+    Saved(Result<String, String>),
+    /// What was read from another tool's file at a URL
+    Imported(String, Box<Result<Imported, String>>),
+    /// Progress or the outcome of a request sent from the tab with this id
+    Tab(usize, Box<Message>),
+    /// Schema introspected from a GraphQL endpoint
+    Introspected(String, Box<Result<Schema, String>>),
+}
+
+pub struct Match {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Default)]
+pub struct Search {
+    pub query: String,
+    /// The query is being typed in
+    pub editing: bool,
+    pub matches: Vec<Match>,
+    pub current: usize,
+}
+
+#[derive(Default)]
+pub struct Filter {
+    pub expr: String,
+    pub editing: bool,
+    /// Pretty printed matches, or why the expression couldn't be applied
+    pub result: Option<Result<String, String>>,
+}
+
+/// Matches of a JSONPath, or of a jq style path like `.items[0].name`
+pub fn query_json(expr: &str, value: &Value) -> Result<Vec<Value>, String> {
+    let expr = expr.trim();
+    let expr = if expr.starts_with('.') {
+        format!("${expr}")
+    } else {
+        expr.to_string()
+    };
+    let path = JsonPath::parse(&expr).map_err(|err| err.to_string())?;
+    Ok(path.query(value).all().into_iter().cloned().collect())
+}
+
+/// The values the enabled captures match in the response, `None` without any
+pub fn captured(
+    captures: &[KeyValueRow],
+    response: &Response,
+) -> Option<Result<Variables, String>> {
+    let captures: Vec<_> = captures
+        .iter()
+        .filter(|row| row.enabled && !row.key.is_empty())
+        .collect();
+    if captures.is_empty() {
+        return None;
+    }
+
+    let captured = serde_json::from_str::<Value>(&response.body)
+        .map_err(|_| "can't capture, response is not JSON".to_string())
+        .and_then(|body| {
+            captures
+                .iter()
+                .map(|row| {
+                    let value = query_json(&row.value, &body)
+                        .map_err(|err| format!("can't capture {}: {err}", row.key))?
+                        .into_iter()
+                        .next()
+                        .ok_or_else(|| format!("`{}` matched nothing", row.value))?;
+                    let value = match value {
+                        Value::String(value) => value,
+                        value => value.to_string(),
+                    };
+                    Ok((row.key.clone(), value))
+                })
+                .collect::<Result<Variables, String>>()
+        });
+    Some(captured)
+}
+
+impl Filter {
+    fn apply(&mut self, body: Option<&str>) {
+        if self.expr.trim().is_empty() {
+            self.result = None;
+            return;
+        }
+
+        let value = body
+            .and_then(|body| serde_json::from_str::<Value>(body).ok())
+            .ok_or_else(|| "response is not JSON".to_string());
+        let nodes = value.and_then(|value| {
+            let nodes = query_json(&self.expr, &value)?;
+            let value = match nodes.len() {
+                1 => nodes.into_iter().next().unwrap_or_default(),
+                _ => Value::Array(nodes),
+            };
+            serde_json::to_string_pretty(&value).map_err(|err| err.to_string())
+        });
+        self.result = Some(nodes);
+    }
+
+    pub fn output(&self) -> Option<&str> {
+        match &self.result {
+            Some(Ok(output)) => Some(output),
+            _ => None,
+        }
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        match &self.result {
+            Some(Err(err)) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// What the export prompt writes out
+pub enum Export {
+    /// A collection, as a Postman collection or a `.http` file
+    Collection(String),
+    /// Requests sent since the app started, as a HAR
+    History,
+}
+
+impl Export {
+    pub fn title(&self) -> String {
+        match self {
+            Self::Collection(name) => format!("{name} to Postman collection or .http"),
+            Self::History => "this session's requests to HAR".to_string(),
+        }
+    }
+}
+
+/// The parts of `State` each request tab keeps for itself
+#[derive(Default)]
+pub struct TabState {
+    response: Option<Result<Response, RequestError>>,
+    resp_scroll: u16,
+    resp_hscroll: u16,
+    search: Option<Search>,
+    filter: Option<Filter>,
+    pinned: Option<String>,
+    diff: Option<String>,
+    pending: Option<Instant>,
+    streamed: Vec<u8>,
+    stream_dirty: bool,
+    socket: Option<UnboundedSender<Outgoing>>,
+    stop: Option<Arc<Notify>>,
+    sent: Option<SavedRequest>,
+    saved_path: Option<String>,
+}
+
+/// A request tab, holding the editors and state of every tab but the open
+/// one, which are in use in the app
+pub struct RequestTab<'a> {
+    pub id: usize,
+    pub editors: Editors<'a>,
+    pub state: TabState,
+}
+
+impl<'a> RequestTab<'a> {
+    pub fn new(id: usize) -> Self {
+        Self {
+            id,
+            editors: Editors::new(),
+            state: TabState::default(),
+        }
+    }
+}
+
+/// A notice shown for a while over the corner of the screen
+pub struct Toast {
+    pub message: Result<String, String>,
+    shown: Instant,
+}
+
+impl Toast {
+    fn expired(&self) -> bool {
+        let duration = match self.message {
+            Ok(_) => TOAST_DURATION,
+            Err(_) => TOAST_DURATION * 2,
+        };
+        self.shown.elapsed() >= duration
+    }
+}
+
+/// Empty for panes that aren't shown
+#[derive(Clone, Copy, Default)]
+pub struct Panes {
+    pub request_tabs: Rect,
+    pub sidebar: Rect,
+    pub method: Rect,
+    pub uri: Rect,
+    pub payload_tabs: Rect,
+    pub payload: Rect,
+    pub response_tabs: Rect,
+    pub response: Rect,
+    /// The request and response columns together
+    pub columns: Rect,
+    /// First column of the response pane
+    pub divider: u16,
 }
 
 pub struct State<'a> {
     pub payload_titles: Vec<&'a str>,
-    pub req_tab_index: usize,
-    pub main_index: usize,
+    pub payload_tab: PayloadTab,
+    pub response_titles: Vec<&'a str>,
+    pub response_tab: ResponseTab,
+    /// Where focus goes back to when leaving the response
+    pub request_mode: InputMode,
     pub input_mode: InputMode,
+    pub response: Option<Result<Response, RequestError>>,
+    pub resp_scroll: u16,
+    pub resp_area: Rect,
+    /// Where the panes were last drawn, for mouse clicks
+    pub panes: Panes,
+    /// Images go on screen after drawing rather than through the buffer
+    pub graphics: bool,
+    /// Where the image preview was last drawn, unless something covers it
+    pub image_area: Option<Rect>,
+    /// Percent of the width for the request pane, see `Config::split`
+    pub split: u16,
+    /// The divider between request and response is being dragged
+    pub dragging_split: bool,
+    pub zoom: Option<Zoom>,
+    pub resp_hscroll: u16,
+    /// Soft wrap long response lines instead of scrolling sideways
+    pub wrap: bool,
+    pub show_redirects: bool,
+    /// Show the body as received instead of pretty printed
+    pub raw_body: bool,
+    pub search: Option<Search>,
+    pub filter: Option<Filter>,
+    /// Body of the response pinned for diffing
+    pub pinned: Option<String>,
+    /// Diff of the current body against the pinned one, while diffing
+    pub diff: Option<String>,
+    /// Path typed in for saving the response body
+    pub save_path: Option<String>,
+    /// Suggestions for the URI, header or GraphQL query being typed
+    pub completion: Option<Completion>,
+    /// GraphQL schemas by endpoint, `None` while introspection is under way
+    /// or after it failed
+    pub schemas: HashMap<String, Option<Schema>>,
+    /// Asking what to do with this many unsaved requests before quitting
+    pub quit_prompt: Option<usize>,
+    /// Results of actions and errors, oldest first, see `notify`
+    pub toasts: VecDeque<Toast>,
+    /// When the in-flight request was sent
+    pub pending: Option<Instant>,
+    pub spinner_index: usize,
+    /// Body received so far while the request is pending
+    pub streamed: Vec<u8>,
+    stream_dirty: bool,
+    /// Sends over the WebSocket of the pending request, while it's open
+    pub socket: Option<UnboundedSender<Outgoing>>,
+    /// Stops reading the body of the pending request
+    pub stop: Option<Arc<Notify>>,
+    pub history: History,
+    /// The history pane, while open
+    pub history_view: Option<HistoryView>,
+    /// Editor contents of the in-flight request, recorded once it completes
+    pub sent: Option<SavedRequest>,
+    pub workspace: String,
+    /// The workspace switcher, while open
+    pub workspace_view: Option<WorkspaceView>,
+    pub collections: Collections,
+    /// `collection/name` typed in for saving the current request
+    pub save_as: Option<String>,
+    /// Where the editors were last saved to or loaded from
+    pub saved_path: Option<String>,
+    /// Whether the collections and history column is shown
+    pub sidebar: bool,
+    pub sidebar_titles: Vec<&'a str>,
+    pub sidebar_tab: SidebarTab,
+    pub collection_selected: usize,
+    /// Counted from the newest entry
+    pub sidebar_history: usize,
+    /// Saved request being moved and the folder typed in for it
+    pub move_to: Option<(String, String)>,
+    /// Autosaved editors left over from last time, until restored or declined
+    pub restore: Option<Draft>,
+    pub environments: Environments,
+    pub environment_selected: usize,
+    /// Name of the environment being edited and its variables
+    pub environment_editor: Option<(String, KeyValueEditor<'a>)>,
+    /// Name typed in for a new environment
+    pub new_environment: Option<String>,
+    /// Environment whose `.env` file is being typed in, and the path
+    pub env_file_prompt: Option<(String, String)>,
+    /// Scroll offset of the resolved request preview, while open
+    pub preview: Option<u16>,
+    /// A curl command being pasted in to fill the editors from
+    pub curl_import: Option<Editor<'a>>,
+    /// The request as code, while the overlay showing it is open
+    pub snippets: Option<Snippets>,
+    /// File typed in to import collections from
+    pub import_path: Option<String>,
+    /// What's being exported and the file typed in for it
+    pub export_path: Option<(Export, String)>,
+    /// The command palette, while open
+    pub palette: Option<Palette>,
+    pub tabs: Vec<RequestTab<'a>>,
+    pub active_tab: usize,
+    /// Given to the next new tab
+    next_tab_id: usize,
+    /// Modal editing state, when turned on in the config
+    pub vim: Option<Vim>,
 }
 
 impl<'a> State<'a> {
-    pub fn new() -> Self {
+    pub fn new(config: &Config) -> Self {
+        let workspace = workspaces::current();
         Self {
-            payload_titles: vec!["Headers", "Body"],
-            req_tab_index: 0,
-            main_index: 0,
+            payload_titles: vec![
+                "Params",
+                "Headers",
+                "Body",
+                "Auth",
+                "Captures",
+                "Assertions",
+                "Options",
+            ],
+            payload_tab: PayloadTab::Params,
+            response_titles: vec!["Body", "Headers", "Cookies", "Timing", "Certificates"],
+            response_tab: ResponseTab::Body,
+            request_mode: InputMode::Normal,
             input_mode: InputMode::UriEditing,
+            response: None,
+            resp_scroll: 0,
+            resp_area: Rect::default(),
+            panes: Panes::default(),
+            graphics: false,
+            image_area: None,
+            split: config.split,
+            dragging_split: false,
+            zoom: None,
+            resp_hscroll: 0,
+            wrap: false,
+            show_redirects: false,
+            raw_body: false,
+            search: None,
+            filter: None,
+            pinned: None,
+            diff: None,
+            save_path: None,
+            toasts: VecDeque::new(),
+            completion: None,
+            schemas: HashMap::new(),
+            quit_prompt: None,
+            pending: None,
+            spinner_index: 0,
+            streamed: Vec::new(),
+            stream_dirty: false,
+            socket: None,
+            stop: None,
+            history: History::load(config.history_size),
+            history_view: None,
+            sent: None,
+            workspace_view: None,
+            collections: Collections::load(&workspace),
+            save_as: None,
+            saved_path: None,
+            sidebar: false,
+            sidebar_titles: vec!["Collections", "History", "Environments"],
+            sidebar_tab: SidebarTab::Collections,
+            collection_selected: 0,
+            sidebar_history: 0,
+            move_to: None,
+            restore: None,
+            environments: Environments::load(&workspace),
+            environment_selected: 0,
+            environment_editor: None,
+            new_environment: None,
+            env_file_prompt: None,
+            preview: None,
+            curl_import: None,
+            snippets: None,
+            import_path: None,
+            export_path: None,
+            palette: None,
+            tabs: vec![RequestTab::new(0)],
+            active_tab: 0,
+            next_tab_id: 1,
+            vim: config.vim.then(Vim::default),
+            workspace,
         }
     }
 
     pub fn next_payload(&mut self) {
-        self.req_tab_index = (self.req_tab_index + 1) % self.payload_titles.len();
+        self.payload_tab = self.payload_tab.next();
     }
 
-    pub fn previous_payload(&mut self) {
-        if self.req_tab_index > 0 {
-            self.req_tab_index -= 1;
-        } else {
-            self.req_tab_index = self.payload_titles.len() - 1;
+    pub fn tick(&mut self) {
+        self.spinner_index = self.spinner_index.wrapping_add(1);
+        self.toasts.retain(|toast| !toast.expired());
+    }
+
+    /// Shows `message` as a toast, the same one again only keeps it up longer
+    pub fn notify(&mut self, message: Result<String, String>) {
+        self.toasts.retain(|toast| toast.message != message);
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.pop_front();
         }
+        self.toasts.push_back(Toast {
+            message,
+            shown: Instant::now(),
+        });
     }
-}
 
-pub struct Editor<'a> {
-    pub title: &'a str,
-    pub text_area: TextArea<'a>,
-}
+    pub fn handle_message(&mut self, message: Message) {
+        match message {
+            Message::Saved(result) => self.notify(result),
+            Message::Tab(id, message) if id == self.tabs[self.active_tab].id => {
+                self.handle_message(*message)
+            }
+            // Handled with that tab's state swapped in, dropped if it's closed
+            Message::Tab(id, message) => {
+                if let Some(index) = self.tabs.iter().position(|tab| tab.id == id) {
+                    self.swap_tab_state(index);
+                    self.handle_message(*message);
+                    self.swap_tab_state(index);
+                }
+            }
+            Message::Imported(source, imported) => self.import(&source, *imported),
+            Message::Introspected(endpoint, schema) => match *schema {
+                Ok(schema) => {
+                    let types = schema.types.len();
+                    self.notify(Ok(format!("{types} types in the schema of {endpoint}")));
+                    self.schemas.insert(endpoint, Some(schema));
+                }
+                Err(err) => self.notify(Err(format!("can't introspect {endpoint}: {err}"))),
+            },
+            Message::Started(head) => {
+                self.response = Some(Ok(*head));
+                self.resp_scroll = 0;
+                self.streamed.clear();
+            }
+            Message::Chunk(chunk) => {
+                self.streamed.extend_from_slice(&chunk);
+                self.stream_dirty = true;
+            }
+            Message::ResponseReceived(response) => {
+                let elapsed = self.pending.take().unwrap_or_else(Instant::now).elapsed();
+                let (mut captures, mut assertions) = (Vec::new(), Vec::new());
+                if let Some(request) = self.sent.take() {
+                    captures = request.captures.clone();
+                    assertions = request.assertions.clone();
+                    let entry = Entry::new(request, &response, elapsed.as_millis() as u64);
+                    if let Err(err) = self.history.record(entry) {
+                        self.notify(Err(err));
+                    }
+                }
+                match response.as_ref() {
+                    Ok(response) => {
+                        if let Some(captured) = self.capture(&captures, response) {
+                            self.notify(captured);
+                        }
+                        if let Some(checked) = self.check(&assertions, response) {
+                            self.notify(checked);
+                        }
+                    }
+                    Err(err) => self.notify(Err(format!("request failed: {err}"))),
+                }
+                self.response = Some(*response);
+                self.resp_scroll = 0;
+                self.streamed = Vec::new();
+                self.stop = None;
+                if self.diff.is_some() {
+                    self.diff = self.compute_diff();
+                }
+                self.refresh_filter();
+                // The log or events end where they were followed to
+                let streamed = matches!(&self.response, Some(Ok(resp)) if resp.is_event_stream());
+                if self.socket.take().is_some() || streamed {
+                    self.scroll_to_bottom();
+                }
+            }
+        }
+    }
 
-impl<'a> Editor<'a> {
-    pub fn default(title: &'a str) -> Self {
-        let mut text_area = TextArea::default();
-        text_area.set_style(Style::default().bg(Color::Black).fg(Color::White));
+    /// Exchanges the request state in use with what tab `index` holds
+    pub fn swap_tab_state(&mut self, index: usize) {
+        let tab = &mut self.tabs[index].state;
+        std::mem::swap(&mut self.response, &mut tab.response);
+        std::mem::swap(&mut self.resp_scroll, &mut tab.resp_scroll);
+        std::mem::swap(&mut self.resp_hscroll, &mut tab.resp_hscroll);
+        std::mem::swap(&mut self.search, &mut tab.search);
+        std::mem::swap(&mut self.filter, &mut tab.filter);
+        std::mem::swap(&mut self.pinned, &mut tab.pinned);
+        std::mem::swap(&mut self.diff, &mut tab.diff);
+        std::mem::swap(&mut self.pending, &mut tab.pending);
+        std::mem::swap(&mut self.streamed, &mut tab.streamed);
+        std::mem::swap(&mut self.stream_dirty, &mut tab.stream_dirty);
+        std::mem::swap(&mut self.socket, &mut tab.socket);
+        std::mem::swap(&mut self.stop, &mut tab.stop);
+        std::mem::swap(&mut self.sent, &mut tab.sent);
+        std::mem::swap(&mut self.saved_path, &mut tab.saved_path);
+    }
 
-        Self { title, text_area }
+    /// Inserts a tab after the open one, returning where
+    pub fn add_tab(&mut self) -> usize {
+        let index = self.active_tab + 1;
+        self.tabs.insert(index, RequestTab::new(self.next_tab_id));
+        self.next_tab_id += 1;
+        index
     }
 
-    pub fn text(&self) -> String {
-        self.text_area.lines().join("\n")
+    /// The editors of a tab and where its request is saved, `editors` are
+    /// the ones in use
+    pub fn tab_request<'b>(
+        &'b self,
+        index: usize,
+        editors: &'b Editors<'a>,
+    ) -> (&'b Editors<'a>, Option<&'b str>) {
+        match index == self.active_tab {
+            true => (editors, self.saved_path.as_deref()),
+            false => {
+                let tab = &self.tabs[index];
+                (&tab.editors, tab.state.saved_path.as_deref())
+            }
+        }
     }
 
-    pub fn validate_uri(&self) -> bool {
-        let url_pattern = r#"^(https?|ftp):\/\/[^\s/$.?#].[^\s]*$"#;
-        let re = Regex::new(url_pattern).unwrap();
+    /// Tabs whose request differs from where it's saved in the collections,
+    /// or from a new request when it was never saved. `editors` are the ones
+    /// in use
+    pub fn unsaved_tabs(&self, editors: &Editors) -> Vec<usize> {
+        let json = |request: &SavedRequest| serde_json::to_string(request).unwrap_or_default();
+        let empty = json(&Editors::new().snapshot());
+        (0..self.tabs.len())
+            .filter(|&index| {
+                let (editors, saved_path) = self.tab_request(index, editors);
+                let saved = match saved_path {
+                    Some(path) => self.collections.find(path).map(|item| json(&item.request)),
+                    None => Some(empty.clone()),
+                };
+                Some(json(&editors.snapshot())) != saved
+            })
+            .collect()
+    }
 
-        !self.text().trim().is_empty() && re.is_match(self.text().as_str())
+    /// Name of the saved request, or the method and URI without the scheme,
+    /// and whether it's waiting on a response. `editors` are the ones in use
+    pub fn tab_title(&self, index: usize, editors: &Editors) -> (String, bool) {
+        let (editors, saved_path) = self.tab_request(index, editors);
+        let pending = match index == self.active_tab {
+            true => self.pending.is_some(),
+            false => self.tabs[index].state.pending.is_some(),
+        };
+        let uri = editors.uri.text();
+        let uri = uri.split_once("://").map_or(uri.as_str(), |(_, rest)| rest);
+        let method = editors.method.text();
+        let title = match saved_path {
+            Some(path) => path.rsplit('/').next().unwrap_or(path).to_string(),
+            None if uri.is_empty() => "new request".to_string(),
+            // Left empty it's worked out at send time
+            None if method.trim().is_empty() => uri.to_string(),
+            None => format!("{} {uri}", method.trim()),
+        };
+        let title = match title.chars().count() > TAB_TITLE_WIDTH {
+            true => title.chars().take(TAB_TITLE_WIDTH - 3).collect::<String>() + "...",
+            false => title,
+        };
+        (title, pending)
     }
 
-    pub fn validate_json(&self) -> bool {
-        let parsed_json: Result<Value, serde_json::Error> =
-            serde_json::from_str(self.text().as_str());
-        parsed_json.is_ok()
+    /// Sets the active environment's variables to what the enabled captures
+    /// match in the response, `None` without any
+    fn capture(
+        &mut self,
+        captures: &[KeyValueRow],
+        response: &Response,
+    ) -> Option<Result<String, String>> {
+        let captured = captured(captures, response)?;
+        Some(captured.and_then(|values| self.environments.capture(values)))
     }
-}
 
-pub struct Request {
-    pub headers: Option<String>,
-    pub body: Option<String>,
-    pub uri: String,
-    pub method: String,
-}
+    /// How the response did against the assertions of the request sent,
+    /// `None` without any
+    fn check(
+        &self,
+        assertions: &[KeyValueRow],
+        response: &Response,
+    ) -> Option<Result<String, String>> {
+        let count = assertions
+            .iter()
+            .filter(|row| row.enabled && !row.key.is_empty())
+            .count();
+        if count == 0 {
+            return None;
+        }
+        let failures = assertions::check(assertions, response, &self.environments.variables());
+        Some(match failures.as_slice() {
+            [] => Ok(format!("{count} of {count} assertions passed")),
+            [failure] => Err(format!("assertion failed: {failure}")),
+            [failure, rest @ ..] => Err(format!(
+                "{} of {count} assertions failed: {failure}, ...",
+                rest.len() + 1
+            )),
+        })
+    }
 
-impl Request {
-    pub async fn fetch() {}
+    pub fn start_save_as(&mut self) {
+        let path = self
+            .saved_path
+            .clone()
+            .unwrap_or_else(|| format!("{DEFAULT_COLLECTION}/"));
+        self.save_as = Some(path);
+    }
+
+    /// Focuses the sidebar when showing it
+    pub fn toggle_sidebar(&mut self) {
+        self.sidebar = !self.sidebar;
+        self.zoom = None;
+        self.input_mode = match (self.sidebar, self.input_mode) {
+            (true, _) => InputMode::Sidebar,
+            (false, InputMode::Sidebar) => InputMode::Normal,
+            (false, mode) => mode,
+        };
+    }
+
+    /// Moves the divider between request and response to `split` percent,
+    /// kept in `SPLIT_RANGE`
+    pub fn resize(&mut self, split: i32) {
+        let (min, max) = (*SPLIT_RANGE.start() as i32, *SPLIT_RANGE.end() as i32);
+        self.split = split.clamp(min, max) as u16;
+    }
+
+    /// Whether a prompt or overlay has the keys, the panes underneath
+    /// shouldn't react to the mouse then
+    pub fn modal(&self) -> bool {
+        self.restore.is_some()
+            || self.quit_prompt.is_some()
+            || self.search.as_ref().is_some_and(|search| search.editing)
+            || self.filter.as_ref().is_some_and(|filter| filter.editing)
+            || self.save_path.is_some()
+            || self.save_as.is_some()
+            || self.history_view.is_some()
+            || self.move_to.is_some()
+            || self.import_path.is_some()
+            || self.export_path.is_some()
+            || self.workspace_view.is_some()
+            || self.new_environment.is_some()
+            || self.env_file_prompt.is_some()
+            || self.environment_editor.is_some()
+            || self.preview.is_some()
+            || self.curl_import.is_some()
+            || self.snippets.is_some()
+            || self.palette.is_some()
+    }
+
+    /// Suggests URLs sent before, the latest first, then the saved ones
+    pub fn complete_uri(&mut self, typed: &str) {
+        let sent = self
+            .history
+            .entries
+            .iter()
+            .rev()
+            .map(|entry| entry.request.uri.clone());
+        let saved = self
+            .collections
+            .list
+            .iter()
+            .filter_map(|collection| self.collections.requests(&collection.name))
+            .flatten()
+            .map(|(_, item)| item.request.uri.clone());
+        self.completion = Completion::new(Target::Uri, typed, sent.chain(saved));
+    }
+
+    /// Zooms the body editor while it's being edited, the response otherwise
+    pub fn toggle_zoom(&mut self) {
+        self.zoom = match (self.zoom, self.input_mode) {
+            (Some(_), _) => None,
+            (None, InputMode::PayloadEditing) => Some(Zoom::Payload),
+            (None, _) => Some(Zoom::Response),
+        };
+    }
+
+    pub fn app_mode(&self) -> AppMode {
+        match self.input_mode {
+            InputMode::Response => AppMode::Response,
+            _ => AppMode::Request,
+        }
+    }
+
+    /// Focuses the response, or back where the request half last had focus
+    pub fn switch_half(&mut self) {
+        self.zoom = None;
+        self.input_mode = match self.app_mode() {
+            AppMode::Request => {
+                self.request_mode = self.input_mode;
+                InputMode::Response
+            }
+            AppMode::Response => match self.request_mode {
+                InputMode::Sidebar if !self.sidebar => InputMode::Normal,
+                mode => mode,
+            },
+        };
+    }
+
+    /// Skips the sidebar while it's hidden, and leaves a zoomed pane as the
+    /// next one isn't on screen
+    pub fn cycle_input_mode(&mut self, forward: bool) {
+        self.zoom = None;
+        loop {
+            self.input_mode = match forward {
+                true => self.input_mode.next(),
+                false => self.input_mode.previous(),
+            };
+            if self.sidebar || self.input_mode != InputMode::Sidebar {
+                break;
+            }
+        }
+    }
+
+    pub fn move_sidebar(&mut self, delta: i32) {
+        let (selected, len) = match self.sidebar_tab {
+            SidebarTab::Collections => (
+                &mut self.collection_selected,
+                self.collections.nodes().len(),
+            ),
+            SidebarTab::History => (&mut self.sidebar_history, self.history.entries.len()),
+            SidebarTab::Environments => {
+                (&mut self.environment_selected, self.environments.list.len())
+            }
+        };
+        let last = len.saturating_sub(1) as i32;
+        *selected = (*selected as i32 + delta).clamp(0, last) as usize;
+    }
+
+    pub fn toggle_workspaces(&mut self) {
+        self.workspace_view = match self.workspace_view {
+            Some(_) => None,
+            None => Some(WorkspaceView::new(&self.workspace)),
+        };
+    }
+
+    pub fn move_workspace(&mut self, delta: i32) {
+        if let Some(view) = &mut self.workspace_view {
+            let last = view.matches().len().saturating_sub(1) as i32;
+            view.selected = (view.selected as i32 + delta).clamp(0, last) as usize;
+        }
+    }
+
+    /// Opens the workspace's collections, creating it if it's new
+    pub fn switch_workspace(&mut self, name: String) {
+        if let Err(err) = workspaces::set_current(&name) {
+            self.notify(Err(err));
+            return;
+        }
+        self.collections = Collections::load(&name);
+        self.collection_selected = 0;
+        self.environments = Environments::load(&name);
+        self.environment_selected = 0;
+        self.saved_path = None;
+        self.notify(Ok(format!("switched to workspace {name}")));
+        self.workspace = name;
+    }
+
+    /// Saves another tool's requests into the workspace, variables are added
+    /// to environments of the same name
+    pub fn import(&mut self, source: &str, imported: Result<Imported, String>) {
+        let imported = match imported {
+            Ok(imported) => imported,
+            Err(err) => {
+                self.notify(Err(err));
+                return;
+            }
+        };
+        let mut failed = None;
+        let requests = imported.requests.len();
+        for (path, request) in imported.requests {
+            if let Err(err) = self.collections.save(&path, request) {
+                failed.get_or_insert(err);
+            }
+        }
+        let environments = imported.environments.len();
+        for (name, variables) in imported.environments {
+            let (mut merged, secrets) = self
+                .environments
+                .list
+                .iter()
+                .find(|env| env.name == name)
+                .map(|env| (env.variables.clone(), env.secrets.clone()))
+                .unwrap_or_default();
+            merged.extend(variables);
+            if let Err(err) = self.environments.save(&name, merged, secrets) {
+                failed.get_or_insert(err);
+            }
+        }
+
+        let count = |n: usize, what: &str| match n {
+            1 => format!("1 {what}"),
+            n => format!("{n} {what}s"),
+        };
+        let mut notice = format!("imported {}", count(requests, "request"));
+        if environments > 0 {
+            notice.push_str(&format!(" and {}", count(environments, "environment")));
+        }
+        self.notify(match failed {
+            Some(err) => Err(err),
+            None => Ok(format!("{notice} from {source}")),
+        });
+    }
+
+    pub fn export(&mut self, export: Export, path: &str) {
+        let path = path.trim();
+        let result = match export {
+            Export::Collection(collection) => self.export_collection(&collection, path),
+            Export::History => self.export_history(),
+        };
+        let result = result.and_then(|(what, text)| {
+            std::fs::write(path, text)
+                .map_err(|err| format!("can't export to {path}: {err}"))
+                .map(|()| format!("exported {what} to {path}"))
+        });
+        self.notify(result);
+    }
+
+    /// A collection as a `.http` file when `path` is named like one, as a
+    /// Postman collection otherwise, along with the variables of the
+    /// environment of the same name that aren't secret
+    fn export_collection(&self, collection: &str, path: &str) -> Result<(String, String), String> {
+        let Some(collection) = self.collections.list.iter().find(|c| c.name == collection) else {
+            return Err(format!("no collection `{collection}`"));
+        };
+        let variables = self
+            .environments
+            .list
+            .iter()
+            .find(|env| env.name == collection.name)
+            .map(|env| {
+                let mut variables = env.variables.clone();
+                variables.retain(|key, _| !env.secrets.contains(key));
+                variables
+            })
+            .unwrap_or_default();
+        let text = match http_file::is_http_file(path) {
+            true => http_file::export(collection, &variables),
+            false => serde_json::to_string_pretty(&postman::export(collection, &variables))
+                .map_err(|err| err.to_string())?,
+        };
+        Ok((collection.name.clone(), text))
+    }
+
+    /// Filled in with the active environment's variables, but not its secrets
+    fn export_history(&self) -> Result<(String, String), String> {
+        let entries = self.history.session();
+        if entries.is_empty() {
+            return Err("nothing has been sent since starting".to_string());
+        }
+        let mut variables = self.environments.variables();
+        if let Some(env) = self.environments.active() {
+            variables.retain(|key, _| !env.secrets.contains(key));
+        }
+        let count = match entries.len() {
+            1 => "1 request".to_string(),
+            n => format!("{n} requests"),
+        };
+        let json = serde_json::to_string_pretty(&har::export(entries, &variables))
+            .map_err(|err| err.to_string())?;
+        Ok((count, json))
+    }
+
+    pub fn edit_environment(&mut self, name: &str) {
+        let environment = self.environments.list.iter().find(|env| env.name == name);
+        let rows: Vec<_> = environment
+            .into_iter()
+            .flat_map(|env| {
+                env.variables.iter().map(|(key, value)| KeyValueRow {
+                    key: key.clone(),
+                    value: value.clone(),
+                    secret: env.secrets.contains(key),
+                    ..KeyValueRow::new()
+                })
+            })
+            .collect();
+        let mut editor = KeyValueEditor::default("variables");
+        editor.set_rows(&rows);
+        self.environment_editor = Some((name.to_string(), editor));
+    }
+
+    /// Disabled rows are dropped, environment files only hold what's used
+    pub fn close_environment_editor(&mut self) {
+        if let Some((name, editor)) = self.environment_editor.take() {
+            let rows = editor
+                .rows
+                .iter()
+                .filter(|row| row.enabled && !row.key.is_empty());
+            let secrets = rows
+                .clone()
+                .filter(|row| row.secret)
+                .map(|row| row.key.clone())
+                .collect();
+            let variables = editor.pairs().into_iter().collect();
+            let saved = self.environments.save(&name, variables, secrets);
+            self.notify(saved);
+        }
+    }
+
+    /// Goes through the environments and then none
+    pub fn cycle_environment(&mut self, forward: bool) {
+        let names: Vec<_> = self.environments.list.iter().map(|env| &env.name).collect();
+        if names.is_empty() {
+            self.notify(Err("no environments, add one in the sidebar".to_string()));
+            return;
+        }
+        let current = self
+            .environments
+            .active
+            .as_ref()
+            .and_then(|active| names.iter().position(|name| *name == active));
+        let next = match (current, forward) {
+            (None, true) => Some(0),
+            (None, false) => Some(names.len() - 1),
+            (Some(index), true) => Some(index + 1).filter(|&index| index < names.len()),
+            (Some(index), false) => index.checked_sub(1),
+        };
+        let name = next.map(|index| names[index].clone());
+        let activated = self.environments.activate(name);
+        self.notify(activated);
+    }
+
+    pub fn toggle_history(&mut self) {
+        self.history_view = match self.history_view {
+            Some(_) => None,
+            None if self.history.entries.is_empty() => {
+                self.notify(Err("no requests sent yet".to_string()));
+                None
+            }
+            None => Some(HistoryView::default()),
+        };
+    }
+
+    pub fn move_history(&mut self, delta: i32) {
+        let Some(view) = &self.history_view else {
+            return;
+        };
+        let last = view.entries(&self.history).len().saturating_sub(1) as i32;
+        if let Some(view) = &mut self.history_view {
+            view.selected = (view.selected as i32 + delta).clamp(0, last) as usize;
+        }
+    }
+
+    pub fn history_entry(&self) -> Option<&Entry> {
+        let view = self.history_view.as_ref()?;
+        view.entries(&self.history).get(view.selected).copied()
+    }
+
+    /// Shows the streamed body, batched since decoding is linear in its size.
+    /// A WebSocket log or server-sent events keep following the end when
+    /// scrolled to the bottom
+    pub fn flush_stream(&mut self) {
+        if !std::mem::take(&mut self.stream_dirty) || self.pending.is_none() {
+            return;
+        }
+
+        let Some(Ok(resp)) = &self.response else {
+            return;
+        };
+        let following = (self.socket.is_some() || resp.is_event_stream())
+            && self.resp_scroll as usize + self.resp_page() as usize >= self.resp_lines();
+        if let Some(Ok(resp)) = &mut self.response {
+            resp.body_size = self.streamed.len();
+            // Compressed bodies are only readable once complete
+            if resp.header("content-encoding").is_none() {
+                resp.body = String::from_utf8_lossy(&self.streamed).into_owned();
+                if resp.is_event_stream() {
+                    resp.pretty = Some(sse::render(&sse::parse(&resp.body)));
+                }
+            }
+        }
+        if following {
+            self.scroll_to_bottom();
+        }
+    }
+
+    /// Hands a message to the open WebSocket
+    pub fn send_socket(&mut self, message: Outgoing) {
+        match &self.socket {
+            Some(socket) => {
+                let _ = socket.send(message);
+            }
+            None => self.notify(Err("no WebSocket is open".to_string())),
+        }
+    }
+
+    pub fn next_response_tab(&mut self) {
+        self.response_tab = self.response_tab.next();
+        self.resp_scroll = 0;
+        self.refresh_search();
+    }
+
+    pub fn previous_response_tab(&mut self) {
+        self.response_tab = self.response_tab.previous();
+        self.resp_scroll = 0;
+        self.refresh_search();
+    }
+
+    pub fn toggle_redirects(&mut self) {
+        self.show_redirects = !self.show_redirects;
+        self.refresh_search();
+    }
+
+    pub fn toggle_raw_body(&mut self) {
+        self.raw_body = !self.raw_body;
+        self.refresh_search();
+    }
+
+    pub fn response_view(&self) -> ResponseView<'_> {
+        ResponseView::new(self.response.as_ref().and_then(|resp| resp.as_ref().ok()))
+            .scroll(self.resp_scroll)
+            .hscroll(self.resp_hscroll)
+            .wrap(self.wrap)
+            .show_redirects(self.show_redirects)
+            .raw(self.raw_body)
+            .tab(self.response_tab)
+            .search(self.search.as_ref())
+            .filtered(self.filter.as_ref().and_then(Filter::output))
+            .diff(self.diff.as_deref())
+            .graphics(self.graphics)
+    }
+
+    /// The filter output, the diff against the pinned response, or the body
+    fn shown_body<'b>(&'b self, resp: &'b Response) -> &'b str {
+        self.filter
+            .as_ref()
+            .and_then(Filter::output)
+            .or(self.diff.as_deref())
+            .unwrap_or_else(|| resp.body_text(self.raw_body))
+    }
+
+    pub fn pin_response(&mut self) {
+        let Some(Ok(resp)) = &self.response else {
+            return;
+        };
+        self.pinned = Some(resp.body_text(false).to_string());
+        self.notify(Ok("pinned response, d to diff".to_string()));
+    }
+
+    pub fn toggle_diff(&mut self) {
+        self.diff = match self.diff {
+            Some(_) => None,
+            None if self.pinned.is_none() => {
+                self.notify(Err("pin a response with P first".to_string()));
+                return;
+            }
+            None => self.compute_diff(),
+        };
+        self.resp_scroll = 0;
+        self.refresh_search();
+    }
+
+    fn compute_diff(&self) -> Option<String> {
+        let (Some(pinned), Some(Ok(resp))) = (&self.pinned, &self.response) else {
+            return None;
+        };
+        Some(diff::diff(pinned, resp.body_text(false)))
+    }
+
+    pub fn clipboard_text(&self, headers: bool) -> Option<String> {
+        let Some(Ok(resp)) = &self.response else {
+            return None;
+        };
+
+        if headers {
+            let mut text = format!("{} {} {}", resp.version, resp.status, resp.reason());
+            for (name, value) in &resp.headers {
+                text.push_str(&format!("\n{name}: {value}"));
+            }
+            return Some(text);
+        }
+
+        let body = self.shown_body(resp);
+        Some(body.to_string())
+    }
+
+    /// Suggests a file name with an extension matching the Content-Type
+    pub fn start_save(&mut self) {
+        let Some(Ok(resp)) = &self.response else {
+            return;
+        };
+        let extension = extension_for(resp.header("content-type").unwrap_or_default());
+        self.save_path = Some(format!("response.{extension}"));
+    }
+
+    pub fn start_filter(&mut self) {
+        self.filter.get_or_insert_with(Filter::default).editing = true;
+    }
+
+    pub fn refresh_filter(&mut self) {
+        if let Some(filter) = &mut self.filter {
+            let body = self.response.as_ref().and_then(|resp| resp.as_ref().ok());
+            filter.apply(body.map(|resp| resp.body.as_str()));
+        }
+        self.resp_scroll = 0;
+        self.refresh_search();
+    }
+
+    pub fn start_search(&mut self) {
+        self.search = Some(Search {
+            editing: true,
+            ..Search::default()
+        });
+    }
+
+    /// Recomputes the matches and jumps to the first one on screen or below
+    pub fn refresh_search(&mut self) {
+        let Some(search) = &self.search else {
+            return;
+        };
+
+        let text = self.response_view().search(None).text();
+        let matches: Vec<_> = text
+            .lines
+            .iter()
+            .enumerate()
+            .flat_map(|(line, spans)| {
+                let plain: String = spans
+                    .spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect();
+                highlight::find(&plain, &search.query)
+                    .into_iter()
+                    .map(move |(start, end)| Match { line, start, end })
+            })
+            .collect();
+
+        let scroll = self.resp_scroll as usize;
+        let current = matches
+            .iter()
+            .position(|found| found.line >= scroll)
+            .unwrap_or_default();
+        if let Some(search) = &mut self.search {
+            search.matches = matches;
+            search.current = current;
+        }
+        self.scroll_to_match();
+    }
+
+    pub fn next_match(&mut self) {
+        if let Some(search) = &mut self.search {
+            if !search.matches.is_empty() {
+                search.current = (search.current + 1) % search.matches.len();
+            }
+        }
+        self.scroll_to_match();
+    }
+
+    pub fn previous_match(&mut self) {
+        if let Some(search) = &mut self.search {
+            if !search.matches.is_empty() {
+                let len = search.matches.len();
+                search.current = (search.current + len - 1) % len;
+            }
+        }
+        self.scroll_to_match();
+    }
+
+    /// Scrolls only when the current match is off screen
+    fn scroll_to_match(&mut self) {
+        let Some(line) = self
+            .search
+            .as_ref()
+            .and_then(|search| search.matches.get(search.current))
+            .map(|found| found.line)
+        else {
+            return;
+        };
+
+        let page = self.resp_page() as usize;
+        let scroll = self.resp_scroll as usize;
+        if line < scroll || line >= scroll + page {
+            self.resp_scroll = 0;
+            self.scroll_by(line.saturating_sub(page / 2) as i32);
+        }
+    }
+
+    /// Lines in the current response tab
+    pub fn resp_lines(&self) -> usize {
+        let Some(Ok(resp)) = &self.response else {
+            return 0;
+        };
+
+        // Wrapped lines take as many rows as they need at the current width
+        let width = self.resp_area.width.saturating_sub(2).max(1) as usize;
+        let rows = |line: &str| match self.wrap {
+            true => line.width().max(1).div_ceil(width),
+            false => 1,
+        };
+
+        match self.response_tab {
+            ResponseTab::Body if resp.image.is_some() && !self.raw_body && self.diff.is_none() => 0,
+            ResponseTab::Body => {
+                let redirects = match (resp.redirects.is_empty(), self.show_redirects) {
+                    (true, _) => 0,
+                    (false, true) => resp.redirects.len() + 1,
+                    (false, false) => 1,
+                };
+                let body = self.shown_body(resp);
+                redirects + body.lines().map(rows).sum::<usize>()
+            }
+            ResponseTab::Headers => resp
+                .headers
+                .iter()
+                .map(|(name, value)| rows(&format!("{name}: {value}")))
+                .sum(),
+            // Plus the column names
+            ResponseTab::Cookies if resp.cookies.is_empty() => 1,
+            ResponseTab::Cookies => resp.cookies.len() + 1,
+            ResponseTab::Timing => 0,
+            ResponseTab::Certificates => self.response_view().text().lines.len(),
+        }
+    }
+
+    pub fn toggle_wrap(&mut self) {
+        self.wrap = !self.wrap;
+        self.resp_hscroll = 0;
+        self.scroll_by(0);
+    }
+
+    /// Only scrolls sideways while lines aren't wrapped
+    pub fn scroll_horizontal(&mut self, delta: i32) {
+        if !self.wrap {
+            self.resp_hscroll = (self.resp_hscroll as i32 + delta).max(0) as u16;
+        }
+    }
+
+    /// Inner height of the response view, set while drawing
+    pub fn resp_page(&self) -> u16 {
+        self.resp_area.height.saturating_sub(2).max(1)
+    }
+
+    /// The view scrolls by up to `u16::MAX` lines, the ones past that can't
+    /// be reached
+    pub fn scroll_by(&mut self, delta: i32) {
+        let max = self
+            .resp_lines()
+            .saturating_sub(self.resp_page() as usize)
+            .min(u16::MAX as usize);
+        let scroll = (self.resp_scroll as i32 + delta).clamp(0, max as i32);
+        self.resp_scroll = scroll as u16;
+    }
+
+    pub fn scroll_down(&mut self) {
+        self.scroll_by(1);
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.scroll_by(-1);
+    }
+
+    pub fn page_down(&mut self) {
+        self.scroll_by(self.resp_page() as i32);
+    }
+
+    pub fn page_up(&mut self) {
+        self.scroll_by(-(self.resp_page() as i32));
+    }
+
+    pub fn scroll_to_top(&mut self) {
+        self.resp_scroll = 0;
+    }
+
+    pub fn scroll_to_bottom(&mut self) {
+        self.scroll_by(i32::MAX / 2);
+    }
+
+    pub fn previous_payload(&mut self) {
+        self.payload_tab = self.payload_tab.previous();
+    }
+}
+
+pub struct Editor<'a> {
+    pub title: &'a str,
+    pub text_area: TextArea<'a>,
+    pub syntax: Syntax,
+    /// Enter doesn't break the line and text given to it is joined into one,
+    /// it scrolls sideways instead
+    pub single_line: bool,
+}
+
+impl<'a> Editor<'a> {
+    pub fn default(title: &'a str) -> Self {
+        Self {
+            title,
+            text_area: TextArea::default(),
+            syntax: Syntax::Json,
+            single_line: false,
+        }
+    }
+
+    pub fn single_line(title: &'a str) -> Self {
+        Self {
+            single_line: true,
+            ..Self::default(title)
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.text_area.lines().join("\n")
+    }
+
+    /// `line:column` of the cursor, counted from 1
+    pub fn position(&self) -> String {
+        let (row, column) = self.text_area.cursor();
+        format!("{}:{}", row + 1, column + 1)
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        let style = self.text_area.style();
+        self.text_area = match self.single_line {
+            true => TextArea::new(vec![join_lines(text)]),
+            false => TextArea::new(text.lines().map(String::from).collect()),
+        };
+        self.text_area.set_style(style);
+    }
+
+    /// Puts back on one line what got split by an edit that breaks lines
+    /// other than Enter, e.g. vim's `o`
+    pub fn keep_single_line(&mut self) {
+        if self.single_line && self.text_area.lines().len() > 1 {
+            let (row, column) = self.text_area.cursor();
+            let before = join_lines(&self.text_area.lines()[..row].join("\n"));
+            self.set_text(&self.text());
+            let column = (before.chars().count() + column) as u16;
+            self.text_area.move_cursor(CursorMove::Jump(0, column));
+        }
+    }
+
+    /// Highlights the placeholders, in red unless all of them are defined
+    pub fn highlight_variables(&mut self, variables: &Variables) {
+        if self.text_area.search_pattern().is_none() {
+            let _ = self.text_area.set_search_pattern(r"\{\{[^{}]*\}\}");
+        }
+        let theme = theme::current();
+        let color = match environments::resolve(&self.text(), variables) {
+            Ok(_) => theme.success,
+            Err(_) => theme.error,
+        };
+        self.text_area.set_search_style(Style::default().fg(color));
+    }
+
+    /// Variables are only checked once filled in at send time
+    pub fn validate_uri(&self) -> bool {
+        let text = self.text();
+        text.contains("{{") || is_valid_uri(&text)
+    }
+
+    /// Any RFC 9110 token is accepted so nonstandard methods can be tested
+    pub fn validate_method(&self) -> bool {
+        let text = self.text();
+        let method = text.trim();
+        method.is_empty() || Method::from_bytes(method.as_bytes()).is_ok()
+    }
+
+    /// Why the text isn't JSON, the error says the line and column
+    pub fn json_error(&self) -> Option<serde_json::Error> {
+        serde_json::from_str::<Value>(self.text().as_str()).err()
+    }
+
+    pub fn validate_json(&self) -> bool {
+        self.json_error().is_none()
+    }
+
+    pub fn validate(&self) -> bool {
+        match self.syntax {
+            Syntax::Json => self.validate_json(),
+            Syntax::Xml | Syntax::Text | Syntax::GraphQl => true,
+        }
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyValueRow {
+    pub key: String,
+    pub value: String,
+    pub enabled: bool,
+    /// Masked when rendered
+    #[serde(skip)]
+    pub secret: bool,
+}
+
+impl KeyValueRow {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// The rows and cursor of a table from before an edit
+struct TableState {
+    rows: Vec<KeyValueRow>,
+    selected: usize,
+    column: usize,
+    cursor: usize,
+}
+
+pub struct KeyValueEditor<'a> {
+    pub title: &'a str,
+    pub rows: Vec<KeyValueRow>,
+    pub selected: usize,
+    /// 0 for the key column, 1 for the value column
+    pub column: usize,
+    /// Cursor position in chars inside the selected cell
+    pub cursor: usize,
+    pub key_validator: Option<fn(&str) -> bool>,
+    /// Rows can't be added, removed or renamed, only their values edited
+    pub fixed: bool,
+    undo: Vec<TableState>,
+    redo: Vec<TableState>,
+}
+
+impl<'a> KeyValueEditor<'a> {
+    pub fn default(title: &'a str) -> Self {
+        Self {
+            title,
+            rows: vec![KeyValueRow::new()],
+            selected: 0,
+            column: 0,
+            cursor: 0,
+            key_validator: None,
+            fixed: false,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// A fixed form of named fields, `(name, secret)`
+    pub fn fields(title: &'a str, fields: &[(&str, bool)]) -> Self {
+        let rows = fields
+            .iter()
+            .map(|(name, secret)| KeyValueRow {
+                key: name.to_string(),
+                secret: *secret,
+                ..KeyValueRow::new()
+            })
+            .collect();
+
+        Self {
+            rows,
+            column: 1,
+            fixed: true,
+            ..Self::default(title)
+        }
+    }
+
+    pub fn set_value(&mut self, key: &str, value: &str) {
+        if let Some(row) = self.rows.iter_mut().find(|row| row.key == key) {
+            row.value = value.to_string();
+        }
+    }
+
+    /// Value of the first row named `key`, empty if there is none
+    pub fn value(&self, key: &str) -> &str {
+        self.rows
+            .iter()
+            .find(|row| row.key == key)
+            .map_or("", |row| row.value.as_str())
+    }
+
+    /// Replaces the rows, keeping one to type into
+    pub fn set_rows(&mut self, rows: &[KeyValueRow]) {
+        self.rows = rows.to_vec();
+        if self.rows.is_empty() {
+            self.rows.push(KeyValueRow::new());
+        }
+        self.selected = 0;
+        self.cursor = 0;
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    fn state(&self) -> TableState {
+        TableState {
+            rows: self.rows.clone(),
+            selected: self.selected,
+            column: self.column,
+            cursor: self.cursor,
+        }
+    }
+
+    fn restore(&mut self, state: TableState) {
+        self.rows = state.rows;
+        self.selected = state.selected;
+        self.column = state.column;
+        self.cursor = state.cursor;
+    }
+
+    /// Takes back the last edit to the rows, false when there's none
+    pub fn undo(&mut self) -> bool {
+        let Some(state) = self.undo.pop() else {
+            return false;
+        };
+        self.redo.push(self.state());
+        self.restore(state);
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(state) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push(self.state());
+        self.restore(state);
+        true
+    }
+
+    /// Drops the blank rows left for typing into
+    pub fn filled_rows(&self) -> Vec<KeyValueRow> {
+        self.rows
+            .iter()
+            .filter(|row| !row.key.is_empty() || !row.value.is_empty())
+            .cloned()
+            .collect()
+    }
+
+    pub fn headers() -> Self {
+        Self {
+            key_validator: Some(|key| HeaderName::from_bytes(key.as_bytes()).is_ok()),
+            ..Self::default("headers")
+        }
+    }
+
+    pub fn validate_key(&self, key: &str) -> bool {
+        self.key_validator.is_none_or(|validator| validator(key))
+    }
+
+    pub fn validate(&self) -> bool {
+        self.rows
+            .iter()
+            .filter(|row| row.enabled && !row.key.is_empty())
+            .all(|row| self.validate_key(&row.key))
+    }
+
+    /// Enabled rows with a non-empty key
+    pub fn pairs(&self) -> Vec<(String, String)> {
+        self.rows
+            .iter()
+            .filter(|row| row.enabled && !row.key.is_empty())
+            .map(|row| (row.key.clone(), row.value.clone()))
+            .collect()
+    }
+
+    fn cell(&mut self) -> &mut String {
+        let row = &mut self.rows[self.selected];
+        if self.column == 0 {
+            &mut row.key
+        } else {
+            &mut row.value
+        }
+    }
+
+    fn byte_offset(cell: &str, cursor: usize) -> usize {
+        cell.char_indices()
+            .nth(cursor)
+            .map_or(cell.len(), |(offset, _)| offset)
+    }
+
+    fn select(&mut self, row: usize) {
+        self.selected = row;
+        self.cursor = self.cell().chars().count();
+    }
+
+    pub fn input(&mut self, key: KeyEvent) {
+        if self.fixed {
+            match (key.modifiers, key.code) {
+                (KeyModifiers::NONE, KeyCode::Enter | KeyCode::Tab)
+                    if self.selected + 1 < self.rows.len() =>
+                {
+                    self.select(self.selected + 1)
+                }
+                (KeyModifiers::SHIFT, KeyCode::BackTab) if self.selected > 0 => {
+                    self.select(self.selected - 1)
+                }
+                (KeyModifiers::NONE, KeyCode::Enter | KeyCode::Tab)
+                | (KeyModifiers::SHIFT, KeyCode::BackTab)
+                | (KeyModifiers::CONTROL, _) => {}
+                _ => self.edit(key),
+            }
+            return;
+        }
+
+        self.edit(key);
+    }
+
+    /// Replaces what's in the selected cell, as one edit
+    pub fn set_cell(&mut self, text: &str) {
+        let before = self.state();
+        *self.cell() = text.to_string();
+        self.cursor = text.chars().count();
+        self.record(before);
+    }
+
+    /// Inserts `text` at the cursor as one edit, it has to be a single line
+    pub fn insert_str(&mut self, text: &str) {
+        let before = self.state();
+        let cursor = self.cursor;
+        let cell = self.cell();
+        let offset = Self::byte_offset(cell, cursor);
+        cell.insert_str(offset, text);
+        self.cursor += text.chars().count();
+        self.record(before);
+    }
+
+    /// A `key: value` line per row, disabled ones commented out with `#`
+    pub fn to_text(&self) -> String {
+        self.rows
+            .iter()
+            .filter(|row| !row.key.is_empty() || !row.value.is_empty())
+            .map(|row| {
+                let comment = if row.enabled { "" } else { "# " };
+                format!("{comment}{}: {}", row.key, row.value)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Rows from `to_text`'s format as one edit, rows with the same key as a
+    /// secret one stay secret
+    pub fn set_text(&mut self, text: &str) {
+        let before = self.state();
+        let mut rows: Vec<KeyValueRow> = text
+            .lines()
+            .filter_map(|line| {
+                let (enabled, line) = match line.trim().strip_prefix('#') {
+                    Some(line) => (false, line),
+                    None => (true, line),
+                };
+                let (key, value) = line.split_once(':').unwrap_or((line, ""));
+                let key = key.trim();
+                (!key.is_empty()).then(|| KeyValueRow {
+                    key: key.to_string(),
+                    value: value.trim().to_string(),
+                    enabled,
+                    secret: before.rows.iter().any(|row| row.secret && row.key == key),
+                })
+            })
+            .collect();
+        if rows.is_empty() {
+            rows.push(KeyValueRow::new());
+        }
+        self.rows = rows;
+        self.selected = self.selected.min(self.rows.len() - 1);
+        self.column = 0;
+        self.cursor = 0;
+        self.record(before);
+    }
+
+    fn edit(&mut self, key: KeyEvent) {
+        let before = self.state();
+        self.apply(key);
+        self.record(before);
+    }
+
+    /// Remembers the rows from before an edit that changed them, for `undo`
+    fn record(&mut self, before: TableState) {
+        if self.rows != before.rows {
+            if self.undo.len() == UNDO_LIMIT {
+                self.undo.remove(0);
+            }
+            self.undo.push(before);
+            self.redo.clear();
+        }
+    }
+
+    fn apply(&mut self, key: KeyEvent) {
+        match (key.modifiers, key.code) {
+            (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char(c)) => {
+                let cursor = self.cursor;
+                let cell = self.cell();
+                let offset = Self::byte_offset(cell, cursor);
+                cell.insert(offset, c);
+                self.cursor += 1;
+            }
+            (KeyModifiers::NONE, KeyCode::Backspace) if self.cursor > 0 => {
+                let cursor = self.cursor - 1;
+                let cell = self.cell();
+                let offset = Self::byte_offset(cell, cursor);
+                cell.remove(offset);
+                self.cursor = cursor;
+            }
+            (KeyModifiers::NONE, KeyCode::Delete) => {
+                let cursor = self.cursor;
+                let cell = self.cell();
+                if cursor < cell.chars().count() {
+                    let offset = Self::byte_offset(cell, cursor);
+                    cell.remove(offset);
+                }
+            }
+            // Past the edge of a cell goes to the other column
+            (KeyModifiers::NONE, KeyCode::Left)
+                if self.cursor == 0 && self.column == 1 && !self.fixed =>
+            {
+                self.column = 0;
+                self.cursor = self.cell().chars().count();
+            }
+            (KeyModifiers::NONE, KeyCode::Right)
+                if self.cursor == self.cell().chars().count() && self.column == 0 =>
+            {
+                self.column = 1;
+                self.cursor = 0;
+            }
+            (KeyModifiers::NONE, KeyCode::Left) => self.cursor = self.cursor.saturating_sub(1),
+            (KeyModifiers::NONE, KeyCode::Right) => {
+                self.cursor = (self.cursor + 1).min(self.cell().chars().count())
+            }
+            (KeyModifiers::NONE, KeyCode::Home) => self.cursor = 0,
+            (KeyModifiers::NONE, KeyCode::End) => self.cursor = self.cell().chars().count(),
+            (KeyModifiers::NONE, KeyCode::Up) if self.selected > 0 => {
+                self.select(self.selected - 1)
+            }
+            (KeyModifiers::NONE, KeyCode::Down) if self.selected + 1 < self.rows.len() => {
+                self.select(self.selected + 1)
+            }
+            (KeyModifiers::NONE, KeyCode::Tab) | (KeyModifiers::SHIFT, KeyCode::BackTab) => {
+                self.column = 1 - self.column;
+                self.cursor = self.cell().chars().count();
+            }
+            (KeyModifiers::NONE, KeyCode::Enter) | (KeyModifiers::CONTROL, KeyCode::Char('n')) => {
+                self.rows.insert(self.selected + 1, KeyValueRow::new());
+                self.selected += 1;
+                self.column = 0;
+                self.cursor = 0;
+            }
+            (KeyModifiers::CONTROL, KeyCode::Char('d')) => {
+                self.rows.remove(self.selected);
+                if self.rows.is_empty() {
+                    self.rows.push(KeyValueRow::new());
+                }
+                self.select(self.selected.min(self.rows.len() - 1));
+            }
+            (KeyModifiers::CONTROL, KeyCode::Char('e')) => {
+                let row = &mut self.rows[self.selected];
+                row.enabled = !row.enabled;
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn is_valid_uri(uri: &str) -> bool {
+    let url_pattern = r#"^(https?|wss?|grpcs?|ftp):\/\/[^\s/$.?#].[^\s]*$"#;
+    let re = Regex::new(url_pattern).unwrap();
+
+    !uri.trim().is_empty() && re.is_match(uri)
+}
+
+pub struct Editors<'a> {
+    pub method: Editor<'a>,
+    pub uri: Editor<'a>,
+    pub params: KeyValueEditor<'a>,
+    pub headers: KeyValueEditor<'a>,
+    pub body_mode: BodyMode,
+    pub body: Editor<'a>,
+    pub form: KeyValueEditor<'a>,
+    pub multipart: KeyValueEditor<'a>,
+    pub file: Editor<'a>,
+    pub query: Editor<'a>,
+    pub variables: Editor<'a>,
+    /// Whether the variables editor has focus in GraphQL mode
+    pub variables_focused: bool,
+    pub auth: AuthEditor<'a>,
+    pub captures: KeyValueEditor<'a>,
+    pub assertions: KeyValueEditor<'a>,
+    pub options: Editor<'a>,
+}
+
+/// What the editors hold, auth without its secret fields
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedRequest {
+    pub method: String,
+    pub uri: String,
+    pub params: Vec<KeyValueRow>,
+    pub headers: Vec<KeyValueRow>,
+    pub body_mode: BodyMode,
+    pub syntax: Syntax,
+    pub body: String,
+    pub form: Vec<KeyValueRow>,
+    pub multipart: Vec<KeyValueRow>,
+    pub file: String,
+    pub query: String,
+    pub variables: String,
+    pub options: String,
+    pub auth_mode: AuthMode,
+    pub auth: Vec<KeyValueRow>,
+    /// Variable names and the JSONPath of the response value they're set to
+    pub captures: Vec<KeyValueRow>,
+    /// What to check the response against, see `assertions::check`
+    pub assertions: Vec<KeyValueRow>,
+}
+
+impl<'a> Editors<'a> {
+    pub fn snapshot(&self) -> SavedRequest {
+        SavedRequest {
+            method: self.method.text(),
+            uri: self.uri.text(),
+            params: self.params.filled_rows(),
+            headers: self.headers.filled_rows(),
+            body_mode: self.body_mode,
+            syntax: self.body.syntax,
+            body: self.body.text(),
+            form: self.form.filled_rows(),
+            multipart: self.multipart.filled_rows(),
+            file: self.file.text(),
+            query: self.query.text(),
+            variables: self.variables.text(),
+            options: self.options.text(),
+            auth_mode: self.auth.mode,
+            auth: self.auth.snapshot(),
+            captures: self.captures.filled_rows(),
+            assertions: self.assertions.filled_rows(),
+        }
+    }
+
+    pub fn load(&mut self, saved: &SavedRequest) {
+        self.method.set_text(&saved.method);
+        self.uri.set_text(&saved.uri);
+        self.params.set_rows(&saved.params);
+        self.headers.set_rows(&saved.headers);
+        self.body_mode = saved.body_mode;
+        self.body.syntax = saved.syntax;
+        self.body.set_text(&saved.body);
+        self.form.set_rows(&saved.form);
+        self.multipart.set_rows(&saved.multipart);
+        self.file.set_text(&saved.file);
+        self.query.set_text(&saved.query);
+        self.variables.set_text(&saved.variables);
+        self.options.set_text(&saved.options);
+        self.auth.load(saved.auth_mode, &saved.auth);
+        self.captures.set_rows(&saved.captures);
+        self.assertions.set_rows(&saved.assertions);
+    }
+
+    pub fn graphql_payload(&self) -> Result<Option<String>, RequestError> {
+        let query = self.query.text();
+        if query.trim().is_empty() {
+            return Ok(None);
+        }
+
+        let variables = self.variables.text();
+        let variables: Value = if variables.trim().is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_str(&variables)
+                .map_err(|err| RequestError::InvalidBody(format!("variables: {err}")))?
+        };
+
+        let payload = serde_json::json!({ "query": query, "variables": variables });
+        Ok(Some(payload.to_string()))
+    }
+
+    /// Path typed in the binary body editor, with an optional curl style `@` prefix
+    pub fn file_path(&self) -> Option<PathBuf> {
+        let text = self.file.text();
+        let path = text.trim();
+        let path = path.strip_prefix('@').unwrap_or(path);
+        (!path.is_empty()).then(|| PathBuf::from(path))
+    }
+
+    pub fn new() -> Self {
+        Self {
+            method: Editor::single_line("method"),
+            uri: Editor::single_line("uri"),
+            params: KeyValueEditor::default("params"),
+            headers: KeyValueEditor::headers(),
+            body_mode: BodyMode::Raw,
+            body: Editor::default("body"),
+            form: KeyValueEditor::default("form"),
+            multipart: KeyValueEditor::default("multipart"),
+            file: Editor::single_line("file"),
+            query: Editor {
+                syntax: Syntax::GraphQl,
+                ..Editor::default("query")
+            },
+            variables: Editor::default("variables"),
+            variables_focused: false,
+            auth: AuthEditor::new(),
+            captures: KeyValueEditor::default("captures"),
+            assertions: KeyValueEditor::default("assertions"),
+            options: Editor::default("options"),
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RequestOptions {
+    /// Timeout in seconds, overriding the config default
+    pub timeout: Option<f64>,
+    pub follow_redirects: Option<bool>,
+    pub max_redirects: Option<usize>,
+    pub retries: Option<u32>,
+    /// Initial backoff in milliseconds, doubled after every attempt
+    pub retry_backoff: Option<u64>,
+    pub retry_on: Option<Vec<u16>>,
+    /// PEM certificate, or a PKCS#12 bundle when `client_key` is not set
+    pub client_cert: Option<PathBuf>,
+    /// PKCS#8 PEM private key for `client_cert`
+    pub client_key: Option<PathBuf>,
+    pub client_cert_password: Option<String>,
+    /// Ask for gzip, deflate or brotli bodies, on by default
+    pub compressed: Option<bool>,
+    /// For picking requests with `curl-rs test --tag`
+    pub tags: Option<Vec<String>>,
+    /// Schema of gRPC requests, server reflection is used without one
+    pub proto: Option<PathBuf>,
+    /// Where the imports of `proto` are looked for, besides next to it
+    pub proto_paths: Option<Vec<PathBuf>>,
+    /// Negotiated over TLS when not set, HTTP/1.1 for plain http
+    pub http_version: Option<HttpVersion>,
+    /// Connected to instead of the host of the uri, like curl's
+    /// `--unix-socket`
+    pub unix_socket: Option<PathBuf>,
+}
+
+/// The protocol a request is forced to use
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum HttpVersion {
+    #[serde(rename = "1.1")]
+    Http1,
+    /// With prior knowledge, so without upgrading from HTTP/1.1 first
+    #[serde(rename = "2")]
+    Http2,
+    /// Accepted so requests keep it, sending fails as QUIC isn't built in
+    #[serde(rename = "3")]
+    Http3,
+}
+
+impl HttpVersion {
+    /// The curl flag asking for it
+    pub fn flag(&self) -> &'static str {
+        match self {
+            Self::Http1 => "--http1.1",
+            Self::Http2 => "--http2-prior-knowledge",
+            Self::Http3 => "--http3-only",
+        }
+    }
+}
+
+impl RequestOptions {
+    pub fn parse(editor: &Editor) -> Result<Self, RequestError> {
+        let text = editor.text();
+        if text.trim().is_empty() {
+            return Ok(Self::default());
+        }
+
+        serde_json::from_str(&text).map_err(|err| RequestError::InvalidOptions(err.to_string()))
+    }
+}
+
+#[derive(Clone)]
+pub struct ClientCert {
+    pub cert: PathBuf,
+    pub key: Option<PathBuf>,
+    /// Only used for PKCS#12 bundles
+    pub password: String,
+}
+
+impl ClientCert {
+    pub fn identity(&self) -> Result<Identity, RequestError> {
+        let read = |path: &Path| {
+            std::fs::read(path).map_err(|err| {
+                RequestError::InvalidCertificate(format!("{}: {err}", path.display()))
+            })
+        };
+
+        let cert = read(&self.cert)?;
+        let identity = match &self.key {
+            Some(key) => Identity::from_pem(&cert, &read(key)?),
+            None => Identity::from_pkcs12(&cert, &self.password),
+        };
+        identity.map_err(RequestError::InvalidCertificate)
+    }
+}
+
+pub enum Body {
+    Raw(String),
+    Form(Vec<(String, String)>),
+    /// Values starting with `@` are paths of files to upload
+    Multipart(Vec<(String, String)>),
+    File(PathBuf),
+}
+
+/// `path` to send, with its length for the Content-Length
+async fn open(path: &Path) -> Result<Chunk, RequestError> {
+    let file_err = |err| RequestError::InvalidBody(format!("{}: {err}", path.display()));
+    let file = tokio::fs::File::open(path).await.map_err(file_err)?;
+    let length = file.metadata().await.map_err(file_err)?.len();
+    Ok(Chunk::File(file, length))
+}
+
+/// Names and file names in a Content-Disposition, which can't hold quotes
+/// or line breaks
+fn disposition(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+impl Body {
+    /// What's sent and the Content-Type it needs, files are opened but read
+    /// as they're sent
+    async fn encode(&self) -> Result<(Vec<Chunk>, Option<String>), RequestError> {
+        match self {
+            Self::Raw(body) => Ok((vec![Chunk::Bytes(body.clone().into_bytes())], None)),
+            Self::Form(pairs) => {
+                let form = url::form_urlencoded::Serializer::new(String::new())
+                    .extend_pairs(pairs)
+                    .finish();
+                let content_type = "application/x-www-form-urlencoded".to_string();
+                Ok((vec![Chunk::Bytes(form.into_bytes())], Some(content_type)))
+            }
+            Self::Multipart(parts) => Self::multipart(parts).await,
+            Self::File(path) => Ok((vec![open(path).await?], None)),
+        }
+    }
+
+    async fn multipart(
+        parts: &[(String, String)],
+    ) -> Result<(Vec<Chunk>, Option<String>), RequestError> {
+        let boundary = format!(
+            "{:016x}{:016x}",
+            rand::random::<u64>(),
+            rand::random::<u64>()
+        );
+        let mut chunks = Vec::new();
+        let mut head = Vec::new();
+        for (name, value) in parts {
+            head.extend_from_slice(
+                format!(
+                    "--{boundary}\r\nContent-Disposition: form-data; name=\"{}\"",
+                    disposition(name)
+                )
+                .as_bytes(),
+            );
+            match value.strip_prefix('@') {
+                Some(path) => {
+                    let path = Path::new(path);
+                    let file_name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    head.extend_from_slice(
+                        format!("; filename=\"{}\"\r\n\r\n", disposition(&file_name)).as_bytes(),
+                    );
+                    chunks.push(Chunk::Bytes(std::mem::take(&mut head)));
+                    chunks.push(open(path).await?);
+                }
+                None => {
+                    head.extend_from_slice(b"\r\n\r\n");
+                    head.extend_from_slice(value.as_bytes());
+                }
+            }
+            head.extend_from_slice(b"\r\n");
+        }
+        head.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+        chunks.push(Chunk::Bytes(head));
+        let content_type = format!("multipart/form-data; boundary={boundary}");
+        Ok((chunks, Some(content_type)))
+    }
+}
+
+/// The certificate a response's connection was made with, along the
+/// response until it's read
+struct PeerCertificates(Option<Result<Vec<Certificate>, String>>);
+
+pub struct Request {
+    /// Appended to the query string of the uri
+    pub params: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Body>,
+    /// Sent unless the headers already contain a Content-Type
+    pub content_type: Option<&'static str>,
+    pub auth: Auth,
+    pub uri: String,
+    pub method: String,
+    pub timeout: Duration,
+    pub follow_redirects: bool,
+    pub max_redirects: usize,
+    pub retry: RetryPolicy,
+    pub identity: Option<Identity>,
+    pub compressed: bool,
+    /// Receives the head and body chunks as they arrive
+    pub progress: Option<UnboundedSender<Message>>,
+    /// Ends the body early, keeping what arrived so far
+    pub stop: Option<Arc<Notify>>,
+    pub proto: Option<PathBuf>,
+    pub proto_paths: Vec<PathBuf>,
+    pub http_version: Option<HttpVersion>,
+    pub unix_socket: Option<PathBuf>,
+}
+
+impl Request {
+    pub fn new(editors: &Editors, config: &Config) -> Result<Self, RequestError> {
+        let non_empty = |editor: &Editor| {
+            let text = editor.text();
+            (!text.trim().is_empty()).then_some(text)
+        };
+
+        let options = RequestOptions::parse(&editors.options)?;
+        let timeout = match options.timeout {
+            Some(secs) => Duration::try_from_secs_f64(secs)
+                .map_err(|_| RequestError::InvalidOptions(format!("bad timeout `{secs}`")))?,
+            None => config.timeout,
+        };
+        if options.retries.is_some_and(|retries| retries > MAX_RETRIES) {
+            let reason = format!("retries has to be at most {MAX_RETRIES}");
+            return Err(RequestError::InvalidOptions(reason));
+        }
+        let backoff = options.retry_backoff.map(Duration::from_millis);
+        if backoff.is_some_and(|backoff| backoff > MAX_DELAY) {
+            let reason = format!(
+                "retry_backoff has to be at most {} milliseconds",
+                MAX_DELAY.as_millis()
+            );
+            return Err(RequestError::InvalidOptions(reason));
+        }
+
+        let client_cert = match options.client_cert {
+            Some(cert) => Some(ClientCert {
+                cert,
+                key: options.client_key,
+                password: options.client_cert_password.unwrap_or_default(),
+            }),
+            None => config.client_cert.clone(),
+        };
+
+        let body = match editors.body_mode {
+            BodyMode::Raw => non_empty(&editors.body).map(Body::Raw),
+            BodyMode::Form => {
+                let pairs = editors.form.pairs();
+                (!pairs.is_empty()).then_some(Body::Form(pairs))
+            }
+            BodyMode::Multipart => {
+                let parts = editors.multipart.pairs();
+                (!parts.is_empty()).then_some(Body::Multipart(parts))
+            }
+            BodyMode::Binary => editors.file_path().map(Body::File),
+            BodyMode::GraphQl => editors.graphql_payload()?.map(Body::Raw),
+        };
+        let method = editors.method.text().trim().to_string();
+        let method = if !method.is_empty() {
+            method
+        } else if body.is_some() {
+            // Same as curl, sending data implies POST
+            "POST".to_string()
+        } else {
+            "GET".to_string()
+        };
+
+        let content_type = match (&body, editors.body_mode) {
+            (Some(_), BodyMode::Raw) => Some(editors.body.syntax.content_type()),
+            (Some(_), BodyMode::GraphQl) => Some(Syntax::Json.content_type()),
+            _ => None,
+        };
+
+        let mut headers = editors.headers.pairs();
+        for (name, value) in &config.default_headers {
+            if !headers
+                .iter()
+                .any(|(key, _)| key.eq_ignore_ascii_case(name))
+            {
+                headers.push((name.clone(), value.clone()));
+            }
+        }
+
+        Ok(Self {
+            params: editors.params.pairs(),
+            headers,
+            body,
+            content_type,
+            auth: editors.auth.auth()?,
+            uri: editors.uri.text().trim().to_string(),
+            method,
+            timeout,
+            follow_redirects: options.follow_redirects.unwrap_or(config.follow_redirects),
+            max_redirects: options.max_redirects.unwrap_or(config.max_redirects),
+            retry: RetryPolicy {
+                retries: options.retries.unwrap_or(config.retry.retries),
+                backoff: backoff.unwrap_or(config.retry.backoff),
+                retry_on: options
+                    .retry_on
+                    .unwrap_or_else(|| config.retry.retry_on.clone()),
+            },
+            identity: client_cert.map(|cert| cert.identity()).transpose()?,
+            compressed: options.compressed.unwrap_or(true),
+            progress: None,
+            stop: None,
+            proto: options.proto,
+            proto_paths: options.proto_paths.unwrap_or_default(),
+            http_version: options.http_version,
+            unix_socket: options.unix_socket,
+        })
+    }
+
+    /// Fills in the `{{variables}}` of everything typed into the editors
+    pub fn resolve(&mut self, variables: &Variables) -> Result<(), RequestError> {
+        let resolve = |text: &mut String| -> Result<(), RequestError> {
+            *text = environments::resolve(text, variables)?;
+            Ok(())
+        };
+
+        resolve(&mut self.uri)?;
+        for (key, value) in self.params.iter_mut().chain(self.headers.iter_mut()) {
+            resolve(key)?;
+            resolve(value)?;
+        }
+        match &mut self.body {
+            Some(Body::Raw(text)) => resolve(text)?,
+            Some(Body::Form(pairs) | Body::Multipart(pairs)) => {
+                for (key, value) in pairs {
+                    resolve(key)?;
+                    resolve(value)?;
+                }
+            }
+            Some(Body::File(path)) => {
+                let mut text = path.to_string_lossy().into_owned();
+                resolve(&mut text)?;
+                *path = PathBuf::from(text);
+            }
+            None => {}
+        }
+        self.auth.values_mut().into_iter().try_for_each(resolve)
+    }
+
+    pub fn header_map(&self) -> Result<HeaderMap, RequestError> {
+        let mut map = HeaderMap::new();
+        for (key, value) in &self.headers {
+            let name = HeaderName::from_bytes(key.as_bytes())
+                .map_err(|_| RequestError::InvalidHeaders(format!("bad name `{key}`")))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| RequestError::InvalidHeaders(format!("bad value for `{key}`")))?;
+            map.append(name, value);
+        }
+
+        Ok(map)
+    }
+
+    fn map_error(&self, err: reqwest::Error) -> RequestError {
+        if err.is_timeout() {
+            RequestError::Timeout(self.timeout)
+        } else {
+            RequestError::Http(err)
+        }
+    }
+
+    pub async fn fetch(&self) -> Result<Response, RequestError> {
+        let mut attempt = 1;
+        loop {
+            let result = self.fetch_once().await;
+            let retryable = match &result {
+                Ok(resp) => !resp.stopped && self.retry.retry_on.contains(&resp.status),
+                Err(
+                    RequestError::Http(_) | RequestError::Timeout(_) | RequestError::Connection(_),
+                ) => true,
+                Err(_) => false,
+            };
+
+            if !retryable || attempt > self.retry.retries {
+                return result.map(|resp| Response {
+                    attempts: attempt,
+                    ..resp
+                });
+            }
+
+            tokio::time::sleep(self.retry.delay(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    /// Offers the allowed HTTP versions with ALPN, and the client
+    /// certificate if there's one
+    fn tls_connector(&self) -> Result<SslConnector, RequestError> {
+        let protocols: &[&str] = match self.http_version {
+            Some(HttpVersion::Http1) => &["http/1.1"],
+            Some(HttpVersion::Http2) => &["h2"],
+            // Refused in `send` before connecting
+            Some(HttpVersion::Http3) | None => &["h2", "http/1.1"],
+        };
+        tls::connector(protocols, self.identity.as_ref()).map_err(RequestError::InvalidCertificate)
+    }
+
+    /// Sends the request once on a connection of its own, with the timing of
+    /// opening it and of the response's head since `start`
+    async fn send(
+        &self,
+        method: Method,
+        url: Url,
+        headers: HeaderMap,
+        with_body: bool,
+        origin: &Url,
+        start: Instant,
+    ) -> Result<(reqwest::Response, Timing), RequestError> {
+        if self.http_version == Some(HttpVersion::Http3) {
+            let reason = "HTTP/3 isn't supported, QUIC isn't built in".to_string();
+            return Err(RequestError::InvalidOptions(reason));
+        }
+        // Built per send, signatures cover the final headers and body
+        let mut request = reqwest::Request::new(method, url);
+        *request.headers_mut() = headers;
+        let mut chunks = Vec::new();
+        if let Some(body) = self.body.as_ref().filter(|_| with_body) {
+            let content_type;
+            (chunks, content_type) = body.encode().await?;
+            if let Some(content_type) = content_type {
+                let value = HeaderValue::from_str(&content_type)
+                    .map_err(|err| RequestError::InvalidBody(err.to_string()))?;
+                request.headers_mut().insert(CONTENT_TYPE, value);
+            }
+            let length: u64 = chunks.iter().map(Chunk::len).sum();
+            request.headers_mut().insert(CONTENT_LENGTH, length.into());
+            // Only bodies in memory can be signed, files go unsigned
+            if let [Chunk::Bytes(bytes)] = chunks.as_slice() {
+                *request.body_mut() = Some(bytes.clone().into());
+            }
+        }
+        let streamed = chunks.iter().any(|chunk| matches!(chunk, Chunk::File(..)));
+        // Only hops to where the request was first sent, signatures name the
+        // host and session tokens aren't for others
+        if request.url().origin() == origin.origin() {
+            self.auth.sign(&mut request, streamed)?;
+        }
+
+        let url = request.url().clone();
+        let request =
+            net::hyper_request(request, net::stream(chunks)).map_err(RequestError::InvalidBody)?;
+        let tls = match url.scheme() {
+            "https" => Some(self.tls_connector()?),
+            _ => None,
+        };
+        // Prior knowledge, TLS connections negotiate it
+        let forced = self.http_version == Some(HttpVersion::Http2);
+        let tls_used = tls.is_some();
+        let proxy = match self.unix_socket {
+            Some(_) => None,
+            None => net::Proxy::from_env(&url).map_err(RequestError::Connection)?,
+        };
+        let mut connection = net::open(&url, tls, self.unix_socket.as_deref(), proxy.as_ref())
+            .await
+            .map_err(RequestError::Connection)?;
+        let mut timing = Timing::opened(&connection);
+        let certificates = connection.certificates.take().map(Ok);
+        if forced && tls_used && !connection.h2 {
+            let reason = format!(
+                "{} didn't agree to HTTP/2",
+                url.host_str().unwrap_or_default()
+            );
+            return Err(RequestError::Connection(reason));
+        }
+        let h2 = forced || connection.h2;
+        let mut resp = net::send(request, url, connection, h2)
+            .await
+            .map_err(RequestError::Connection)?;
+        resp.extensions_mut().insert(PeerCertificates(certificates));
+        timing.first_byte = start.elapsed();
+        Ok((resp, timing))
+    }
+
+    /// Sends the request and follows its redirects, with the timing of the
+    /// first send
+    async fn exchange(
+        &self,
+        mut method: Method,
+        mut url: Url,
+        mut headers: HeaderMap,
+        redirects: &mut Vec<Redirect>,
+        start: Instant,
+    ) -> Result<(reqwest::Response, Timing), RequestError> {
+        let origin = url.clone();
+        let mut first = None;
+        let mut with_body = true;
+        loop {
+            let (resp, timing) = self
+                .send(
+                    method.clone(),
+                    url.clone(),
+                    headers.clone(),
+                    with_body,
+                    &origin,
+                    start,
+                )
+                .await?;
+            let timing = *first.get_or_insert(timing);
+            let status = resp.status().as_u16();
+            let next = resp
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| url.join(location).ok());
+            let next = match next {
+                Some(next)
+                    if self.follow_redirects && matches!(status, 301 | 302 | 303 | 307 | 308) =>
+                {
+                    next
+                }
+                _ => return Ok((resp, timing)),
+            };
+            if redirects.len() >= self.max_redirects {
+                return Err(RequestError::TooManyRedirects(self.max_redirects));
+            }
+
+            // What browsers do, 307 and 308 resend as they were
+            if status == 303 && method != Method::HEAD
+                || matches!(status, 301 | 302) && method == Method::POST
+            {
+                method = Method::GET;
+                with_body = false;
+                for name in [
+                    CONTENT_TYPE,
+                    CONTENT_LENGTH,
+                    CONTENT_ENCODING,
+                    TRANSFER_ENCODING,
+                ] {
+                    headers.remove(name);
+                }
+            }
+            // Credentials stay with the host they were sent to
+            if next.host_str() != url.host_str()
+                || next.port_or_known_default() != url.port_or_known_default()
+            {
+                for name in [AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, WWW_AUTHENTICATE] {
+                    headers.remove(name);
+                }
+            }
+            redirects.push(Redirect {
+                status,
+                from: url.to_string(),
+                to: next.to_string(),
+            });
+            url = next;
+        }
+    }
+
+    async fn fetch_once(&self) -> Result<Response, RequestError> {
+        let mut url = Url::parse(&self.uri).map_err(|_| RequestError::InvalidUri)?;
+        if !self.params.is_empty() {
+            url.query_pairs_mut().extend_pairs(&self.params);
+        }
+        let method = Method::from_bytes(self.method.as_bytes())
+            .map_err(|_| RequestError::InvalidMethod(self.method.clone()))?;
+
+        let mut headers = self.header_map()?;
+        if let Some(content_type) = self.content_type {
+            if !headers.contains_key(CONTENT_TYPE) {
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+            }
+        }
+        if self.compressed && !headers.contains_key(ACCEPT_ENCODING) {
+            headers.insert(
+                ACCEPT_ENCODING,
+                HeaderValue::from_static("gzip, deflate, br"),
+            );
+        }
+        // Covering fetching a token, every send and the body. A login in the
+        // browser has its own timeout and isn't counted
+        let mut start = Instant::now();
+        let timed_out = |_| RequestError::Timeout(self.timeout);
+        let apply = self.auth.apply(&mut url, &mut headers, self.timeout);
+        if self.auth.logs_in() {
+            apply.await?;
+            start = Instant::now();
+        } else {
+            let deadline = tokio::time::Instant::from_std(start + self.timeout);
+            tokio::time::timeout_at(deadline, apply)
+                .await
+                .map_err(timed_out)??;
+        }
+        let deadline = tokio::time::Instant::from_std(start + self.timeout);
+        let mut redirects = Vec::new();
+        let exchange = self.exchange(
+            method.clone(),
+            url.clone(),
+            headers.clone(),
+            &mut redirects,
+            start,
+        );
+        let (mut resp, mut timing) = tokio::time::timeout_at(deadline, exchange)
+            .await
+            .map_err(timed_out)??;
+        let respond = self.auth.respond(&resp, &method, &url, self.timeout);
+        let challenged = match tokio::time::timeout_at(deadline, respond)
+            .await
+            .map_err(timed_out)??
+        {
+            Some(authorization) => {
+                let challenged = Attempt {
+                    status: resp.status().as_u16(),
+                    challenge: resp
+                        .headers()
+                        .get(WWW_AUTHENTICATE)
+                        .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned()),
+                    duration_ms: start.elapsed().as_millis() as u64,
+                };
+                headers.insert(AUTHORIZATION, authorization);
+                let exchange = self.exchange(method, url, headers, &mut redirects, start);
+                resp = tokio::time::timeout_at(deadline, exchange)
+                    .await
+                    .map_err(timed_out)??
+                    .0;
+                Some(challenged)
+            }
+            None => None,
+        };
+
+        let status = resp.status().as_u16();
+        let version = format!("{:?}", resp.version());
+        let headers: Vec<(String, String)> = resp
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).to_string(),
+                )
+            })
+            .collect();
+        let content_encoding = resp
+            .headers()
+            .get(CONTENT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_ascii_lowercase);
+        let certificates = resp
+            .extensions_mut()
+            .remove::<PeerCertificates>()
+            .and_then(|certificates| certificates.0);
+        if let Some(progress) = &self.progress {
+            let _ = progress.send(Message::Started(Box::new(Response {
+                status,
+                version: version.clone(),
+                headers: headers.clone(),
+                ..Response::default()
+            })));
+        }
+
+        let mut wire = Vec::new();
+        let mut stopped = false;
+        loop {
+            let chunk = tokio::select! {
+                chunk = resp.chunk() => chunk.map_err(|err| self.map_error(err))?,
+                () = notified(self.stop.as_deref()) => {
+                    stopped = true;
+                    break;
+                }
+                () = tokio::time::sleep_until(deadline) => {
+                    return Err(RequestError::Timeout(self.timeout));
+                }
+            };
+            let Some(chunk) = chunk else {
+                break;
+            };
+            wire.extend_from_slice(&chunk);
+            if let Some(progress) = &self.progress {
+                let _ = progress.send(Message::Chunk(chunk));
+            }
+        }
+        let wire = Bytes::from(wire);
+        let (bytes, encoding) = match content_encoding {
+            Some(encoding) => match decompress(&encoding, &wire) {
+                Ok(bytes) => (Bytes::from(bytes), Some(encoding)),
+                Err(_) => (wire.clone(), None),
+            },
+            None => (wire.clone(), None),
+        };
+        timing.total = start.elapsed();
+
+        let mut response = Response {
+            status,
+            version,
+            headers,
+            body_size: wire.len(),
+            bytes: bytes.clone(),
+            encoding,
+            redirects,
+            attempts: 1,
+            challenged,
+            timing,
+            certificates,
+            stopped,
+            ..Response::default()
+        };
+        let charset = charset(&bytes, response.header("content-type"));
+        response.body = charset.decode_with_bom_removal(&bytes).0.into_owned();
+        if charset != UTF_8 {
+            response.charset = Some(charset.name());
+        }
+        response.cookies = response.parse_cookies();
+        if response.is_binary() {
+            response.hex = Some(hex_dump(&bytes));
+            response.image = Preview::decode(&bytes);
+        } else if response.is_json() {
+            response.pretty = serde_json::from_str::<Value>(&response.body)
+                .ok()
+                .and_then(|value| serde_json::to_string_pretty(&value).ok());
+        } else if response.is_event_stream() {
+            response.pretty = Some(sse::render(&sse::parse(&response.body)));
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, UnixListener},
+        sync::mpsc::{self, UnboundedReceiver},
+    };
+
+    use super::*;
+
+    /// Answers a connection at a time with the next of `responses`, passing
+    /// on the requests as they came in. Returns the base url
+    async fn serve<R>(responses: Vec<R>) -> (String, UnboundedReceiver<String>)
+    where
+        R: AsRef<[u8]> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 4096];
+                loop {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request);
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length = head
+                            .lines()
+                            .find_map(|line| {
+                                let (name, value) = line.split_once(':')?;
+                                name.eq_ignore_ascii_case("content-length")
+                                    .then(|| value.trim().parse().unwrap())
+                            })
+                            .unwrap_or(0);
+                        if read == 0 || body.len() >= length {
+                            break;
+                        }
+                    }
+                }
+                let _ = sender.send(String::from_utf8_lossy(&request).into_owned());
+                let _ = stream.write_all(response.as_ref()).await;
+                let _ = stream.shutdown().await;
+            }
+        });
+        (base, receiver)
+    }
+
+    /// A response with `body` that closes the connection
+    fn ok(headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+    }
+
+    /// Fresh state, with history and collections under a directory of the
+    /// test run's
+    fn state() -> State<'static> {
+        let dir = std::env::temp_dir().join(format!("curl-rs-tests-{}", std::process::id()));
+        crate::history::set_data_dir(dir);
+        State::new(&Config::default())
+    }
+
+    /// The request the editors would send with `saved` loaded
+    fn request(saved: SavedRequest) -> Request {
+        let mut editors = Editors::new();
+        editors.load(&saved);
+        Request::new(&editors, &Config::default()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn fetches() {
+        let (base, mut requests) = serve(vec![ok("X-Test: yes\r\n", "hello")]).await;
+        let resp = request(SavedRequest {
+            uri: format!("{base}/path?query=1"),
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        assert_eq!(resp.status, 200);
+        assert_eq!(resp.body, "hello");
+        assert_eq!(resp.header("x-test"), Some("yes"));
+        let sent = requests.recv().await.unwrap();
+        assert!(sent.starts_with("GET /path?query=1 HTTP/1.1\r\n"));
+    }
+
+    #[test]
+    fn receives_response() {
+        let mut state = state();
+        state.pending = Some(Instant::now());
+        let resp = Response {
+            status: 204,
+            ..Response::default()
+        };
+        state.handle_message(Message::ResponseReceived(Box::new(Ok(resp))));
+        assert!(state.pending.is_none());
+        assert!(matches!(state.response, Some(Ok(ref resp)) if resp.status == 204));
+    }
+
+    #[tokio::test]
+    async fn times_out() {
+        // Accepts and never answers
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let _stream = listener.accept().await;
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+        let result = request(SavedRequest {
+            uri: uri.clone(),
+            options: r#"{"timeout": 0.2}"#.to_string(),
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await;
+        assert!(
+            matches!(result, Err(RequestError::Timeout(timeout)) if timeout.as_millis() == 200)
+        );
+
+        let mut editors = Editors::new();
+        editors.load(&SavedRequest {
+            uri,
+            options: r#"{"timeout": -1}"#.to_string(),
+            ..SavedRequest::default()
+        });
+        let result = Request::new(&editors, &Config::default());
+        assert!(matches!(result, Err(RequestError::InvalidOptions(_))));
+    }
+
+    #[tokio::test]
+    async fn follows_redirects() {
+        let see_other = "HTTP/1.1 303 See Other\r\nLocation: /next\r\nContent-Length: 0\r\n\r\n";
+        let (base, mut requests) = serve(vec![see_other.to_string(), ok("", "done")]).await;
+        let resp = request(SavedRequest {
+            uri: format!("{base}/start"),
+            body: "data".to_string(),
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        assert_eq!((resp.status, resp.body.as_str()), (200, "done"));
+        assert_eq!(resp.redirects.len(), 1);
+        assert_eq!(resp.redirects[0].status, 303);
+        assert_eq!(resp.redirects[0].to, format!("{base}/next"));
+        assert!(requests.recv().await.unwrap().starts_with("POST /start "));
+        // 303 goes on with a GET, without the body
+        let next = requests.recv().await.unwrap();
+        assert!(next.starts_with("GET /next "));
+        assert!(next.ends_with("\r\n\r\n"));
+
+        let (base, _) = serve(vec![see_other.to_string()]).await;
+        let resp = request(SavedRequest {
+            uri: format!("{base}/start"),
+            options: r#"{"follow_redirects": false}"#.to_string(),
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        assert_eq!(resp.status, 303);
+        assert!(resp.redirects.is_empty());
+    }
+
+    #[tokio::test]
+    async fn retries() {
+        let unavailable = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n";
+        let responses = vec![unavailable.to_string(), ok("", "up")];
+        let (base, _) = serve(responses).await;
+        let resp = request(SavedRequest {
+            uri: base,
+            options: r#"{"retries": 2, "retry_backoff": 1}"#.to_string(),
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        assert_eq!((resp.status, resp.attempts), (200, 2));
+
+        // Statuses not listed are handed back as they are
+        let (base, _) = serve(vec![unavailable.to_string()]).await;
+        let resp = request(SavedRequest {
+            uri: base,
+            options: r#"{"retries": 2, "retry_on": [429]}"#.to_string(),
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        assert_eq!((resp.status, resp.attempts), (503, 1));
+    }
+
+    /// Types `text` into `editor`, a tab for `\t` and a new row for `\n`
+    fn type_rows(editor: &mut KeyValueEditor, text: &str) {
+        for c in text.chars() {
+            let code = match c {
+                '\t' => KeyCode::Tab,
+                '\n' => KeyCode::Enter,
+                c => KeyCode::Char(c),
+            };
+            editor.input(KeyEvent::new(code, KeyModifiers::NONE));
+        }
+    }
+
+    #[test]
+    fn edits_headers() {
+        let mut headers = KeyValueEditor::headers();
+        type_rows(&mut headers, "Accept\tjson\nX-Off\tyes");
+        headers.input(KeyEvent::new(KeyCode::Char('e'), KeyModifiers::CONTROL));
+        type_rows(&mut headers, "\n\n");
+        assert_eq!(headers.rows.len(), 4);
+        assert_eq!(
+            headers.pairs(),
+            vec![("Accept".to_string(), "json".to_string())]
+        );
+        assert_eq!(headers.to_text(), "Accept: json\n# X-Off: yes");
+        assert!(headers.validate());
+
+        type_rows(&mut headers, "Bad Name");
+        assert!(!headers.validate());
+        headers.input(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL));
+        assert!(headers.validate());
+        assert_eq!(headers.rows.len(), 3);
+    }
+
+    fn rows(pairs: &[(&str, &str)]) -> Vec<KeyValueRow> {
+        pairs
+            .iter()
+            .map(|(key, value)| KeyValueRow {
+                key: key.to_string(),
+                value: value.to_string(),
+                ..KeyValueRow::new()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn sends_form() {
+        let (base, mut requests) = serve(vec![ok("", "")]).await;
+        request(SavedRequest {
+            uri: base,
+            body_mode: BodyMode::Form,
+            form: rows(&[("name", "a&b c"), ("", "skipped"), ("empty", "")]),
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        let sent = requests.recv().await.unwrap().to_ascii_lowercase();
+        assert!(sent.starts_with("post / "));
+        assert!(sent.contains("\r\ncontent-type: application/x-www-form-urlencoded\r\n"));
+        assert!(sent.ends_with("\r\n\r\nname=a%26b+c&empty="));
+    }
+
+    #[tokio::test]
+    async fn sends_multipart() {
+        let path = std::env::temp_dir().join(format!("curl-rs-upload-{}.txt", std::process::id()));
+        std::fs::write(&path, "file contents").unwrap();
+        let (base, mut requests) = serve(vec![ok("", "")]).await;
+        let upload = format!("@{}", path.display());
+        request(SavedRequest {
+            uri: base,
+            body_mode: BodyMode::Multipart,
+            multipart: rows(&[("field", "value"), ("upload", &upload)]),
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let sent = requests.recv().await.unwrap();
+        let (head, body) = sent.split_once("\r\n\r\n").unwrap();
+        let boundary = head
+            .lines()
+            .find_map(|line| line.split_once("boundary="))
+            .map(|(_, boundary)| boundary)
+            .unwrap();
+        let file_name = path.file_name().unwrap().to_string_lossy();
+        assert_eq!(
+            body,
+            format!(
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"field\"\r\n\r\nvalue\r\n\
+                 --{boundary}\r\nContent-Disposition: form-data; name=\"upload\"; \
+                 filename=\"{file_name}\"\r\n\r\nfile contents\r\n--{boundary}--\r\n"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn sends_file() {
+        let path = std::env::temp_dir().join(format!("curl-rs-binary-{}", std::process::id()));
+        std::fs::write(&path, [0, 159, 146, 150]).unwrap();
+        let (base, mut requests) = serve(vec![ok("", "")]).await;
+        request(SavedRequest {
+            method: "PUT".to_string(),
+            uri: base.clone(),
+            body_mode: BodyMode::Binary,
+            file: format!("@{}", path.display()),
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let sent = requests.recv().await.unwrap().to_ascii_lowercase();
+        assert!(sent.starts_with("put / "));
+        assert!(sent.contains("\r\ncontent-length: 4\r\n"));
+        assert!(sent.ends_with("\r\n\r\n\0\u{fffd}\u{fffd}\u{fffd}"));
+
+        let result = request(SavedRequest {
+            uri: base,
+            body_mode: BodyMode::Binary,
+            file: path.display().to_string(),
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await;
+        assert!(matches!(result, Err(RequestError::InvalidBody(_))));
+    }
+
+    #[tokio::test]
+    async fn types_body_by_syntax() {
+        let (base, mut requests) = serve(vec![ok("", ""), ok("", "")]).await;
+        request(SavedRequest {
+            uri: base.clone(),
+            syntax: Syntax::Xml,
+            body: "<a/>".to_string(),
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        let sent = requests.recv().await.unwrap().to_ascii_lowercase();
+        assert!(sent.contains("\r\ncontent-type: application/xml\r\n"));
+
+        // A Content-Type header of the user's wins
+        request(SavedRequest {
+            uri: base,
+            headers: rows(&[("Content-Type", "application/soap+xml")]),
+            syntax: Syntax::Xml,
+            body: "<a/>".to_string(),
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        let sent = requests.recv().await.unwrap().to_ascii_lowercase();
+        assert!(sent.contains("\r\ncontent-type: application/soap+xml\r\n"));
+        assert!(!sent.contains("application/xml"));
+    }
+
+    #[tokio::test]
+    async fn sends_graphql() {
+        let query = "query($id: ID) { user(id: $id) { name } }";
+        let (base, mut requests) = serve(vec![ok("", "")]).await;
+        request(SavedRequest {
+            uri: base.clone(),
+            body_mode: BodyMode::GraphQl,
+            query: query.to_string(),
+            variables: r#"{"id": 1}"#.to_string(),
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        let sent = requests.recv().await.unwrap();
+        assert!(sent
+            .to_ascii_lowercase()
+            .contains("\r\ncontent-type: application/json\r\n"));
+        let (_, body) = sent.split_once("\r\n\r\n").unwrap();
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({ "query": query, "variables": { "id": 1 } })
+        );
+
+        let mut editors = Editors::new();
+        editors.load(&SavedRequest {
+            uri: base,
+            body_mode: BodyMode::GraphQl,
+            query: query.to_string(),
+            variables: "{".to_string(),
+            ..SavedRequest::default()
+        });
+        let result = Request::new(&editors, &Config::default());
+        assert!(matches!(result, Err(RequestError::InvalidBody(_))));
+    }
+
+    #[tokio::test]
+    async fn sends_custom_method() {
+        let (base, mut requests) = serve(vec![ok("", "")]).await;
+        request(SavedRequest {
+            method: "purge".to_string(),
+            uri: base.clone(),
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        assert!(requests.recv().await.unwrap().starts_with("purge / "));
+
+        let result = request(SavedRequest {
+            method: "NOT A METHOD".to_string(),
+            uri: base,
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await;
+        assert!(matches!(result, Err(RequestError::InvalidMethod(_))));
+    }
+
+    #[tokio::test]
+    async fn token_counts_against_timeout() {
+        // Both answer a read after 200ms, in time on their own
+        let mut addresses = Vec::new();
+        for body in [r#"{"access_token": "abc"}"#, ""] {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            addresses.push(listener.local_addr().unwrap());
+            tokio::spawn(async move {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let _ = stream.read(&mut [0; 4096]).await;
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                let _ = stream.write_all(ok("", body).as_bytes()).await;
+            });
+        }
+        let result = request(SavedRequest {
+            uri: format!("http://{}/", addresses[1]),
+            options: r#"{"timeout": 0.3}"#.to_string(),
+            auth_mode: AuthMode::OAuth2,
+            auth: rows(&[
+                ("token url", &format!("http://{}/token", addresses[0])),
+                ("client id", "client"),
+            ]),
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await;
+        assert!(matches!(result, Err(RequestError::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn pretty_prints_json() {
+        let json = "Content-Type: application/json\r\n";
+        let (base, _) = serve(vec![ok(json, r#"{"a":[1,true]}"#), ok(json, "{broken")]).await;
+        let resp = request(SavedRequest {
+            uri: base.clone(),
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        assert_eq!(
+            resp.pretty.as_deref(),
+            Some("{\n  \"a\": [\n    1,\n    true\n  ]\n}")
+        );
+
+        let resp = request(SavedRequest {
+            uri: base,
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        assert_eq!((resp.pretty, resp.body.as_str()), (None, "{broken"));
+    }
+
+    #[tokio::test]
+    async fn reads_status_line() {
+        let teapot = "HTTP/1.1 418 I'm a teapot\r\nContent-Length: 0\r\n\r\n";
+        let (base, _) = serve(vec![teapot.to_string()]).await;
+        let resp = request(SavedRequest {
+            uri: base,
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        assert_eq!(
+            (resp.status, resp.reason(), resp.version.as_str()),
+            (418, "I'm a teapot", "HTTP/1.1")
+        );
+
+        let unknown = Response {
+            status: 599,
+            ..Response::default()
+        };
+        assert_eq!(unknown.reason(), "");
+    }
+
+    #[tokio::test]
+    async fn times_phases() {
+        let (base, _) = serve(vec![ok("", "timed")]).await;
+        let resp = request(SavedRequest {
+            uri: base,
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        let timing = &resp.timing;
+        assert!(timing.connect.is_some());
+        assert!(timing.tls.is_none());
+        assert!(timing.first_byte <= timing.total);
+    }
+
+    #[tokio::test]
+    async fn signs_same_origin_hops() {
+        let path = std::env::temp_dir().join(format!("curl-rs-signed-{}", std::process::id()));
+        std::fs::write(&path, "signed").unwrap();
+        let (other, mut other_requests) = serve(vec![ok("", "")]).await;
+        let moved = format!(
+            "HTTP/1.1 307 Temporary Redirect\r\nLocation: {other}/moved\r\nContent-Length: 0\r\n\r\n"
+        );
+        let (base, mut requests) = serve(vec![moved]).await;
+        let mut editors = Editors::new();
+        editors.load(&SavedRequest {
+            uri: base,
+            body_mode: BodyMode::Binary,
+            file: path.display().to_string(),
+            auth_mode: AuthMode::AwsSigV4,
+            auth: rows(&[
+                ("access key", "AKID"),
+                ("region", "us-east-1"),
+                ("service", "s3"),
+            ]),
+            ..SavedRequest::default()
+        });
+        editors.auth.aws.set_value("secret key", "secret");
+        let resp = Request::new(&editors, &Config::default())
+            .unwrap()
+            .fetch()
+            .await
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(resp.redirects.len(), 1);
+
+        // Files are streamed, so their hash isn't known up front
+        let signed = requests.recv().await.unwrap().to_ascii_lowercase();
+        assert!(signed.contains("\r\nauthorization: aws4-hmac-sha256 credential=akid/"));
+        assert!(signed.contains("\r\nx-amz-content-sha256: unsigned-payload\r\n"));
+        let moved = other_requests.recv().await.unwrap().to_ascii_lowercase();
+        assert!(moved.starts_with("post /moved "));
+        assert!(moved.ends_with("\r\n\r\nsigned"));
+        assert!(!moved.contains("authorization:"));
+        assert!(!moved.contains("x-amz-"));
+    }
+
+    #[test]
+    fn sizes_headers() {
+        let resp = Response {
+            status: 200,
+            version: "HTTP/1.1".to_string(),
+            headers: vec![("a".to_string(), "b".to_string())],
+            ..Response::default()
+        };
+        assert_eq!(resp.header_size(), "HTTP/1.1 200 OK\r\na: b\r\n\r\n".len());
+    }
+
+    /// State showing a response of `lines` lines in a view `height` rows
+    /// high, borders included
+    fn scrolled(lines: usize, height: u16) -> State<'static> {
+        let mut state = state();
+        state.resp_area = Rect::new(0, 0, 80, height);
+        state.response = Some(Ok(Response {
+            status: 200,
+            body: "line\n".repeat(lines),
+            ..Response::default()
+        }));
+        state
+    }
+
+    #[test]
+    fn scrolls_within_body() {
+        let mut state = scrolled(25, 12);
+        state.page_down();
+        assert_eq!(state.resp_scroll, 10);
+        state.page_down();
+        assert_eq!(state.resp_scroll, 15);
+        state.scroll_down();
+        assert_eq!(state.resp_scroll, 15);
+        state.page_up();
+        state.scroll_up();
+        assert_eq!(state.resp_scroll, 4);
+        state.scroll_to_top();
+        state.scroll_up();
+        assert_eq!(state.resp_scroll, 0);
+        state.scroll_to_bottom();
+        assert_eq!(state.resp_scroll, 15);
+
+        // Short bodies don't scroll
+        let mut state = scrolled(3, 12);
+        state.scroll_to_bottom();
+        assert_eq!(state.resp_scroll, 0);
+    }
+
+    #[test]
+    fn scrolls_up_to_u16_max() {
+        let mut state = scrolled(u16::MAX as usize + 100, 12);
+        state.scroll_to_bottom();
+        assert_eq!(state.resp_scroll, u16::MAX);
+        state.page_down();
+        assert_eq!(state.resp_scroll, u16::MAX);
+    }
+
+    #[test]
+    fn searches_response() {
+        let mut state = scrolled(0, 12);
+        let mut body = vec!["line"; 30];
+        (body[2], body[20], body[25]) = ("needle", "a Needle", "needle needle");
+        if let Some(Ok(resp)) = &mut state.response {
+            resp.body = body.join("\n");
+        }
+        state.start_search();
+        state.search.as_mut().unwrap().query = "NEEDLE".to_string();
+        state.refresh_search();
+        let found = |state: &State| {
+            let search = state.search.as_ref().unwrap();
+            let found = &search.matches[search.current];
+            (found.line, found.start, state.resp_scroll)
+        };
+        let search = state.search.as_ref().unwrap();
+        assert_eq!(search.matches.len(), 4);
+        assert_eq!(found(&state), (2, 0, 0));
+
+        // Off screen matches are scrolled to the middle
+        state.next_match();
+        assert_eq!(found(&state), (20, 2, 15));
+        state.next_match();
+        assert_eq!(found(&state), (25, 0, 20));
+        state.next_match();
+        assert_eq!(found(&state), (25, 7, 20));
+        state.next_match();
+        assert_eq!(found(&state), (2, 0, 0));
+        state.previous_match();
+        assert_eq!(found(&state), (25, 7, 20));
+    }
+
+    #[test]
+    fn filters_json() {
+        let body = r#"{"items": [{"id": 1}, {"id": 2}], "next": null}"#;
+        let filter = |expr: &str, body| {
+            let mut filter = Filter {
+                expr: expr.to_string(),
+                ..Filter::default()
+            };
+            filter.apply(body);
+            filter.result
+        };
+        // jq style paths get the `$` they're missing
+        assert_eq!(
+            filter(".items[1].id", Some(body)),
+            Some(Ok("2".to_string()))
+        );
+        assert_eq!(
+            filter("$.items[*].id", Some(body)),
+            Some(Ok("[\n  1,\n  2\n]".to_string()))
+        );
+        assert_eq!(filter("  ", Some(body)), None);
+        assert!(matches!(filter("$.items[", Some(body)), Some(Err(_))));
+        assert_eq!(
+            filter(".next", Some("not json")),
+            Some(Err("response is not JSON".to_string()))
+        );
+    }
+
+    #[test]
+    fn suggests_save_path() {
+        let mut state = state();
+        state.start_save();
+        assert_eq!(state.save_path, None);
+        state.response = Some(Ok(Response {
+            headers: vec![("Content-Type".to_string(), "image/png".to_string())],
+            ..Response::default()
+        }));
+        state.start_save();
+        assert_eq!(state.save_path.as_deref(), Some("response.png"));
+    }
+
+    #[test]
+    fn copies_response() {
+        let mut state = state();
+        assert_eq!(state.clipboard_text(false), None);
+        state.response = Some(Ok(Response {
+            status: 200,
+            version: "HTTP/1.1".to_string(),
+            headers: vec![("content-type".to_string(), "application/json".to_string())],
+            body: r#"{"a":{"b":1}}"#.to_string(),
+            pretty: Some("{\n  \"a\": {\n    \"b\": 1\n  }\n}".to_string()),
+            ..Response::default()
+        }));
+        assert_eq!(
+            state.clipboard_text(true).as_deref(),
+            Some("HTTP/1.1 200 OK\ncontent-type: application/json")
+        );
+        assert_eq!(
+            state.clipboard_text(false).as_deref(),
+            Some("{\n  \"a\": {\n    \"b\": 1\n  }\n}")
+        );
+
+        // The filtered sub-tree when there's one
+        state.start_filter();
+        state.filter.as_mut().unwrap().expr = ".a".to_string();
+        state.refresh_filter();
+        assert_eq!(
+            state.clipboard_text(false).as_deref(),
+            Some("{\n  \"b\": 1\n}")
+        );
+    }
+
+    #[test]
+    fn tells_binary_apart() {
+        let resp = |content_type: &str, bytes: &[u8]| Response {
+            headers: vec![("content-type".to_string(), content_type.to_string())],
+            bytes: Bytes::copy_from_slice(bytes),
+            ..Response::default()
+        };
+        assert!(resp("application/octet-stream", b"text").is_binary());
+        assert!(resp("image/png", b"").is_binary());
+        assert!(!resp("image/svg+xml", b"<svg/>").is_binary());
+        assert!(resp("", b"\xff\xfe").is_binary());
+        // Decoded from the declared charset instead
+        assert!(!resp("text/plain; charset=latin1", b"\xe9").is_binary());
+        assert!(!resp("application/json", b"\xe9").is_binary());
+    }
+
+    #[test]
+    fn decodes_previews() {
+        let mut png = Vec::new();
+        RgbaImage::new(1024, 8)
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        let first = Preview::decode(&png).unwrap();
+        assert_eq!((first.width, first.height), (1024, 8));
+        assert_eq!(first.image.dimensions(), (512, 4));
+        // The same bytes again are a new image to put on screen
+        let second = Preview::decode(&png).unwrap();
+        assert_ne!(first.id, second.id);
+        assert!(Preview::decode(b"not an image").is_none());
+    }
+
+    /// What `encoder` reads out
+    fn encoded(mut encoder: impl Read) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        encoder.read_to_end(&mut encoded).unwrap();
+        encoded
+    }
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        encoded(flate2::read::GzEncoder::new(bytes, Default::default()))
+    }
+
+    #[test]
+    fn decompresses() {
+        let gzipped = gzip(b"both");
+        let brotli = encoded(brotli::CompressorReader::new(
+            gzipped.as_slice(),
+            4096,
+            5,
+            22,
+        ));
+        assert_eq!(decompress("gzip, br", &brotli).unwrap(), b"both");
+
+        let zlib = encoded(flate2::read::ZlibEncoder::new(
+            &b"zlib"[..],
+            Default::default(),
+        ));
+        assert_eq!(decompress("deflate", &zlib).unwrap(), b"zlib");
+        let raw = encoded(flate2::read::DeflateEncoder::new(
+            &b"raw"[..],
+            Default::default(),
+        ));
+        assert_eq!(decompress("deflate", &raw).unwrap(), b"raw");
+
+        assert_eq!(decompress("identity", b"plain").unwrap(), b"plain");
+        assert!(decompress("zstd", b"").is_err());
+    }
+
+    #[tokio::test]
+    async fn decodes_gzip_responses() {
+        let gzip = gzip(b"unzipped");
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n",
+            gzip.len()
+        );
+        let (base, mut requests) = serve(vec![[head.as_bytes(), &gzip].concat()]).await;
+        let resp = request(SavedRequest {
+            uri: base,
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        assert_eq!(resp.body, "unzipped");
+        assert_eq!(resp.encoding.as_deref(), Some("gzip"));
+        assert_eq!(resp.body_size, gzip.len());
+        let sent = requests.recv().await.unwrap().to_ascii_lowercase();
+        assert!(sent.contains("\r\naccept-encoding: gzip, deflate, br\r\n"));
+    }
+
+    #[test]
+    fn toggles_wrap() {
+        // 20 lines 200 wide, 78 columns inside the borders
+        let mut state = scrolled(0, 12);
+        if let Some(Ok(resp)) = &mut state.response {
+            resp.body = vec!["x".repeat(200); 20].join("\n");
+        }
+        state.scroll_horizontal(30);
+        assert_eq!((state.resp_lines(), state.resp_hscroll), (20, 30));
+
+        state.toggle_wrap();
+        assert_eq!((state.resp_lines(), state.resp_hscroll), (60, 0));
+        state.scroll_horizontal(30);
+        assert_eq!(state.resp_hscroll, 0);
+        state.scroll_to_bottom();
+        assert_eq!(state.resp_scroll, 50);
+
+        // Back to one row a line, the scroll stays in reach
+        state.toggle_wrap();
+        assert_eq!(state.resp_scroll, 10);
+    }
+
+    #[test]
+    fn shows_body_as_it_streams() {
+        let mut state = state();
+        state.pending = Some(Instant::now());
+        let head = Response {
+            status: 200,
+            ..Response::default()
+        };
+        state.handle_message(Message::Started(Box::new(head)));
+        state.handle_message(Message::Chunk(Bytes::from_static(b"first ")));
+        state.handle_message(Message::Chunk(Bytes::from_static(b"half")));
+        state.flush_stream();
+        let shown = |state: &State| match &state.response {
+            Some(Ok(resp)) => (resp.body.clone(), resp.body_size),
+            _ => panic!("no response"),
+        };
+        assert_eq!(shown(&state), ("first half".to_string(), 10));
+
+        let done = Response {
+            status: 200,
+            body: "first half, then the rest".to_string(),
+            ..Response::default()
+        };
+        state.handle_message(Message::ResponseReceived(Box::new(Ok(done))));
+        assert_eq!(shown(&state).0, "first half, then the rest");
+        assert!(state.streamed.is_empty());
+    }
+
+    #[test]
+    fn picks_charset() {
+        let name = |bytes: &[u8], content_type| charset(bytes, content_type).name();
+        assert_eq!(
+            name(b"\xef\xbb\xbfbom", Some("text/plain; charset=latin1")),
+            "UTF-8"
+        );
+        assert_eq!(
+            name(b"text", Some("text/plain; charset=\"Shift_JIS\"")),
+            "Shift_JIS"
+        );
+        assert_eq!(
+            name(b"<meta charset='koi8-r'>", Some("text/html")),
+            "KOI8-R"
+        );
+        assert_eq!(name(b"<?xml encoding=\"iso-8859-2\"?>", None), "ISO-8859-2");
+        assert_eq!(name("caf\u{e9}".as_bytes(), None), "UTF-8");
+        assert_eq!(name(b"caf\xe9", None), "windows-1252");
+    }
+
+    #[tokio::test]
+    async fn decodes_charset() {
+        let head = "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=iso-8859-1\r\nContent-Length: 4\r\n\r\n";
+        let (base, _) = serve(vec![[head.as_bytes(), b"caf\xe9"].concat()]).await;
+        let resp = request(SavedRequest {
+            uri: base,
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        assert_eq!(resp.body, "caf\u{e9}");
+        assert_eq!(resp.charset, Some("windows-1252"));
+        assert!(resp.hex.is_none());
+    }
+
+    #[test]
+    fn focuses_sidebar() {
+        let mut state = state();
+        state.collections = Collections::default();
+        state.input_mode = InputMode::Response;
+        state.cycle_input_mode(true);
+        // Hidden, so skipped
+        assert_eq!(state.input_mode, InputMode::MethodEditing);
+
+        state.toggle_sidebar();
+        assert_eq!(state.input_mode, InputMode::Sidebar);
+        state.cycle_input_mode(false);
+        assert_eq!(state.input_mode, InputMode::Response);
+        state.cycle_input_mode(true);
+        assert_eq!(state.input_mode, InputMode::Sidebar);
+
+        state
+            .collections
+            .save("api/a", SavedRequest::default())
+            .unwrap();
+        state
+            .collections
+            .save("api/b", SavedRequest::default())
+            .unwrap();
+        state.move_sidebar(5);
+        assert_eq!(state.collection_selected, 2);
+        state.move_sidebar(-5);
+        assert_eq!(state.collection_selected, 0);
+
+        state.toggle_sidebar();
+        assert_eq!(state.input_mode, InputMode::Normal);
+    }
+
+    #[test]
+    fn cycles_environments() {
+        let mut state = state();
+        let environment = |name: &str| environments::Environment {
+            name: name.to_string(),
+            ..environments::Environment::default()
+        };
+        state.environments = Environments::default();
+        state.environments.list = vec![environment("dev"), environment("prod")];
+        let mut active = |forward| {
+            state.cycle_environment(forward);
+            state.environments.active.clone()
+        };
+        assert_eq!(active(true).as_deref(), Some("dev"));
+        assert_eq!(active(true).as_deref(), Some("prod"));
+        // Then none
+        assert_eq!(active(true), None);
+        assert_eq!(active(false).as_deref(), Some("prod"));
+        assert_eq!(active(false).as_deref(), Some("dev"));
+        assert_eq!(active(false), None);
+    }
+
+    #[test]
+    fn captures_into_environment() {
+        let response = Response {
+            body: r#"{"token": "abc", "user": {"id": 7}, "items": []}"#.to_string(),
+            ..Response::default()
+        };
+        let captures = rows(&[("token", "$.token"), ("id", "$.user.id")]);
+        let values = captured(&captures, &response).unwrap().unwrap();
+        assert_eq!(values["token"], "abc");
+        assert_eq!(values["id"], "7");
+        assert!(captured(&[], &response).is_none());
+        let err = captured(&rows(&[("first", "$.items[0]")]), &response).unwrap();
+        assert_eq!(err.unwrap_err(), "`$.items[0]` matched nothing");
+
+        let mut state = state();
+        state.environments = Environments::default();
+        assert!(state.capture(&captures, &response).unwrap().is_err());
+        state
+            .environments
+            .save("dev", Variables::new(), Default::default())
+            .unwrap();
+        state
+            .environments
+            .activate(Some("dev".to_string()))
+            .unwrap();
+        assert_eq!(
+            state.capture(&captures, &response),
+            Some(Ok("captured id, token into dev".to_string()))
+        );
+        assert_eq!(state.environments.variables()["token"], "abc");
+    }
+
+    #[test]
+    fn resizes_split() {
+        let mut state = state();
+        state.resize(65);
+        assert_eq!(state.split, 65);
+        state.resize(5);
+        assert_eq!(state.split, *SPLIT_RANGE.start());
+        state.resize(i32::MAX);
+        assert_eq!(state.split, *SPLIT_RANGE.end());
+    }
+
+    #[test]
+    fn zooms_panes() {
+        let mut state = state();
+        state.input_mode = InputMode::PayloadEditing;
+        state.toggle_zoom();
+        assert_eq!(state.zoom, Some(Zoom::Payload));
+        state.toggle_zoom();
+        assert_eq!(state.zoom, None);
+
+        state.input_mode = InputMode::Normal;
+        state.toggle_zoom();
+        assert_eq!(state.zoom, Some(Zoom::Response));
+        // The next pane isn't on screen
+        state.cycle_input_mode(true);
+        assert_eq!(state.zoom, None);
+        state.toggle_zoom();
+        state.switch_half();
+        assert_eq!(state.zoom, None);
+    }
+
+    #[test]
+    fn stacks_toasts() {
+        let mut state = state();
+        state.toasts.clear();
+        for n in 0..5 {
+            state.notify(Ok(format!("saved {n}")));
+        }
+        // The oldest goes early
+        let messages = |state: &State| {
+            state
+                .toasts
+                .iter()
+                .map(|toast| toast.message.clone().unwrap_or_else(|err| err))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            messages(&state),
+            ["saved 1", "saved 2", "saved 3", "saved 4"]
+        );
+        // The same one again moves to the end
+        state.notify(Ok("saved 2".to_string()));
+        assert_eq!(
+            messages(&state),
+            ["saved 1", "saved 3", "saved 4", "saved 2"]
+        );
+
+        let ago = |secs| Instant::now() - Duration::from_secs(secs);
+        state.toasts.clear();
+        state.notify(Ok("done".to_string()));
+        state.notify(Err("failed".to_string()));
+        state
+            .toasts
+            .iter_mut()
+            .for_each(|toast| toast.shown = ago(5));
+        state.tick();
+        // Errors stay up twice as long
+        assert_eq!(messages(&state), ["failed"]);
+        state.toasts[0].shown = ago(8);
+        state.tick();
+        assert!(state.toasts.is_empty());
+    }
+
+    #[test]
+    fn finds_unsaved_tabs() {
+        let mut state = state();
+        state.collections = Collections::default();
+        let mut editors = Editors::new();
+        // A new request left empty has nothing to lose
+        assert!(state.unsaved_tabs(&editors).is_empty());
+        let ping = SavedRequest {
+            uri: "http://localhost/ping".to_string(),
+            ..SavedRequest::default()
+        };
+        editors.load(&ping);
+        assert_eq!(state.unsaved_tabs(&editors), [0]);
+
+        // Saved as it is in the background tab
+        let index = state.add_tab();
+        state.collections.save("api/ping", ping.clone()).unwrap();
+        state.tabs[index].editors.load(&ping);
+        state.tabs[index].state.saved_path = Some("api/ping".to_string());
+        assert_eq!(state.unsaved_tabs(&editors), [0]);
+        state.tabs[index].editors.load(&SavedRequest {
+            uri: "http://localhost/pong".to_string(),
+            ..ping
+        });
+        assert_eq!(state.unsaved_tabs(&editors), [0, index]);
+    }
+
+    #[test]
+    fn undoes_table_edits() {
+        let mut headers = KeyValueEditor::headers();
+        type_rows(&mut headers, "Accept\tjson");
+        headers.input(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL));
+        assert!(headers.pairs().is_empty());
+        assert!(headers.undo());
+        assert_eq!(headers.to_text(), "Accept: json");
+        // Each key typed is an edit of its own
+        assert!(headers.undo());
+        assert_eq!(headers.to_text(), "Accept: jso");
+        assert!(headers.redo());
+        assert_eq!(headers.to_text(), "Accept: json");
+
+        // A new edit leaves nothing to redo
+        headers.undo();
+        type_rows(&mut headers, "!");
+        assert!(!headers.redo());
+        assert_eq!(headers.to_text(), "Accept: jso!");
+
+        // Moving around isn't an edit, loading rows forgets them all
+        headers.input(KeyEvent::new(KeyCode::Left, KeyModifiers::NONE));
+        headers.undo();
+        assert_eq!(headers.to_text(), "Accept: jso");
+        headers.set_rows(&rows(&[("Host", "example.com")]));
+        assert!(!headers.undo());
+        assert_eq!(headers.to_text(), "Host: example.com");
+    }
+
+    #[test]
+    fn completes_uris() {
+        let mut state = state();
+        state.history = History::default();
+        state.collections = Collections::default();
+        let saved = |uri: &str| SavedRequest {
+            uri: uri.to_string(),
+            ..SavedRequest::default()
+        };
+        for uri in ["http://localhost/old", "http://localhost/new"] {
+            let entry = Entry::new(saved(uri), &Ok(Response::default()), 1);
+            state.history.entries.push(entry);
+        }
+        state
+            .collections
+            .save("api/users", saved("http://localhost/users"))
+            .unwrap();
+
+        // The latest sent first, then the saved ones
+        state.complete_uri("localhost");
+        let completion = state.completion.as_ref().unwrap();
+        assert_eq!(completion.target, Target::Uri);
+        assert_eq!(
+            completion.items,
+            [
+                "http://localhost/new",
+                "http://localhost/old",
+                "http://localhost/users"
+            ]
+        );
+        state.complete_uri("nowhere");
+        assert!(state.completion.is_none());
+    }
+
+    #[test]
+    fn tells_cursor_position() {
+        let mut body = Editor::default("Body");
+        assert_eq!(body.position(), "1:1");
+        body.set_text("{\n  \"a\": 1\n}");
+        body.text_area.move_cursor(CursorMove::Jump(1, 4));
+        assert_eq!(body.position(), "2:5");
+        body.text_area.move_cursor(CursorMove::Bottom);
+        body.text_area.move_cursor(CursorMove::End);
+        assert_eq!(body.position(), "3:2");
+    }
+
+    #[test]
+    fn keeps_uri_on_one_line() {
+        let mut uri = Editor::single_line("URI");
+        uri.set_text("http://localhost/users");
+        // Like vim's o in the middle of the line
+        uri.text_area.move_cursor(CursorMove::Jump(0, 17));
+        uri.text_area.insert_newline();
+        uri.text_area.insert_str("all");
+        uri.keep_single_line();
+        assert_eq!(uri.text(), "http://localhost/allusers");
+        assert_eq!(uri.position(), "1:21");
+        uri.set_text("http://localhost/\n  users");
+        assert_eq!(uri.text(), "http://localhost/users");
+
+        let mut body = Editor::default("Body");
+        body.set_text("a\nb");
+        body.keep_single_line();
+        assert_eq!(body.text(), "a\nb");
+    }
+
+    #[test]
+    fn tabs_through_focus() {
+        let mut mode = InputMode::MethodEditing;
+        let order: Vec<_> = (0..6)
+            .map(|_| {
+                mode = mode.next();
+                mode
+            })
+            .collect();
+        assert_eq!(
+            order,
+            [
+                InputMode::UriEditing,
+                InputMode::Normal,
+                InputMode::PayloadEditing,
+                InputMode::Response,
+                InputMode::Sidebar,
+                InputMode::MethodEditing
+            ]
+        );
+        assert_eq!(InputMode::MethodEditing.previous(), InputMode::Sidebar);
+
+        // Tables cross to the other column past the edge of a cell
+        let mut headers = KeyValueEditor::headers();
+        type_rows(&mut headers, "Accept");
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        headers.input(key(KeyCode::Right));
+        assert_eq!((headers.column, headers.cursor), (1, 0));
+        type_rows(&mut headers, "json");
+        headers.input(key(KeyCode::Right));
+        assert_eq!((headers.column, headers.cursor), (1, 4));
+        headers.input(key(KeyCode::Home));
+        headers.input(key(KeyCode::Left));
+        assert_eq!((headers.column, headers.cursor), (0, 6));
+    }
+
+    #[test]
+    fn switches_halves() {
+        let mut state = state();
+        state.input_mode = InputMode::PayloadEditing;
+        state.switch_half();
+        assert_eq!(state.input_mode, InputMode::Response);
+        assert_eq!(state.app_mode(), AppMode::Response);
+        state.switch_half();
+        assert_eq!(state.input_mode, InputMode::PayloadEditing);
+        assert_eq!(state.app_mode(), AppMode::Request);
+
+        // Not back into a sidebar that was hidden meanwhile
+        state.sidebar = true;
+        state.input_mode = InputMode::Sidebar;
+        state.switch_half();
+        state.sidebar = false;
+        state.switch_half();
+        assert_eq!(state.input_mode, InputMode::Normal);
+    }
+
+    #[tokio::test]
+    async fn sends_over_unix_socket() {
+        let dir = std::env::temp_dir().join(format!("curl-rs-tests-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("api.sock");
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut head = Vec::new();
+            let mut buffer = [0; 4096];
+            while !head.ends_with(b"\r\n\r\n") {
+                let read = stream.read(&mut buffer).await.unwrap();
+                head.extend_from_slice(&buffer[..read]);
+            }
+            let answer = ok("", "ok");
+            stream.write_all(answer.as_bytes()).await.unwrap();
+            String::from_utf8(head).unwrap()
+        });
+
+        let options = serde_json::json!({ "unix_socket": path }).to_string();
+        let resp = request(SavedRequest {
+            uri: "http://localhost/ping?n=1".to_string(),
+            options: options.clone(),
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await
+        .unwrap();
+        assert_eq!((resp.status, resp.body.as_str()), (200, "ok"));
+        let sent = server.await.unwrap().to_lowercase();
+        assert!(sent.starts_with("get /ping?n=1 http/1.1\r\n"), "{sent}");
+        assert!(sent.contains("host: localhost\r\n"), "{sent}");
+
+        // Nothing listening any more
+        std::fs::remove_file(&path).unwrap();
+        let resp = request(SavedRequest {
+            uri: "http://localhost/ping".to_string(),
+            options,
+            ..SavedRequest::default()
+        })
+        .fetch()
+        .await;
+        assert!(matches!(resp, Err(RequestError::Connection(_))));
+    }
 }
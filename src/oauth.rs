@@ -0,0 +1,476 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::Url;
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+};
+
+use crate::models::RequestError;
+
+/// How long the authorization code flow waits for the browser redirect
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(120);
+/// Tokens this close to expiry are refreshed before being sent
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Grant {
+    ClientCredentials,
+    AuthorizationCode,
+}
+
+impl Grant {
+    pub fn parse(grant: &str) -> Result<Self, RequestError> {
+        match grant.trim() {
+            "client_credentials" => Ok(Self::ClientCredentials),
+            "authorization_code" => Ok(Self::AuthorizationCode),
+            other => Err(RequestError::InvalidAuth(format!(
+                "grant type must be `client_credentials` or `authorization_code`, not `{other}`"
+            ))),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct OAuth2Config {
+    pub grant: Grant,
+    pub token_url: String,
+    pub auth_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scope: String,
+    pub redirect_port: u16,
+}
+
+#[derive(Clone)]
+pub struct Token {
+    pub access_token: String,
+    pub expires_at: Option<Instant>,
+    pub refresh_token: Option<String>,
+}
+
+impl Token {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| Instant::now() + EXPIRY_MARGIN >= expires_at)
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+    refresh_token: Option<String>,
+}
+
+impl From<TokenResponse> for Token {
+    fn from(resp: TokenResponse) -> Self {
+        Self {
+            access_token: resp.access_token,
+            expires_at: resp
+                .expires_in
+                .map(|secs| Instant::now() + Duration::from_secs(secs)),
+            refresh_token: resp.refresh_token,
+        }
+    }
+}
+
+/// Tokens shared between requests, keyed by token url, client and scope
+#[derive(Clone, Default)]
+pub struct TokenCache(Arc<Mutex<HashMap<String, Token>>>);
+
+impl TokenCache {
+    pub fn get(&self, key: &str) -> Option<Token> {
+        self.0.lock().unwrap().get(key).cloned()
+    }
+
+    pub fn insert(&self, key: String, token: Token) {
+        self.0.lock().unwrap().insert(key, token);
+    }
+}
+
+impl OAuth2Config {
+    fn cache_key(&self) -> String {
+        format!("{} {} {}", self.token_url, self.client_id, self.scope)
+    }
+
+    /// The token of the last grant, unless it has expired
+    pub fn cached_token(&self, cache: &TokenCache) -> Option<String> {
+        cache
+            .get(&self.cache_key())
+            .filter(|token| !token.is_expired())
+            .map(|token| token.access_token)
+    }
+
+    /// Token requests give up after `timeout`, the browser login of the
+    /// authorization code grant after `CALLBACK_TIMEOUT`
+    pub async fn access_token(
+        &self,
+        cache: &TokenCache,
+        timeout: Duration,
+    ) -> Result<String, RequestError> {
+        let key = self.cache_key();
+        if let Some(token) = cache.get(&key).filter(|token| !token.is_expired()) {
+            return Ok(token.access_token);
+        }
+        // A failed refresh falls back to running the whole grant again
+        if let Ok(Some(access_token)) = self.refresh(cache, timeout).await {
+            return Ok(access_token);
+        }
+
+        let token = match self.grant {
+            Grant::ClientCredentials => {
+                self.request_token(&[("grant_type", "client_credentials")], timeout)
+                    .await?
+            }
+            Grant::AuthorizationCode => self.authorization_code(timeout).await?,
+        };
+        let access_token = token.access_token.clone();
+        cache.insert(key, token);
+        Ok(access_token)
+    }
+
+    /// Swaps the stored refresh token for a new access token, `None` if
+    /// there is nothing to refresh with
+    pub async fn refresh(
+        &self,
+        cache: &TokenCache,
+        timeout: Duration,
+    ) -> Result<Option<String>, RequestError> {
+        let key = self.cache_key();
+        let Some(refresh_token) = cache.get(&key).and_then(|token| token.refresh_token) else {
+            return Ok(None);
+        };
+
+        let mut token = self
+            .request_token(
+                &[
+                    ("grant_type", "refresh_token"),
+                    ("refresh_token", &refresh_token),
+                ],
+                timeout,
+            )
+            .await?;
+        // Servers may keep the refresh token and only rotate the access token
+        token.refresh_token.get_or_insert(refresh_token);
+        let access_token = token.access_token.clone();
+        cache.insert(key, token);
+        Ok(Some(access_token))
+    }
+
+    async fn request_token(
+        &self,
+        params: &[(&str, &str)],
+        timeout: Duration,
+    ) -> Result<Token, RequestError> {
+        let mut form = params.to_vec();
+        form.push(("client_id", &self.client_id));
+        if !self.client_secret.is_empty() {
+            form.push(("client_secret", &self.client_secret));
+        }
+        if !self.scope.is_empty() {
+            form.push(("scope", &self.scope));
+        }
+
+        let resp = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()?
+            .post(&self.token_url)
+            .form(&form)
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(RequestError::InvalidAuth(format!(
+                "token endpoint returned {status}: {body}"
+            )));
+        }
+
+        let token: TokenResponse = resp.json().await?;
+        Ok(token.into())
+    }
+
+    fn redirect_uri(&self) -> String {
+        format!("http://127.0.0.1:{}/callback", self.redirect_port)
+    }
+
+    async fn authorization_code(&self, timeout: Duration) -> Result<Token, RequestError> {
+        let listener = TcpListener::bind(("127.0.0.1", self.redirect_port))
+            .await
+            .map_err(|err| {
+                RequestError::InvalidAuth(format!("can't listen for the redirect: {err}"))
+            })?;
+
+        let state: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(16)
+            .map(char::from)
+            .collect();
+        let redirect_uri = self.redirect_uri();
+        let mut auth_url = Url::parse(&self.auth_url)
+            .map_err(|_| RequestError::InvalidAuth("invalid authorization url".to_string()))?;
+        auth_url
+            .query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.client_id)
+            .append_pair("redirect_uri", &redirect_uri)
+            .append_pair("state", &state);
+        if !self.scope.is_empty() {
+            auth_url.query_pairs_mut().append_pair("scope", &self.scope);
+        }
+
+        open_browser(auth_url.as_str())?;
+
+        let code = tokio::time::timeout(CALLBACK_TIMEOUT, Self::wait_for_code(&listener, &state))
+            .await
+            .map_err(|_| {
+                RequestError::InvalidAuth("timed out waiting for the authorization".to_string())
+            })??;
+
+        self.request_token(
+            &[
+                ("grant_type", "authorization_code"),
+                ("code", &code),
+                ("redirect_uri", &redirect_uri),
+            ],
+            timeout,
+        )
+        .await
+    }
+
+    async fn wait_for_code(listener: &TcpListener, state: &str) -> Result<String, RequestError> {
+        let io_err = |err: std::io::Error| RequestError::InvalidAuth(err.to_string());
+
+        loop {
+            let (mut stream, _) = listener.accept().await.map_err(io_err)?;
+            let mut line = String::new();
+            BufReader::new(&mut stream)
+                .read_line(&mut line)
+                .await
+                .map_err(io_err)?;
+
+            // GET /callback?code=...&state=... HTTP/1.1
+            let Some(target) = line.split_whitespace().nth(1) else {
+                continue;
+            };
+            let Ok(url) = Url::parse(&format!("http://127.0.0.1{target}")) else {
+                continue;
+            };
+            if url.path() != "/callback" {
+                continue;
+            }
+
+            let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+            let (body, result) = match (params.get("code"), params.get("error")) {
+                _ if params.get("state").map(String::as_str) != Some(state) => (
+                    "State mismatch, authorization rejected.",
+                    Err(RequestError::InvalidAuth(
+                        "oauth2 state mismatch".to_string(),
+                    )),
+                ),
+                (Some(code), _) => (
+                    "Authorization complete, you can close this window.",
+                    Ok(code.clone()),
+                ),
+                (None, error) => (
+                    "Authorization failed.",
+                    Err(RequestError::InvalidAuth(format!(
+                        "authorization failed: {}",
+                        error.map_or("no code returned", String::as_str)
+                    ))),
+                ),
+            };
+
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/plain\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+            return result;
+        }
+    }
+}
+
+fn open_browser(url: &str) -> Result<(), RequestError> {
+    let command = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+
+    std::process::Command::new(command)
+        .arg(url)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map(|_| ())
+        .map_err(|_| RequestError::InvalidAuth(format!("open {url} in a browser to authorize")))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{io::AsyncReadExt, sync::mpsc};
+
+    use super::*;
+
+    /// A token endpoint answering a token request at a time with the next
+    /// of `bodies`, passing on the forms it was sent
+    async fn token_endpoint(
+        bodies: Vec<&'static str>,
+    ) -> (String, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/token", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for body in bodies {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 4096];
+                let form = loop {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request);
+                    let Some((head, form)) = text.split_once("\r\n\r\n") else {
+                        continue;
+                    };
+                    let length = head
+                        .lines()
+                        .find_map(|line| {
+                            line.to_ascii_lowercase()
+                                .strip_prefix("content-length:")?
+                                .trim()
+                                .parse()
+                                .ok()
+                        })
+                        .unwrap_or(0);
+                    if read == 0 || form.len() >= length {
+                        break form.to_string();
+                    }
+                };
+                let _ = sender.send(form);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (url, receiver)
+    }
+
+    fn config(grant: Grant, token_url: String) -> OAuth2Config {
+        OAuth2Config {
+            grant,
+            token_url,
+            auth_url: String::new(),
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+            scope: "read".to_string(),
+            redirect_port: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn client_credentials() {
+        let (url, mut forms) =
+            token_endpoint(vec![r#"{"access_token": "abc", "expires_in": 3600}"#]).await;
+        let config = config(Grant::ClientCredentials, url);
+        let cache = TokenCache::default();
+        let timeout = Duration::from_secs(5);
+        assert_eq!(config.access_token(&cache, timeout).await.unwrap(), "abc");
+        assert_eq!(
+            forms.recv().await.unwrap(),
+            "grant_type=client_credentials&client_id=client&client_secret=secret&scope=read"
+        );
+        // Cached, the endpoint only answers once
+        assert_eq!(config.access_token(&cache, timeout).await.unwrap(), "abc");
+        assert_eq!(config.cached_token(&cache).as_deref(), Some("abc"));
+    }
+
+    #[tokio::test]
+    async fn token_requests_time_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/token", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let _stream = listener.accept().await;
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+        let config = config(Grant::ClientCredentials, url);
+        let result = config
+            .access_token(&TokenCache::default(), Duration::from_millis(200))
+            .await;
+        assert!(matches!(result, Err(RequestError::Http(err)) if err.is_timeout()));
+    }
+
+    #[tokio::test]
+    async fn waits_for_code() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let redirect = |target: &'static str| async move {
+            let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+            let request = format!("GET {target} HTTP/1.1\r\n\r\n");
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            let _ = stream.read_to_string(&mut response).await;
+            response
+        };
+
+        let wait = OAuth2Config::wait_for_code(&listener, "xyz");
+        let (code, _) = tokio::join!(wait, async {
+            // Other paths are skipped
+            redirect("/favicon.ico").await;
+            redirect("/callback?code=42&state=xyz").await
+        });
+        assert_eq!(code.unwrap(), "42");
+
+        let wait = OAuth2Config::wait_for_code(&listener, "xyz");
+        let (code, response) = tokio::join!(wait, redirect("/callback?code=42&state=forged"));
+        assert!(matches!(code, Err(RequestError::InvalidAuth(_))));
+        assert!(response.ends_with("State mismatch, authorization rejected."));
+    }
+
+    #[tokio::test]
+    async fn refreshes_expired_tokens() {
+        let bodies = vec![
+            r#"{"access_token": "new", "expires_in": 3600}"#,
+            r#"{"access_token": "newer", "refresh_token": "rotated"}"#,
+        ];
+        let (url, mut forms) = token_endpoint(bodies).await;
+        let config = config(Grant::ClientCredentials, url);
+        let cache = TokenCache::default();
+        let expired = Token {
+            access_token: "old".to_string(),
+            expires_at: Some(Instant::now() + EXPIRY_MARGIN / 2),
+            refresh_token: Some("refresh".to_string()),
+        };
+        cache.insert(config.cache_key(), expired);
+        assert_eq!(config.cached_token(&cache), None);
+
+        let timeout = Duration::from_secs(5);
+        assert_eq!(config.access_token(&cache, timeout).await.unwrap(), "new");
+        assert_eq!(
+            forms.recv().await.unwrap(),
+            "grant_type=refresh_token&refresh_token=refresh&client_id=client&client_secret=secret&scope=read"
+        );
+        // The refresh token is kept when the server doesn't send a new one
+        let cached = cache.get(&config.cache_key()).unwrap();
+        assert_eq!(cached.refresh_token.as_deref(), Some("refresh"));
+
+        // As after a 401
+        assert_eq!(
+            config.refresh(&cache, timeout).await.unwrap().as_deref(),
+            Some("newer")
+        );
+        let cached = cache.get(&config.cache_key()).unwrap();
+        assert_eq!(cached.refresh_token.as_deref(), Some("rotated"));
+    }
+}
@@ -0,0 +1,576 @@
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::{
+    auth::AuthMode,
+    environments::Variables,
+    import::{self, row, Imported},
+    models::{BodyMode, KeyValueRow, SavedRequest, Syntax},
+};
+
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// How far example bodies follow nested schemas, specs can refer to
+/// themselves
+const MAX_DEPTH: usize = 8;
+
+pub fn is_spec(json: &Value) -> bool {
+    json["openapi"].as_str().is_some_and(|v| v.starts_with('3')) || json["swagger"] == "2.0"
+}
+
+/// An OpenAPI 3.x or Swagger 2.0 spec as a collection named after its title,
+/// one request per operation, in folders by their first tag. The server goes
+/// into a `baseUrl` variable of an environment of the same name
+pub fn import(spec: &Value) -> Imported {
+    let mut imported = Imported::default();
+    let collection = import::name(spec["info"]["title"].as_str().unwrap_or("openapi"));
+
+    let paths = spec["paths"].as_object().into_iter().flatten();
+    for (path, item) in paths {
+        let item = resolve(spec, item);
+        for method in METHODS {
+            let operation = resolve(spec, &item[method]);
+            if !operation.is_object() {
+                continue;
+            }
+            let mut folders = vec![collection.clone()];
+            if let Some(tag) = operation["tags"][0].as_str() {
+                folders.push(import::name(tag));
+            }
+            let name = operation["operationId"]
+                .as_str()
+                .or(operation["summary"].as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("{} {path}", method.to_uppercase()));
+            let request = request(spec, path, method, item, operation);
+            imported.push(&folders, &name, request);
+        }
+    }
+
+    if let Some(server) = server(spec) {
+        let variables = Variables::from([("baseUrl".to_string(), server)]);
+        imported.environments.push((collection, variables));
+    }
+    imported
+}
+
+/// The first server, with its variables at their defaults
+fn server(spec: &Value) -> Option<String> {
+    if let Some(server) = spec["servers"][0].as_object() {
+        let mut url = server.get("url")?.as_str()?.to_string();
+        let variables = server.get("variables").and_then(Value::as_object);
+        for (name, variable) in variables.into_iter().flatten() {
+            let default = variable["default"].as_str().unwrap_or_default();
+            url = url.replace(&format!("{{{name}}}"), default);
+        }
+        return Some(url.trim_end_matches('/').to_string());
+    }
+    let host = spec["host"].as_str()?;
+    let scheme = spec["schemes"][0].as_str().unwrap_or("https");
+    let base = spec["basePath"].as_str().unwrap_or_default();
+    Some(
+        format!("{scheme}://{host}{base}")
+            .trim_end_matches('/')
+            .to_string(),
+    )
+}
+
+/// What a `$ref` points to within the spec, other files aren't followed
+fn resolve<'a>(spec: &'a Value, value: &'a Value) -> &'a Value {
+    let mut value = value;
+    for _ in 0..MAX_DEPTH {
+        let Some(pointer) = value["$ref"].as_str().and_then(|r| r.strip_prefix('#')) else {
+            break;
+        };
+        match spec.pointer(pointer) {
+            Some(target) => value = target,
+            None => break,
+        }
+    }
+    value
+}
+
+fn request(spec: &Value, path: &str, method: &str, item: &Value, op: &Value) -> SavedRequest {
+    let mut request = SavedRequest {
+        method: method.to_uppercase(),
+        ..SavedRequest::default()
+    };
+
+    // Operation parameters override the path's of the same name and place
+    let mut parameters: Vec<&Value> = Vec::new();
+    let all = item["parameters"].as_array().into_iter().flatten();
+    for parameter in all.chain(op["parameters"].as_array().into_iter().flatten()) {
+        let parameter = resolve(spec, parameter);
+        parameters.retain(|p| p["name"] != parameter["name"] || p["in"] != parameter["in"]);
+        parameters.push(parameter);
+    }
+
+    let mut uri = path.to_string();
+    let mut form = Vec::new();
+    for parameter in &parameters {
+        let name = parameter["name"].as_str().unwrap_or_default();
+        let schema = match parameter["schema"].is_null() {
+            true => *parameter,
+            false => resolve(spec, &parameter["schema"]),
+        };
+        // Made up values would only get in the way in the path
+        let given = given(parameter).or(given(schema)).map(text);
+        let value = given.clone().or_else(|| scalar(spec, schema));
+        let parameter_row = || KeyValueRow {
+            enabled: parameter["required"].as_bool().unwrap_or(false),
+            ..row(name, value.clone().unwrap_or_default())
+        };
+        match parameter["in"].as_str().unwrap_or_default() {
+            "path" => {
+                if let Some(given) = &given {
+                    uri = uri.replace(&format!("{{{name}}}"), given);
+                }
+            }
+            "query" => request.params.push(parameter_row()),
+            "header" => request.headers.push(parameter_row()),
+            "formData" if parameter["type"] == "file" => {
+                form.push(row(name, "@".to_string()));
+            }
+            "formData" => form.push(parameter_row()),
+            "body" => {
+                let consumes = op["consumes"].get(0).or(spec["consumes"].get(0));
+                let mime = consumes
+                    .and_then(Value::as_str)
+                    .unwrap_or("application/json");
+                body(&mut request, spec, mime, &parameter["schema"], None);
+            }
+            _ => {}
+        }
+    }
+    request.uri = format!("{{{{baseUrl}}}}{}", template(&uri));
+
+    if !form.is_empty() {
+        let multipart = parameters.iter().any(|p| p["type"] == "file")
+            || op["consumes"].to_string().contains("multipart/form-data");
+        match multipart {
+            true => {
+                request.body_mode = BodyMode::Multipart;
+                request.multipart = form;
+            }
+            false => {
+                request.body_mode = BodyMode::Form;
+                request.form = form;
+            }
+        }
+    }
+
+    let content = resolve(spec, &op["requestBody"])["content"].as_object();
+    if let Some(content) = content {
+        let mime = ["application/json", "application/x-www-form-urlencoded"]
+            .into_iter()
+            .find(|mime| content.contains_key(*mime))
+            .or(content.keys().next().map(String::as_str));
+        if let Some(mime) = mime {
+            let media = &content[mime];
+            let example = media["example"].clone();
+            let example = match example {
+                Value::Null => media["examples"]
+                    .as_object()
+                    .and_then(|examples| examples.values().next())
+                    .map(|example| resolve(spec, example)["value"].clone()),
+                example => Some(example),
+            };
+            body(&mut request, spec, mime, &media["schema"], example);
+        }
+    }
+
+    let security = op.get("security").or(spec.get("security"));
+    (request.auth_mode, request.auth) = auth(spec, security);
+    request
+}
+
+/// Form bodies get a row per property, anything else an example document
+fn body(
+    request: &mut SavedRequest,
+    spec: &Value,
+    mime: &str,
+    schema: &Value,
+    example: Option<Value>,
+) {
+    let reference = schema;
+    let schema = resolve(spec, schema);
+    match mime {
+        "application/x-www-form-urlencoded" | "multipart/form-data" => {
+            let properties = properties(spec, schema, 0);
+            let rows = properties.iter().map(|(name, property)| {
+                let property = resolve(spec, property);
+                match property["format"] == "binary" || property["type"] == "file" {
+                    true => row(name, "@".to_string()),
+                    false => row(name, scalar(spec, property).unwrap_or_default()),
+                }
+            });
+            match mime {
+                "multipart/form-data" => {
+                    request.body_mode = BodyMode::Multipart;
+                    request.multipart = rows.collect();
+                }
+                _ => {
+                    request.body_mode = BodyMode::Form;
+                    request.form = rows.collect();
+                }
+            }
+        }
+        "application/octet-stream" => request.body_mode = BodyMode::Binary,
+        mime => {
+            let example = example.unwrap_or_else(|| self::example(spec, reference, &[]));
+            request.body_mode = BodyMode::Raw;
+            (request.syntax, request.body) = match mime {
+                mime if mime.contains("json") => (
+                    Syntax::Json,
+                    serde_json::to_string_pretty(&example).unwrap_or_default(),
+                ),
+                mime if mime.contains("xml") => (Syntax::Xml, text(&example)),
+                _ => (Syntax::Text, text(&example)),
+            };
+            if !request
+                .headers
+                .iter()
+                .any(|h| h.key.eq_ignore_ascii_case("content-type"))
+            {
+                request.headers.push(row("Content-Type", mime.to_string()));
+            }
+        }
+    }
+}
+
+fn properties(spec: &Value, schema: &Value, depth: usize) -> Map<String, Value> {
+    let schema = resolve(spec, schema);
+    let mut properties = schema["properties"]
+        .as_object()
+        .cloned()
+        .unwrap_or_default();
+    if depth < MAX_DEPTH {
+        for part in schema["allOf"].as_array().into_iter().flatten() {
+            properties.extend(self::properties(spec, part, depth + 1));
+        }
+    }
+    properties
+}
+
+/// A value that fits the schema, from its examples where it has them.
+/// `refs` are the schemas being filled in, a property referring back to one
+/// of them is left out
+fn example(spec: &Value, schema: &Value, refs: &[&str]) -> Value {
+    let mut refs = refs.to_vec();
+    if let Some(target) = schema["$ref"].as_str() {
+        if refs.contains(&target) || refs.len() >= MAX_DEPTH {
+            return Value::Null;
+        }
+        refs.push(target);
+    }
+    let schema = resolve(spec, schema);
+    if let Some(given) = given(schema) {
+        return given.clone();
+    }
+    if let Some(first) = schema["oneOf"].get(0).or(schema["anyOf"].get(0)) {
+        return example(spec, first, &refs);
+    }
+    let properties = properties(spec, schema, 0);
+    match schema["type"].as_str() {
+        Some("array") => match example(spec, &schema["items"], &refs) {
+            Value::Null => Value::Array(Vec::new()),
+            item => Value::from(vec![item]),
+        },
+        Some("string") => Value::from(match schema["format"].as_str() {
+            Some("date-time") => "1970-01-01T00:00:00Z",
+            Some("date") => "1970-01-01",
+            Some("email") => "user@example.com",
+            Some("uuid") => "00000000-0000-0000-0000-000000000000",
+            Some("uri") | Some("url") => "https://example.com",
+            _ => "string",
+        }),
+        Some("integer") | Some("number") => Value::from(0),
+        Some("boolean") => Value::from(false),
+        Some("object") | None if !properties.is_empty() || schema["type"] == "object" => {
+            Value::Object(
+                properties
+                    .iter()
+                    .map(|(name, property)| (name.clone(), example(spec, property, &refs)))
+                    .filter(|(_, value)| !value.is_null())
+                    .collect(),
+            )
+        }
+        _ => Value::Null,
+    }
+}
+
+/// What the spec itself suggests
+fn given(schema: &Value) -> Option<&Value> {
+    schema
+        .get("example")
+        .or(schema.get("default"))
+        .or(schema["enum"].get(0))
+}
+
+/// Parameters and form fields only hold text
+fn scalar(spec: &Value, schema: &Value) -> Option<String> {
+    match example(spec, schema, &[]) {
+        Value::Null => None,
+        Value::Array(values) => values.first().map(text),
+        value => Some(text(&value)),
+    }
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+/// The first scheme the operation accepts. Secret fields are left for the
+/// user to fill in
+fn auth(spec: &Value, security: Option<&Value>) -> (AuthMode, Vec<KeyValueRow>) {
+    let schemes = match spec["components"]["securitySchemes"].is_object() {
+        true => &spec["components"]["securitySchemes"],
+        false => &spec["securityDefinitions"],
+    };
+    let requirement = security.and_then(|security| security.get(0));
+    let Some((name, scopes)) = requirement
+        .and_then(Value::as_object)
+        .and_then(|r| r.iter().next())
+    else {
+        return (AuthMode::None, Vec::new());
+    };
+    let scheme = resolve(spec, &schemes[name]);
+    let scope = scopes
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let kind = scheme["type"].as_str().unwrap_or_default();
+    let http = scheme["scheme"].as_str().unwrap_or_default().to_lowercase();
+    match (kind, http.as_str()) {
+        ("basic", _) | ("http", "basic") => (AuthMode::Basic, vec![row("username", String::new())]),
+        ("http", "bearer") => (AuthMode::Bearer, Vec::new()),
+        ("http", "digest") => (AuthMode::Digest, vec![row("username", String::new())]),
+        ("apiKey", _) => {
+            let add_to = match scheme["in"].as_str() {
+                Some("query") => "query",
+                _ => "header",
+            };
+            let key = text(&scheme["name"]);
+            (
+                AuthMode::ApiKey,
+                vec![row("key", key), row("add to", add_to.to_string())],
+            )
+        }
+        ("oauth2", _) => {
+            // Swagger 2 has one flow, OpenAPI 3 a map of them
+            let flows = &scheme["flows"];
+            let (grant, flow) = match (&flows["authorizationCode"], &flows["clientCredentials"]) {
+                (flow, _) if flow.is_object() => ("authorization_code", flow),
+                (_, flow) if flow.is_object() => ("client_credentials", flow),
+                _ if scheme["flow"] == "accessCode" => ("authorization_code", scheme),
+                _ => ("client_credentials", scheme),
+            };
+            let rows = vec![
+                row("grant type", grant.to_string()),
+                row("token url", text(&flow["tokenUrl"])),
+                row("auth url", text(&flow["authorizationUrl"])),
+                row("client id", String::new()),
+                row("scope", scope),
+            ];
+            (AuthMode::OAuth2, rows)
+        }
+        _ => (AuthMode::None, Vec::new()),
+    }
+}
+
+/// Path parameters with nothing to fill them with are left as variables
+fn template(path: &str) -> String {
+    Regex::new(r"\{([^{}]+)\}")
+        .unwrap()
+        .replace_all(path, "{{$1}}")
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::import::pairs;
+
+    #[test]
+    fn imports_openapi_3() {
+        let spec = json!({
+            "openapi": "3.0.3",
+            "info": { "title": "Pets" },
+            "servers": [{ "url": "https://{region}.pets.example.com/v1/", "variables": {
+                "region": { "default": "eu" },
+            } }],
+            "security": [{ "key": [] }],
+            "components": {
+                "securitySchemes": {
+                    "key": { "type": "apiKey", "in": "header", "name": "X-Api-Key" },
+                    "oauth": { "type": "oauth2", "flows": { "clientCredentials": {
+                        "tokenUrl": "https://auth.example.com/token", "scopes": {},
+                    } } },
+                },
+                "parameters": {
+                    "limit": { "name": "limit", "in": "query", "schema": { "type": "integer", "default": 20 } },
+                },
+                "schemas": {
+                    "Pet": { "type": "object", "properties": {
+                        "id": { "type": "string", "format": "uuid" },
+                        "name": { "type": "string", "example": "Rex" },
+                        "tags": { "type": "array", "items": { "type": "string" } },
+                        "parent": { "$ref": "#/components/schemas/Pet" },
+                    } },
+                },
+            },
+            "paths": {
+                "/pets/{petId}": {
+                    "parameters": [{ "name": "petId", "in": "path", "required": true, "schema": { "type": "string" } }],
+                    "get": {
+                        "operationId": "getPet",
+                        "tags": ["pets"],
+                        "parameters": [
+                            { "$ref": "#/components/parameters/limit" },
+                            { "name": "X-Trace", "in": "header", "required": true, "schema": { "enum": ["on", "off"] } },
+                        ],
+                    },
+                    "put": {
+                        "summary": "Replace",
+                        "security": [{ "oauth": ["write", "read"] }],
+                        "requestBody": { "content": { "application/json": {
+                            "schema": { "$ref": "#/components/schemas/Pet" },
+                        } } },
+                    },
+                },
+                "/upload": { "post": {
+                    "security": [],
+                    "requestBody": { "content": { "multipart/form-data": { "schema": {
+                        "properties": {
+                            "file": { "type": "string", "format": "binary" },
+                            "note": { "type": "string" },
+                        },
+                    } } } },
+                } },
+            },
+        });
+        assert!(is_spec(&spec));
+        let imported = import(&spec);
+        let paths: Vec<&str> = imported
+            .requests
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            ["Pets/pets/getPet", "Pets/Replace", "Pets/POST -upload"]
+        );
+        assert_eq!(
+            imported.environments,
+            [(
+                "Pets".to_string(),
+                Variables::from([(
+                    "baseUrl".to_string(),
+                    "https://eu.pets.example.com/v1".to_string()
+                )])
+            )]
+        );
+
+        let get = &imported.requests[0].1;
+        assert_eq!(get.method, "GET");
+        assert_eq!(get.uri, "{{baseUrl}}/pets/{{petId}}");
+        assert_eq!(pairs(&get.params), [("limit", "20")]);
+        assert!(!get.params[0].enabled);
+        assert_eq!(pairs(&get.headers), [("X-Trace", "on")]);
+        assert!(get.headers[0].enabled);
+        assert_eq!(get.auth_mode, AuthMode::ApiKey);
+        assert_eq!(
+            pairs(&get.auth),
+            [("key", "X-Api-Key"), ("add to", "header")]
+        );
+
+        // The recursive parent is left out
+        let put = &imported.requests[1].1;
+        assert_eq!(put.syntax, Syntax::Json);
+        let body: Value = serde_json::from_str(&put.body).unwrap();
+        assert_eq!(
+            body,
+            json!({ "id": "00000000-0000-0000-0000-000000000000", "name": "Rex", "tags": ["string"] })
+        );
+        assert_eq!(pairs(&put.headers), [("Content-Type", "application/json")]);
+        assert_eq!(put.auth_mode, AuthMode::OAuth2);
+        assert_eq!(
+            pairs(&put.auth),
+            [
+                ("grant type", "client_credentials"),
+                ("token url", "https://auth.example.com/token"),
+                ("auth url", ""),
+                ("client id", ""),
+                ("scope", "write read"),
+            ]
+        );
+
+        let upload = &imported.requests[2].1;
+        assert_eq!(upload.body_mode, BodyMode::Multipart);
+        assert_eq!(
+            pairs(&upload.multipart),
+            [("file", "@"), ("note", "string")]
+        );
+        assert_eq!(upload.auth_mode, AuthMode::None);
+    }
+
+    #[test]
+    fn imports_swagger_2() {
+        let spec = json!({
+            "swagger": "2.0",
+            "info": { "title": "Legacy" },
+            "host": "api.example.com",
+            "basePath": "/v2/",
+            "schemes": ["http"],
+            "securityDefinitions": { "basic": { "type": "basic" } },
+            "paths": {
+                "/login": { "post": {
+                    "security": [{ "basic": [] }],
+                    "consumes": ["application/x-www-form-urlencoded"],
+                    "parameters": [
+                        { "name": "user", "in": "formData", "type": "string", "required": true },
+                        { "name": "remember", "in": "formData", "type": "boolean", "default": true },
+                    ],
+                } },
+                "/items/{id}": { "patch": {
+                    "parameters": [
+                        { "name": "id", "in": "path", "type": "integer", "default": 7 },
+                        { "name": "body", "in": "body", "schema": { "type": "object", "properties": {
+                            "count": { "type": "integer" },
+                        } } },
+                    ],
+                } },
+            },
+        });
+        assert!(is_spec(&spec));
+        let imported = import(&spec);
+        assert_eq!(
+            imported.environments[0].1["baseUrl"],
+            "http://api.example.com/v2"
+        );
+
+        let login = &imported.requests[0].1;
+        assert_eq!(login.body_mode, BodyMode::Form);
+        assert_eq!(
+            pairs(&login.form),
+            [("user", "string"), ("remember", "true")]
+        );
+        assert_eq!(login.auth_mode, AuthMode::Basic);
+
+        let patch = &imported.requests[1].1;
+        assert_eq!(patch.uri, "{{baseUrl}}/items/7");
+        assert_eq!(patch.body, "{\n  \"count\": 0\n}");
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{
+    keys::{Action, KeyMap},
+    theme::THEMES,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Action(Action),
+    Environment(String),
+    /// Index into `THEMES`
+    Theme(usize),
+}
+
+pub struct Entry {
+    pub title: String,
+    /// Key hint, empty for environments and themes
+    pub key: String,
+    pub command: Command,
+}
+
+/// The command palette, typing narrows the list down by fuzzy matching
+pub struct Palette {
+    entries: Vec<Entry>,
+    pub query: String,
+    pub selected: usize,
+}
+
+/// Matches when the query's characters appear in order in `text`, scored
+/// higher for runs of them and for starting words
+fn score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut at = 0;
+    let mut previous = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let index = at + text[at..].iter().position(|&t| t == c)?;
+        if previous.is_some_and(|previous| previous + 1 == index) {
+            score += 3;
+        }
+        if index == 0 || text[index - 1] == ' ' {
+            score += 2;
+        }
+        score -= (index - at) as i32;
+        previous = Some(index);
+        at = index + 1;
+    }
+    Some(score)
+}
+
+impl Palette {
+    pub fn new(keys: &KeyMap, environments: &[String]) -> Self {
+        let actions = keys
+            .actions()
+            .filter(|action| *action != Action::Palette)
+            .map(|action| Entry {
+                title: action.title().to_string(),
+                key: keys.label(action),
+                command: Command::Action(action),
+            });
+        let environments = environments.iter().map(|name| Entry {
+            title: format!("switch to environment {name}"),
+            key: String::new(),
+            command: Command::Environment(name.clone()),
+        });
+        let themes = THEMES.iter().enumerate().map(|(index, theme)| Entry {
+            title: format!("use the {} theme", theme.name),
+            key: String::new(),
+            command: Command::Theme(index),
+        });
+        Self {
+            entries: actions.chain(environments).chain(themes).collect(),
+            query: String::new(),
+            selected: 0,
+        }
+    }
+
+    /// Best match first, in the usual order when nothing's typed
+    pub fn matches(&self) -> Vec<&Entry> {
+        let mut matches: Vec<(i32, &Entry)> = self
+            .entries
+            .iter()
+            .filter_map(|entry| Some((score(&self.query, &entry.title)?, entry)))
+            .collect();
+        matches.sort_by_key(|(score, _)| -score);
+        matches.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    pub fn move_selection(&mut self, delta: i32) {
+        let last = self.matches().len().saturating_sub(1) as i32;
+        self.selected = (self.selected as i32 + delta).clamp(0, last) as usize;
+    }
+
+    pub fn choice(&self) -> Option<Command> {
+        self.matches()
+            .get(self.selected)
+            .map(|entry| entry.command.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette(query: &str) -> Palette {
+        let mut palette = Palette::new(&KeyMap::default(), &["staging".to_string()]);
+        palette.query = query.to_string();
+        palette
+    }
+
+    #[test]
+    fn scores_fuzzy_matches() {
+        assert!(score("snd", "send the request").is_some());
+        assert!(score("dns", "send the request").is_none());
+        // Runs and word starts count
+        assert!(score("send", "send the request") > score("sedn", "send the request"));
+        assert!(score("tr", "the request") > score("tr", "control"));
+        assert_eq!(score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn picks_commands() {
+        let all = palette("");
+        let first = &all.matches()[0];
+        assert_eq!(first.title, "send the request");
+        assert_eq!(first.key, "Ctrl+Enter");
+        assert!(all
+            .matches()
+            .iter()
+            .all(|entry| entry.command != Command::Action(Action::Palette)));
+
+        assert_eq!(
+            palette("send req").choice(),
+            Some(Command::Action(Action::Send))
+        );
+        assert_eq!(
+            palette("env stag").choice(),
+            Some(Command::Environment("staging".to_string()))
+        );
+        assert_eq!(palette("solarized").choice(), Some(Command::Theme(2)));
+
+        let mut quit = palette("quit");
+        quit.move_selection(5);
+        assert_eq!(quit.selected, quit.matches().len() - 1);
+        quit.query = "zzzz".to_string();
+        assert_eq!(quit.choice(), None);
+    }
+}
@@ -0,0 +1,597 @@
+use serde_json::{json, Value};
+
+use crate::{
+    auth::AuthMode,
+    collections::{Collection, Folder},
+    environments::Variables,
+    import::{self, row, Imported},
+    models::{BodyMode, KeyValueRow, SavedRequest, Syntax},
+};
+
+const SCHEMA: &str = "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+/// Postman's dynamic variables that have a counterpart here, the first of a
+/// name is the one exported
+const DYNAMIC: [(&str, &str); 5] = [
+    ("{{$guid}}", "{{uuid}}"),
+    ("{{$randomUUID}}", "{{uuid}}"),
+    ("{{$timestamp}}", "{{timestamp}}"),
+    ("{{$isoTimestamp}}", "{{isoTimestamp}}"),
+    ("{{$randomInt}}", "{{randomInt}}"),
+];
+
+pub fn is_collection(json: &Value) -> bool {
+    json["info"]["schema"]
+        .as_str()
+        .is_some_and(|schema| schema.contains("getpostman.com/json/collection/v2"))
+}
+
+/// A v2.1 collection, or v2.0 which differs only in how auth is written. Its
+/// variables become an environment named after it. Secret auth fields are
+/// left out since saved requests never hold them
+pub fn import(json: &Value) -> Imported {
+    let name = import::name(json["info"]["name"].as_str().unwrap_or("postman"));
+    let mut imported = Imported::default();
+    items(
+        &json["item"],
+        std::slice::from_ref(&name),
+        &json["auth"],
+        &mut imported,
+    );
+
+    let variables: Variables = json["variable"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|variable| !variable["disabled"].as_bool().unwrap_or(false))
+        .map(|variable| (text(&variable["key"]), text(&variable["value"])))
+        .filter(|(key, _)| !key.is_empty())
+        .collect();
+    if !variables.is_empty() {
+        imported.environments.push((name, variables));
+    }
+    imported
+}
+
+/// Anything but a string as its JSON, with the dynamic variables renamed
+fn text(value: &Value) -> String {
+    let mut text = match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    };
+    for (postman, own) in DYNAMIC {
+        text = text.replace(postman, own);
+    }
+    text
+}
+
+/// `[{key, value, disabled}]`
+fn rows(list: &Value) -> Vec<KeyValueRow> {
+    list.as_array()
+        .into_iter()
+        .flatten()
+        .map(|entry| {
+            let value = match entry["type"].as_str() {
+                // The editor's way of naming files to upload
+                Some("file") => {
+                    let src = match &entry["src"] {
+                        Value::Array(paths) => paths.first().map(text).unwrap_or_default(),
+                        src => text(src),
+                    };
+                    format!("@{src}")
+                }
+                _ => text(&entry["value"]),
+            };
+            KeyValueRow {
+                enabled: !entry["disabled"].as_bool().unwrap_or(false),
+                ..row(&text(&entry["key"]), value)
+            }
+        })
+        .filter(|row| !row.key.is_empty() || !row.value.is_empty())
+        .collect()
+}
+
+/// Folders have items of their own, requests without auth use their
+/// folder's
+fn items(list: &Value, folders: &[String], auth: &Value, imported: &mut Imported) {
+    for item in list.as_array().into_iter().flatten() {
+        let auth = if item["auth"].is_null() {
+            auth
+        } else {
+            &item["auth"]
+        };
+        let name = item["name"].as_str().unwrap_or_default();
+        if item["item"].is_array() {
+            let mut path = folders.to_vec();
+            path.push(import::name(name));
+            items(&item["item"], &path, auth, imported);
+        } else if !item["request"].is_null() {
+            imported.push(folders, name, request(&item["request"], auth));
+        }
+    }
+}
+
+/// Also just a url
+fn request(json: &Value, auth: &Value) -> SavedRequest {
+    if json.is_string() {
+        return SavedRequest {
+            method: "GET".to_string(),
+            uri: text(json),
+            ..SavedRequest::default()
+        };
+    }
+    let mut request = SavedRequest {
+        method: json["method"].as_str().unwrap_or("GET").to_string(),
+        headers: rows(&json["header"]),
+        ..SavedRequest::default()
+    };
+    let auth = if json["auth"].is_null() {
+        auth
+    } else {
+        &json["auth"]
+    };
+    (request.auth_mode, request.auth) = self::auth(auth);
+
+    // Either just the url, or its parts along with the raw url
+    match &json["url"] {
+        url @ Value::String(_) => request.uri = text(url),
+        url => {
+            request.uri = text(&url["raw"]);
+            request.params = rows(&url["query"]);
+            if !request.params.is_empty() {
+                request.uri = request
+                    .uri
+                    .split('?')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string();
+            }
+            // `:id` path variables
+            for variable in rows(&url["variable"]) {
+                request.uri = request.uri.replace(
+                    &format!("/:{}", variable.key),
+                    &format!("/{}", variable.value),
+                );
+            }
+        }
+    }
+
+    let body = &json["body"];
+    match body["mode"].as_str() {
+        Some("raw") => {
+            request.body = text(&body["raw"]);
+            request.syntax = match body["options"]["raw"]["language"].as_str() {
+                Some("json") => Syntax::Json,
+                Some("xml") => Syntax::Xml,
+                Some(_) => Syntax::Text,
+                None if serde_json::from_str::<Value>(&request.body).is_ok() => Syntax::Json,
+                None => Syntax::Text,
+            };
+        }
+        Some("urlencoded") => {
+            request.body_mode = BodyMode::Form;
+            request.form = rows(&body["urlencoded"]);
+        }
+        Some("formdata") => {
+            request.body_mode = BodyMode::Multipart;
+            request.multipart = rows(&body["formdata"]);
+        }
+        Some("file") => {
+            request.body_mode = BodyMode::Binary;
+            request.file = text(&body["file"]["src"]);
+        }
+        Some("graphql") => {
+            request.body_mode = BodyMode::GraphQl;
+            request.query = text(&body["graphql"]["query"]);
+            request.variables = text(&body["graphql"]["variables"]);
+        }
+        _ => {}
+    }
+    request
+}
+
+/// An attribute of the auth, `[{key, value}]` in v2.1 and an object in v2.0
+fn attribute(auth: &Value, key: &str) -> String {
+    let kind = auth["type"].as_str().unwrap_or_default();
+    match &auth[kind] {
+        Value::Array(attributes) => attributes
+            .iter()
+            .find(|attribute| attribute["key"] == key)
+            .map(|attribute| text(&attribute["value"]))
+            .unwrap_or_default(),
+        attributes => text(&attributes[key]),
+    }
+}
+
+fn auth(auth: &Value) -> (AuthMode, Vec<KeyValueRow>) {
+    let get = |key| attribute(auth, key);
+    match auth["type"].as_str().unwrap_or_default() {
+        "basic" => (AuthMode::Basic, vec![row("username", get("username"))]),
+        "digest" => (AuthMode::Digest, vec![row("username", get("username"))]),
+        "bearer" => (AuthMode::Bearer, Vec::new()),
+        "apikey" => {
+            let add_to = match get("in").as_str() {
+                "query" => "query",
+                _ => "header",
+            };
+            let rows = vec![row("key", get("key")), row("add to", add_to.to_string())];
+            (AuthMode::ApiKey, rows)
+        }
+        "awsv4" => (
+            AuthMode::AwsSigV4,
+            vec![
+                row("access key", get("accessKey")),
+                row("region", get("region")),
+                row("service", get("service")),
+            ],
+        ),
+        "oauth2" => {
+            let grant = match get("grant_type").as_str() {
+                "authorization_code" => "authorization_code",
+                _ => "client_credentials",
+            };
+            (
+                AuthMode::OAuth2,
+                vec![
+                    row("grant type", grant.to_string()),
+                    row("token url", get("accessTokenUrl")),
+                    row("auth url", get("authUrl")),
+                    row("client id", get("clientId")),
+                    row("scope", get("scope")),
+                ],
+            )
+        }
+        _ => (AuthMode::None, Vec::new()),
+    }
+}
+
+/// A v2.1 collection, with `variables` as its collection variables. Captures
+/// and options have no counterpart and are left out
+pub fn export(collection: &Collection, variables: &Variables) -> Value {
+    let variables: Vec<Value> = variables
+        .iter()
+        .map(|(key, value)| json!({ "key": key, "value": value }))
+        .collect();
+    json!({
+        "info": { "name": collection.name, "schema": SCHEMA },
+        "item": export_items(&collection.root),
+        "variable": variables,
+    })
+}
+
+/// With the built-in dynamic variables renamed to Postman's
+fn export_text(text: &str) -> String {
+    let mut text = text.to_string();
+    for (postman, own) in DYNAMIC {
+        text = text.replace(own, postman);
+    }
+    text
+}
+
+fn export_rows(rows: &[KeyValueRow]) -> Vec<Value> {
+    rows.iter()
+        .map(|row| {
+            let mut entry = json!({
+                "key": export_text(&row.key),
+                "value": export_text(&row.value),
+            });
+            if !row.enabled {
+                entry["disabled"] = Value::Bool(true);
+            }
+            entry
+        })
+        .collect()
+}
+
+fn export_items(folder: &Folder) -> Vec<Value> {
+    let folders = folder
+        .folders
+        .iter()
+        .map(|folder| json!({ "name": folder.name, "item": export_items(folder) }));
+    let requests = folder
+        .requests
+        .iter()
+        .map(|item| json!({ "name": item.name, "request": export_request(&item.request) }));
+    folders.chain(requests).collect()
+}
+
+fn export_request(request: &SavedRequest) -> Value {
+    let query: Vec<_> = request
+        .params
+        .iter()
+        .filter(|row| row.enabled)
+        .map(|row| format!("{}={}", row.key, row.value))
+        .collect();
+    let mut raw = request.uri.clone();
+    if !query.is_empty() {
+        raw.push(if raw.contains('?') { '&' } else { '?' });
+        raw.push_str(&query.join("&"));
+    }
+    let method = match request.method.trim() {
+        "" => "GET",
+        method => method,
+    };
+
+    let body = match request.body_mode {
+        BodyMode::Raw if request.body.is_empty() => Value::Null,
+        BodyMode::Raw => {
+            let language = match request.syntax {
+                Syntax::Json => "json",
+                Syntax::Xml => "xml",
+                Syntax::Text | Syntax::GraphQl => "text",
+            };
+            json!({
+                "mode": "raw",
+                "raw": export_text(&request.body),
+                "options": { "raw": { "language": language } },
+            })
+        }
+        BodyMode::Form => json!({ "mode": "urlencoded", "urlencoded": export_rows(&request.form) }),
+        BodyMode::Multipart => {
+            let mut rows = export_rows(&request.multipart);
+            for (row, entry) in request.multipart.iter().zip(&mut rows) {
+                entry["type"] = Value::from("text");
+                if let Some(path) = row.value.strip_prefix('@') {
+                    entry["type"] = Value::from("file");
+                    entry["src"] = Value::from(path);
+                    entry["value"] = Value::Null;
+                }
+            }
+            json!({ "mode": "formdata", "formdata": rows })
+        }
+        BodyMode::Binary => json!({ "mode": "file", "file": { "src": request.file } }),
+        BodyMode::GraphQl => json!({
+            "mode": "graphql",
+            "graphql": {
+                "query": export_text(&request.query),
+                "variables": export_text(&request.variables),
+            },
+        }),
+    };
+
+    let mut json = json!({
+        "method": method,
+        "header": export_rows(&request.headers),
+        "url": { "raw": export_text(&raw), "query": export_rows(&request.params) },
+        "auth": export_auth(request),
+    });
+    if !body.is_null() {
+        json["body"] = body;
+    }
+    json
+}
+
+/// Secret fields aren't saved, so they're exported empty
+fn export_auth(request: &SavedRequest) -> Value {
+    let get = |key: &str| {
+        let value = request
+            .auth
+            .iter()
+            .find(|row| row.key == key)
+            .map_or("", |row| row.value.as_str());
+        export_text(value)
+    };
+    let attributes = |pairs: &[(&str, String)]| -> Vec<Value> {
+        pairs
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": value, "type": "string" }))
+            .collect()
+    };
+    let (kind, pairs) = match request.auth_mode {
+        AuthMode::None => return json!({ "type": "noauth" }),
+        AuthMode::Basic => (
+            "basic",
+            vec![("username", get("username")), ("password", String::new())],
+        ),
+        AuthMode::Digest => (
+            "digest",
+            vec![("username", get("username")), ("password", String::new())],
+        ),
+        AuthMode::Bearer => ("bearer", vec![("token", String::new())]),
+        AuthMode::ApiKey => (
+            "apikey",
+            vec![
+                ("key", get("key")),
+                ("value", String::new()),
+                ("in", get("add to")),
+            ],
+        ),
+        AuthMode::AwsSigV4 => (
+            "awsv4",
+            vec![
+                ("accessKey", get("access key")),
+                ("secretKey", String::new()),
+                ("region", get("region")),
+                ("service", get("service")),
+            ],
+        ),
+        AuthMode::OAuth2 => (
+            "oauth2",
+            vec![
+                ("grant_type", get("grant type")),
+                ("accessTokenUrl", get("token url")),
+                ("authUrl", get("auth url")),
+                ("clientId", get("client id")),
+                ("clientSecret", String::new()),
+                ("scope", get("scope")),
+            ],
+        ),
+    };
+    json!({ "type": kind, kind: attributes(&pairs) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collections::SavedItem, import::pairs};
+
+    #[test]
+    fn imports_collection() {
+        let collection = json!({
+            "info": { "name": "Store", "schema": SCHEMA },
+            "auth": { "type": "bearer", "bearer": [{ "key": "token", "value": "secret" }] },
+            "variable": [
+                { "key": "base", "value": "https://store.example.com" },
+                { "key": "limit", "value": 10 },
+                { "key": "old", "value": "x", "disabled": true },
+            ],
+            "item": [
+                { "name": "Orders", "auth": {
+                    "type": "basic",
+                    "basic": [{ "key": "username", "value": "{{user}}" }, { "key": "password", "value": "p" }],
+                }, "item": [
+                    { "name": "Get order", "request": {
+                        "method": "GET",
+                        "header": [{ "key": "X-Id", "value": "{{$guid}}", "disabled": true }],
+                        "url": {
+                            "raw": "{{base}}/orders/:id?expand=items",
+                            "query": [{ "key": "expand", "value": "items" }],
+                            "variable": [{ "key": "id", "value": "42" }],
+                        },
+                    } },
+                ] },
+                { "name": "Upload", "request": {
+                    "method": "POST",
+                    "url": "{{base}}/upload",
+                    "body": { "mode": "formdata", "formdata": [
+                        { "key": "note", "value": "hi", "type": "text" },
+                        { "key": "file", "src": ["/tmp/a.png"], "type": "file" },
+                    ] },
+                } },
+                { "name": "Search", "request": {
+                    "method": "POST",
+                    "url": "{{base}}/graphql",
+                    "auth": { "type": "apikey", "apikey": { "key": "X-Key", "in": "query" } },
+                    "body": { "mode": "graphql", "graphql": { "query": "{ a }", "variables": "{}" } },
+                } },
+                { "name": "Raw", "request": {
+                    "method": "PUT",
+                    "url": "{{base}}/raw",
+                    "body": { "mode": "raw", "raw": "{\"n\": {{$randomInt}}}" },
+                } },
+                { "name": "Plain", "request": "https://example.com" },
+            ],
+        });
+        assert!(is_collection(&collection));
+        let imported = import(&collection);
+        let paths: Vec<&str> = imported
+            .requests
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "Store/Orders/Get order",
+                "Store/Upload",
+                "Store/Search",
+                "Store/Raw",
+                "Store/Plain",
+            ]
+        );
+        assert_eq!(
+            imported.environments,
+            [(
+                "Store".to_string(),
+                Variables::from([
+                    ("base".to_string(), "https://store.example.com".to_string()),
+                    ("limit".to_string(), "10".to_string()),
+                ])
+            )]
+        );
+
+        let order = &imported.requests[0].1;
+        assert_eq!(order.uri, "{{base}}/orders/42");
+        assert_eq!(pairs(&order.params), [("expand", "items")]);
+        assert_eq!(pairs(&order.headers), [("X-Id", "{{uuid}}")]);
+        assert!(!order.headers[0].enabled);
+        // From the folder, without the password
+        assert_eq!(order.auth_mode, AuthMode::Basic);
+        assert_eq!(pairs(&order.auth), [("username", "{{user}}")]);
+
+        let upload = &imported.requests[1].1;
+        assert_eq!(upload.auth_mode, AuthMode::Bearer);
+        assert_eq!(upload.body_mode, BodyMode::Multipart);
+        assert_eq!(
+            pairs(&upload.multipart),
+            [("note", "hi"), ("file", "@/tmp/a.png")]
+        );
+
+        // v2.0 auth, an object
+        let search = &imported.requests[2].1;
+        assert_eq!(search.auth_mode, AuthMode::ApiKey);
+        assert_eq!(pairs(&search.auth), [("key", "X-Key"), ("add to", "query")]);
+        assert_eq!(search.body_mode, BodyMode::GraphQl);
+        assert_eq!(search.query, "{ a }");
+
+        let raw = &imported.requests[3].1;
+        assert_eq!(raw.body, "{\"n\": {{randomInt}}}");
+        assert_eq!(raw.syntax, Syntax::Text);
+
+        let plain = &imported.requests[4].1;
+        assert_eq!(
+            (plain.method.as_str(), plain.uri.as_str()),
+            ("GET", "https://example.com")
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let request = SavedRequest {
+            method: "POST".to_string(),
+            uri: "{{base}}/items".to_string(),
+            params: vec![row("id", "{{uuid}}".to_string())],
+            body_mode: BodyMode::Form,
+            form: vec![KeyValueRow {
+                enabled: false,
+                ..row("a", "1".to_string())
+            }],
+            auth_mode: AuthMode::AwsSigV4,
+            auth: vec![
+                row("access key", "AKID".to_string()),
+                row("region", "us-east-1".to_string()),
+                row("service", "s3".to_string()),
+            ],
+            ..SavedRequest::default()
+        };
+        let collection = Collection {
+            name: "Items".to_string(),
+            root: Folder {
+                folders: vec![Folder {
+                    name: "Nested".to_string(),
+                    requests: vec![SavedItem {
+                        name: "Create".to_string(),
+                        request,
+                    }],
+                    ..Folder::default()
+                }],
+                ..Folder::default()
+            },
+        };
+        let variables = Variables::from([("base".to_string(), "http://x".to_string())]);
+        let exported = export(&collection, &variables);
+        assert_eq!(
+            exported["item"][0]["item"][0]["request"]["url"]["raw"],
+            "{{base}}/items?id={{$guid}}"
+        );
+
+        let imported = import(&exported);
+        assert_eq!(imported.environments, [("Items".to_string(), variables)]);
+        let (path, request) = &imported.requests[0];
+        assert_eq!(path, "Items/Nested/Create");
+        assert_eq!(request.uri, "{{base}}/items");
+        assert_eq!(pairs(&request.params), [("id", "{{uuid}}")]);
+        assert_eq!(request.body_mode, BodyMode::Form);
+        assert!(!request.form[0].enabled);
+        assert_eq!(request.auth_mode, AuthMode::AwsSigV4);
+        assert_eq!(
+            pairs(&request.auth),
+            [
+                ("access key", "AKID"),
+                ("region", "us-east-1"),
+                ("service", "s3")
+            ]
+        );
+    }
+}
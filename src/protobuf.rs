@@ -0,0 +1,1581 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{Map, Number, Value};
+
+/// Definitions of the well-known types, for imports that aren't on disk
+const WELL_KNOWN: [(&str, &str); 5] = [
+    (
+        "google/protobuf/empty.proto",
+        "syntax = \"proto3\"; package google.protobuf; message Empty {}",
+    ),
+    (
+        "google/protobuf/timestamp.proto",
+        "syntax = \"proto3\"; package google.protobuf;
+         message Timestamp { int64 seconds = 1; int32 nanos = 2; }",
+    ),
+    (
+        "google/protobuf/duration.proto",
+        "syntax = \"proto3\"; package google.protobuf;
+         message Duration { int64 seconds = 1; int32 nanos = 2; }",
+    ),
+    (
+        "google/protobuf/wrappers.proto",
+        "syntax = \"proto3\"; package google.protobuf;
+         message DoubleValue { double value = 1; } message FloatValue { float value = 1; }
+         message Int64Value { int64 value = 1; } message UInt64Value { uint64 value = 1; }
+         message Int32Value { int32 value = 1; } message UInt32Value { uint32 value = 1; }
+         message BoolValue { bool value = 1; } message StringValue { string value = 1; }
+         message BytesValue { bytes value = 1; }",
+    ),
+    (
+        "google/protobuf/struct.proto",
+        "syntax = \"proto3\"; package google.protobuf;
+         message Struct { map<string, Value> fields = 1; }
+         message Value { oneof kind { NullValue null_value = 1; double number_value = 2;
+           string string_value = 3; bool bool_value = 4; Struct struct_value = 5;
+           ListValue list_value = 6; } }
+         enum NullValue { NULL_VALUE = 0; }
+         message ListValue { repeated Value values = 1; }",
+    ),
+];
+
+/// How deep `example` fills in nested messages
+const EXAMPLE_DEPTH: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scalar {
+    Double,
+    Float,
+    Int64,
+    Uint64,
+    Int32,
+    Fixed64,
+    Fixed32,
+    Bool,
+    String,
+    Bytes,
+    Uint32,
+    Sfixed32,
+    Sfixed64,
+    Sint32,
+    Sint64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "double" => Self::Double,
+            "float" => Self::Float,
+            "int64" => Self::Int64,
+            "uint64" => Self::Uint64,
+            "int32" => Self::Int32,
+            "fixed64" => Self::Fixed64,
+            "fixed32" => Self::Fixed32,
+            "bool" => Self::Bool,
+            "string" => Self::String,
+            "bytes" => Self::Bytes,
+            "uint32" => Self::Uint32,
+            "sfixed32" => Self::Sfixed32,
+            "sfixed64" => Self::Sfixed64,
+            "sint32" => Self::Sint32,
+            "sint64" => Self::Sint64,
+            _ => return None,
+        })
+    }
+
+    /// By the number `FieldDescriptorProto.Type` gives it
+    fn from_descriptor(number: u64) -> Option<Self> {
+        Some(match number {
+            1 => Self::Double,
+            2 => Self::Float,
+            3 => Self::Int64,
+            4 => Self::Uint64,
+            5 => Self::Int32,
+            6 => Self::Fixed64,
+            7 => Self::Fixed32,
+            8 => Self::Bool,
+            9 => Self::String,
+            12 => Self::Bytes,
+            13 => Self::Uint32,
+            15 => Self::Sfixed32,
+            16 => Self::Sfixed64,
+            17 => Self::Sint32,
+            18 => Self::Sint64,
+            _ => return None,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Double => "double",
+            Self::Float => "float",
+            Self::Int64 => "int64",
+            Self::Uint64 => "uint64",
+            Self::Int32 => "int32",
+            Self::Fixed64 => "fixed64",
+            Self::Fixed32 => "fixed32",
+            Self::Bool => "bool",
+            Self::String => "string",
+            Self::Bytes => "bytes",
+            Self::Uint32 => "uint32",
+            Self::Sfixed32 => "sfixed32",
+            Self::Sfixed64 => "sfixed64",
+            Self::Sint32 => "sint32",
+            Self::Sint64 => "sint64",
+        }
+    }
+
+    /// Only numbers can be packed
+    fn packable(&self) -> bool {
+        !matches!(self, Self::String | Self::Bytes)
+    }
+
+    /// 64-bit integers are strings in JSON, numbers can't hold all of them
+    fn is_64_bit(&self) -> bool {
+        matches!(
+            self,
+            Self::Int64 | Self::Uint64 | Self::Fixed64 | Self::Sfixed64 | Self::Sint64
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Kind {
+    Scalar(Scalar),
+    Message(String),
+    Enum(String),
+    /// A type name as written and the scope it was written in, until
+    /// `Schema::resolve` looks it up
+    Unresolved(String, String),
+}
+
+#[derive(Clone, Debug)]
+pub struct Field {
+    pub name: String,
+    pub json_name: String,
+    pub number: u32,
+    pub kind: Kind,
+    pub repeated: bool,
+    pub packed: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MessageType {
+    pub fields: Vec<Field>,
+    /// The generated key and value message of a map field
+    pub map_entry: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EnumType {
+    pub values: Vec<(String, i32)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Method {
+    pub name: String,
+    pub input: String,
+    pub output: String,
+    pub client_streaming: bool,
+    pub server_streaming: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Service {
+    pub name: String,
+    pub methods: Vec<Method>,
+    /// Package the method types are looked up from
+    scope: String,
+}
+
+/// Messages, enums and services by their full names, without a leading dot
+#[derive(Default)]
+pub struct Schema {
+    pub messages: BTreeMap<String, MessageType>,
+    pub enums: BTreeMap<String, EnumType>,
+    pub services: Vec<Service>,
+    /// Files already added
+    files: HashSet<String>,
+}
+
+fn json_name(name: &str) -> String {
+    let mut json = String::new();
+    let mut upper = false;
+    for c in name.chars() {
+        match c {
+            '_' => upper = true,
+            c if upper => {
+                json.extend(c.to_uppercase());
+                upper = false;
+            }
+            c => json.push(c),
+        }
+    }
+    json
+}
+
+fn join(scope: &str, name: &str) -> String {
+    match scope.is_empty() {
+        true => name.to_string(),
+        false => format!("{scope}.{name}"),
+    }
+}
+
+impl Schema {
+    /// Parses `path` and what it imports, looked up in `include` and then
+    /// next to `path`
+    pub fn load(path: &Path, include: &[PathBuf]) -> Result<Self, String> {
+        let mut schema = Self::default();
+        let mut dirs = include.to_vec();
+        dirs.push(path.parent().unwrap_or(Path::new(".")).to_path_buf());
+        let mut queue = vec![(path.to_path_buf(), path.display().to_string())];
+        while let Some((path, name)) = queue.pop() {
+            if !schema.files.insert(name.clone()) {
+                continue;
+            }
+            let source = match std::fs::read_to_string(&path) {
+                Ok(source) => source,
+                Err(err) => match WELL_KNOWN.iter().find(|(known, _)| *known == name) {
+                    Some((_, source)) => source.to_string(),
+                    None => return Err(format!("{}: {err}", path.display())),
+                },
+            };
+            let imports = Parser::new(&source)
+                .file(&mut schema)
+                .map_err(|err| format!("{name}: {err}"))?;
+            for import in imports {
+                let found = dirs
+                    .iter()
+                    .map(|dir| dir.join(&import))
+                    .find(|p| p.exists());
+                queue.push((found.unwrap_or_else(|| PathBuf::from(&import)), import));
+            }
+        }
+        schema.resolve()?;
+        Ok(schema)
+    }
+
+    pub fn has_file(&self, name: &str) -> bool {
+        self.files.contains(name)
+    }
+
+    /// Adds a serialized `FileDescriptorProto`, returning the files it
+    /// depends on. `resolve` once they're all added
+    pub fn add_descriptor(&mut self, bytes: &[u8]) -> Result<Vec<String>, String> {
+        let mut name = String::new();
+        let mut package = String::new();
+        let mut dependencies = Vec::new();
+        let mut proto3 = false;
+        let mut messages = Vec::new();
+        let mut enums = Vec::new();
+        let mut services = Vec::new();
+        for (number, value) in wire_fields(bytes)? {
+            match (number, value) {
+                (1, Wire::Bytes(bytes)) => name = string(bytes),
+                (2, Wire::Bytes(bytes)) => package = string(bytes),
+                (3, Wire::Bytes(bytes)) => dependencies.push(string(bytes)),
+                (4, Wire::Bytes(bytes)) => messages.push(bytes),
+                (5, Wire::Bytes(bytes)) => enums.push(bytes),
+                (6, Wire::Bytes(bytes)) => services.push(bytes),
+                (12, Wire::Bytes(bytes)) => proto3 = string(bytes) == "proto3",
+                _ => {}
+            }
+        }
+        if !self.files.insert(name) {
+            return Ok(Vec::new());
+        }
+        for message in messages {
+            self.add_message_descriptor(&package, message, proto3)?;
+        }
+        for enumeration in enums {
+            self.add_enum_descriptor(&package, enumeration)?;
+        }
+        for service in services {
+            let mut name = String::new();
+            let mut methods = Vec::new();
+            for (number, value) in wire_fields(service)? {
+                match (number, value) {
+                    (1, Wire::Bytes(bytes)) => name = string(bytes),
+                    (2, Wire::Bytes(bytes)) => {
+                        let mut method = Method {
+                            name: String::new(),
+                            input: String::new(),
+                            output: String::new(),
+                            client_streaming: false,
+                            server_streaming: false,
+                        };
+                        for (number, value) in wire_fields(bytes)? {
+                            match (number, value) {
+                                (1, Wire::Bytes(bytes)) => method.name = string(bytes),
+                                (2, Wire::Bytes(bytes)) => method.input = string(bytes),
+                                (3, Wire::Bytes(bytes)) => method.output = string(bytes),
+                                (5, Wire::Varint(value)) => method.client_streaming = value != 0,
+                                (6, Wire::Varint(value)) => method.server_streaming = value != 0,
+                                _ => {}
+                            }
+                        }
+                        methods.push(method);
+                    }
+                    _ => {}
+                }
+            }
+            self.services.push(Service {
+                name: join(&package, &name),
+                methods,
+                scope: package.clone(),
+            });
+        }
+        Ok(dependencies)
+    }
+
+    fn add_message_descriptor(
+        &mut self,
+        scope: &str,
+        bytes: &[u8],
+        proto3: bool,
+    ) -> Result<(), String> {
+        let fields = wire_fields(bytes)?;
+        let name = fields
+            .iter()
+            .find_map(|(number, value)| match (number, value) {
+                (1, Wire::Bytes(bytes)) => Some(string(bytes)),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let full = join(scope, &name);
+        let mut message = MessageType::default();
+        for (number, value) in fields {
+            let Wire::Bytes(bytes) = value else {
+                continue;
+            };
+            match number {
+                2 => message.fields.push(field_descriptor(&full, bytes, proto3)?),
+                3 => self.add_message_descriptor(&full, bytes, proto3)?,
+                4 => self.add_enum_descriptor(&full, bytes)?,
+                // MessageOptions.map_entry
+                7 => {
+                    message.map_entry = wire_fields(bytes)?
+                        .iter()
+                        .any(|(number, value)| matches!((number, value), (7, Wire::Varint(1))))
+                }
+                _ => {}
+            }
+        }
+        self.messages.insert(full, message);
+        Ok(())
+    }
+
+    fn add_enum_descriptor(&mut self, scope: &str, bytes: &[u8]) -> Result<(), String> {
+        let mut name = String::new();
+        let mut enumeration = EnumType::default();
+        for (number, value) in wire_fields(bytes)? {
+            match (number, value) {
+                (1, Wire::Bytes(bytes)) => name = string(bytes),
+                (2, Wire::Bytes(bytes)) => {
+                    let mut value = (String::new(), 0);
+                    for field in wire_fields(bytes)? {
+                        match field {
+                            (1, Wire::Bytes(bytes)) => value.0 = string(bytes),
+                            (2, Wire::Varint(number)) => value.1 = number as i32,
+                            _ => {}
+                        }
+                    }
+                    enumeration.values.push(value);
+                }
+                _ => {}
+            }
+        }
+        self.enums.insert(join(scope, &name), enumeration);
+        Ok(())
+    }
+
+    /// Looks `name` up from `scope` outwards, as protoc does
+    fn lookup(&self, scope: &str, name: &str) -> Option<Kind> {
+        let found = |full: &str| {
+            if self.messages.contains_key(full) {
+                Some(Kind::Message(full.to_string()))
+            } else if self.enums.contains_key(full) {
+                Some(Kind::Enum(full.to_string()))
+            } else {
+                None
+            }
+        };
+        if let Some(full) = name.strip_prefix('.') {
+            return found(full);
+        }
+        let mut scope = scope;
+        loop {
+            if let Some(kind) = found(&join(scope, name)) {
+                return Some(kind);
+            }
+            if scope.is_empty() {
+                return None;
+            }
+            scope = scope.rsplit_once('.').map(|(outer, _)| outer).unwrap_or("");
+        }
+    }
+
+    /// Replaces the type names of fields and methods with what they refer to
+    pub fn resolve(&mut self) -> Result<(), String> {
+        let mut resolved = Vec::new();
+        for (message, definition) in &self.messages {
+            for (index, field) in definition.fields.iter().enumerate() {
+                if let Kind::Unresolved(scope, name) = &field.kind {
+                    let kind = self
+                        .lookup(scope, name)
+                        .ok_or_else(|| format!("unknown type `{name}` in {message}"))?;
+                    resolved.push((message.clone(), index, kind));
+                }
+            }
+        }
+        for (message, index, kind) in resolved {
+            if let Some(definition) = self.messages.get_mut(&message) {
+                definition.fields[index].kind = kind;
+            }
+        }
+
+        let mut services = std::mem::take(&mut self.services);
+        for service in &mut services {
+            for method in &mut service.methods {
+                for name in [&mut method.input, &mut method.output] {
+                    match self.lookup(&service.scope, name) {
+                        Some(Kind::Message(full)) => *name = full,
+                        _ => return Err(format!("unknown message `{name}` in {}", service.name)),
+                    }
+                }
+            }
+        }
+        self.services = services;
+        Ok(())
+    }
+
+    /// `package.Service/Method`
+    pub fn method(&self, path: &str) -> Option<&Method> {
+        let (service, method) = path.rsplit_once('/')?;
+        self.services
+            .iter()
+            .find(|found| found.name == service)?
+            .methods
+            .iter()
+            .find(|found| found.name == method)
+    }
+
+    fn message(&self, name: &str) -> Result<&MessageType, String> {
+        self.messages
+            .get(name)
+            .ok_or_else(|| format!("unknown message `{name}`"))
+    }
+
+    /// Every service with its methods, and the messages they take and
+    /// return with a JSON example of the request
+    pub fn describe(&self) -> String {
+        let mut text = Vec::new();
+        let mut types = Vec::new();
+        for service in &self.services {
+            text.push(format!("service {}", service.name));
+            for method in &service.methods {
+                let stream = |streaming: bool| if streaming { "stream " } else { "" };
+                text.push(format!(
+                    "  rpc {}({}{}) returns ({}{})",
+                    method.name,
+                    stream(method.client_streaming),
+                    method.input,
+                    stream(method.server_streaming),
+                    method.output
+                ));
+                for name in [&method.input, &method.output] {
+                    if !types.contains(name) {
+                        types.push(name.clone());
+                    }
+                }
+            }
+            text.push(String::new());
+        }
+        for name in types {
+            let Some(message) = self.messages.get(&name) else {
+                continue;
+            };
+            text.push(format!("message {name}"));
+            let kind_name = |kind: &Kind| match kind {
+                Kind::Scalar(scalar) => scalar.name().to_string(),
+                Kind::Message(name) | Kind::Enum(name) | Kind::Unresolved(_, name) => name.clone(),
+            };
+            for field in &message.fields {
+                let entry = match &field.kind {
+                    Kind::Message(entry) => self.messages.get(entry).filter(|m| m.map_entry),
+                    _ => None,
+                };
+                let kind = match entry.map(|entry| entry.fields.as_slice()) {
+                    Some([key, value]) => {
+                        format!("map<{}, {}>", kind_name(&key.kind), kind_name(&value.kind))
+                    }
+                    _ if field.repeated => format!("repeated {}", kind_name(&field.kind)),
+                    _ => kind_name(&field.kind),
+                };
+                text.push(format!("  {kind} {} = {}", field.name, field.number));
+            }
+            let example = serde_json::to_string_pretty(&self.example(&name, 0)).unwrap_or_default();
+            text.push(format!("  example {}", example.replace('\n', "\n  ")));
+            text.push(String::new());
+        }
+        text.join("\n").trim_end().to_string()
+    }
+
+    /// A message with every field at its default, to fill in
+    pub fn example(&self, name: &str, depth: usize) -> Value {
+        let Some(message) = self.messages.get(name) else {
+            return Value::Object(Map::new());
+        };
+        let mut object = Map::new();
+        for field in &message.fields {
+            let value = match &field.kind {
+                Kind::Message(name) if self.messages.get(name).is_some_and(|m| m.map_entry) => {
+                    Value::Object(Map::new())
+                }
+                _ if field.repeated => Value::Array(Vec::new()),
+                Kind::Message(_) if depth >= EXAMPLE_DEPTH => Value::Object(Map::new()),
+                Kind::Message(name) => self.example(name, depth + 1),
+                Kind::Enum(name) => self
+                    .enums
+                    .get(name)
+                    .and_then(|enumeration| enumeration.values.first())
+                    .map(|(value, _)| Value::from(value.clone()))
+                    .unwrap_or(Value::from(0)),
+                Kind::Scalar(Scalar::Bool) => Value::from(false),
+                Kind::Scalar(Scalar::String | Scalar::Bytes) => Value::from(""),
+                Kind::Scalar(scalar) if scalar.is_64_bit() => Value::from("0"),
+                Kind::Scalar(_) | Kind::Unresolved(..) => Value::from(0),
+            };
+            object.insert(field.json_name.clone(), value);
+        }
+        Value::Object(object)
+    }
+
+    /// The message `name` as proto3 JSON, fields not in the schema are left out
+    pub fn decode(&self, name: &str, bytes: &[u8]) -> Result<Value, String> {
+        let message = self.message(name)?;
+        let mut object = Map::new();
+        for (number, wire) in wire_fields(bytes)? {
+            let Some(field) = message.fields.iter().find(|field| field.number == number) else {
+                continue;
+            };
+            let values = match (&field.kind, wire) {
+                // Packed repeated numbers
+                (Kind::Scalar(scalar), Wire::Bytes(bytes)) if scalar.packable() => {
+                    packed(*scalar, bytes)?
+                }
+                (Kind::Enum(_), Wire::Bytes(bytes)) => packed(Scalar::Int32, bytes)?,
+                (kind, wire) => vec![self.decode_value(kind, wire)?],
+            };
+            let mut values = values.into_iter().map(|value| match &field.kind {
+                Kind::Enum(name) => self.enum_name(name, value),
+                _ => value,
+            });
+            let entry = object.entry(field.json_name.clone());
+            match &field.kind {
+                Kind::Message(entry_type)
+                    if self.messages.get(entry_type).is_some_and(|m| m.map_entry) =>
+                {
+                    let map = entry.or_insert_with(|| Value::Object(Map::new()));
+                    for value in values {
+                        let key = match value.get("key") {
+                            Some(Value::String(key)) => key.clone(),
+                            Some(key) => key.to_string(),
+                            None => String::new(),
+                        };
+                        let value = value.get("value").cloned().unwrap_or(Value::Null);
+                        if let Some(map) = map.as_object_mut() {
+                            map.insert(key, value);
+                        }
+                    }
+                }
+                _ if field.repeated => {
+                    if let Some(array) = entry
+                        .or_insert_with(|| Value::Array(Vec::new()))
+                        .as_array_mut()
+                    {
+                        array.extend(values);
+                    }
+                }
+                _ => {
+                    if let Some(value) = values.next_back() {
+                        *entry.or_insert(Value::Null) = value;
+                    }
+                }
+            }
+        }
+        Ok(Value::Object(object))
+    }
+
+    fn enum_name(&self, name: &str, value: Value) -> Value {
+        let number = value.as_i64().unwrap_or_default() as i32;
+        self.enums
+            .get(name)
+            .and_then(|enumeration| enumeration.values.iter().find(|(_, n)| *n == number))
+            .map(|(name, _)| Value::from(name.clone()))
+            .unwrap_or(value)
+    }
+
+    fn decode_value(&self, kind: &Kind, wire: Wire) -> Result<Value, String> {
+        let scalar = match kind {
+            Kind::Message(name) => {
+                let Wire::Bytes(bytes) = wire else {
+                    return Err(format!("{name} isn't length delimited"));
+                };
+                return self.decode(name, bytes);
+            }
+            Kind::Enum(_) => Scalar::Int32,
+            Kind::Scalar(scalar) => *scalar,
+            Kind::Unresolved(_, name) => return Err(format!("unresolved type `{name}`")),
+        };
+        let value = match (scalar, wire) {
+            (Scalar::Int32, Wire::Varint(v)) => Value::from(v as i32),
+            (Scalar::Int64, Wire::Varint(v)) => Value::from((v as i64).to_string()),
+            (Scalar::Uint32, Wire::Varint(v)) => Value::from(v as u32),
+            (Scalar::Uint64, Wire::Varint(v)) => Value::from(v.to_string()),
+            (Scalar::Sint32, Wire::Varint(v)) => Value::from(unzigzag(v) as i32),
+            (Scalar::Sint64, Wire::Varint(v)) => Value::from(unzigzag(v).to_string()),
+            (Scalar::Bool, Wire::Varint(v)) => Value::from(v != 0),
+            (Scalar::Fixed32, Wire::Fixed32(v)) => Value::from(v),
+            (Scalar::Sfixed32, Wire::Fixed32(v)) => Value::from(v as i32),
+            (Scalar::Float, Wire::Fixed32(v)) => float(f32::from_bits(v) as f64),
+            (Scalar::Fixed64, Wire::Fixed64(v)) => Value::from(v.to_string()),
+            (Scalar::Sfixed64, Wire::Fixed64(v)) => Value::from((v as i64).to_string()),
+            (Scalar::Double, Wire::Fixed64(v)) => float(f64::from_bits(v)),
+            (Scalar::String, Wire::Bytes(bytes)) => Value::from(string(bytes)),
+            (Scalar::Bytes, Wire::Bytes(bytes)) => Value::from(STANDARD.encode(bytes)),
+            (scalar, _) => return Err(format!("{} has the wrong wire type", scalar.name())),
+        };
+        Ok(value)
+    }
+
+    /// The JSON `value` as the message `name`, fields go by their proto or
+    /// JSON names
+    pub fn encode(&self, name: &str, value: &Value) -> Result<Vec<u8>, String> {
+        self.encode_message(name, value, "")
+    }
+
+    fn encode_message(&self, name: &str, value: &Value, path: &str) -> Result<Vec<u8>, String> {
+        let message = self.message(name)?;
+        let object = match value {
+            Value::Object(object) => object,
+            Value::Null => return Ok(Vec::new()),
+            _ => return Err(format!("`{}`: expected an object", display_path(path))),
+        };
+        let mut bytes = Vec::new();
+        for (key, value) in object {
+            let path = join(path, key);
+            let field = message
+                .fields
+                .iter()
+                .find(|field| field.name == *key || field.json_name == *key)
+                .ok_or_else(|| format!("unknown field `{path}` in {name}"))?;
+            if value.is_null() {
+                continue;
+            }
+            let map_entry = match &field.kind {
+                Kind::Message(entry) if self.messages.get(entry).is_some_and(|m| m.map_entry) => {
+                    Some(entry)
+                }
+                _ => None,
+            };
+            match (map_entry, value) {
+                (Some(entry), Value::Object(map)) => {
+                    for (key, value) in map {
+                        let entry_value = serde_json::json!({ "key": key, "value": value });
+                        let encoded = self.encode_message(entry, &entry_value, &path)?;
+                        write_len(&mut bytes, field.number, &encoded);
+                    }
+                }
+                (Some(_), _) => return Err(format!("`{path}`: expected an object")),
+                (None, Value::Array(values)) if field.repeated => {
+                    let packed = field.packed
+                        && match &field.kind {
+                            Kind::Scalar(scalar) => scalar.packable(),
+                            kind => matches!(kind, Kind::Enum(_)),
+                        };
+                    if packed {
+                        let mut payload = Vec::new();
+                        for (index, value) in values.iter().enumerate() {
+                            let path = format!("{path}[{index}]");
+                            self.encode_value(&mut payload, None, &field.kind, value, &path)?;
+                        }
+                        write_len(&mut bytes, field.number, &payload);
+                    } else {
+                        for (index, value) in values.iter().enumerate() {
+                            let path = format!("{path}[{index}]");
+                            let number = Some(field.number);
+                            self.encode_value(&mut bytes, number, &field.kind, value, &path)?;
+                        }
+                    }
+                }
+                (None, _) if field.repeated => {
+                    return Err(format!("`{path}`: expected an array"));
+                }
+                (None, value) => {
+                    self.encode_value(&mut bytes, Some(field.number), &field.kind, value, &path)?
+                }
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Writes a value with its tag, or bare inside a packed field when
+    /// `number` is `None`
+    fn encode_value(
+        &self,
+        bytes: &mut Vec<u8>,
+        number: Option<u32>,
+        kind: &Kind,
+        value: &Value,
+        path: &str,
+    ) -> Result<(), String> {
+        let tag = |bytes: &mut Vec<u8>, wire_type: u64| {
+            if let Some(number) = number {
+                write_varint(bytes, (number as u64) << 3 | wire_type);
+            }
+        };
+        let bad = |expected: &str| format!("`{}`: expected {expected}", display_path(path));
+        let scalar = match kind {
+            Kind::Message(name) => {
+                let encoded = self.encode_message(name, value, path)?;
+                tag(bytes, 2);
+                write_varint(bytes, encoded.len() as u64);
+                bytes.extend_from_slice(&encoded);
+                return Ok(());
+            }
+            Kind::Enum(name) => {
+                let number = match value {
+                    Value::String(value) => self
+                        .enums
+                        .get(name)
+                        .and_then(|e| e.values.iter().find(|(n, _)| n == value))
+                        .map(|(_, number)| *number)
+                        .ok_or_else(|| bad(&format!("a value of {name}")))?,
+                    value => value
+                        .as_i64()
+                        .ok_or_else(|| bad("an enum name or number"))?
+                        as i32,
+                };
+                tag(bytes, 0);
+                write_varint(bytes, number as i64 as u64);
+                return Ok(());
+            }
+            Kind::Scalar(scalar) => *scalar,
+            Kind::Unresolved(_, name) => return Err(format!("unresolved type `{name}`")),
+        };
+        // Numbers can also be given as strings, which 64-bit ones are in JSON
+        let integer = || match value {
+            Value::Number(number) => number
+                .as_i64()
+                .or_else(|| number.as_u64().map(|n| n as i64)),
+            Value::String(text) => text
+                .parse::<i64>()
+                .ok()
+                .or_else(|| text.parse::<u64>().ok().map(|n| n as i64)),
+            _ => None,
+        };
+        let decimal = || match value {
+            Value::Number(number) => number.as_f64(),
+            Value::String(text) => match text.as_str() {
+                "NaN" => Some(f64::NAN),
+                "Infinity" => Some(f64::INFINITY),
+                "-Infinity" => Some(f64::NEG_INFINITY),
+                text => text.parse().ok(),
+            },
+            _ => None,
+        };
+        match scalar {
+            Scalar::Int32 | Scalar::Int64 | Scalar::Uint32 | Scalar::Uint64 => {
+                let n = integer().ok_or_else(|| bad("an integer"))?;
+                tag(bytes, 0);
+                write_varint(bytes, n as u64);
+            }
+            Scalar::Sint32 | Scalar::Sint64 => {
+                let n = integer().ok_or_else(|| bad("an integer"))?;
+                tag(bytes, 0);
+                write_varint(bytes, ((n << 1) ^ (n >> 63)) as u64);
+            }
+            Scalar::Bool => {
+                let b = value.as_bool().ok_or_else(|| bad("true or false"))?;
+                tag(bytes, 0);
+                write_varint(bytes, b as u64);
+            }
+            Scalar::Fixed32 | Scalar::Sfixed32 => {
+                let n = integer().ok_or_else(|| bad("an integer"))?;
+                tag(bytes, 5);
+                bytes.extend_from_slice(&(n as u32).to_le_bytes());
+            }
+            Scalar::Fixed64 | Scalar::Sfixed64 => {
+                let n = integer().ok_or_else(|| bad("an integer"))?;
+                tag(bytes, 1);
+                bytes.extend_from_slice(&(n as u64).to_le_bytes());
+            }
+            Scalar::Float => {
+                let n = decimal().ok_or_else(|| bad("a number"))?;
+                tag(bytes, 5);
+                bytes.extend_from_slice(&(n as f32).to_le_bytes());
+            }
+            Scalar::Double => {
+                let n = decimal().ok_or_else(|| bad("a number"))?;
+                tag(bytes, 1);
+                bytes.extend_from_slice(&n.to_le_bytes());
+            }
+            Scalar::String => {
+                let text = value.as_str().ok_or_else(|| bad("a string"))?;
+                tag(bytes, 2);
+                write_varint(bytes, text.len() as u64);
+                bytes.extend_from_slice(text.as_bytes());
+            }
+            Scalar::Bytes => {
+                let text = value.as_str().ok_or_else(|| bad("a base64 string"))?;
+                let decoded = STANDARD.decode(text).map_err(|_| bad("a base64 string"))?;
+                tag(bytes, 2);
+                write_varint(bytes, decoded.len() as u64);
+                bytes.extend_from_slice(&decoded);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn display_path(path: &str) -> &str {
+    match path.is_empty() {
+        true => "message",
+        false => path,
+    }
+}
+
+fn float(value: f64) -> Value {
+    match Number::from_f64(value) {
+        Some(number) => Value::Number(number),
+        None if value.is_nan() => Value::from("NaN"),
+        None if value > 0.0 => Value::from("Infinity"),
+        None => Value::from("-Infinity"),
+    }
+}
+
+fn string(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// A field's value as it is on the wire
+#[derive(Clone, Copy)]
+pub enum Wire<'b> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'b [u8]),
+    Fixed32(u32),
+}
+
+fn read_varint(bytes: &[u8], at: &mut usize) -> Result<u64, String> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*at).ok_or("truncated varint")?;
+        *at += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("varint is too long".to_string())
+}
+
+fn take<'b>(bytes: &'b [u8], at: &mut usize, length: usize) -> Result<&'b [u8], String> {
+    let end = at
+        .checked_add(length)
+        .filter(|end| *end <= bytes.len())
+        .ok_or("truncated message")?;
+    let taken = &bytes[*at..end];
+    *at = end;
+    Ok(taken)
+}
+
+/// The field numbers and values of an encoded message, in order
+pub fn wire_fields(bytes: &[u8]) -> Result<Vec<(u32, Wire<'_>)>, String> {
+    let mut fields = Vec::new();
+    let mut at = 0;
+    while at < bytes.len() {
+        let key = read_varint(bytes, &mut at)?;
+        let number = (key >> 3) as u32;
+        let wire = match key & 7 {
+            0 => Wire::Varint(read_varint(bytes, &mut at)?),
+            1 => Wire::Fixed64(u64::from_le_bytes(
+                take(bytes, &mut at, 8)?.try_into().unwrap_or_default(),
+            )),
+            2 => {
+                let length = read_varint(bytes, &mut at)? as usize;
+                Wire::Bytes(take(bytes, &mut at, length)?)
+            }
+            5 => Wire::Fixed32(u32::from_le_bytes(
+                take(bytes, &mut at, 4)?.try_into().unwrap_or_default(),
+            )),
+            wire_type => return Err(format!("unsupported wire type {wire_type}")),
+        };
+        fields.push((number, wire));
+    }
+    Ok(fields)
+}
+
+/// The values of a packed repeated field
+fn packed(scalar: Scalar, bytes: &[u8]) -> Result<Vec<Value>, String> {
+    let kind = Kind::Scalar(scalar);
+    let schema = Schema::default();
+    let mut values = Vec::new();
+    let mut at = 0;
+    while at < bytes.len() {
+        let wire = match scalar {
+            Scalar::Fixed32 | Scalar::Sfixed32 | Scalar::Float => Wire::Fixed32(
+                u32::from_le_bytes(take(bytes, &mut at, 4)?.try_into().unwrap_or_default()),
+            ),
+            Scalar::Fixed64 | Scalar::Sfixed64 | Scalar::Double => Wire::Fixed64(
+                u64::from_le_bytes(take(bytes, &mut at, 8)?.try_into().unwrap_or_default()),
+            ),
+            _ => Wire::Varint(read_varint(bytes, &mut at)?),
+        };
+        values.push(schema.decode_value(&kind, wire)?);
+    }
+    Ok(values)
+}
+
+pub fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// A length delimited field
+pub fn write_len(bytes: &mut Vec<u8>, number: u32, payload: &[u8]) {
+    write_varint(bytes, (number as u64) << 3 | 2);
+    write_varint(bytes, payload.len() as u64);
+    bytes.extend_from_slice(payload);
+}
+
+fn field_descriptor(scope: &str, bytes: &[u8], proto3: bool) -> Result<Field, String> {
+    let (mut name, mut json, mut number, mut repeated) = (String::new(), None, 0, false);
+    let (mut kind, mut type_name, mut packed) = (None, String::new(), None);
+    for (field, value) in wire_fields(bytes)? {
+        match (field, value) {
+            (1, Wire::Bytes(bytes)) => name = string(bytes),
+            (3, Wire::Varint(value)) => number = value as u32,
+            (4, Wire::Varint(label)) => repeated = label == 3,
+            (5, Wire::Varint(value)) => kind = Some(value),
+            (6, Wire::Bytes(bytes)) => type_name = string(bytes),
+            (10, Wire::Bytes(bytes)) => json = Some(string(bytes)),
+            // FieldOptions.packed
+            (8, Wire::Bytes(bytes)) => {
+                for option in wire_fields(bytes)? {
+                    if let (2, Wire::Varint(value)) = option {
+                        packed = Some(value != 0);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    let kind = match kind.and_then(Scalar::from_descriptor) {
+        Some(scalar) => Kind::Scalar(scalar),
+        None => Kind::Unresolved(scope.to_string(), type_name),
+    };
+    Ok(Field {
+        json_name: json.unwrap_or_else(|| json_name(&name)),
+        name,
+        number,
+        kind,
+        repeated,
+        packed: repeated && packed.unwrap_or(proto3),
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Int(i64),
+    Str(String),
+    /// Floats and whatever else only shows up in options
+    Other(String),
+    Symbol(char),
+}
+
+/// Reads `.proto` files into a `Schema`
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+    package: String,
+    proto3: bool,
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut at = 0;
+    while at < chars.len() {
+        let c = chars[at];
+        match c {
+            c if c.is_whitespace() => at += 1,
+            '/' if chars.get(at + 1) == Some(&'/') => {
+                while at < chars.len() && chars[at] != '\n' {
+                    at += 1;
+                }
+            }
+            '/' if chars.get(at + 1) == Some(&'*') => {
+                at += 2;
+                while at + 1 < chars.len() && !(chars[at] == '*' && chars[at + 1] == '/') {
+                    at += 1;
+                }
+                at += 2;
+            }
+            '"' | '\'' => {
+                let mut text = String::new();
+                at += 1;
+                while at < chars.len() && chars[at] != c {
+                    if chars[at] == '\\' {
+                        at += 1;
+                    }
+                    if let Some(&next) = chars.get(at) {
+                        text.push(next);
+                    }
+                    at += 1;
+                }
+                at += 1;
+                tokens.push(Token::Str(text));
+            }
+            // Fully qualified names start with a dot
+            c if c.is_alphabetic()
+                || c == '_'
+                || c == '.' && chars.get(at + 1).is_some_and(|c| c.is_alphabetic()) =>
+            {
+                let start = at;
+                while at < chars.len()
+                    && (chars[at].is_alphanumeric() || chars[at] == '_' || chars[at] == '.')
+                {
+                    at += 1;
+                }
+                tokens.push(Token::Ident(chars[start..at].iter().collect()));
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => {
+                let start = at;
+                at += 1;
+                while at < chars.len() && (chars[at].is_alphanumeric() || chars[at] == '.') {
+                    at += 1;
+                }
+                let text: String = chars[start..at].iter().collect();
+                let number = match text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+                    Some(hex) => i64::from_str_radix(hex, 16).ok(),
+                    None => text.parse().ok(),
+                };
+                tokens.push(match number {
+                    Some(number) => Token::Int(number),
+                    None => Token::Other(text),
+                });
+            }
+            c => {
+                tokens.push(Token::Symbol(c));
+                at += 1;
+            }
+        }
+    }
+    tokens
+}
+
+impl Parser {
+    fn new(source: &str) -> Self {
+        Self {
+            tokens: tokenize(source),
+            at: 0,
+            package: String::new(),
+            proto3: false,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self
+            .tokens
+            .get(self.at)
+            .cloned()
+            .ok_or("unexpected end of file")?;
+        self.at += 1;
+        Ok(token)
+    }
+
+    fn ident(&mut self) -> Result<String, String> {
+        match self.next()? {
+            Token::Ident(ident) => Ok(ident),
+            token => Err(format!("expected a name, found {token:?}")),
+        }
+    }
+
+    fn int(&mut self) -> Result<i64, String> {
+        match self.next()? {
+            Token::Int(number) => Ok(number),
+            token => Err(format!("expected a number, found {token:?}")),
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        match self.next()? {
+            Token::Symbol(found) if found == symbol => Ok(()),
+            token => Err(format!("expected `{symbol}`, found {token:?}")),
+        }
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        let found = self.peek() == Some(&Token::Symbol(symbol));
+        if found {
+            self.at += 1;
+        }
+        found
+    }
+
+    fn eat_ident(&mut self, ident: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Ident(found)) if found == ident);
+        if found {
+            self.at += 1;
+        }
+        found
+    }
+
+    /// Skips to the end of a statement, or past a block
+    fn skip(&mut self) -> Result<(), String> {
+        let mut depth = 0;
+        loop {
+            match self.next()? {
+                Token::Symbol(';') if depth == 0 => return Ok(()),
+                Token::Symbol('{') => depth += 1,
+                Token::Symbol('}') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Parses the file into `schema`, returning what it imports
+    fn file(&mut self, schema: &mut Schema) -> Result<Vec<String>, String> {
+        let mut imports = Vec::new();
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::Ident(keyword) => {
+                    self.at += 1;
+                    match keyword.as_str() {
+                        "syntax" => {
+                            self.expect('=')?;
+                            self.proto3 = self.next()? == Token::Str("proto3".to_string());
+                            self.expect(';')?;
+                        }
+                        "package" => {
+                            self.package = self.ident()?;
+                            self.expect(';')?;
+                        }
+                        "import" => {
+                            let _ = self.eat_ident("public") || self.eat_ident("weak");
+                            match self.next()? {
+                                Token::Str(path) => imports.push(path),
+                                token => return Err(format!("expected a path, found {token:?}")),
+                            }
+                            self.expect(';')?;
+                        }
+                        "message" => {
+                            let scope = self.package.clone();
+                            self.message(schema, &scope)?;
+                        }
+                        "enum" => {
+                            let scope = self.package.clone();
+                            self.enumeration(schema, &scope)?;
+                        }
+                        "service" => self.service(schema)?,
+                        _ => self.skip()?,
+                    }
+                }
+                Token::Symbol(';') => self.at += 1,
+                token => return Err(format!("unexpected {token:?}")),
+            }
+        }
+        Ok(imports)
+    }
+
+    fn message(&mut self, schema: &mut Schema, scope: &str) -> Result<(), String> {
+        let name = join(scope, &self.ident()?);
+        self.expect('{')?;
+        let mut message = MessageType::default();
+        self.message_body(schema, &name, &mut message)?;
+        schema.messages.insert(name, message);
+        Ok(())
+    }
+
+    /// Fields and nested definitions up to the closing brace, oneofs add
+    /// theirs to the message they're in
+    fn message_body(
+        &mut self,
+        schema: &mut Schema,
+        name: &str,
+        message: &mut MessageType,
+    ) -> Result<(), String> {
+        while !self.eat('}') {
+            if self.eat(';') {
+                continue;
+            }
+            let keyword = self.ident()?;
+            match keyword.as_str() {
+                "message" => self.message(schema, name)?,
+                "enum" => self.enumeration(schema, name)?,
+                "oneof" => {
+                    self.ident()?;
+                    self.expect('{')?;
+                    self.message_body(schema, name, message)?;
+                }
+                "option" | "reserved" | "extensions" | "extend" => self.skip()?,
+                "map" => {
+                    self.expect('<')?;
+                    let key = self.ident()?;
+                    self.expect(',')?;
+                    let value = self.ident()?;
+                    self.expect('>')?;
+                    let field = self.ident()?;
+                    self.expect('=')?;
+                    let number = self.int()? as u32;
+                    self.field_options()?;
+                    // What protoc generates for a map field
+                    let mut entry_name = json_name(&format!("_{field}"));
+                    entry_name.push_str("Entry");
+                    let entry = join(name, &entry_name);
+                    let key_field = self.field("key", 1, &key, false, name);
+                    let value_field = self.field("value", 2, &value, false, name);
+                    schema.messages.insert(
+                        entry.clone(),
+                        MessageType {
+                            fields: vec![key_field, value_field],
+                            map_entry: true,
+                        },
+                    );
+                    message.fields.push(Field {
+                        json_name: json_name(&field),
+                        name: field,
+                        number,
+                        kind: Kind::Unresolved(String::new(), format!(".{entry}")),
+                        repeated: true,
+                        packed: false,
+                    });
+                }
+                _ => {
+                    let repeated = keyword == "repeated";
+                    let kind = match keyword.as_str() {
+                        "repeated" | "optional" | "required" => self.ident()?,
+                        _ => keyword,
+                    };
+                    let field = self.ident()?;
+                    self.expect('=')?;
+                    let number = self.int()? as u32;
+                    let (packed, json) = self.field_options()?;
+                    let mut field = self.field(&field, number, &kind, repeated, name);
+                    if let Some(json) = json {
+                        field.json_name = json;
+                    }
+                    field.packed = repeated
+                        && packed.unwrap_or(self.proto3)
+                        && !matches!(field.kind, Kind::Scalar(Scalar::String | Scalar::Bytes));
+                    message.fields.push(field);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn field(&self, name: &str, number: u32, kind: &str, repeated: bool, scope: &str) -> Field {
+        Field {
+            name: name.to_string(),
+            json_name: json_name(name),
+            number,
+            kind: match Scalar::parse(kind) {
+                Some(scalar) => Kind::Scalar(scalar),
+                None => Kind::Unresolved(scope.to_string(), kind.to_string()),
+            },
+            repeated,
+            packed: false,
+        }
+    }
+
+    /// `[packed = true, json_name = "x"]` up to the semicolon
+    fn field_options(&mut self) -> Result<(Option<bool>, Option<String>), String> {
+        let (mut packed, mut json) = (None, None);
+        if self.eat('[') {
+            while !self.eat(']') {
+                let option = match self.next()? {
+                    Token::Ident(option) => option,
+                    Token::Symbol('(') => {
+                        // Custom options, `(name).field = value`
+                        while !self.eat(')') {
+                            self.next()?;
+                        }
+                        String::new()
+                    }
+                    _ => continue,
+                };
+                if !self.eat('=') {
+                    continue;
+                }
+                match (option.as_str(), self.next()?) {
+                    ("packed", Token::Ident(value)) => packed = Some(value == "true"),
+                    ("json_name", Token::Str(value)) => json = Some(value),
+                    (_, Token::Symbol('{')) => {
+                        self.at -= 1;
+                        self.skip()?;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        self.expect(';')?;
+        Ok((packed, json))
+    }
+
+    fn enumeration(&mut self, schema: &mut Schema, scope: &str) -> Result<(), String> {
+        let name = join(scope, &self.ident()?);
+        self.expect('{')?;
+        let mut enumeration = EnumType::default();
+        while !self.eat('}') {
+            if self.eat(';') {
+                continue;
+            }
+            let value = self.ident()?;
+            if value == "option" || value == "reserved" {
+                self.skip()?;
+                continue;
+            }
+            self.expect('=')?;
+            let number = self.int()? as i32;
+            self.field_options()?;
+            enumeration.values.push((value, number));
+        }
+        schema.enums.insert(name, enumeration);
+        Ok(())
+    }
+
+    fn service(&mut self, schema: &mut Schema) -> Result<(), String> {
+        let name = self.ident()?;
+        let name = join(&self.package, &name);
+        self.expect('{')?;
+        let mut methods = Vec::new();
+        while !self.eat('}') {
+            if self.eat(';') {
+                continue;
+            }
+            if !self.eat_ident("rpc") {
+                self.skip()?;
+                continue;
+            }
+            let method = self.ident()?;
+            let mut types = Vec::new();
+            for keyword in ["", "returns"] {
+                if !keyword.is_empty() && !self.eat_ident(keyword) {
+                    return Err(format!("expected `{keyword}` in rpc {method}"));
+                }
+                self.expect('(')?;
+                let streaming = self.eat_ident("stream");
+                types.push((streaming, self.ident()?));
+                self.expect(')')?;
+            }
+            match self.peek() {
+                Some(Token::Symbol('{')) => self.skip()?,
+                _ => self.expect(';')?,
+            }
+            let (client_streaming, input) = types.remove(0);
+            let (server_streaming, output) = types.remove(0);
+            methods.push(Method {
+                name: method,
+                input,
+                output,
+                client_streaming,
+                server_streaming,
+            });
+        }
+        schema.services.push(Service {
+            name,
+            methods,
+            scope: self.package.clone(),
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const COMMON: &str = r#"
+        syntax = "proto3";
+        package common;
+
+        enum Status {
+          STATUS_UNKNOWN = 0;
+          STATUS_ACTIVE = 1;
+        }
+    "#;
+
+    const USERS: &str = r#"
+        syntax = "proto3";
+        package users;
+
+        import "common.proto";
+
+        // Nested types can be used before they're declared
+        message User {
+          string user_name = 1;
+          sint64 balance = 2;
+          repeated int32 scores = 3;
+          common.Status status = 4;
+          Address address = 5;
+          map<string, string> labels = 6;
+
+          message Address {
+            string city = 1;
+          }
+        }
+
+        service Users {
+          rpc Get (User) returns (stream User);
+        }
+    "#;
+
+    /// `users.proto` and the `common.proto` it imports, from a directory of
+    /// their own
+    fn schema() -> Schema {
+        let dir = std::env::temp_dir().join(format!("curl-rs-protobuf-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("common.proto"), COMMON).unwrap();
+        std::fs::write(dir.join("users.proto"), USERS).unwrap();
+        let schema = Schema::load(&dir.join("users.proto"), &[]);
+        std::fs::remove_dir_all(&dir).unwrap();
+        schema.unwrap()
+    }
+
+    #[test]
+    fn varint_round_trip() {
+        let mut bytes = Vec::new();
+        write_varint(&mut bytes, 300);
+        assert_eq!(bytes, [0xac, 0x02]);
+        for value in [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut bytes = Vec::new();
+            write_varint(&mut bytes, value);
+            assert_eq!(read_varint(&bytes, &mut 0), Ok(value));
+        }
+        assert!(read_varint(&[0x80], &mut 0).is_err());
+    }
+
+    #[test]
+    fn zigzag_round_trip() {
+        let schema = schema();
+        assert_eq!(
+            schema.encode("users.User", &json!({ "balance": -1 })),
+            Ok(vec![0x10, 1])
+        );
+        assert_eq!(
+            schema.encode("users.User", &json!({ "balance": 1 })),
+            Ok(vec![0x10, 2])
+        );
+        for balance in [0, -64, 64, i64::MIN, i64::MAX] {
+            let value = json!({ "balance": balance.to_string() });
+            let bytes = schema.encode("users.User", &value).unwrap();
+            assert_eq!(schema.decode("users.User", &bytes), Ok(value));
+        }
+    }
+
+    #[test]
+    fn packed_round_trip() {
+        let schema = schema();
+        let value = json!({ "scores": [1, 300, -1] });
+        let bytes = schema.encode("users.User", &value).unwrap();
+        // One length delimited field 3 holding all three
+        assert_eq!(bytes[..5], [0x1a, 13, 1, 0xac, 0x02]);
+        assert_eq!(schema.decode("users.User", &bytes), Ok(value));
+
+        // Unpacked values are read as well
+        let bytes = [0x18, 1, 0x18, 2];
+        let decoded = schema.decode("users.User", &bytes).unwrap();
+        assert_eq!(decoded, json!({ "scores": [1, 2] }));
+    }
+
+    #[test]
+    fn nested_round_trip() {
+        let schema = schema();
+        let value = json!({
+            "userName": "ada",
+            "status": "STATUS_ACTIVE",
+            "address": { "city": "Oslo" },
+            "labels": { "team": "core" },
+        });
+        let bytes = schema.encode("users.User", &value).unwrap();
+        assert_eq!(schema.decode("users.User", &bytes), Ok(value));
+        // Proto field names are accepted too
+        let bytes = schema
+            .encode("users.User", &json!({ "user_name": "ada" }))
+            .unwrap();
+        assert_eq!(bytes, [0x0a, 3, b'a', b'd', b'a']);
+        assert!(schema.encode("users.User", &json!({ "nope": 1 })).is_err());
+    }
+
+    #[test]
+    fn parses_proto() {
+        let schema = schema();
+        let user = &schema.messages["users.User"];
+        let kinds: Vec<_> = user
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), format!("{:?}", field.kind)))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("user_name", "Scalar(String)".to_string()),
+                ("balance", "Scalar(Sint64)".to_string()),
+                ("scores", "Scalar(Int32)".to_string()),
+                ("status", "Enum(\"common.Status\")".to_string()),
+                ("address", "Message(\"users.User.Address\")".to_string()),
+                ("labels", "Message(\"users.User.LabelsEntry\")".to_string()),
+            ]
+        );
+        assert!(user.fields[2].packed);
+        assert!(schema.messages["users.User.LabelsEntry"].map_entry);
+        assert_eq!(
+            schema.enums["common.Status"].values,
+            [
+                ("STATUS_UNKNOWN".to_string(), 0),
+                ("STATUS_ACTIVE".to_string(), 1)
+            ]
+        );
+
+        let method = schema.method("users.Users/Get").unwrap();
+        assert_eq!(
+            (method.input.as_str(), method.output.as_str()),
+            ("users.User", "users.User")
+        );
+        assert!(!method.client_streaming && method.server_streaming);
+    }
+
+    #[test]
+    fn missing_import() {
+        let mut schema = Schema::default();
+        let imports = Parser::new(USERS).file(&mut schema).unwrap();
+        assert_eq!(imports, ["common.proto"]);
+        assert!(schema.resolve().is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::{
+    header::{HeaderName, HeaderValue, AUTHORIZATION},
+    Request, Url,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{models::RequestError, utils::hex};
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+pub struct Credentials {
+    pub access_key: String,
+    pub secret_key: String,
+    pub session_token: String,
+    pub region: String,
+    pub service: String,
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent encodes everything but the RFC 3986 unreserved characters
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn insert(request: &mut Request, name: &'static str, value: &str) -> Result<(), RequestError> {
+    let value = HeaderValue::from_str(value)
+        .map_err(|_| RequestError::InvalidAuth(format!("bad value for `{name}`")))?;
+    request
+        .headers_mut()
+        .insert(HeaderName::from_static(name), value);
+    Ok(())
+}
+
+/// The canonical request, and the names of the headers it signs. Header
+/// names are lowercase, as `HeaderName`s are
+fn canonical_request(
+    method: &str,
+    url: &Url,
+    mut headers: Vec<(String, String)>,
+    payload_hash: &str,
+) -> (String, String) {
+    for (_, value) in &mut headers {
+        *value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    }
+    headers.sort();
+
+    let mut canonical_headers = String::new();
+    for (name, value) in &headers {
+        canonical_headers.push_str(&format!("{name}:{value}\n"));
+    }
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let mut query: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| (encode(&key), encode(&value)))
+        .collect();
+    query.sort();
+    let query = query
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let canonical_request = format!(
+        "{method}\n{}\n{query}\n{canonical_headers}\n{signed_headers}\n{payload_hash}",
+        url.path(),
+    );
+    (canonical_request, signed_headers)
+}
+
+impl Credentials {
+    /// Signs the request as it is about to be sent, `streamed` bodies are
+    /// sent as `UNSIGNED-PAYLOAD`
+    pub fn sign(&self, request: &mut Request, streamed: bool) -> Result<(), RequestError> {
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+
+        let payload_hash = match request.body() {
+            _ if streamed => "UNSIGNED-PAYLOAD".to_string(),
+            Some(body) => match body.as_bytes() {
+                Some(bytes) => hex(&Sha256::digest(bytes)),
+                None => "UNSIGNED-PAYLOAD".to_string(),
+            },
+            None => hex(&Sha256::digest([])),
+        };
+
+        let host = match (request.url().host_str(), request.url().port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(RequestError::InvalidUri),
+        };
+        insert(request, "x-amz-date", &amz_date)?;
+        insert(request, "x-amz-content-sha256", &payload_hash)?;
+        if !self.session_token.is_empty() {
+            insert(request, "x-amz-security-token", &self.session_token)?;
+        }
+
+        let mut headers: Vec<(String, String)> = request
+            .headers()
+            .iter()
+            .filter(|(name, _)| *name != AUTHORIZATION)
+            .map(|(name, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes());
+                (name.to_string(), value.into_owned())
+            })
+            .collect();
+        headers.push(("host".to_string(), host));
+        let (canonical_request, signed_headers) = canonical_request(
+            request.method().as_str(),
+            request.url(),
+            headers,
+            &payload_hash,
+        );
+        let (scope, signature) = self.signature(&canonical_request, now);
+
+        let mut authorization = HeaderValue::from_str(&format!(
+            "{ALGORITHM} Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            self.access_key
+        ))
+        .map_err(|_| RequestError::InvalidAuth("bad aws access key".to_string()))?;
+        authorization.set_sensitive(true);
+        request.headers_mut().insert(AUTHORIZATION, authorization);
+        Ok(())
+    }
+
+    /// The credential scope and signature of `canonical_request` made at
+    /// `now`
+    fn signature(&self, canonical_request: &str, now: DateTime<Utc>) -> (String, String) {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let scope = format!("{date}/{}/{}/aws4_request", self.region, self.service);
+        let string_to_sign = format!(
+            "{ALGORITHM}\n{amz_date}\n{scope}\n{}",
+            hex(&Sha256::digest(canonical_request))
+        );
+
+        let mut key = hmac(format!("AWS4{}", self.secret_key).as_bytes(), &date);
+        for part in [self.region.as_str(), self.service.as_str(), "aws4_request"] {
+            key = hmac(&key, part);
+        }
+        (scope, hex(&hmac(&key, &string_to_sign)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    /// The credentials and time of AWS's SigV4 test suite
+    fn suite() -> (Credentials, DateTime<Utc>) {
+        let credentials = Credentials {
+            access_key: "AKIDEXAMPLE".to_string(),
+            secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: String::new(),
+            region: "us-east-1".to_string(),
+            service: "service".to_string(),
+        };
+        (
+            credentials,
+            Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap(),
+        )
+    }
+
+    /// The signature of a suite request without a body
+    fn sign(method: &str, url: &str) -> (String, String) {
+        let (credentials, now) = suite();
+        let headers = vec![
+            ("x-amz-date".to_string(), "20150830T123600Z".to_string()),
+            ("host".to_string(), "example.amazonaws.com".to_string()),
+        ];
+        let url = Url::parse(url).unwrap();
+        let payload_hash = hex(&Sha256::digest([]));
+        let (canonical_request, signed_headers) =
+            canonical_request(method, &url, headers, &payload_hash);
+        let (scope, signature) = credentials.signature(&canonical_request, now);
+        assert_eq!(signed_headers, "host;x-amz-date");
+        assert_eq!(scope, "20150830/us-east-1/service/aws4_request");
+        (canonical_request, signature)
+    }
+
+    #[test]
+    fn get_vanilla() {
+        let (canonical_request, signature) = sign("GET", "https://example.amazonaws.com/");
+        assert_eq!(
+            canonical_request,
+            "GET\n/\n\nhost:example.amazonaws.com\nx-amz-date:20150830T123600Z\n\n\
+             host;x-amz-date\ne3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            signature,
+            "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn get_vanilla_query_order_key_case() {
+        let url = "https://example.amazonaws.com/?Param2=value2&Param1=value1";
+        let (canonical_request, signature) = sign("GET", url);
+        assert!(canonical_request.starts_with("GET\n/\nParam1=value1&Param2=value2\n"));
+        assert_eq!(
+            signature,
+            "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"
+        );
+    }
+
+    #[test]
+    fn post_vanilla() {
+        let (_, signature) = sign("POST", "https://example.amazonaws.com/");
+        assert_eq!(
+            signature,
+            "5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b"
+        );
+    }
+
+    #[test]
+    fn signs_request() {
+        let (credentials, _) = suite();
+        let mut request = reqwest::Client::new()
+            .post("https://example.amazonaws.com:8443/path")
+            .header("x-custom", "  a   b ")
+            .body("body")
+            .build()
+            .unwrap();
+        credentials.sign(&mut request, false).unwrap();
+        let headers = request.headers();
+        assert_eq!(
+            headers["x-amz-content-sha256"],
+            hex(&Sha256::digest(b"body")).as_str()
+        );
+        let authorization = headers[AUTHORIZATION].to_str().unwrap();
+        assert!(authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/"));
+        assert!(authorization.contains(
+            "/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-custom, Signature="
+        ));
+    }
+}
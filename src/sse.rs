@@ -0,0 +1,131 @@
+use serde_json::Value;
+
+/// A dispatched server-sent event
+#[derive(Default)]
+pub struct Event {
+    /// `message` unless the stream named it
+    pub name: String,
+    /// The last event id, which carries over to the events after it
+    pub id: Option<String>,
+    pub data: String,
+    /// Reconnection time in milliseconds the server asked for
+    pub retry: Option<u64>,
+}
+
+/// Events as the EventSource spec dispatches them, a trailing one the
+/// blank line hasn't ended yet is left out
+pub fn parse(text: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    let mut id = None;
+    let mut current = Event::default();
+    let mut data: Option<String> = None;
+    // Lines can end in CRLF, LF or a lone CR
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let mut lines: Vec<&str> = text.split('\n').collect();
+    // What follows the last line break is still arriving
+    lines.pop();
+    for line in lines {
+        if line.is_empty() {
+            let retry = current.retry.take();
+            let name = std::mem::take(&mut current.name);
+            // Events without data aren't dispatched
+            if let Some(data) = data.take() {
+                events.push(Event {
+                    name: match name.is_empty() {
+                        true => "message".to_string(),
+                        false => name,
+                    },
+                    id: id.clone(),
+                    data,
+                    retry,
+                });
+            }
+            continue;
+        }
+        if line.starts_with(':') {
+            continue;
+        }
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => current.name = value.to_string(),
+            "data" => match &mut data {
+                Some(data) => {
+                    data.push('\n');
+                    data.push_str(value);
+                }
+                None => data = Some(value.to_string()),
+            },
+            "id" if !value.contains('\0') => id = Some(value.to_string()),
+            "retry" => current.retry = value.parse().ok().or(current.retry),
+            _ => {}
+        }
+    }
+    events
+}
+
+/// One block per event, a heading with its number, name and id and then its
+/// data, pretty printed when it's JSON
+pub fn render(events: &[Event]) -> String {
+    let blocks: Vec<String> = events
+        .iter()
+        .enumerate()
+        .map(|(index, event)| {
+            let mut heading = format!("#{} {}", index + 1, event.name);
+            if let Some(id) = &event.id {
+                heading.push_str(&format!("  id {id}"));
+            }
+            if let Some(retry) = event.retry {
+                heading.push_str(&format!("  retry {retry}ms"));
+            }
+            let data = serde_json::from_str::<Value>(&event.data)
+                .ok()
+                .filter(|value| value.is_object() || value.is_array())
+                .and_then(|value| serde_json::to_string_pretty(&value).ok())
+                .unwrap_or_else(|| event.data.clone());
+            format!("{heading}\n{data}")
+        })
+        .collect();
+    blocks.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dispatches_on_blank_lines() {
+        let text = ": comment\r\nevent: update\r\nid: 7\r\nretry: 3000\r\ndata: one\r\ndata:two\r\n\r\ndata: three\n\nid\nretry: soon\ndata\n\ndata: partial\n";
+        let events = parse(text);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].name, "update");
+        assert_eq!(events[0].id.as_deref(), Some("7"));
+        assert_eq!(events[0].data, "one\ntwo");
+        assert_eq!(events[0].retry, Some(3000));
+        // The id carries over, the name and retry don't
+        assert_eq!(events[1].name, "message");
+        assert_eq!(events[1].id.as_deref(), Some("7"));
+        assert_eq!(events[1].retry, None);
+        // An empty id resets it
+        assert_eq!(events[2].id.as_deref(), Some(""));
+        assert_eq!(events[2].data, "");
+        assert_eq!(events[2].retry, None);
+    }
+
+    #[test]
+    fn skips_events_without_data() {
+        let events = parse("event: ping\n\nid: 1\r\rdata: x\r\r");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "x");
+        assert_eq!(events[0].name, "message");
+    }
+
+    #[test]
+    fn renders_json_pretty() {
+        let events = parse("event: a\nid: 1\ndata: {\"n\":1}\n\ndata: 2\n\n");
+        assert_eq!(
+            render(&events),
+            "#1 a  id 1\n{\n  \"n\": 1\n}\n\n#2 message  id 1\n2"
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ratatui::style::Color;
+
+/// Colors the ui is drawn with, by what they're for
+pub struct Theme {
+    pub name: &'static str,
+    pub background: Color,
+    pub text: Color,
+    /// Hints, disabled rows and other secondary text
+    pub muted: Color,
+    /// Borders of the focused pane, labels and JSON keys
+    pub focus: Color,
+    /// Background of the selected tab or list item
+    pub selection: Color,
+    /// Prompts, redirects and numbers
+    pub accent: Color,
+    pub success: Color,
+    pub error: Color,
+    /// JSON `true`, `false` and `null`
+    pub literal: Color,
+}
+
+pub const THEMES: [Theme; 3] = [
+    Theme {
+        name: "dark",
+        background: Color::Black,
+        text: Color::White,
+        muted: Color::DarkGray,
+        focus: Color::Cyan,
+        selection: Color::Blue,
+        accent: Color::Yellow,
+        success: Color::Green,
+        error: Color::Red,
+        literal: Color::Magenta,
+    },
+    Theme {
+        name: "light",
+        background: Color::White,
+        text: Color::Black,
+        muted: Color::Gray,
+        focus: Color::Blue,
+        selection: Color::LightCyan,
+        accent: Color::Rgb(175, 95, 0),
+        success: Color::Rgb(0, 135, 0),
+        error: Color::Red,
+        literal: Color::Magenta,
+    },
+    Theme {
+        name: "solarized",
+        background: Color::Rgb(0, 43, 54),
+        text: Color::Rgb(131, 148, 150),
+        muted: Color::Rgb(88, 110, 117),
+        focus: Color::Rgb(42, 161, 152),
+        selection: Color::Rgb(7, 54, 66),
+        accent: Color::Rgb(181, 137, 0),
+        success: Color::Rgb(133, 153, 0),
+        error: Color::Rgb(220, 50, 47),
+        literal: Color::Rgb(211, 54, 130),
+    },
+];
+
+/// Index into `THEMES`, switchable while running
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+/// `default` is what the dark theme was called before there were others
+pub fn find(name: &str) -> Option<usize> {
+    match name {
+        "default" => Some(0),
+        name => THEMES.iter().position(|theme| theme.name == name),
+    }
+}
+
+pub fn current() -> &'static Theme {
+    &THEMES[CURRENT.load(Ordering::Relaxed)]
+}
+
+pub fn set(index: usize) {
+    CURRENT.store(index % THEMES.len(), Ordering::Relaxed);
+}
+
+/// Switches to the next theme, returning it
+pub fn next() -> &'static Theme {
+    set(CURRENT.load(Ordering::Relaxed) + 1);
+    current()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_themes() {
+        assert_eq!(find("default"), Some(0));
+        assert_eq!(find("dark"), Some(0));
+        assert_eq!(find("solarized"), Some(2));
+        assert_eq!(find("Solarized"), None);
+    }
+
+    #[test]
+    fn themes_are_readable() {
+        for theme in &THEMES {
+            let colors = [
+                theme.text,
+                theme.muted,
+                theme.focus,
+                theme.accent,
+                theme.error,
+            ];
+            assert!(!colors.contains(&theme.background), "{}", theme.name);
+            assert_ne!(theme.success, theme.error, "{}", theme.name);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use crate::net::Connection;
+
+/// The steps of the first exchange, redirects and resends after a 401 only
+/// count towards the total
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Timing {
+    /// `None` when the host is an ip address or the lookup didn't run
+    pub dns: Option<Duration>,
+    /// `None` when the connection wasn't timed
+    pub connect: Option<Duration>,
+    /// `None` for plain http
+    pub tls: Option<Duration>,
+    /// Until the headers of the first response arrived
+    pub first_byte: Duration,
+    pub total: Duration,
+}
+
+impl Timing {
+    /// The lookup, connect and handshake of `connection`
+    pub fn opened(connection: &Connection) -> Self {
+        Self {
+            dns: connection.dns,
+            connect: Some(connection.connect),
+            tls: connection.tls,
+            ..Self::default()
+        }
+    }
+
+    pub fn download(&self) -> Duration {
+        self.total.saturating_sub(self.first_byte)
+    }
+
+    /// Time spent waiting on the server once connected
+    pub fn wait(&self) -> Duration {
+        [self.dns, self.connect, self.tls]
+            .into_iter()
+            .flatten()
+            .fold(self.first_byte, Duration::saturating_sub)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wait_leaves_out_connecting() {
+        let ms = Duration::from_millis;
+        let timing = Timing {
+            dns: Some(ms(5)),
+            connect: Some(ms(10)),
+            tls: Some(ms(20)),
+            first_byte: ms(100),
+            total: ms(150),
+        };
+        assert_eq!(timing.wait(), ms(65));
+        assert_eq!(timing.download(), ms(50));
+
+        let plain = Timing {
+            first_byte: ms(30),
+            ..Timing::default()
+        };
+        assert_eq!(plain.wait(), ms(30));
+    }
+}
@@ -0,0 +1,423 @@
+use std::{
+    io::{self, Read, Write},
+    pin::Pin,
+    sync::Once,
+    task::{Context, Poll, Waker},
+};
+
+use openssl::{
+    pkcs12::Pkcs12,
+    pkey::{PKey, Private},
+    ssl::{ErrorCode, ShutdownResult, SslConnector, SslMethod, SslStream},
+    x509::{X509VerifyResult, X509},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::certs;
+
+/// A client certificate and its key, with the intermediates sent along
+#[derive(Clone)]
+pub struct Identity {
+    cert: X509,
+    chain: Vec<X509>,
+    key: PKey<Private>,
+}
+
+impl Identity {
+    /// PEM certificates, the first being the client's, and a PEM key
+    pub fn from_pem(certs: &[u8], key: &[u8]) -> Result<Self, String> {
+        let mut chain = X509::stack_from_pem(certs).map_err(|err| err.to_string())?;
+        if chain.is_empty() {
+            return Err("no certificate found".to_string());
+        }
+        let cert = chain.remove(0);
+        let key = PKey::private_key_from_pem(key).map_err(|err| err.to_string())?;
+        Ok(Self { cert, chain, key })
+    }
+
+    pub fn from_pkcs12(der: &[u8], password: &str) -> Result<Self, String> {
+        let parsed = Pkcs12::from_der(der)
+            .and_then(|pkcs12| pkcs12.parse2(password))
+            .map_err(|err| err.to_string())?;
+        match (parsed.cert, parsed.pkey) {
+            (Some(cert), Some(key)) => Ok(Self {
+                cert,
+                chain: parsed.ca.into_iter().flatten().collect(),
+                key,
+            }),
+            _ => Err("no certificate and key in the bundle".to_string()),
+        }
+    }
+}
+
+/// Verifying against the system's certificates, offering the ALPN
+/// `protocols` and presenting `identity` when asked
+pub fn connector(protocols: &[&str], identity: Option<&Identity>) -> Result<SslConnector, String> {
+    // Where native-tls finds the system's certificates too
+    static PROBE: Once = Once::new();
+    PROBE.call_once(openssl_probe::init_ssl_cert_env_vars);
+
+    let mut builder =
+        SslConnector::builder(SslMethod::tls_client()).map_err(|err| err.to_string())?;
+    if !protocols.is_empty() {
+        let wire: Vec<u8> = protocols
+            .iter()
+            .flat_map(|protocol| [&[protocol.len() as u8], protocol.as_bytes()].concat())
+            .collect();
+        builder
+            .set_alpn_protos(&wire)
+            .map_err(|err| err.to_string())?;
+    }
+    if let Some(identity) = identity {
+        builder
+            .set_certificate(&identity.cert)
+            .and_then(|_| builder.set_private_key(&identity.key))
+            .map_err(|err| err.to_string())?;
+        for cert in &identity.chain {
+            builder
+                .add_extra_chain_cert(cert.clone())
+                .map_err(|err| err.to_string())?;
+        }
+    }
+    Ok(builder.build())
+}
+
+/// Does the handshake with `host` over `stream`. When the server's
+/// certificates don't check out, the error lists what it sent
+pub async fn connect<S: AsyncRead + AsyncWrite + Unpin>(
+    connector: &SslConnector,
+    host: &str,
+    stream: S,
+) -> Result<TlsStream<S>, String> {
+    let ssl = connector
+        .configure()
+        .and_then(|config| config.into_ssl(host))
+        .map_err(|err| err.to_string())?;
+    let adapter = Adapter {
+        stream,
+        waker: Waker::noop().clone(),
+    };
+    let mut stream = TlsStream(SslStream::new(ssl, adapter).map_err(|err| err.to_string())?);
+    let handshake = std::future::poll_fn(|cx| match stream.with(cx, |ssl| ssl.connect()) {
+        Err(err) if blocked(&err) => Poll::Pending,
+        result => Poll::Ready(result),
+    })
+    .await;
+    let verified = stream.0.ssl().verify_result();
+    match handshake {
+        Ok(()) => Ok(stream),
+        Err(_) if verified != X509VerifyResult::OK => {
+            let sent: Vec<String> = stream
+                .peer_chain()
+                .iter()
+                .map(|cert| format!("{} issued by {}", cert.subject, cert.issuer))
+                .collect();
+            let sent = match sent.is_empty() {
+                true => "nothing".to_string(),
+                false => sent.join("; "),
+            };
+            Err(format!(
+                "{host}: {}, the server sent {sent}",
+                verified.error_string()
+            ))
+        }
+        Err(err) => Err(format!("{host}: {err}")),
+    }
+}
+
+/// Whether `err` only means the stream has to be polled again
+fn blocked(err: &openssl::ssl::Error) -> bool {
+    matches!(err.code(), ErrorCode::WANT_READ | ErrorCode::WANT_WRITE)
+        && err
+            .io_error()
+            .is_none_or(|err| err.kind() == io::ErrorKind::WouldBlock)
+}
+
+/// An async stream as openssl's blocking `Read` and `Write`, a `Pending`
+/// poll becomes `WouldBlock`
+struct Adapter<S> {
+    stream: S,
+    /// Of the task polling the `TlsStream`
+    waker: Waker,
+}
+
+impl<S: AsyncRead + Unpin> Read for Adapter<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut cx = Context::from_waker(&self.waker);
+        let mut buf = ReadBuf::new(buf);
+        match Pin::new(&mut self.stream).poll_read(&mut cx, &mut buf)? {
+            Poll::Ready(()) => Ok(buf.filled().len()),
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> Write for Adapter<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut cx = Context::from_waker(&self.waker);
+        match Pin::new(&mut self.stream).poll_write(&mut cx, buf) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut cx = Context::from_waker(&self.waker);
+        match Pin::new(&mut self.stream).poll_flush(&mut cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+/// A TLS connection over an async stream, which unlike native-tls's hands
+/// out the server's whole certificate chain
+pub struct TlsStream<S>(SslStream<Adapter<S>>);
+
+impl<S> TlsStream<S> {
+    /// Runs `f` on the connection with the waker of `cx`
+    fn with<T>(&mut self, cx: &mut Context, f: impl FnOnce(&mut SslStream<Adapter<S>>) -> T) -> T {
+        self.0.get_mut().waker = cx.waker().clone();
+        f(&mut self.0)
+    }
+
+    /// The certificates the server sent, its own first
+    pub fn peer_chain(&self) -> Vec<certs::Certificate> {
+        self.0
+            .ssl()
+            .peer_cert_chain()
+            .map(|chain| chain.iter().map(certs::Certificate::new).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn alpn(&self) -> Option<&[u8]> {
+        self.0.ssl().selected_alpn_protocol()
+    }
+}
+
+/// `WouldBlock` meaning the stream has to be polled again
+fn poll<T>(result: io::Result<T>) -> Poll<io::Result<T>> {
+    match result {
+        Err(err) if err.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+        result => Poll::Ready(result),
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for TlsStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut ReadBuf,
+    ) -> Poll<io::Result<()>> {
+        poll(self.get_mut().with(cx, |ssl| {
+            let read = ssl.read(buf.initialize_unfilled())?;
+            buf.advance(read);
+            Ok(())
+        }))
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for TlsStream<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        poll(self.get_mut().with(cx, |ssl| ssl.write(buf)))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        poll(self.get_mut().with(cx, |ssl| ssl.flush()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match this.with(cx, |ssl| ssl.shutdown()) {
+            Ok(ShutdownResult::Sent | ShutdownResult::Received) => {}
+            Err(err) if err.code() == ErrorCode::ZERO_RETURN => {}
+            Err(err) if blocked(&err) => return Poll::Pending,
+            Err(err) => {
+                let err = err
+                    .into_io_error()
+                    .unwrap_or_else(|err| io::Error::other(err.to_string()));
+                return Poll::Ready(Err(err));
+            }
+        }
+        Pin::new(&mut this.0.get_mut().stream).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread};
+
+    use openssl::{
+        asn1::Asn1Time,
+        bn::BigNum,
+        hash::MessageDigest,
+        pkcs12::Pkcs12,
+        rsa::Rsa,
+        ssl::{SslAcceptor, SslVerifyMode},
+        x509::{
+            extension::{BasicConstraints, SubjectAlternativeName},
+            X509NameBuilder,
+        },
+    };
+    use tokio::{io::AsyncReadExt, net::TcpStream};
+
+    use super::*;
+
+    /// A certificate for `cn` signed by `issuer`, or by itself without one
+    fn cert(cn: &str, issuer: Option<&(X509, PKey<Private>)>, ca: bool) -> (X509, PKey<Private>) {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", cn).unwrap();
+        let name = name.build();
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        let serial = BigNum::from_u32(rand::random::<u32>()).unwrap();
+        builder
+            .set_serial_number(&serial.to_asn1_integer().unwrap())
+            .unwrap();
+        builder.set_subject_name(&name).unwrap();
+        let (issuer_name, issuer_key) = match issuer {
+            Some((cert, key)) => (cert.subject_name(), key),
+            None => (name.as_ref(), &key),
+        };
+        builder.set_issuer_name(issuer_name).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(10).unwrap())
+            .unwrap();
+        match ca {
+            true => {
+                let constraints = BasicConstraints::new().critical().ca().build().unwrap();
+                builder.append_extension(constraints).unwrap();
+            }
+            false => {
+                let sans = SubjectAlternativeName::new()
+                    .dns(cn)
+                    .build(&builder.x509v3_context(issuer.map(|(cert, _)| cert.as_ref()), None))
+                    .unwrap();
+                builder.append_extension(sans).unwrap();
+            }
+        }
+        builder.sign(issuer_key, MessageDigest::sha256()).unwrap();
+        (builder.build(), key)
+    }
+
+    /// Serves one TLS connection with `leaf` and `intermediate`, answering
+    /// with "hello"
+    fn serve(leaf: (X509, PKey<Private>), intermediate: X509) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+        acceptor.set_certificate(&leaf.0).unwrap();
+        acceptor.set_private_key(&leaf.1).unwrap();
+        acceptor.add_extra_chain_cert(intermediate).unwrap();
+        let acceptor = acceptor.build();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            if let Ok(mut stream) = acceptor.accept(stream) {
+                let _ = stream.write_all(b"hello");
+                let _ = stream.shutdown();
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn reads_whole_chain() {
+        let root = cert("Root", None, true);
+        let intermediate = cert("Intermediate", Some(&root), true);
+        let leaf = cert("localhost", Some(&intermediate), false);
+        let port = serve(leaf, intermediate.0);
+
+        let mut builder = SslConnector::builder(SslMethod::tls_client()).unwrap();
+        builder.cert_store_mut().add_cert(root.0).unwrap();
+        let connector = builder.build();
+        let stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let mut stream = connect(&connector, "localhost", stream).await.unwrap();
+        let chain: Vec<(String, String)> = stream
+            .peer_chain()
+            .into_iter()
+            .map(|cert| (cert.subject, cert.issuer))
+            .collect();
+        assert_eq!(
+            chain,
+            [
+                ("CN=localhost".to_string(), "CN=Intermediate".to_string()),
+                ("CN=Intermediate".to_string(), "CN=Root".to_string()),
+            ]
+        );
+        let mut hello = String::new();
+        stream.read_to_string(&mut hello).await.unwrap();
+        assert_eq!(hello, "hello");
+    }
+
+    #[tokio::test]
+    async fn lists_untrusted_chain() {
+        let root = cert("Root", None, true);
+        let intermediate = cert("Intermediate", Some(&root), true);
+        let leaf = cert("localhost", Some(&intermediate), false);
+        let port = serve(leaf, intermediate.0);
+
+        let connector = SslConnector::builder(SslMethod::tls_client())
+            .unwrap()
+            .build();
+        let stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let err = connect(&connector, "localhost", stream)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            err,
+            "localhost: unable to get local issuer certificate, the server sent \
+             CN=localhost issued by CN=Intermediate; CN=Intermediate issued by CN=Root"
+        );
+    }
+
+    #[test]
+    fn presents_identity() {
+        let root = cert("Client CA", None, true);
+        let client = cert("client", Some(&root), false);
+        let pem = [client.0.to_pem().unwrap(), root.0.to_pem().unwrap()].concat();
+        let key = client.1.private_key_to_pem_pkcs8().unwrap();
+        let identity = Identity::from_pem(&pem, &key).unwrap();
+        assert_eq!(identity.chain.len(), 1);
+        assert!(Identity::from_pem(b"", &key).is_err());
+
+        let mut bundle = Pkcs12::builder();
+        bundle.name("client").pkey(&client.1).cert(&client.0);
+        let der = bundle.build2("password").unwrap().to_der().unwrap();
+        assert!(Identity::from_pkcs12(&der, "password").is_ok());
+        assert!(Identity::from_pkcs12(&der, "wrong").is_err());
+
+        // A server that only takes clients with a certificate of the CA's
+        let server = cert("localhost", None, false);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+        acceptor.set_certificate(&server.0).unwrap();
+        acceptor.set_private_key(&server.1).unwrap();
+        acceptor.cert_store_mut().add_cert(root.0).unwrap();
+        acceptor.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+        let acceptor = acceptor.build();
+        let accepted = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut stream = acceptor.accept(stream).unwrap();
+            let _ = stream.write_all(b"hello");
+            let peer = stream.ssl().peer_certificate().unwrap();
+            certs::Certificate::new(&peer).subject
+        });
+
+        // Not verifying the server, its certificate isn't the point here
+        let connector = connector(&[], Some(&identity)).unwrap();
+        let mut config = connector.configure().unwrap().verify_hostname(false);
+        config.set_verify(SslVerifyMode::NONE);
+        let stream = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let mut stream = config.connect("localhost", stream).unwrap();
+        let mut hello = [0; 5];
+        stream.read_exact(&mut hello).unwrap();
+        assert_eq!(accepted.join().unwrap(), "CN=client");
+    }
+}
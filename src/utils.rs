@@ -0,0 +1,174 @@
+use std::time::Duration;
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let millis = duration.as_secs_f64() * 1000.0;
+    if millis < 1000.0 {
+        format!("{millis:.1} ms")
+    } else {
+        format!("{:.2} s", millis / 1000.0)
+    }
+}
+
+/// Breaks `text` into lines at most `width` columns wide, between words
+/// where it can
+pub fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let gap = usize::from(!line.is_empty());
+        if line.width() + gap + word.width() <= width {
+            if gap == 1 {
+                line.push(' ');
+            }
+            line.push_str(word);
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        for c in word.chars() {
+            if line.width() + c.width().unwrap_or_default() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            line.push(c);
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// The lines trimmed and put back together without the breaks, for a URL
+/// wrapped over several lines
+pub fn join_lines(text: &str) -> String {
+    text.lines().map(str::trim).collect()
+}
+
+pub fn extension_for(content_type: &str) -> &'static str {
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    match mime {
+        "application/json" => "json",
+        mime if mime.ends_with("+json") => "json",
+        "text/html" => "html",
+        "application/xml" | "text/xml" => "xml",
+        "image/svg+xml" => "svg",
+        mime if mime.ends_with("+xml") => "xml",
+        "text/csv" => "csv",
+        "text/css" => "css",
+        "text/javascript" | "application/javascript" => "js",
+        "application/pdf" => "pdf",
+        "application/zip" => "zip",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        mime if mime.starts_with("text/") => "txt",
+        _ => "bin",
+    }
+}
+
+/// `xxd` style dump, 16 bytes per line with offsets and printable ASCII
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let mut dump = String::with_capacity(bytes.len() * 4 + bytes.len() / 16 * 12);
+    for (index, chunk) in bytes.chunks(16).enumerate() {
+        if index > 0 {
+            dump.push('\n');
+        }
+        dump.push_str(&format!("{:08x}  ", index * 16));
+        for column in 0..16 {
+            match chunk.get(column) {
+                Some(byte) => dump.push_str(&format!("{byte:02x} ")),
+                None => dump.push_str("   "),
+            }
+            if column == 7 {
+                dump.push(' ');
+            }
+        }
+        dump.push_str(" |");
+        dump.extend(chunk.iter().map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            }
+        }));
+        dump.push('|');
+    }
+    dump
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 << 20), "5.0 MiB");
+        assert_eq!(format_size(u64::MAX), "16777216.0 TiB");
+    }
+
+    #[test]
+    fn extensions_by_content_type() {
+        assert_eq!(extension_for("application/json; charset=utf-8"), "json");
+        assert_eq!(extension_for("application/problem+json"), "json");
+        assert_eq!(extension_for("image/svg+xml"), "svg");
+        assert_eq!(extension_for("application/atom+xml"), "xml");
+        assert_eq!(extension_for("text/markdown"), "txt");
+        assert_eq!(extension_for(""), "bin");
+    }
+
+    #[test]
+    fn dumps_hex() {
+        let bytes: Vec<u8> = (0x3c..0x52).collect();
+        assert_eq!(
+            hex_dump(&bytes),
+            "00000000  3c 3d 3e 3f 40 41 42 43  44 45 46 47 48 49 4a 4b  |<=>?@ABCDEFGHIJK|\n\
+             00000010  4c 4d 4e 4f 50 51                                 |LMNOPQ|"
+        );
+        assert_eq!(
+            hex_dump(b"\0 \xff"),
+            "00000000  00 20 ff                                          |. .|"
+        );
+    }
+
+    #[test]
+    fn wraps_words() {
+        assert_eq!(
+            wrap_words("the quick brown fox", 10),
+            ["the quick", "brown fox"]
+        );
+        assert_eq!(
+            wrap_words("a verylongword", 5),
+            ["a", "veryl", "ongwo", "rd"]
+        );
+        assert_eq!(wrap_words("日本語の文", 4), ["日本", "語の", "文"]);
+        assert_eq!(wrap_words("", 10), [""]);
+    }
+}
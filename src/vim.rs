@@ -0,0 +1,413 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tui_textarea::{CursorMove, TextArea};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Normal,
+    Insert,
+    /// By character, or whole lines for `V`
+    Visual {
+        lines: bool,
+    },
+}
+
+impl Mode {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Normal => "NORMAL",
+            Self::Insert => "INSERT",
+            Self::Visual { lines: false } => "VISUAL",
+            Self::Visual { lines: true } => "VISUAL LINE",
+        }
+    }
+}
+
+/// Modal editing for the text editors, the register is shared between them
+/// like vim's unnamed one
+#[derive(Default)]
+pub struct Vim {
+    pub mode: Mode,
+    /// First key of `dd`, `yy`, `gg` and the like
+    pending: Option<char>,
+    register: String,
+    /// Whether the register holds whole lines, which are put below or above
+    /// the cursor line
+    linewise: bool,
+    /// Where visual mode started
+    anchor: (usize, usize),
+}
+
+fn jump(text_area: &mut TextArea, (row, col): (usize, usize)) {
+    text_area.move_cursor(CursorMove::Jump(row as u16, col as u16));
+}
+
+fn line_len(text_area: &TextArea, row: usize) -> usize {
+    text_area.lines()[row].chars().count()
+}
+
+/// `insert_str` can't take newlines
+fn insert(text_area: &mut TextArea, text: &str) {
+    for (index, line) in text.split('\n').enumerate() {
+        if index > 0 {
+            text_area.insert_newline();
+        }
+        text_area.insert_str(line);
+    }
+}
+
+/// The characters from `start` to `end` included, with newlines between lines
+fn text_between(text_area: &TextArea, start: (usize, usize), end: (usize, usize)) -> String {
+    let lines = text_area.lines();
+    (start.0..=end.0)
+        .map(|row| {
+            let chars = lines[row].chars();
+            let skip = if row == start.0 { start.1 } else { 0 };
+            match row == end.0 {
+                true => chars
+                    .skip(skip)
+                    .take((end.1 + 1).saturating_sub(skip))
+                    .collect(),
+                false => chars.skip(skip).collect::<String>(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl Vim {
+    pub fn input(&mut self, text_area: &mut TextArea, key: KeyEvent) {
+        match self.mode {
+            Mode::Insert => {
+                if key.code == KeyCode::Esc {
+                    self.mode = Mode::Normal;
+                    if text_area.cursor().1 > 0 {
+                        text_area.move_cursor(CursorMove::Back);
+                    }
+                } else {
+                    text_area.input(key);
+                }
+            }
+            Mode::Normal => self.normal(text_area, key),
+            Mode::Visual { lines } => self.visual(text_area, key, lines),
+        }
+    }
+
+    /// `h`, `j`, `w`, `gg` and the rest, shared by normal and visual mode
+    fn motion(&mut self, text_area: &mut TextArea, c: char) -> bool {
+        let (row, col) = text_area.cursor();
+        match c {
+            'h' if col > 0 => text_area.move_cursor(CursorMove::Back),
+            'l' if col + 1 < line_len(text_area, row) => text_area.move_cursor(CursorMove::Forward),
+            'j' => text_area.move_cursor(CursorMove::Down),
+            'k' => text_area.move_cursor(CursorMove::Up),
+            'w' => text_area.move_cursor(CursorMove::WordForward),
+            'b' => text_area.move_cursor(CursorMove::WordBack),
+            '0' => text_area.move_cursor(CursorMove::Head),
+            '$' => {
+                let len = line_len(text_area, row);
+                jump(text_area, (row, len.saturating_sub(1)));
+            }
+            '^' => {
+                let indent = text_area.lines()[row]
+                    .chars()
+                    .take_while(|c| c.is_whitespace())
+                    .count();
+                jump(text_area, (row, indent));
+            }
+            'G' => {
+                text_area.move_cursor(CursorMove::Bottom);
+                text_area.move_cursor(CursorMove::Head);
+            }
+            '}' => text_area.move_cursor(CursorMove::ParagraphForward),
+            '{' => text_area.move_cursor(CursorMove::ParagraphBack),
+            'h' | 'l' => {}
+            _ => return false,
+        }
+        true
+    }
+
+    fn yank(&mut self, text: String, linewise: bool) {
+        self.register = text;
+        self.linewise = linewise;
+    }
+
+    /// Whole line `row`, the cursor ends up at the start of the line that
+    /// takes its place
+    fn delete_line(&mut self, text_area: &mut TextArea, row: usize) {
+        let count = text_area.lines().len();
+        jump(text_area, (row, 0));
+        text_area.delete_str(0, usize::MAX);
+        if row + 1 < count {
+            text_area.delete_next_char();
+        } else if row > 0 {
+            text_area.delete_newline();
+            text_area.move_cursor(CursorMove::Head);
+        }
+    }
+
+    fn delete_lines(&mut self, text_area: &mut TextArea, first: usize, last: usize) {
+        let lines = text_area.lines()[first..=last].join("\n");
+        self.yank(lines + "\n", true);
+        for _ in first..=last {
+            self.delete_line(text_area, first);
+        }
+    }
+
+    /// Characters from `start` to `end` included, newlines between lines too
+    fn delete_range(
+        &mut self,
+        text_area: &mut TextArea,
+        start: (usize, usize),
+        end: (usize, usize),
+    ) {
+        let text = text_between(text_area, start, end);
+        jump(text_area, start);
+        for _ in 0..text.chars().count() {
+            text_area.delete_next_char();
+        }
+        self.yank(text, false);
+    }
+
+    fn put(&mut self, text_area: &mut TextArea, before: bool) {
+        let (row, _) = text_area.cursor();
+        match (self.linewise, before) {
+            (true, false) => {
+                text_area.move_cursor(CursorMove::End);
+                text_area.insert_newline();
+                insert(text_area, self.register.trim_end_matches('\n'));
+                jump(text_area, (row + 1, 0));
+            }
+            (true, true) => {
+                text_area.move_cursor(CursorMove::Head);
+                insert(text_area, &self.register);
+                jump(text_area, (row, 0));
+            }
+            (false, false) => {
+                if line_len(text_area, row) > 0 {
+                    text_area.move_cursor(CursorMove::Forward);
+                }
+                insert(text_area, &self.register);
+            }
+            (false, true) => insert(text_area, &self.register),
+        }
+    }
+
+    fn normal(&mut self, text_area: &mut TextArea, key: KeyEvent) {
+        let c = match (key.modifiers, key.code) {
+            (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char(c)) => c,
+            (KeyModifiers::NONE, KeyCode::Left) => 'h',
+            (KeyModifiers::NONE, KeyCode::Right) => 'l',
+            (KeyModifiers::NONE, KeyCode::Down) => 'j',
+            (KeyModifiers::NONE, KeyCode::Up) => 'k',
+            (KeyModifiers::NONE, KeyCode::Home) => '0',
+            (KeyModifiers::NONE, KeyCode::End) => '$',
+            (KeyModifiers::NONE, KeyCode::Esc) if self.pending.is_some() => {
+                self.pending = None;
+                return;
+            }
+            _ => return,
+        };
+        let (row, col) = text_area.cursor();
+        let last = text_area.lines().len() - 1;
+        match (self.pending.take(), c) {
+            (Some('g'), 'g') => text_area.move_cursor(CursorMove::Top),
+            (Some('d'), 'd') => self.delete_lines(text_area, row, row),
+            (Some('d'), 'j') => self.delete_lines(text_area, row, (row + 1).min(last)),
+            (Some('d'), 'k') => self.delete_lines(text_area, row.saturating_sub(1), row),
+            (Some(operator @ ('d' | 'c')), 'w' | 'b' | '$') => {
+                match c {
+                    'w' => text_area.delete_next_word(),
+                    'b' => text_area.delete_word(),
+                    _ => text_area.delete_str(col, usize::MAX),
+                };
+                self.yank(text_area.yank_text().to_string(), false);
+                if operator == 'c' {
+                    self.mode = Mode::Insert;
+                }
+            }
+            (Some('y'), 'y') => {
+                let line = text_area.lines()[row].clone();
+                self.yank(line + "\n", true);
+            }
+            (Some('c'), 'c') => {
+                let line = text_area.lines()[row].clone();
+                self.yank(line + "\n", true);
+                text_area.move_cursor(CursorMove::Head);
+                text_area.delete_str(0, usize::MAX);
+                self.mode = Mode::Insert;
+            }
+            (Some(_), _) => {}
+            (None, 'g' | 'd' | 'y' | 'c') => self.pending = Some(c),
+            (None, c) if self.motion(text_area, c) => {}
+            (None, 'i') => self.mode = Mode::Insert,
+            (None, 'a') => {
+                if line_len(text_area, row) > 0 {
+                    text_area.move_cursor(CursorMove::Forward);
+                }
+                self.mode = Mode::Insert;
+            }
+            (None, 'I') => {
+                self.motion(text_area, '^');
+                self.mode = Mode::Insert;
+            }
+            (None, 'A') => {
+                text_area.move_cursor(CursorMove::End);
+                self.mode = Mode::Insert;
+            }
+            (None, 'o') => {
+                text_area.move_cursor(CursorMove::End);
+                text_area.insert_newline();
+                self.mode = Mode::Insert;
+            }
+            (None, 'O') => {
+                text_area.move_cursor(CursorMove::Head);
+                text_area.insert_newline();
+                text_area.move_cursor(CursorMove::Up);
+                self.mode = Mode::Insert;
+            }
+            (None, 'x') if line_len(text_area, row) > 0 => {
+                self.delete_range(text_area, (row, col), (row, col))
+            }
+            (None, 'X') if col > 0 => self.delete_range(text_area, (row, col - 1), (row, col - 1)),
+            (None, 'D' | 'C') => {
+                text_area.delete_str(col, usize::MAX);
+                self.yank(text_area.yank_text().to_string(), false);
+                if c == 'C' {
+                    self.mode = Mode::Insert;
+                }
+            }
+            (None, 'p') => self.put(text_area, false),
+            (None, 'P') => self.put(text_area, true),
+            (None, 'u') => {
+                text_area.undo();
+            }
+            (None, 'v') => {
+                self.anchor = (row, col);
+                self.mode = Mode::Visual { lines: false };
+            }
+            (None, 'V') => {
+                self.anchor = (row, col);
+                self.mode = Mode::Visual { lines: true };
+            }
+            _ => {}
+        }
+    }
+
+    fn visual(&mut self, text_area: &mut TextArea, key: KeyEvent, lines: bool) {
+        let c = match (key.modifiers, key.code) {
+            (KeyModifiers::NONE, KeyCode::Esc) => {
+                self.mode = Mode::Normal;
+                return;
+            }
+            (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char(c)) => c,
+            (KeyModifiers::NONE, KeyCode::Left) => 'h',
+            (KeyModifiers::NONE, KeyCode::Right) => 'l',
+            (KeyModifiers::NONE, KeyCode::Down) => 'j',
+            (KeyModifiers::NONE, KeyCode::Up) => 'k',
+            _ => return,
+        };
+        // The anchor can be off the end after switching editors
+        let last = text_area.lines().len() - 1;
+        self.anchor.0 = self.anchor.0.min(last);
+        let cursor = text_area.cursor();
+        let (start, end) = match self.anchor <= cursor {
+            true => (self.anchor, cursor),
+            false => (cursor, self.anchor),
+        };
+        match (self.pending.take(), c) {
+            (Some('g'), 'g') => text_area.move_cursor(CursorMove::Top),
+            (Some(_), _) => {}
+            (None, 'g') => self.pending = Some('g'),
+            (None, c) if self.motion(text_area, c) => {}
+            (None, 'y') => {
+                match lines {
+                    true => {
+                        let text = text_area.lines()[start.0..=end.0].join("\n");
+                        self.yank(text + "\n", true);
+                    }
+                    false => self.yank(text_between(text_area, start, end), false),
+                }
+                jump(text_area, start);
+                self.mode = Mode::Normal;
+            }
+            (None, 'd' | 'x' | 'c') => {
+                match lines {
+                    true => self.delete_lines(text_area, start.0, end.0),
+                    false => self.delete_range(text_area, start, end),
+                }
+                self.mode = match c {
+                    'c' => Mode::Insert,
+                    _ => Mode::Normal,
+                };
+            }
+            (None, 'v') if !lines => self.mode = Mode::Normal,
+            (None, 'V') if lines => self.mode = Mode::Normal,
+            (None, 'v') => self.mode = Mode::Visual { lines: false },
+            (None, 'V') => self.mode = Mode::Visual { lines: true },
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Types `keys` into a text area holding `text`, `\x1b` is Esc
+    fn typed(vim: &mut Vim, text: &str, keys: &str) -> TextArea<'static> {
+        let mut text_area = TextArea::from(text.lines());
+        for c in keys.chars() {
+            let key = match c {
+                '\x1b' => KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE),
+                c if c.is_ascii_uppercase() => KeyEvent::new(KeyCode::Char(c), KeyModifiers::SHIFT),
+                c => KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+            };
+            vim.input(&mut text_area, key);
+        }
+        text_area
+    }
+
+    #[test]
+    fn deletes_and_puts_lines() {
+        let mut vim = Vim::default();
+        let text_area = typed(&mut vim, "one\ntwo\nthree", "jddp");
+        assert_eq!(text_area.lines(), ["one", "three", "two"]);
+        assert_eq!(text_area.cursor(), (2, 0));
+
+        let text_area = typed(&mut vim, "a\nb", "yyP");
+        assert_eq!(text_area.lines(), ["a", "a", "b"]);
+        let text_area = typed(&mut vim, "a\nb\nc", "Gdk");
+        assert_eq!(text_area.lines(), ["a"]);
+    }
+
+    #[test]
+    fn edits_words() {
+        let mut vim = Vim::default();
+        let text_area = typed(&mut vim, "let x = 1;", "wcwy\x1bAz\x1b");
+        assert_eq!(text_area.lines(), ["let y = 1;z"]);
+        assert_eq!(vim.mode, Mode::Normal);
+
+        let text_area = typed(&mut vim, "abc", "lxp$D");
+        assert_eq!(text_area.lines(), ["ac"]);
+        let text_area = typed(&mut vim, "  indented", "Ix\x1b");
+        assert_eq!(text_area.lines(), ["  xindented"]);
+    }
+
+    #[test]
+    fn selects_visually() {
+        let mut vim = Vim::default();
+        let text_area = typed(&mut vim, "hello world", "wvlly0P");
+        assert_eq!(text_area.lines(), ["worhello world"]);
+
+        let text_area = typed(&mut vim, "a\nb\nc", "Vjd");
+        assert_eq!(text_area.lines(), ["c"]);
+        assert_eq!(vim.mode, Mode::Normal);
+        let text_area = typed(&mut vim, "x", "p");
+        assert_eq!(text_area.lines(), ["x", "a", "b"]);
+
+        typed(&mut vim, "a", "vV");
+        assert_eq!(vim.mode, Mode::Visual { lines: true });
+        assert_eq!(vim.mode.title(), "VISUAL LINE");
+    }
+}
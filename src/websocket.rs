@@ -0,0 +1,524 @@
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use chrono::Local;
+use rand::Rng;
+use reqwest::{header::HeaderMap, Url};
+use sha1::{Digest, Sha1};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader},
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+};
+
+use crate::{
+    models::{Message, Request, RequestError, Response},
+    net::{self, Stream},
+    timing::Timing,
+};
+
+/// Appended to the key to get the accept header the server must answer with
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// Frames bigger than this end the connection
+const MAX_FRAME: u64 = 64 * 1024 * 1024;
+/// How long to wait for the server to answer a close
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Bytes of a binary message shown in the log
+const BINARY_PREVIEW: usize = 32;
+
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const BINARY: u8 = 0x2;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xa;
+
+/// What the app asks an open connection to send
+pub enum Outgoing {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping,
+    Close,
+}
+
+pub fn is_websocket(uri: &str) -> bool {
+    let scheme = uri.split_once("://").map(|(scheme, _)| scheme);
+    matches!(scheme, Some("ws" | "wss"))
+}
+
+fn socket_error(err: impl ToString) -> RequestError {
+    RequestError::WebSocket(err.to_string())
+}
+
+/// A whole message, put together from its fragments
+struct Incoming {
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+/// Reads the next frame, as its fin bit, opcode and unmasked payload
+async fn read_frame(reader: &mut (impl AsyncRead + Unpin)) -> std::io::Result<(bool, u8, Vec<u8>)> {
+    let mut head = [0; 2];
+    reader.read_exact(&mut head).await?;
+    let (fin, opcode) = (head[0] & 0x80 != 0, head[0] & 0x0f);
+    let masked = head[1] & 0x80 != 0;
+    let length = match head[1] & 0x7f {
+        126 => reader.read_u16().await? as u64,
+        127 => reader.read_u64().await?,
+        length => length as u64,
+    };
+    if length > MAX_FRAME {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("frame of {length} bytes is too big"),
+        ));
+    }
+    let mut mask = [0; 4];
+    if masked {
+        reader.read_exact(&mut mask).await?;
+    }
+    let mut payload = vec![0; length as usize];
+    reader.read_exact(&mut payload).await?;
+    if masked {
+        payload
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, byte)| *byte ^= mask[i % 4]);
+    }
+    Ok((fin, opcode, payload))
+}
+
+/// Reads messages until the connection fails, control frames can come
+/// between the fragments of another message
+async fn read_messages(
+    mut reader: impl AsyncRead + Unpin,
+    messages: UnboundedSender<std::io::Result<Incoming>>,
+) {
+    let mut fragments: Option<Incoming> = None;
+    loop {
+        let (fin, opcode, payload) = match read_frame(&mut reader).await {
+            Ok(frame) => frame,
+            Err(err) => {
+                let _ = messages.send(Err(err));
+                return;
+            }
+        };
+        let message = match (opcode, &mut fragments) {
+            (CONTINUATION, Some(message)) => {
+                message.payload.extend_from_slice(&payload);
+                match fin {
+                    true => fragments.take(),
+                    false => None,
+                }
+            }
+            (TEXT | BINARY, None) if !fin => {
+                fragments = Some(Incoming { opcode, payload });
+                None
+            }
+            (CLOSE | PING | PONG, _) | (TEXT | BINARY, None) => Some(Incoming { opcode, payload }),
+            _ => {
+                let err = format!("unexpected frame with opcode {opcode:#x}");
+                let _ = messages.send(Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    err,
+                )));
+                return;
+            }
+        };
+        if let Some(message) = message {
+            if messages.send(Ok(message)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// What the connection loop woke up for
+enum Event {
+    Received(Option<std::io::Result<Incoming>>),
+    Command(Option<Outgoing>),
+}
+
+/// A single final frame, masked as clients must
+fn encode(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        length @ 0..=125 => frame.push(0x80 | length as u8),
+        length @ 126..=0xffff => {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(0x80 | 127);
+            frame.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+    let mask: [u8; 4] = rand::thread_rng().gen();
+    frame.extend_from_slice(&mask);
+    frame.extend(
+        payload
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| byte ^ mask[i % 4]),
+    );
+    frame
+}
+
+/// `1000 bye` for the status code and reason of a close frame
+fn close_reason(payload: &[u8]) -> String {
+    match payload {
+        [high, low, reason @ ..] => {
+            let code = u16::from_be_bytes([*high, *low]);
+            format!("{code} {}", String::from_utf8_lossy(reason))
+                .trim_end()
+                .to_string()
+        }
+        _ => "without a status".to_string(),
+    }
+}
+
+/// What's sent and received, one line per message, streamed to the app as
+/// the response body
+struct Log {
+    text: String,
+    progress: Option<UnboundedSender<Message>>,
+}
+
+impl Log {
+    fn push(&mut self, direction: &str, what: &str) {
+        let prefix = format!("{} {direction} ", Local::now().format("%H:%M:%S"));
+        // Lines of multi-line messages are lined up under the first
+        let indent = " ".repeat(prefix.len());
+        let line = format!("{prefix}{}\n", what.replace('\n', &format!("\n{indent}")));
+        if let Some(progress) = &self.progress {
+            let _ = progress.send(Message::Chunk(Bytes::from(line.clone())));
+        }
+        self.text.push_str(&line);
+    }
+
+    fn message(&mut self, direction: &str, opcode: u8, payload: &[u8]) {
+        match opcode {
+            TEXT => self.push(direction, &String::from_utf8_lossy(payload)),
+            BINARY => {
+                let mut preview: Vec<String> = payload
+                    .iter()
+                    .take(BINARY_PREVIEW)
+                    .map(|byte| format!("{byte:02x}"))
+                    .collect();
+                if payload.len() > BINARY_PREVIEW {
+                    preview.push("...".to_string());
+                }
+                let what = format!("binary, {} bytes: {}", payload.len(), preview.join(" "));
+                self.push(direction, what.trim_end_matches(": "));
+            }
+            PING => self.push(direction, "ping"),
+            PONG => self.push(direction, "pong"),
+            _ => self.push(direction, &format!("close {}", close_reason(payload))),
+        }
+    }
+}
+
+/// Connects, through `unix_socket` when given, and upgrades, returning the
+/// stream and the head of the server's 101 response
+async fn handshake(
+    url: &Url,
+    headers: &HeaderMap,
+    unix_socket: Option<&Path>,
+) -> Result<(Box<dyn Stream>, Response), RequestError> {
+    let host = url.host_str().ok_or(RequestError::InvalidUri)?;
+    let stream = net::connect(url, url.scheme() == "wss", &[], unix_socket)
+        .await
+        .map_err(socket_error)?;
+    // Buffered for reading the head line by line, what the server sends
+    // right after it stays in the buffer for the frames
+    let mut stream = BufReader::new(stream);
+
+    let key = STANDARD.encode(rand::thread_rng().gen::<[u8; 16]>());
+    let target = match url.query() {
+        Some(query) => format!("{}?{query}", url.path()),
+        None => url.path().to_string(),
+    };
+    let authority = match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_string(),
+    };
+    let mut head = format!(
+        "GET {target} HTTP/1.1\r\nHost: {authority}\r\nUpgrade: websocket\r\n\
+         Connection: Upgrade\r\nSec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n"
+    );
+    for (name, value) in headers {
+        head.push_str(&format!(
+            "{name}: {}\r\n",
+            String::from_utf8_lossy(value.as_bytes())
+        ));
+    }
+    head.push_str("\r\n");
+    stream
+        .write_all(head.as_bytes())
+        .await
+        .map_err(socket_error)?;
+    stream.flush().await.map_err(socket_error)?;
+
+    let mut status_line = String::new();
+    stream
+        .read_line(&mut status_line)
+        .await
+        .map_err(socket_error)?;
+    let mut parts = status_line.split_whitespace();
+    let version = parts.next().unwrap_or_default().to_string();
+    let status: u16 = parts
+        .next()
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| socket_error(format!("bad status line `{}`", status_line.trim())))?;
+    let mut response_headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await.map_err(socket_error)? == 0 {
+            return Err(socket_error("connection closed during the handshake"));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            response_headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let response = Response {
+        status,
+        version,
+        headers: response_headers,
+        ..Response::default()
+    };
+
+    if status != 101 {
+        return Err(socket_error(format!(
+            "server answered {status} {} instead of switching protocols",
+            response.reason()
+        )));
+    }
+    let accept = STANDARD.encode(Sha1::digest(format!("{key}{GUID}")));
+    if response.header("sec-websocket-accept") != Some(accept.as_str()) {
+        return Err(socket_error("server sent the wrong Sec-WebSocket-Accept"));
+    }
+    Ok((Box::new(stream), response))
+}
+
+/// Opens a WebSocket and logs every message until either side closes it or
+/// `outgoing` is dropped. The log is the body of the returned response
+pub async fn connect(
+    request: &Request,
+    mut outgoing: UnboundedReceiver<Outgoing>,
+) -> Result<Response, RequestError> {
+    let mut url = Url::parse(&request.uri).map_err(|_| RequestError::InvalidUri)?;
+    if !request.params.is_empty() {
+        url.query_pairs_mut().extend_pairs(&request.params);
+    }
+    let mut headers = request.header_map()?;
+    request
+        .auth
+        .apply(&mut url, &mut headers, request.timeout)
+        .await?;
+
+    let start = Instant::now();
+    let (stream, head) = tokio::time::timeout(
+        request.timeout,
+        handshake(&url, &headers, request.unix_socket.as_deref()),
+    )
+    .await
+    .map_err(|_| RequestError::Timeout(request.timeout))??;
+    let first_byte = start.elapsed();
+    if let Some(progress) = &request.progress {
+        let _ = progress.send(Message::Started(Box::new(Response {
+            status: head.status,
+            version: head.version.clone(),
+            headers: head.headers.clone(),
+            ..Response::default()
+        })));
+    }
+
+    let (reader, mut writer) = tokio::io::split(stream);
+    let (messages, mut incoming) = mpsc::unbounded_channel();
+    let reading = tokio::spawn(read_messages(reader, messages));
+    let mut log = Log {
+        text: String::new(),
+        progress: request.progress.clone(),
+    };
+    // Set once a close is sent, until the server answers it
+    let mut closing: Option<Instant> = None;
+    loop {
+        let waited = closing.map(|sent| sent.elapsed()).unwrap_or_default();
+        let event = tokio::select! {
+            message = incoming.recv() => Event::Received(message),
+            command = outgoing.recv(), if closing.is_none() => Event::Command(command),
+            () = tokio::time::sleep(CLOSE_TIMEOUT.saturating_sub(waited)), if closing.is_some() => {
+                log.push("!", "no answer to the close");
+                break;
+            }
+        };
+        // Whether this is the answer to the server's close
+        let mut answer = false;
+        let (opcode, payload) = match event {
+            Event::Command(Some(Outgoing::Text(text))) => (TEXT, text.into_bytes()),
+            Event::Command(Some(Outgoing::Binary(bytes))) => (BINARY, bytes),
+            Event::Command(Some(Outgoing::Ping)) => (PING, Vec::new()),
+            // The app is done with it when its tab was closed
+            Event::Command(Some(Outgoing::Close) | None) => (CLOSE, 1000u16.to_be_bytes().to_vec()),
+            Event::Received(Some(Ok(message))) => {
+                log.message("<", message.opcode, &message.payload);
+                match message.opcode {
+                    PING => (PONG, message.payload),
+                    CLOSE if closing.is_some() => break,
+                    // Answered with the same status
+                    CLOSE => {
+                        answer = true;
+                        (CLOSE, message.payload)
+                    }
+                    _ => continue,
+                }
+            }
+            Event::Received(Some(Err(err))) => {
+                log.push("!", &format!("connection lost: {err}"));
+                break;
+            }
+            Event::Received(None) => break,
+        };
+        if let Err(err) = writer.write_all(&encode(opcode, &payload)).await {
+            log.push("!", &format!("connection lost: {err}"));
+            break;
+        }
+        log.message(">", opcode, &payload);
+        if opcode == CLOSE {
+            if answer {
+                break;
+            }
+            closing = Some(Instant::now());
+        }
+    }
+    reading.abort();
+    let _ = writer.shutdown().await;
+
+    let bytes = Bytes::from(log.text.clone());
+    Ok(Response {
+        body_size: bytes.len(),
+        body: log.text,
+        bytes,
+        attempts: 1,
+        timing: Timing {
+            first_byte,
+            total: start.elapsed(),
+            ..Timing::default()
+        },
+        ..head
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{
+        config::Config,
+        models::{Editors, SavedRequest},
+    };
+
+    /// A frame from the server, which doesn't mask
+    fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x80 | opcode, payload.len() as u8];
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[tokio::test]
+    async fn frames_round_trip() {
+        for length in [0, 125, 126, 70_000] {
+            let payload: Vec<u8> = (0..length).map(|n| n as u8).collect();
+            let encoded = encode(TEXT, &payload);
+            let (fin, opcode, decoded) = read_frame(&mut encoded.as_slice()).await.unwrap();
+            assert!(fin);
+            assert_eq!(opcode, TEXT);
+            assert_eq!(decoded, payload);
+        }
+        assert_eq!(close_reason(&[0x03, 0xe8, b'b', b'y', b'e']), "1000 bye");
+        assert_eq!(close_reason(&[]), "without a status");
+        assert!(is_websocket("wss://localhost/chat"));
+        assert!(!is_websocket("https://localhost/chat"));
+    }
+
+    #[tokio::test]
+    async fn talks_to_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("ws://{}/chat", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            let mut key = String::new();
+            loop {
+                let mut line = String::new();
+                stream.read_line(&mut line).await.unwrap();
+                if let Some(value) = line.strip_prefix("Sec-WebSocket-Key:") {
+                    key = value.trim().to_string();
+                }
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let accept = STANDARD.encode(Sha1::digest(format!("{key}{GUID}")));
+            let head = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                 Connection: Upgrade\r\nSec-WebSocket-Accept: {accept}\r\n\r\n"
+            );
+            stream.write_all(head.as_bytes()).await.unwrap();
+
+            let mut received = vec![read_frame(&mut stream).await.unwrap()];
+            stream.write_all(&frame(TEXT, b"hello")).await.unwrap();
+            stream.write_all(&frame(PING, b"")).await.unwrap();
+            received.push(read_frame(&mut stream).await.unwrap());
+            stream
+                .write_all(&frame(CLOSE, &[0x03, 0xe8, b'b', b'y', b'e']))
+                .await
+                .unwrap();
+            received.push(read_frame(&mut stream).await.unwrap());
+            received
+        });
+
+        let mut editors = Editors::new();
+        editors.load(&SavedRequest {
+            uri,
+            ..SavedRequest::default()
+        });
+        let request = Request::new(&editors, &Config::default()).unwrap();
+        let (outgoing, commands) = mpsc::unbounded_channel();
+        assert!(outgoing.send(Outgoing::Text("hi".to_string())).is_ok());
+        let response = connect(&request, commands).await.unwrap();
+
+        // The server's close is echoed back
+        let received = server.await.unwrap();
+        assert_eq!(
+            received,
+            [
+                (true, TEXT, b"hi".to_vec()),
+                (true, PONG, Vec::new()),
+                (true, CLOSE, vec![0x03, 0xe8, b'b', b'y', b'e']),
+            ]
+        );
+        assert_eq!(response.status, 101);
+        let log: Vec<_> = response.body.lines().map(|line| &line[9..]).collect();
+        assert_eq!(
+            log,
+            [
+                "> hi",
+                "< hello",
+                "< ping",
+                "> pong",
+                "< close 1000 bye",
+                "> close 1000 bye"
+            ]
+        );
+        drop(outgoing);
+    }
+}
@@ -0,0 +1,987 @@
+use image::{
+    imageops::{self, FilterType},
+    RgbaImage,
+};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Cell, Paragraph, Row, Table, Widget, Wrap},
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    certs::EXPIRY_WARNING_DAYS,
+    environments::{self, Part, Variables, MASK},
+    highlight,
+    models::{BodyMode, KeyValueEditor, KeyValueRow, Response, ResponseTab, SavedRequest, Search},
+    theme,
+    utils::format_duration,
+};
+
+pub struct ResponseView<'a> {
+    response: Option<&'a Response>,
+    block: Option<Block<'a>>,
+    scroll: u16,
+    hscroll: u16,
+    wrap: bool,
+    show_redirects: bool,
+    raw: bool,
+    tab: ResponseTab,
+    search: Option<&'a Search>,
+    filtered: Option<&'a str>,
+    diff: Option<&'a str>,
+    /// Images are put on screen after drawing, see `graphics::Graphics`
+    graphics: bool,
+}
+
+impl<'a> ResponseView<'a> {
+    pub fn new(response: Option<&'a Response>) -> Self {
+        Self {
+            response,
+            block: None,
+            scroll: 0,
+            hscroll: 0,
+            wrap: false,
+            show_redirects: false,
+            raw: false,
+            tab: ResponseTab::Body,
+            search: None,
+            filtered: None,
+            diff: None,
+            graphics: false,
+        }
+    }
+
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    pub fn scroll(mut self, scroll: u16) -> Self {
+        self.scroll = scroll;
+        self
+    }
+
+    pub fn hscroll(mut self, hscroll: u16) -> Self {
+        self.hscroll = hscroll;
+        self
+    }
+
+    pub fn wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn show_redirects(mut self, show_redirects: bool) -> Self {
+        self.show_redirects = show_redirects;
+        self
+    }
+
+    pub fn raw(mut self, raw: bool) -> Self {
+        self.raw = raw;
+        self
+    }
+
+    pub fn tab(mut self, tab: ResponseTab) -> Self {
+        self.tab = tab;
+        self
+    }
+
+    pub fn search(mut self, search: Option<&'a Search>) -> Self {
+        self.search = search;
+        self
+    }
+
+    /// Shown in place of the body, already pretty printed
+    pub fn filtered(mut self, filtered: Option<&'a str>) -> Self {
+        self.filtered = filtered;
+        self
+    }
+
+    /// Diff against the pinned response, shown in place of the body
+    pub fn diff(mut self, diff: Option<&'a str>) -> Self {
+        self.diff = diff;
+        self
+    }
+
+    pub fn graphics(mut self, graphics: bool) -> Self {
+        self.graphics = graphics;
+        self
+    }
+
+    /// The image shown instead of the body and where it goes when drawn into
+    /// `area`
+    pub fn image(&self, area: Rect) -> Option<(&'a RgbaImage, Rect)> {
+        match (self.response, self.tab, self.raw, self.diff) {
+            (Some(resp), ResponseTab::Body, false, None) => {
+                let inner = match &self.block {
+                    Some(block) => block.inner(area),
+                    None => area,
+                };
+                resp.image.as_ref().map(|preview| (&preview.image, inner))
+            }
+            _ => None,
+        }
+    }
+
+    /// The lines of the selected tab, before search matches are marked
+    pub fn text(&self) -> Text<'a> {
+        match self.response {
+            Some(resp) => match self.tab {
+                ResponseTab::Body => self.body(resp),
+                ResponseTab::Headers => Self::headers(resp),
+                ResponseTab::Cookies => Self::cookies(resp),
+                ResponseTab::Timing => Self::timing(resp),
+                ResponseTab::Certificates => Self::certificates(resp),
+            },
+            None => Text::default(),
+        }
+    }
+
+    fn body(&self, resp: &'a Response) -> Text<'a> {
+        let theme = theme::current();
+        let mut text = Text::default();
+        if !resp.redirects.is_empty() {
+            let redirect_style = Style::default().fg(theme.accent);
+            let (marker, hint) = if self.show_redirects {
+                ("▾", "")
+            } else {
+                ("▸", " (r to expand)")
+            };
+            text.extend(Text::styled(
+                format!("{marker} {} redirect(s){hint}", resp.redirects.len()),
+                redirect_style,
+            ));
+            if self.show_redirects {
+                text.extend(resp.redirects.iter().map(|redirect| {
+                    Line::from(Span::styled(
+                        format!("  {} {} -> {}", redirect.status, redirect.from, redirect.to),
+                        redirect_style,
+                    ))
+                }));
+            }
+        }
+
+        match (self.filtered, self.diff, &resp.hex, &resp.pretty) {
+            (Some(filtered), _, _, _) => text.extend(filtered.lines().map(highlight::json_line)),
+            (None, Some(diff), _, _) => text.extend(diff.lines().map(highlight::diff_line)),
+            (None, None, Some(hex), _) => text.extend(hex.lines().map(highlight::hex_line)),
+            (None, None, None, Some(pretty)) if !self.raw => {
+                text.extend(pretty.lines().map(highlight::json_line))
+            }
+            _ => text.extend(Text::raw(resp.body.as_str())),
+        }
+        text
+    }
+
+    fn headers(resp: &'a Response) -> Text<'a> {
+        let theme = theme::current();
+        let name_style = Style::default().fg(theme.focus);
+        Text::from(
+            resp.headers
+                .iter()
+                .map(|(name, value)| {
+                    Line::from(vec![
+                        Span::styled(name.as_str(), name_style),
+                        Span::raw(": "),
+                        Span::raw(value.as_str()),
+                    ])
+                })
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn timing(resp: &'a Response) -> Text<'a> {
+        let theme = theme::current();
+        let timing = &resp.timing;
+        let label_style = Style::default().fg(theme.focus);
+        let line = |label: &'static str, value: String| {
+            Line::from(vec![
+                Span::styled(format!("{label:<24}"), label_style),
+                Span::raw(value),
+            ])
+        };
+
+        Text::from(vec![
+            line("protocol", resp.version.clone()),
+            line(
+                "dns lookup",
+                timing.dns.map_or("-".to_string(), format_duration),
+            ),
+            line(
+                "connect",
+                timing.connect.map_or("-".to_string(), format_duration),
+            ),
+            line(
+                "tls handshake",
+                timing.tls.map_or("-".to_string(), format_duration),
+            ),
+            line("waiting", format_duration(timing.wait())),
+            line("time to first byte", format_duration(timing.first_byte)),
+            line("download", format_duration(timing.download())),
+            line("total", format_duration(timing.total)),
+        ])
+    }
+
+    fn certificates(resp: &'a Response) -> Text<'a> {
+        let theme = theme::current();
+        let chain = match &resp.certificates {
+            None => return Text::styled("not an https response", Style::default().fg(theme.muted)),
+            Some(Err(err)) => {
+                return Text::styled(
+                    format!("can't read certificates: {err}"),
+                    Style::default().fg(theme.error),
+                )
+            }
+            Some(Ok(chain)) => chain,
+        };
+
+        let label_style = Style::default().fg(theme.focus);
+        let line = |label: &'static str, value: String, style: Style| {
+            Line::from(vec![
+                Span::styled(format!("  {label:<12}"), label_style),
+                Span::styled(value, style),
+            ])
+        };
+
+        let mut lines = Vec::new();
+        for (index, cert) in chain.iter().enumerate() {
+            let kind = match index {
+                0 => "server",
+                _ if cert.subject == cert.issuer => "root",
+                _ => "intermediate",
+            };
+            if index > 0 {
+                lines.push(Line::default());
+            }
+            lines.push(Line::from(Span::styled(
+                format!("#{} {kind}", index + 1),
+                Style::default().add_modifier(Modifier::BOLD),
+            )));
+            lines.push(line("subject", cert.subject.clone(), Style::default()));
+            lines.push(line("issuer", cert.issuer.clone(), Style::default()));
+            if !cert.sans.is_empty() {
+                lines.push(line("names", cert.sans.join(", "), Style::default()));
+            }
+            lines.push(line(
+                "valid from",
+                cert.not_before.clone(),
+                Style::default(),
+            ));
+
+            let (expiry, style) = match cert.days_left {
+                Some(days) if days < 0 => (
+                    format!("expired {} day(s) ago", -days),
+                    Style::default()
+                        .fg(theme.error)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Some(days) if days < EXPIRY_WARNING_DAYS => (
+                    format!("expires in {days} day(s)"),
+                    Style::default()
+                        .fg(theme.accent)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Some(days) => (
+                    format!("{days} days left"),
+                    Style::default().fg(theme.success),
+                ),
+                None => (String::new(), Style::default()),
+            };
+            lines.push(line(
+                "valid until",
+                format!("{}  {expiry}", cert.not_after),
+                style,
+            ));
+        }
+        Text::from(lines)
+    }
+
+    /// Lined up columns rather than a table so search and scrolling work
+    fn cookies(resp: &'a Response) -> Text<'a> {
+        let theme = theme::current();
+        if resp.cookies.is_empty() {
+            return Text::styled("no cookies set", Style::default().fg(theme.muted));
+        }
+
+        let rows: Vec<[String; 6]> = resp
+            .cookies
+            .iter()
+            .map(|cookie| {
+                let or_dash = |value: &Option<String>| value.clone().unwrap_or("-".to_string());
+                [
+                    cookie.name.clone(),
+                    cookie.value.clone(),
+                    or_dash(&cookie.domain),
+                    or_dash(&cookie.path),
+                    or_dash(&cookie.expires),
+                    cookie.flags(),
+                ]
+            })
+            .collect();
+        let header = ["name", "value", "domain", "path", "expires", "flags"].map(String::from);
+        let widths: Vec<usize> = (0..header.len())
+            .map(|column| {
+                std::iter::once(&header)
+                    .chain(&rows)
+                    .map(|row| row[column].width())
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect();
+        let line = |row: &[String; 6]| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell}{}", " ".repeat(width - cell.width() + 2)))
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        };
+
+        let mut lines = vec![Line::from(Span::styled(
+            line(&header),
+            Style::default()
+                .fg(theme.focus)
+                .add_modifier(Modifier::BOLD),
+        ))];
+        lines.extend(rows.iter().map(|row| Line::from(line(row))));
+        Text::from(lines)
+    }
+}
+
+impl<'a> Widget for ResponseView<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let theme = theme::current();
+        if let Some((image, inner)) = self.image(area) {
+            if let Some(block) = self.block {
+                block.render(area, buf);
+            }
+            if !self.graphics {
+                ImagePreview::new(image).render(inner, buf);
+            }
+            return;
+        }
+
+        let mut text = self.text();
+        if let Some(search) = self.search {
+            let match_style = Style::default().bg(theme.accent).fg(theme.background);
+            let current_style = Style::default().bg(theme.error).fg(theme.background);
+            let lines = std::mem::take(&mut text.lines);
+            text.lines = lines
+                .into_iter()
+                .enumerate()
+                .map(|(index, line)| {
+                    let ranges: Vec<_> = search
+                        .matches
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, found)| found.line == index)
+                        .map(|(number, found)| {
+                            let style = if number == search.current {
+                                current_style
+                            } else {
+                                match_style
+                            };
+                            (found.start, found.end, style)
+                        })
+                        .collect();
+                    highlight::mark(line, &ranges)
+                })
+                .collect();
+        }
+
+        let mut paragraph = if self.wrap {
+            Paragraph::new(text)
+                .wrap(Wrap { trim: false })
+                .scroll((self.scroll, 0))
+        } else {
+            Paragraph::new(text).scroll((self.scroll, self.hscroll))
+        };
+        if let Some(block) = self.block {
+            paragraph = paragraph.block(block);
+        }
+        paragraph.render(area, buf);
+    }
+}
+
+/// Draws an image with half blocks, two pixels per cell
+pub struct ImagePreview<'a> {
+    image: &'a RgbaImage,
+}
+
+impl<'a> ImagePreview<'a> {
+    pub fn new(image: &'a RgbaImage) -> Self {
+        Self { image }
+    }
+}
+
+impl<'a> Widget for ImagePreview<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let (width, height) = self.image.dimensions();
+        if area.width == 0 || area.height == 0 || width == 0 || height == 0 {
+            return;
+        }
+
+        let scale = f64::min(
+            area.width as f64 / width as f64,
+            area.height as f64 * 2.0 / height as f64,
+        );
+        let target_width = ((width as f64 * scale) as u32).clamp(1, area.width as u32);
+        let target_height = ((height as f64 * scale) as u32).clamp(1, area.height as u32 * 2);
+        let image = imageops::resize(
+            self.image,
+            target_width,
+            target_height,
+            FilterType::Triangle,
+        );
+
+        let left = area.x + (area.width - target_width as u16) / 2;
+        let top = area.y + (area.height - target_height.div_ceil(2) as u16) / 2;
+        // Transparent pixels are blended onto the black background
+        let color = |x: u32, y: u32| match image.get_pixel_checked(x, y) {
+            Some(pixel) => {
+                let [r, g, b, a] = pixel.0;
+                let blend = |channel: u8| (channel as u16 * a as u16 / 255) as u8;
+                Color::Rgb(blend(r), blend(g), blend(b))
+            }
+            None => Color::Black,
+        };
+
+        for y in (0..target_height).step_by(2) {
+            for x in 0..target_width {
+                buf.get_mut(left + x as u16, top + (y / 2) as u16)
+                    .set_symbol("▀")
+                    .set_fg(color(x, y))
+                    .set_bg(color(x, y + 1));
+            }
+        }
+    }
+}
+
+/// Marks a line of a text area drawn with line numbers, found by its number
+/// since the text area doesn't say how far it's scrolled
+pub struct LineHighlight {
+    /// Counted from 1
+    line: usize,
+    lines: usize,
+    color: Color,
+}
+
+impl LineHighlight {
+    pub fn new(line: usize, lines: usize, color: Color) -> Self {
+        Self { line, lines, color }
+    }
+}
+
+impl Widget for LineHighlight {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let digits = |n: usize| n.max(1).to_string().len();
+        let width = digits(self.lines) + 2;
+        let label = format!("{:>1$} ", self.line, width - 1);
+        if area.width as usize <= width {
+            return;
+        }
+        let row = (area.top()..area.bottom()).find(|&y| {
+            let shown: String = (0..width as u16)
+                .map(|x| buf.get(area.x + x, y).symbol.as_str())
+                .collect();
+            shown == label
+        });
+        if let Some(y) = row {
+            let number = Rect::new(area.x, y, width as u16 - 1, 1);
+            let background = theme::current().background;
+            buf.set_style(number, Style::default().fg(background).bg(self.color));
+            let rest = Rect::new(area.x + width as u16, y, area.width - width as u16, 1);
+            buf.set_style(rest, Style::default().fg(self.color));
+        }
+    }
+}
+
+pub struct KeyValueTable<'a> {
+    editor: &'a KeyValueEditor<'a>,
+    block: Option<Block<'a>>,
+    focused: bool,
+    variables: Option<&'a Variables>,
+}
+
+impl<'a> KeyValueTable<'a> {
+    pub fn new(editor: &'a KeyValueEditor<'a>) -> Self {
+        Self {
+            editor,
+            block: None,
+            focused: false,
+            variables: None,
+        }
+    }
+
+    /// Shows placeholders filled in, except in the cell being edited
+    pub fn variables(mut self, variables: Option<&'a Variables>) -> Self {
+        self.variables = variables;
+        self
+    }
+
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    pub fn focused(mut self, focused: bool) -> Self {
+        self.focused = focused;
+        self
+    }
+
+    fn active_cell(&self, text: &str) -> Line<'static> {
+        let offset = |cursor: usize| {
+            text.char_indices()
+                .nth(cursor)
+                .map_or(text.len(), |(offset, _)| offset)
+        };
+        let start = offset(self.editor.cursor);
+        let end = offset(self.editor.cursor + 1);
+        let cursor = if start == end { " " } else { &text[start..end] };
+
+        Line::from(vec![
+            Span::raw(text[..start].to_string()),
+            Span::styled(
+                cursor.to_string(),
+                Style::default().add_modifier(Modifier::REVERSED),
+            ),
+            Span::raw(text[end..].to_string()),
+        ])
+    }
+}
+
+impl<'a> Widget for KeyValueTable<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let theme = theme::current();
+        let rows = self.editor.rows.iter().enumerate().map(|(index, row)| {
+            let mut style = Style::default();
+            if !row.enabled {
+                style = style.fg(theme.muted);
+            }
+            if index == self.editor.selected {
+                style = style.bg(theme.muted).fg(theme.text);
+            }
+
+            let key_style = if row.key.is_empty() || self.editor.validate_key(&row.key) {
+                Style::default()
+            } else {
+                Style::default().fg(theme.error)
+            };
+            let cell = |column: usize, text: &str| {
+                let text = if column == 1 && row.secret {
+                    "•".repeat(text.chars().count())
+                } else {
+                    text.to_string()
+                };
+                if self.focused && index == self.editor.selected && column == self.editor.column {
+                    Cell::from(self.active_cell(&text))
+                } else if let Some(variables) = self.variables.filter(|_| !row.secret) {
+                    Cell::from(resolved_spans(&text, variables))
+                } else {
+                    Cell::from(text)
+                }
+            };
+
+            let mut cells = vec![cell(0, &row.key).style(key_style), cell(1, &row.value)];
+            if !self.editor.fixed {
+                cells.insert(0, Cell::from(if row.enabled { "[x]" } else { "[ ]" }));
+            }
+            Row::new(cells).style(style)
+        });
+
+        let (header, widths) = if self.editor.fixed {
+            (
+                vec!["Field", "Value"],
+                vec![Constraint::Percentage(30), Constraint::Percentage(70)],
+            )
+        } else {
+            (
+                vec!["", "Key", "Value"],
+                vec![
+                    Constraint::Length(3),
+                    Constraint::Percentage(40),
+                    Constraint::Percentage(60),
+                ],
+            )
+        };
+
+        let mut table = Table::new(rows)
+            .header(Row::new(header).style(Style::default().add_modifier(Modifier::BOLD)))
+            .widths(&widths);
+        if let Some(block) = self.block {
+            table = table.block(block);
+        }
+        Widget::render(table, area, buf);
+    }
+}
+
+/// Placeholders replaced by their values, dynamic ones are left as they are
+/// since they only get a value when sent, and unknown ones are red
+pub fn resolved_spans(text: &str, variables: &Variables) -> Line<'static> {
+    let theme = theme::current();
+    let spans: Vec<_> = environments::parts(text)
+        .into_iter()
+        .map(|part| match part {
+            Part::Text(text) => Span::raw(text.to_string()),
+            Part::Variable(name) => match variables.get(name) {
+                Some(value) => Span::styled(value.clone(), Style::default().fg(theme.success)),
+                None => {
+                    let color = match environments::dynamic(name) {
+                        Some(_) => theme.literal,
+                        None => theme.error,
+                    };
+                    Span::styled(format!("{{{{{name}}}}}"), Style::default().fg(color))
+                }
+            },
+        })
+        .collect();
+    Line::from(spans)
+}
+
+/// The request as it'd be sent, a section at a time with its placeholders
+/// filled in and secrets masked, and the unknown variables in order of use
+pub fn resolved_request(
+    request: &SavedRequest,
+    variables: &Variables,
+) -> (Vec<Line<'static>>, Vec<String>) {
+    let rows = |rows: &[KeyValueRow], separator: &str| -> Vec<String> {
+        rows.iter()
+            .filter(|row| row.enabled)
+            .map(|row| {
+                let value = if row.secret { MASK } else { &row.value };
+                format!("{}{separator}{value}", row.key)
+            })
+            .collect()
+    };
+    let lines = |text: &str| -> Vec<String> { text.lines().map(str::to_string).collect() };
+
+    let method = match request.method.trim() {
+        "" => "GET",
+        method => method,
+    };
+    let mut sections = vec![
+        (String::new(), vec![format!("{method} {}", request.uri)]),
+        ("params".to_string(), rows(&request.params, "=")),
+        ("headers".to_string(), rows(&request.headers, ": ")),
+        (
+            format!("auth ({})", request.auth_mode.title()),
+            rows(&request.auth, ": "),
+        ),
+    ];
+    sections.extend(match request.body_mode {
+        BodyMode::Raw => vec![("body".to_string(), lines(&request.body))],
+        BodyMode::Form => vec![("body (form)".to_string(), rows(&request.form, "="))],
+        BodyMode::Multipart => vec![(
+            "body (form-data)".to_string(),
+            rows(&request.multipart, "="),
+        )],
+        BodyMode::Binary => vec![("body (file)".to_string(), lines(&request.file))],
+        BodyMode::GraphQl => vec![
+            ("body (graphql)".to_string(), lines(&request.query)),
+            ("variables".to_string(), lines(&request.variables)),
+        ],
+    });
+
+    let mut text = Vec::new();
+    let mut unknown: Vec<String> = Vec::new();
+    for (title, section) in sections.iter().filter(|(_, lines)| !lines.is_empty()) {
+        if !title.is_empty() {
+            text.push(Line::default());
+            text.push(Line::from(Span::styled(
+                title.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            )));
+        }
+        for line in section {
+            for name in environments::unknown(line, variables) {
+                if !unknown.iter().any(|known| known == name) {
+                    unknown.push(name.to_string());
+                }
+            }
+            text.push(resolved_spans(line, variables));
+        }
+    }
+    (text, unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{certs::Certificate, cookies::Cookie, models::Redirect, timing::Timing};
+
+    /// The text of each line, without styles
+    fn lines(view: ResponseView) -> Vec<String> {
+        view.text()
+            .lines
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shows_body() {
+        let resp = Response {
+            status: 200,
+            body: "first\nsecond".to_string(),
+            ..Response::default()
+        };
+        assert_eq!(lines(ResponseView::new(Some(&resp))), ["first", "second"]);
+        assert!(lines(ResponseView::new(None)).is_empty());
+    }
+
+    #[test]
+    fn shows_redirect_chain() {
+        let resp = Response {
+            status: 200,
+            body: "done".to_string(),
+            redirects: vec![Redirect {
+                status: 301,
+                from: "http://a/".to_string(),
+                to: "https://a/".to_string(),
+            }],
+            ..Response::default()
+        };
+        assert_eq!(
+            lines(ResponseView::new(Some(&resp))),
+            ["▸ 1 redirect(s) (r to expand)", "done"]
+        );
+        assert_eq!(
+            lines(ResponseView::new(Some(&resp)).show_redirects(true)),
+            ["▾ 1 redirect(s)", "  301 http://a/ -> https://a/", "done"]
+        );
+    }
+
+    #[test]
+    fn shows_headers() {
+        let resp = Response {
+            status: 200,
+            headers: vec![
+                ("content-type".to_string(), "text/plain".to_string()),
+                ("set-cookie".to_string(), "a=1".to_string()),
+                ("set-cookie".to_string(), "b=2".to_string()),
+            ],
+            body: "body".to_string(),
+            ..Response::default()
+        };
+        assert_eq!(
+            lines(ResponseView::new(Some(&resp)).tab(ResponseTab::Headers)),
+            [
+                "content-type: text/plain",
+                "set-cookie: a=1",
+                "set-cookie: b=2"
+            ]
+        );
+    }
+
+    #[test]
+    fn shows_timing() {
+        let ms = Duration::from_millis;
+        let resp = Response {
+            status: 200,
+            version: "HTTP/2.0".to_string(),
+            timing: Timing {
+                dns: None,
+                connect: Some(ms(10)),
+                tls: Some(ms(20)),
+                first_byte: ms(100),
+                total: ms(1500),
+            },
+            ..Response::default()
+        };
+        let lines: Vec<String> = lines(ResponseView::new(Some(&resp)).tab(ResponseTab::Timing))
+            .iter()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "protocol HTTP/2.0",
+                "dns lookup -",
+                "connect 10.0 ms",
+                "tls handshake 20.0 ms",
+                "waiting 70.0 ms",
+                "time to first byte 100.0 ms",
+                "download 1.40 s",
+                "total 1.50 s",
+            ]
+        );
+    }
+
+    #[test]
+    fn draws_half_blocks() {
+        // Red over half transparent blue, drawn into the middle rows
+        let mut image = RgbaImage::from_pixel(4, 2, image::Rgba([255, 0, 0, 255]));
+        (0..4).for_each(|x| image.put_pixel(x, 1, image::Rgba([0, 0, 255, 128])));
+        let area = Rect::new(0, 0, 4, 3);
+        let mut buf = Buffer::empty(area);
+        ImagePreview::new(&image).render(area, &mut buf);
+        for x in 0..4 {
+            let cell = buf.get(x, 1);
+            assert_eq!(cell.symbol, "▀");
+            assert_eq!(cell.fg, Color::Rgb(255, 0, 0));
+            assert_eq!(cell.bg, Color::Rgb(0, 0, 128));
+        }
+        assert_eq!(buf.get(0, 0).symbol, " ");
+        assert_eq!(buf.get(0, 2).symbol, " ");
+    }
+
+    #[test]
+    fn shows_cookies() {
+        let mut resp = Response {
+            status: 200,
+            ..Response::default()
+        };
+        let cookies =
+            |resp: &Response| lines(ResponseView::new(Some(resp)).tab(ResponseTab::Cookies));
+        assert_eq!(cookies(&resp), ["no cookies set"]);
+
+        resp.cookies = [
+            "id=42; Path=/; Secure; HttpOnly",
+            "theme=dark; Domain=example.com",
+        ]
+        .into_iter()
+        .filter_map(Cookie::parse)
+        .collect();
+        assert_eq!(
+            cookies(&resp),
+            [
+                "name   value  domain       path  expires  flags",
+                "id     42     -            /     -        Secure HttpOnly",
+                "theme  dark   example.com  -     -",
+            ]
+        );
+    }
+
+    #[test]
+    fn shows_certificates() {
+        let cert = |subject: &str, issuer: &str, days_left| Certificate {
+            subject: subject.to_string(),
+            issuer: issuer.to_string(),
+            sans: Vec::new(),
+            not_before: "Jan  1 00:00:00 2026 GMT".to_string(),
+            not_after: "Dec 31 00:00:00 2026 GMT".to_string(),
+            days_left: Some(days_left),
+        };
+        let mut server = cert("CN=example.com", "CN=CA", 12);
+        server.sans = vec!["example.com".to_string(), "127.0.0.1".to_string()];
+        let resp = Response {
+            status: 200,
+            certificates: Some(Ok(vec![server, cert("CN=CA", "CN=CA", -3)])),
+            ..Response::default()
+        };
+        assert_eq!(
+            lines(ResponseView::new(Some(&resp)).tab(ResponseTab::Certificates)),
+            [
+                "#1 server",
+                "  subject     CN=example.com",
+                "  issuer      CN=CA",
+                "  names       example.com, 127.0.0.1",
+                "  valid from  Jan  1 00:00:00 2026 GMT",
+                "  valid until Dec 31 00:00:00 2026 GMT  expires in 12 day(s)",
+                "",
+                "#2 root",
+                "  subject     CN=CA",
+                "  issuer      CN=CA",
+                "  valid from  Jan  1 00:00:00 2026 GMT",
+                "  valid until Dec 31 00:00:00 2026 GMT  expired 3 day(s) ago",
+            ]
+        );
+
+        let plain = Response::default();
+        assert_eq!(
+            lines(ResponseView::new(Some(&plain)).tab(ResponseTab::Certificates)),
+            ["not an https response"]
+        );
+    }
+
+    #[test]
+    fn fills_in_variables() {
+        let variables = Variables::from([("host".to_string(), "example.com".to_string())]);
+        let line = resolved_spans("http://{{host}}/{{ id }}/{{uuid}}", &variables);
+        let spans: Vec<_> = line
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+        assert_eq!(
+            spans,
+            ["http://", "example.com", "/", "{{id}}", "/", "{{uuid}}"]
+        );
+        let theme = theme::current();
+        assert_eq!(line.spans[1].style.fg, Some(theme.success));
+        assert_eq!(line.spans[3].style.fg, Some(theme.error));
+        assert_eq!(line.spans[5].style.fg, Some(theme.literal));
+    }
+
+    #[test]
+    fn previews_resolved_request() {
+        let row = |key: &str, value: &str, secret| KeyValueRow {
+            key: key.to_string(),
+            value: value.to_string(),
+            secret,
+            ..KeyValueRow::new()
+        };
+        let request = SavedRequest {
+            uri: "http://{{host}}/{{path}}".to_string(),
+            headers: vec![
+                row("X-Token", "{{token}}", true),
+                row("X-Trace", "{{uuid}}", false),
+                KeyValueRow::default(),
+            ],
+            body_mode: BodyMode::Form,
+            form: vec![row("name", "{{path}} {{user}}", false)],
+            ..SavedRequest::default()
+        };
+        let variables = Variables::from([("host".to_string(), "example.com".to_string())]);
+        let (text, unknown) = resolved_request(&request, &variables);
+        let text: Vec<String> = text
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "GET http://example.com/{{path}}",
+                "",
+                "headers",
+                "X-Token: ••••••",
+                "X-Trace: {{uuid}}",
+                "",
+                "body (form)",
+                "name={{path}} {{user}}",
+            ]
+        );
+        assert_eq!(unknown, ["path", "user"]);
+    }
+
+    #[test]
+    fn highlights_line() {
+        let area = Rect::new(0, 0, 12, 3);
+        let mut buffer = Buffer::empty(area);
+        for (y, line) in ["  9 \"a\": 1,", " 10 \"b\":", " 11 }"].iter().enumerate() {
+            buffer.set_string(0, y as u16, line, Style::default());
+        }
+        // Found by its number wherever the text area scrolled to
+        LineHighlight::new(10, 11, Color::Red).render(area, &mut buffer);
+        assert_eq!(buffer.get(1, 1).bg, Color::Red);
+        assert_eq!(buffer.get(3, 1).bg, Color::Reset);
+        assert_eq!(buffer.get(5, 1).fg, Color::Red);
+        assert_eq!(buffer.get(1, 0).bg, Color::Reset);
+        assert_eq!(buffer.get(5, 2).fg, Color::Reset);
+    }
+}
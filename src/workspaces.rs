@@ -0,0 +1,147 @@
+use std::{fs, path::PathBuf};
+
+use crate::history::data_dir;
+
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+/// Where a workspace keeps its collections and environments. The default one
+/// lives directly in the data directory, where collections were kept before
+/// there were workspaces
+pub fn dir(name: &str) -> Option<PathBuf> {
+    let data = data_dir()?;
+    Some(match name {
+        DEFAULT_WORKSPACE => data,
+        name => data.join("workspaces").join(name),
+    })
+}
+
+/// Sorted, starting with the default workspace
+pub fn list() -> Vec<String> {
+    let mut names: Vec<String> = data_dir()
+        .and_then(|dir| fs::read_dir(dir.join("workspaces")).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            entry.file_type().ok()?.is_dir().then_some(())?;
+            entry.file_name().into_string().ok()
+        })
+        .filter(|name| name != DEFAULT_WORKSPACE)
+        .collect();
+    names.sort();
+    names.insert(0, DEFAULT_WORKSPACE.to_string());
+    names
+}
+
+/// The workspace open when curl-rs last quit
+pub fn current() -> String {
+    data_dir()
+        .and_then(|dir| fs::read_to_string(dir.join("workspace")).ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| check(name).is_ok())
+        .unwrap_or_else(|| DEFAULT_WORKSPACE.to_string())
+}
+
+pub fn set_current(name: &str) -> Result<(), String> {
+    check(name)?;
+    let (Some(data), Some(dir)) = (data_dir(), dir(name)) else {
+        return Ok(());
+    };
+    fs::create_dir_all(dir)
+        .and_then(|()| fs::write(data.join("workspace"), format!("{name}\n")))
+        .map_err(|err| format!("can't switch to {name}: {err}"))
+}
+
+/// Workspaces name directories, so no path tricks
+fn check(name: &str) -> Result<(), String> {
+    match name.is_empty() || name.contains(['/', '\\', '.']) {
+        true => Err(format!("bad workspace name `{name}`")),
+        false => Ok(()),
+    }
+}
+
+/// The workspace switcher, typing narrows the list down or names a new one
+#[derive(Default)]
+pub struct WorkspaceView {
+    pub names: Vec<String>,
+    pub query: String,
+    pub selected: usize,
+}
+
+impl WorkspaceView {
+    /// Starts on the current workspace
+    pub fn new(current: &str) -> Self {
+        let names = list();
+        let selected = names.iter().position(|name| name == current);
+        Self {
+            selected: selected.unwrap_or_default(),
+            names,
+            query: String::new(),
+        }
+    }
+
+    /// Matching workspaces, then the typed name when it would be a new one
+    pub fn matches(&self) -> Vec<&str> {
+        let query = self.query.trim();
+        let lower = query.to_ascii_lowercase();
+        let mut matches: Vec<&str> = self
+            .names
+            .iter()
+            .map(String::as_str)
+            .filter(|name| name.to_ascii_lowercase().contains(&lower))
+            .collect();
+        if !query.is_empty() && !self.exists(query) {
+            matches.push(query);
+        }
+        matches
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.names.iter().any(|existing| existing == name)
+    }
+
+    pub fn choice(&self) -> Option<String> {
+        self.matches()
+            .get(self.selected)
+            .map(|name| name.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_workspaces() {
+        let data = std::env::temp_dir().join(format!("curl-rs-tests-{}", std::process::id()));
+        crate::history::set_data_dir(data.clone());
+        let data = data_dir().unwrap();
+        assert_eq!(dir(DEFAULT_WORKSPACE), Some(data.clone()));
+        let team = dir("team").unwrap();
+        assert_eq!(team, data.join("workspaces/team"));
+
+        fs::create_dir_all(&team).unwrap();
+        let names = list();
+        fs::remove_dir_all(&team).unwrap();
+        assert_eq!(names[0], DEFAULT_WORKSPACE);
+        assert!(names.contains(&"team".to_string()));
+
+        assert!(check("../up").is_err());
+        assert!(check("").is_err());
+        assert!(check("team-2").is_ok());
+    }
+
+    #[test]
+    fn offers_new_workspaces() {
+        let mut view = WorkspaceView {
+            names: vec!["default".to_string(), "Staging".to_string()],
+            ..WorkspaceView::default()
+        };
+        view.query = "stag".to_string();
+        assert_eq!(view.matches(), ["Staging", "stag"]);
+        view.query = "Staging".to_string();
+        assert_eq!(view.matches(), ["Staging"]);
+        view.query = "prod".to_string();
+        assert_eq!(view.choice().as_deref(), Some("prod"));
+    }
+}